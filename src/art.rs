@@ -0,0 +1,77 @@
+// src/art.rs
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+use std::io::Cursor;
+
+use crate::error::Result;
+
+/// JPEG qualities tried at full size before shrinking the image
+const QUALITIES: [u8; 3] = [85, 70, 55];
+/// Quality used once the image is being shrunk
+const SHRINK_QUALITY: u8 = 80;
+/// Art is not shrunk below this many pixels on its longest side
+const MIN_SIDE: u32 = 300;
+
+/// What `fit_to_bytes` did to make cover art small enough to embed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit {
+    /// Already within the limit; embedded byte-for-byte
+    Unchanged,
+    /// Re-encoded as JPEG at this quality, same dimensions
+    Reencoded { quality: u8 },
+    /// Shrunk to these dimensions and re-encoded as JPEG
+    Downscaled { width: u32, height: u32 },
+    /// Still too large at the smallest size tried, or not decodable
+    TooLarge,
+}
+
+/// Width and height from the image header, without decoding the pixels
+pub fn dimensions(image_data: &[u8]) -> Option<(u32, u32)> {
+    image::io::Reader::new(Cursor::new(image_data))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Bring cover art under `max_bytes` for embedding, lowering JPEG quality
+/// before shrinking the image. Returns the bytes to embed (`None` with
+/// `Fit::TooLarge`) and what was done.
+pub fn fit_to_bytes(image_data: &[u8], max_bytes: usize) -> Result<(Option<Vec<u8>>, Fit)> {
+    if image_data.len() <= max_bytes {
+        return Ok((Some(image_data.to_vec()), Fit::Unchanged));
+    }
+    let Ok(img) = image::load_from_memory(image_data) else {
+        return Ok((None, Fit::TooLarge));
+    };
+    // JPEG has no alpha channel
+    let img = DynamicImage::ImageRgb8(img.to_rgb8());
+
+    for quality in QUALITIES {
+        let encoded = encode_jpeg(&img, quality)?;
+        if encoded.len() <= max_bytes {
+            return Ok((Some(encoded), Fit::Reencoded { quality }));
+        }
+    }
+
+    let (width, height) = img.dimensions();
+    let mut side = width.max(height);
+    loop {
+        side = side * 3 / 4;
+        if side < MIN_SIDE {
+            return Ok((None, Fit::TooLarge));
+        }
+        let smaller = img.resize(side, side, FilterType::Lanczos3);
+        let encoded = encode_jpeg(&smaller, SHRINK_QUALITY)?;
+        if encoded.len() <= max_bytes {
+            let (width, height) = smaller.dimensions();
+            return Ok((Some(encoded), Fit::Downscaled { width, height }));
+        }
+    }
+}
+
+fn encode_jpeg(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let mut output = Cursor::new(Vec::new());
+    img.write_to(&mut output, ImageOutputFormat::Jpeg(quality))?;
+    Ok(output.into_inner())
+}
@@ -0,0 +1,264 @@
+// src/art_picker.rs
+use anyhow::Result;
+use base64::Engine;
+use clap::ValueEnum;
+use colored::Colorize;
+use dialoguer::Select;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::error::Error as MbError;
+use crate::musicbrainz::{Album, ArtCandidate, DownloadedArt, MusicBrainzClient};
+use crate::profile::TagSettings;
+use crate::symbols;
+
+/// Largest chunk of image data in one kitty graphics escape
+const KITTY_CHUNK: usize = 4096;
+/// Terminal rows a preview takes up
+const PREVIEW_ROWS: u32 = 8;
+/// Previews are scaled to this many pixels per side before sending
+const PREVIEW_SIZE: u32 = 256;
+
+/// What to do when the only art is below the minimum resolution
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmallArt {
+    /// Embed it anyway and say the album needs better art
+    #[default]
+    Warn,
+    /// Leave it out
+    Skip,
+}
+
+/// The art's dimensions if either side is under `min_size` pixels
+pub fn below_minimum(art: &[u8], min_size: u32) -> Option<(u32, u32)> {
+    crate::art::dimensions(art).filter(|(width, height)| *width < min_size || *height < min_size)
+}
+
+/// The release's cover art. With several front cover candidates and
+/// `interactive`, the user picks one (or none, returning `None`); otherwise
+/// the marked front cover is taken. Art cached by an earlier run, including
+/// an earlier pick, is used without asking.
+pub async fn cover_art(
+    client: &MusicBrainzClient,
+    release_id: &str,
+    max_size: u32,
+    interactive: bool,
+) -> Result<Option<Vec<u8>>> {
+    if let Some(art) = client.cached_cover_art(release_id, max_size) {
+        println!("{}", "Using cached cover art".bright_black());
+        return Ok(Some(art));
+    }
+    let art = pick(client, release_id, max_size, interactive).await?;
+    if let Some(art) = &art {
+        client.cache_cover_art(release_id, max_size, art);
+    }
+    Ok(art)
+}
+
+async fn pick(
+    client: &MusicBrainzClient,
+    release_id: &str,
+    max_size: u32,
+    interactive: bool,
+) -> Result<Option<Vec<u8>>> {
+    let candidates = client.cover_art_candidates(release_id).await?;
+    if candidates.len() < 2 || !interactive {
+        let image = client.fetch_art_candidate(&candidates[0]).await?;
+        return Ok(Some(client.shrink_art(release_id, image, max_size)?));
+    }
+
+    println!(
+        "Found {} front cover candidates, downloading them to compare...",
+        candidates.len()
+    );
+    let mut downloaded: Vec<(&ArtCandidate, DownloadedArt)> = Vec::new();
+    for candidate in &candidates {
+        match client.download_art_candidate(candidate).await {
+            Ok(image) => downloaded.push((candidate, image)),
+            Err(e) => println!("  {} {}", symbols::warn().bright_yellow(), e),
+        }
+    }
+    let Some(first) = downloaded.first() else {
+        return Err(MbError::NotFound(format!("Cover art for release {}", release_id)).into());
+    };
+    if downloaded.len() == 1 {
+        let image = first.1.bytes.clone();
+        return Ok(Some(client.shrink_art(release_id, image, max_size)?));
+    }
+
+    let labels: Vec<String> = downloaded
+        .iter()
+        .enumerate()
+        .map(|(i, (candidate, image))| format!("{}. {}", i + 1, describe(candidate, image)))
+        .collect();
+    if previews_supported() {
+        println!();
+        for (label, (_, image)) in labels.iter().zip(&downloaded) {
+            println!("{}", label);
+            show_preview(&image.bytes);
+        }
+    }
+    println!();
+    let choice = Select::new()
+        .with_prompt("Which cover? (Esc for none)")
+        .items(&labels)
+        .default(0)
+        .interact_opt()?;
+    let Some(i) = choice else {
+        return Ok(None);
+    };
+    let image = downloaded.swap_remove(i).1.bytes;
+    Ok(Some(client.shrink_art(release_id, image, max_size)?))
+}
+
+/// Art of their own for the selected discs of a multi-disc release, ready
+/// to embed, by disc number. Discs without it (or whose art can't be
+/// fetched or made to fit) are left out and get the release's cover, as do
+/// discs whose art is below `min_size` with `SmallArt::Skip`.
+pub async fn disc_art(
+    client: &MusicBrainzClient,
+    album: &Album,
+    settings: &TagSettings,
+    min_size: Option<u32>,
+    small_art: SmallArt,
+) -> BTreeMap<u32, Vec<u8>> {
+    let mut art = BTreeMap::new();
+    if album.media_count < 2 {
+        return art;
+    }
+    let candidates = match client.disc_art_candidates(&album.id).await {
+        Ok(candidates) => candidates,
+        Err(_) => return art,
+    };
+    for (disc, candidate) in candidates {
+        if !album.tracks.iter().any(|t| t.disc_number == disc) {
+            continue;
+        }
+        let image = match client.fetch_art_candidate(&candidate).await {
+            Ok(image) => image,
+            Err(e) => {
+                println!(
+                    "{} Disc {} cover art: {}",
+                    symbols::warn().bright_yellow(),
+                    disc,
+                    e
+                );
+                continue;
+            }
+        };
+        if let Some((width, height)) = min_size.and_then(|min| below_minimum(&image, min)) {
+            let action = match small_art {
+                SmallArt::Warn => "embedding it anyway",
+                SmallArt::Skip => "it gets the release cover",
+            };
+            println!(
+                "{} Disc {} cover art is only {}x{}, below the minimum; {}",
+                symbols::warn().bright_yellow(),
+                disc,
+                width,
+                height,
+                action
+            );
+            if small_art == SmallArt::Skip {
+                continue;
+            }
+        }
+        let fitted = client
+            .shrink_art(&album.id, image, settings.max_art_size)
+            .ok()
+            .and_then(|image| crate::art::fit_to_bytes(&image, settings.max_embed_bytes).ok())
+            .and_then(|(fitted, _)| fitted);
+        let Some(fitted) = fitted else {
+            println!(
+                "{} Disc {} cover art can't be made to fit; it gets the release cover",
+                symbols::warn().bright_yellow(),
+                disc
+            );
+            continue;
+        };
+        println!(
+            "{} Disc {} has its own cover art ({:.1} KB)",
+            symbols::ok().bright_green(),
+            disc,
+            fitted.len() as f64 / 1024.0
+        );
+        art.insert(disc, fitted);
+    }
+    art
+}
+
+/// "1200×1200 thumbnail, 245 KB, Cover Art Archive, front cover (Front,
+/// Booklet) "comment"". The size is that of the image downloaded, which is
+/// what gets embedded; a thumbnail's original may be larger.
+fn describe(candidate: &ArtCandidate, image: &DownloadedArt) -> String {
+    let mut text = match crate::art::dimensions(&image.bytes) {
+        Some((width, height)) => format!("{}×{}", width, height),
+        None => "unknown size".to_string(),
+    };
+    if image.thumbnail {
+        text.push_str(" thumbnail");
+    }
+    text.push_str(&format!(
+        ", {:.0} KB, {}",
+        image.bytes.len() as f64 / 1024.0,
+        candidate.source
+    ));
+    if candidate.front {
+        text.push_str(", front cover");
+    }
+    if !candidate.types.is_empty() {
+        text.push_str(&format!(" ({})", candidate.types.join(", ")));
+    }
+    if !candidate.comment.is_empty() {
+        text.push_str(&format!(" \"{}\"", candidate.comment));
+    }
+    text
+}
+
+/// Terminals known to speak the kitty graphics protocol
+fn previews_supported() -> bool {
+    let term = std::env::var("TERM").unwrap_or_default();
+    let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+    std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || matches!(program.as_str(), "WezTerm" | "ghostty")
+}
+
+/// Draw `image` inline with the kitty graphics protocol. Images that can't be
+/// decoded are left out.
+fn show_preview(image: &[u8]) {
+    let Ok(img) = image::load_from_memory(image) else {
+        return;
+    };
+    let thumbnail = img.thumbnail(PREVIEW_SIZE, PREVIEW_SIZE);
+    let mut png = std::io::Cursor::new(Vec::new());
+    if thumbnail
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .is_err()
+    {
+        return;
+    }
+    let encoded = base64::engine::general_purpose::STANDARD.encode(png.into_inner());
+
+    let mut stdout = std::io::stdout().lock();
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let control = if i == 0 {
+            format!("a=T,f=100,r={},m={}", PREVIEW_ROWS, more)
+        } else {
+            format!("m={}", more)
+        };
+        let _ = write!(
+            stdout,
+            "\x1b_G{};{}\x1b\\",
+            control,
+            String::from_utf8_lossy(chunk)
+        );
+    }
+    let _ = writeln!(stdout);
+    let _ = stdout.flush();
+}
@@ -0,0 +1,203 @@
+// src/artify.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use id3::frame::PictureType;
+use id3::Tag;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::art::dimensions;
+use crate::art_picker::SmallArt;
+use crate::profile::TagSettings;
+use crate::provider::MetadataProvider;
+use crate::symbols;
+use crate::tagger::{add_cover_art, extended_text, read_tag};
+
+/// Embed cover art from the Cover Art Archive into tagged files that have none,
+/// or whose front cover is smaller than `min_size` pixels on either side
+pub async fn run(
+    path: &Path,
+    min_size: Option<u32>,
+    small_art: SmallArt,
+    dry_run: bool,
+    settings: &TagSettings,
+) -> Result<()> {
+    let files = crate::maintenance::collect_tree(path)?;
+    if files.is_empty() {
+        anyhow::bail!("No MP3 files found in {}", path.display());
+    }
+
+    let mut releases: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    let mut without_release = 0;
+    let pb = crate::progress::bar(files.len(), "Reading tags");
+    for file in files {
+        pb.inc(1);
+        let tag = Tag::read_from_path(&file).ok();
+        if !needs_art(tag.as_ref(), min_size) {
+            continue;
+        }
+        match tag
+            .as_ref()
+            .and_then(|t| extended_text(t, "MusicBrainz Album Id"))
+        {
+            Some(album_id) => releases.entry(album_id).or_default().push(file),
+            None => without_release += 1,
+        }
+    }
+    pb.finish_and_clear();
+
+    let needing: usize = releases.values().map(Vec::len).sum();
+    if needing == 0 && without_release == 0 {
+        println!(
+            "{} Every file already has cover art",
+            symbols::ok().bright_green()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} {} file(s) in {} release(s) need cover art",
+        symbols::ok().bright_green(),
+        needing,
+        releases.len()
+    );
+    if without_release > 0 {
+        println!(
+            "  {} file(s) without a MusicBrainz Album Id were skipped",
+            without_release
+        );
+    }
+    println!();
+
+    let client = crate::config::musicbrainz_client();
+    let mut embedded = 0;
+    let mut need_better_art = Vec::new();
+    for (album_id, files) in &releases {
+        if dry_run {
+            println!(
+                "  {} {} {}",
+                symbols::arrow().bright_cyan(),
+                album_id.bright_white(),
+                format!("({} file(s))", files.len()).bright_black()
+            );
+            continue;
+        }
+
+        let art = match client.get_cover_art(album_id, settings.max_art_size).await {
+            Ok(art) => art,
+            Err(e) => {
+                println!(
+                    "  {} {}: {}",
+                    symbols::warn().bright_yellow(),
+                    album_id.bright_white(),
+                    e
+                );
+                continue;
+            }
+        };
+
+        let mut targets: Vec<&PathBuf> = files.iter().collect();
+        if let (Some(min), Some((width, height))) = (min_size, dimensions(&art)) {
+            if width < min || height < min {
+                println!(
+                    "  {} {}: Cover Art Archive only has {}x{}",
+                    symbols::warn().bright_yellow(),
+                    album_id.bright_white(),
+                    width,
+                    height
+                );
+                need_better_art.push(album_id.as_str());
+                // Replacing small art with equally small art gains nothing
+                targets.retain(|file| small_art == SmallArt::Warn && !has_picture(file));
+                if targets.is_empty() {
+                    continue;
+                }
+            }
+        }
+
+        let art = match crate::art::fit_to_bytes(&art, settings.max_embed_bytes) {
+            Ok((Some(fitted), _)) => fitted,
+            _ => {
+                println!(
+                    "  {} {}: cover art ({:.1} KB) can't be made to fit {:.1} KB",
+                    symbols::warn().bright_yellow(),
+                    album_id.bright_white(),
+                    art.len() as f64 / 1024.0,
+                    settings.max_embed_bytes as f64 / 1024.0
+                );
+                crate::save_folder_art(&art, files)?;
+                continue;
+            }
+        };
+
+        for file in &targets {
+            let mut tag = read_tag(file).unwrap_or_else(|_| Tag::new());
+            add_cover_art(&mut tag, &art);
+            tag.write_to_path(file, settings.version)
+                .with_context(|| format!("Failed to write tags to {}", file.display()))?;
+            embedded += 1;
+        }
+        println!(
+            "  {} {} {}",
+            symbols::ok().bright_green(),
+            album_id.bright_white(),
+            format!(
+                "({} file(s), {:.1} KB)",
+                targets.len(),
+                art.len() as f64 / 1024.0
+            )
+            .bright_black()
+        );
+    }
+
+    println!();
+    if dry_run {
+        println!(
+            "{} {} file(s) would get cover art.",
+            "Dry run -".bright_yellow(),
+            needing
+        );
+    } else {
+        println!(
+            "{} Embedded cover art in {} of {} file(s)",
+            symbols::ok().bright_green(),
+            embedded,
+            needing
+        );
+    }
+    if !need_better_art.is_empty() {
+        println!();
+        println!(
+            "{} {} release(s) need better cover art than the Cover Art Archive has:",
+            symbols::warn().bright_yellow(),
+            need_better_art.len()
+        );
+        for album_id in need_better_art {
+            println!("  {}", crate::browser::entity_url("release", album_id));
+        }
+    }
+
+    Ok(())
+}
+
+fn has_picture(file: &Path) -> bool {
+    read_tag(file).is_ok_and(|tag| tag.pictures().next().is_some())
+}
+
+fn needs_art(tag: Option<&Tag>, min_size: Option<u32>) -> bool {
+    let Some(tag) = tag else {
+        return true;
+    };
+    let Some(front) = tag
+        .pictures()
+        .find(|p| p.picture_type == PictureType::CoverFront)
+        .or_else(|| tag.pictures().next())
+    else {
+        return true;
+    };
+
+    match (min_size, dimensions(&front.data)) {
+        (Some(min), Some((width, height))) => width < min || height < min,
+        _ => false,
+    }
+}
@@ -0,0 +1,336 @@
+// src/audio.rs
+use id3::TagLike;
+use sha2::{Digest, Sha256};
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::TimeBase;
+
+use crate::error::{Error, Result};
+
+/// Encoders that leave their name and version in the first frame
+const ENCODER_MARKERS: [&[u8]; 3] = [b"LAME", b"Lavc", b"Lavf"];
+/// How much of the stream after the ID3 tag is searched for one
+const ENCODER_SCAN_BYTES: u64 = 64 * 1024;
+
+/// Seconds of audio looked at to find the lowpass
+const CUTOFF_SECONDS: u32 = 30;
+/// Samples per analysis block; a power of two for the FFT
+const CUTOFF_BLOCK: usize = 2048;
+/// Bands searched for the lowpass, by center frequency in Hz
+const CUTOFF_LOW_HZ: u32 = 11_000;
+const CUTOFF_HIGH_HZ: u32 = 21_000;
+const CUTOFF_STEP_HZ: u32 = 250;
+/// Encoder lowpass filters drop by far more than this within 500 Hz; music
+/// rolling off on its own doesn't
+const CUTOFF_DROP: f64 = 1e-3;
+
+/// Stream properties read from the audio frames themselves
+#[derive(Debug, Clone, Copy)]
+pub struct AudioInfo {
+    pub duration_ms: u32,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    /// Average over the audio frames, so embedded cover art doesn't inflate it
+    pub bitrate_kbps: Option<u32>,
+    /// Frame sizes vary by more than the padding byte CBR streams add
+    pub vbr: bool,
+}
+
+/// Probe a file by walking its frames. Headers (Xing/VBRI) are missing or wrong
+/// often enough that counting the frames is the only reliable duration.
+pub fn probe(path: &Path) -> Option<AudioInfo> {
+    let file = File::open(path).ok()?;
+    let source = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+    let mut format = probed.format;
+
+    let track = format.default_track()?;
+    let track_id = track.id;
+    let params = &track.codec_params;
+    let sample_rate = params.sample_rate;
+    let channels = params.channels.map(|c| c.count() as u32);
+    let header_frames = params.n_frames;
+    let time_base = params
+        .time_base
+        .or_else(|| sample_rate.map(|rate| TimeBase::new(1, rate)))?;
+
+    let mut frames = 0u64;
+    let mut audio_bytes = 0u64;
+    let mut smallest = usize::MAX;
+    let mut largest = 0;
+    while let Ok(packet) = format.next_packet() {
+        if packet.track_id() == track_id {
+            frames += packet.dur;
+            audio_bytes += packet.buf().len() as u64;
+            smallest = smallest.min(packet.buf().len());
+            largest = largest.max(packet.buf().len());
+        }
+    }
+    if frames == 0 {
+        frames = header_frames?;
+    }
+
+    let time = time_base.calc_time(frames);
+    let seconds = time.seconds as f64 + time.frac;
+    let bitrate_kbps = (audio_bytes > 0 && seconds > 0.0)
+        .then(|| (audio_bytes as f64 * 8.0 / seconds / 1000.0).round() as u32);
+
+    Some(AudioInfo {
+        duration_ms: (seconds * 1000.0).round() as u32,
+        sample_rate,
+        channels,
+        bitrate_kbps,
+        vbr: largest > smallest.saturating_add(1),
+    })
+}
+
+/// The encoder named in the file: the ID3 encoder settings (TSSE), or the
+/// LAME/libavcodec tag in the first frame, e.g. "LAME3.100"
+pub fn encoder(path: &Path) -> Option<String> {
+    if let Some(settings) = id3::Tag::read_from_path(path)
+        .ok()
+        .and_then(|tag| tag.get("TSSE")?.content().text().map(str::to_string))
+        .filter(|s| !s.trim().is_empty())
+    {
+        return Some(settings.trim().to_string());
+    }
+
+    let mut file = File::open(path).ok()?;
+    let skip = id3v2_len(&mut file).ok()?;
+    file.seek(SeekFrom::Start(skip)).ok()?;
+    let mut bytes = Vec::new();
+    file.take(ENCODER_SCAN_BYTES).read_to_end(&mut bytes).ok()?;
+
+    let start = bytes
+        .windows(4)
+        .position(|w| ENCODER_MARKERS.contains(&w))?;
+    let name: String = bytes[start..]
+        .iter()
+        .take(9)
+        .take_while(|b| b.is_ascii_alphanumeric() || **b == b'.')
+        .map(|b| char::from(*b))
+        .collect();
+    Some(name.trim_end_matches('.').to_string())
+}
+
+/// SHA-256 of the audio stream alone, as `sha256:<hex>`: everything between
+/// the ID3v2 tag and any APEv2 or ID3v1 tag at the end, so retagging never
+/// changes it
+pub fn stream_hash(path: &Path) -> Result<String> {
+    let io_error = |source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut file = File::open(path).map_err(io_error)?;
+    let len = file.metadata().map_err(io_error)?.len();
+    let start = id3v2_len(&mut file).map_err(io_error)?.min(len);
+    let end = trailing_tags_start(&mut file, len)
+        .map_err(io_error)?
+        .max(start);
+
+    file.seek(SeekFrom::Start(start)).map_err(io_error)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file.take(end - start), &mut hasher).map_err(io_error)?;
+    let digest: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    Ok(format!("sha256:{}", digest))
+}
+
+/// Bytes taken up by the ID3v2 tag at the start of the file, 0 without one
+fn id3v2_len(file: &mut File) -> std::io::Result<u64> {
+    let mut header = [0u8; 10];
+    file.seek(SeekFrom::Start(0))?;
+    if file.read_exact(&mut header).is_err() || &header[..3] != b"ID3" {
+        return Ok(0);
+    }
+    let size = header[6..10]
+        .iter()
+        .fold(0u64, |size, b| (size << 7) | u64::from(b & 0x7f));
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+    Ok(10 + size + footer)
+}
+
+/// Where the ID3v1 and APEv2 tags at the end of the file begin, or `len`
+fn trailing_tags_start(file: &mut File, len: u64) -> std::io::Result<u64> {
+    let mut end = len;
+    let mut marker = [0u8; 3];
+    if end >= 128 {
+        file.seek(SeekFrom::Start(end - 128))?;
+        file.read_exact(&mut marker)?;
+        if &marker == b"TAG" {
+            end -= 128;
+        }
+    }
+    // APEv2 footer: "APETAGEX", version, then the tag size without its header
+    let mut footer = [0u8; 32];
+    if end >= 32 {
+        file.seek(SeekFrom::Start(end - 32))?;
+        file.read_exact(&mut footer)?;
+        if &footer[..8] == b"APETAGEX" {
+            let size = u64::from(u32::from_le_bytes([
+                footer[12], footer[13], footer[14], footer[15],
+            ]));
+            let flags = u32::from_le_bytes([footer[20], footer[21], footer[22], footer[23]]);
+            let header = if flags & 0x8000_0000 != 0 { 32 } else { 0 };
+            end = end.saturating_sub(size + header);
+        }
+    }
+    Ok(end)
+}
+
+/// Where the spectrum falls off a cliff in the first seconds of the file, in
+/// Hz: the encoder's lowpass. A file encoded at a high bitrate from a lossy
+/// low-bitrate source keeps that source's lower cutoff. `None` for silence
+/// or when nothing is cut below the top of the probed range.
+pub fn cutoff_hz(path: &Path) -> Option<u32> {
+    let file = File::open(path).ok()?;
+    let source = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?
+        .format;
+    let track = format.default_track()?;
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .ok()?;
+
+    let frequencies: Vec<u32> = (CUTOFF_LOW_HZ..=CUTOFF_HIGH_HZ)
+        .step_by(CUTOFF_STEP_HZ as usize)
+        .take_while(|f| f * 2 < sample_rate)
+        .collect();
+    let window: Vec<f64> = (0..CUTOFF_BLOCK)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / CUTOFF_BLOCK as f64).cos())
+        .collect();
+
+    let mut power = vec![0.0; frequencies.len()];
+    let mut block: Vec<f64> = Vec::with_capacity(CUTOFF_BLOCK);
+    let mut remaining = sample_rate as usize * CUTOFF_SECONDS as usize;
+    let mut samples: Option<SampleBuffer<f32>> = None;
+    while remaining > 0 {
+        let Ok(packet) = format.next_packet() else {
+            break;
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let Ok(decoded) = decoder.decode(&packet) else {
+            continue;
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        let buffer = match &mut samples {
+            Some(buffer) if buffer.capacity() >= decoded.capacity() * channels => buffer,
+            _ => samples.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+
+        for frame in buffer.samples().chunks(channels) {
+            let mono = frame.iter().map(|s| f64::from(*s)).sum::<f64>() / channels as f64;
+            block.push(mono * window[block.len()]);
+            if block.len() < CUTOFF_BLOCK {
+                continue;
+            }
+            add_band_power(&mut power, &block, sample_rate);
+            block.clear();
+            remaining = remaining.saturating_sub(CUTOFF_BLOCK);
+        }
+    }
+
+    let loudest = power.iter().cloned().fold(0.0, f64::max);
+    if loudest <= f64::EPSILON {
+        return None;
+    }
+    // Two steps apart, so a lowpass's transition band still reads as a drop
+    power
+        .windows(3)
+        .position(|w| w[2] < w[0] * CUTOFF_DROP)
+        .map(|i| frequencies[i + 1])
+}
+
+/// Add the energy of `block` in each band of `power` (CUTOFF_STEP_HZ wide,
+/// centered from CUTOFF_LOW_HZ up)
+fn add_band_power(power: &mut [f64], block: &[f64], sample_rate: u32) {
+    let mut spectrum: Vec<(f64, f64)> = block.iter().map(|s| (*s, 0.0)).collect();
+    fft(&mut spectrum);
+    let bin_hz = f64::from(sample_rate) / block.len() as f64;
+    let low = f64::from(CUTOFF_LOW_HZ) - f64::from(CUTOFF_STEP_HZ) / 2.0;
+    for (bin, (re, im)) in spectrum.iter().enumerate().take(block.len() / 2) {
+        let hz = bin as f64 * bin_hz;
+        if hz < low {
+            continue;
+        }
+        let band = ((hz - low) / f64::from(CUTOFF_STEP_HZ)) as usize;
+        if let Some(total) = power.get_mut(band) {
+            *total += re * re + im * im;
+        }
+    }
+}
+
+/// In-place radix-2 FFT of (re, im) pairs; the length must be a power of two
+fn fft(data: &mut [(f64, f64)]) {
+    let n = data.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let (re, im) = data[start + k + len / 2];
+                let t = (re * cos - im * sin, re * sin + im * cos);
+                let u = data[start + k];
+                data[start + k] = (u.0 + t.0, u.1 + t.1);
+                data[start + k + len / 2] = (u.0 - t.0, u.1 - t.1);
+            }
+        }
+        len <<= 1;
+    }
+}
@@ -0,0 +1,115 @@
+// src/browser.rs
+use anyhow::Result;
+use clap::ValueEnum;
+use colored::Colorize;
+use dialoguer::Select;
+use std::process::{Command, Stdio};
+
+use crate::musicbrainz::Album;
+use crate::symbols;
+
+const MUSICBRAINZ_URL: &str = "https://musicbrainz.org";
+
+/// MusicBrainz pages that can be opened for a release
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Page {
+    Release,
+    ReleaseGroup,
+    Artist,
+}
+
+impl Page {
+    fn label(self) -> &'static str {
+        match self {
+            Page::Release => "Release",
+            Page::ReleaseGroup => "Release group (all editions)",
+            Page::Artist => "Album artist",
+        }
+    }
+}
+
+/// The page's URL, if the album has the ID it needs
+pub fn page_url(album: &Album, page: Page) -> Option<String> {
+    match page {
+        Page::Release => Some(entity_url("release", &album.id)),
+        Page::ReleaseGroup => album
+            .release_group_id
+            .as_ref()
+            .map(|id| entity_url("release-group", id)),
+        Page::Artist => album
+            .album_artist_id
+            .as_ref()
+            .map(|id| entity_url("artist", id)),
+    }
+}
+
+/// `https://musicbrainz.org/<kind>/<id>`
+pub fn entity_url(kind: &str, id: &str) -> String {
+    format!("{}/{}/{}", MUSICBRAINZ_URL, kind, id)
+}
+
+/// Open the album's `page`, or warn if it doesn't have one
+pub fn open_page(album: &Album, page: Page) {
+    match page_url(album, page) {
+        Some(url) => open(&url),
+        None => println!(
+            "{} MusicBrainz doesn't list {} for this release",
+            symbols::warn().bright_yellow(),
+            match page {
+                Page::ReleaseGroup => "a release group",
+                _ => "an album artist",
+            }
+        ),
+    }
+}
+
+/// Let the user pick one of the album's pages and open it
+pub fn choose_and_open(album: &Album) -> Result<()> {
+    let pages: Vec<(Page, String)> = [Page::Release, Page::ReleaseGroup, Page::Artist]
+        .into_iter()
+        .filter_map(|page| page_url(album, page).map(|url| (page, url)))
+        .collect();
+    let labels: Vec<String> = pages
+        .iter()
+        .map(|(page, url)| format!("{} {}", page.label(), url.bright_black()))
+        .collect();
+    let choice = Select::new()
+        .with_prompt("Open which page? (Esc to go back)")
+        .items(&labels)
+        .default(0)
+        .interact_opt()?;
+    if let Some(i) = choice {
+        open(&pages[i].1);
+    }
+    println!();
+    Ok(())
+}
+
+/// Open `url` in the default browser. The URL is always printed, so it can
+/// be copied when no browser can be started (e.g. over SSH).
+pub fn open(url: &str) {
+    println!("{} Opening {}", symbols::arrow().bright_cyan(), url);
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        // The empty string is the window title `start` expects first
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    let result = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(e) = result {
+        println!(
+            "{} Could not open a browser: {}",
+            symbols::warn().bright_yellow(),
+            e
+        );
+    }
+}
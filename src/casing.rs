@@ -0,0 +1,161 @@
+// src/casing.rs
+use clap::ValueEnum;
+
+use crate::musicbrainz::Album;
+
+/// Casing style applied to a text field before writing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum CaseStyle {
+    /// Leave the value exactly as MusicBrainz has it
+    #[default]
+    Keep,
+    /// Capitalize every word except short articles, conjunctions, and prepositions
+    Title,
+    /// Capitalize only the first word
+    Sentence,
+}
+
+/// Per-field casing configuration
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CasingRules {
+    pub title: CaseStyle,
+    pub album: CaseStyle,
+    pub artist: CaseStyle,
+}
+
+const SMALL_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "en", "for", "from", "if", "in", "nor", "of", "on",
+    "or", "per", "the", "to", "up", "via", "vs", "vs.", "with",
+];
+
+impl CasingRules {
+    pub fn is_noop(&self) -> bool {
+        self.title == CaseStyle::Keep
+            && self.album == CaseStyle::Keep
+            && self.artist == CaseStyle::Keep
+    }
+
+    pub fn apply_to_album(&self, album: &mut Album) {
+        album.title = apply(self.album, &album.title);
+        album.artist = apply(self.artist, &album.artist);
+
+        for track in &mut album.tracks {
+            track.title = apply(self.title, &track.title);
+            track.artist = apply(self.artist, &track.artist);
+            for artist in &mut track.artists {
+                *artist = apply(self.artist, artist);
+            }
+            if let Some(disc_title) = &track.disc_title {
+                track.disc_title = Some(apply(self.album, disc_title));
+            }
+        }
+    }
+}
+
+pub fn apply(style: CaseStyle, text: &str) -> String {
+    // An all-caps value carries no casing information worth preserving,
+    // unless it is itself an acronym ("ABBA", "AC/DC")
+    let shouting = !keeps_own_casing(text)
+        && text.chars().any(|c| c.is_alphabetic())
+        && text
+            .chars()
+            .filter(|c| c.is_alphabetic())
+            .all(|c| c.is_uppercase());
+    let text = if shouting && style != CaseStyle::Keep {
+        text.to_lowercase()
+    } else {
+        text.to_string()
+    };
+
+    match style {
+        CaseStyle::Keep => text,
+        CaseStyle::Title => title_case(&text),
+        CaseStyle::Sentence => sentence_case(&text),
+    }
+}
+
+fn title_case(text: &str) -> String {
+    let words: Vec<&str> = text.split(' ').collect();
+    let last = words.len().saturating_sub(1);
+    let mut capitalize_next = true;
+
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            let bare = word.trim_start_matches(['(', '[', '"', '\'']);
+            let opens_group = bare.len() != word.len();
+            let force = capitalize_next || opens_group || i == last;
+
+            // Words ending a clause force capitalization of the next word
+            capitalize_next = word.ends_with([':', '.', '?', '!']) || *word == "-";
+
+            if keeps_own_casing(bare) {
+                word.to_string()
+            } else if !force && SMALL_WORDS.contains(&bare.to_lowercase().as_str()) {
+                word.to_lowercase()
+            } else {
+                capitalize(word)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn sentence_case(text: &str) -> String {
+    let mut first = true;
+
+    text.split(' ')
+        .map(|word| {
+            let bare = word.trim_start_matches(['(', '[', '"', '\'']);
+            let result = if keeps_own_casing(bare) || bare == "I" || bare.starts_with("I'") {
+                word.to_string()
+            } else if first && !bare.is_empty() {
+                capitalize(word)
+            } else {
+                word.to_lowercase()
+            };
+            if !bare.is_empty() {
+                first = false;
+            }
+            result
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Acronyms ("ABBA", "DJ") and intentionally mixed-case words ("McCartney", "iTunes")
+fn keeps_own_casing(word: &str) -> bool {
+    let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() < 2 {
+        return false;
+    }
+    let all_upper = letters.iter().all(|c| c.is_uppercase());
+    let inner_upper = letters[1..].iter().any(|c| c.is_uppercase());
+
+    (all_upper && letters.len() <= 4) || (inner_upper && !all_upper)
+}
+
+/// Uppercase the first letter, lowercase the rest (skipping leading punctuation)
+fn capitalize(word: &str) -> String {
+    let mut result = String::with_capacity(word.len());
+    let mut seen_letter = false;
+    let mut after_hyphen = false;
+
+    for c in word.chars() {
+        if c.is_alphabetic() {
+            if !seen_letter || after_hyphen {
+                result.extend(c.to_uppercase());
+            } else {
+                result.extend(c.to_lowercase());
+            }
+            seen_letter = true;
+            after_hyphen = false;
+        } else {
+            after_hyphen = c == '-';
+            result.push(c);
+        }
+    }
+
+    result
+}
@@ -0,0 +1,156 @@
+// src/chapters.rs
+use id3::frame::{Chapter, TableOfContents};
+use id3::{Frame, TagLike};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::musicbrainz::{Album, Track};
+use crate::profile::TagSettings;
+use crate::tagger::{self, TagData};
+
+/// How far, as a share of their length, a run of tracks may be from the
+/// file's length and still be taken to be what it holds, allowing for
+/// trimmed silence and slightly fast turntables
+const MAX_DEVIATION: f64 = 0.08;
+
+/// Element ID of the table of contents
+const TOC_ID: &str = "toc";
+
+/// One track's place in a single-file rip
+#[derive(Debug, Clone)]
+pub struct ChapterMark {
+    pub title: String,
+    pub artist: String,
+    pub start_ms: u32,
+    pub end_ms: u32,
+}
+
+/// The tracks a file of `file_ms` looks like a rip of in one piece (the whole
+/// tracklist, a vinyl side, a continuous mix) rather than a single track: the
+/// run of two or more consecutive tracks whose lengths add up closest to the
+/// file's, if close enough. Longer runs win ties.
+pub fn album_rip_tracks(file_ms: u32, tracks: &[Track]) -> Option<Range<usize>> {
+    let mut best: Option<(u32, Range<usize>)> = None;
+    for start in 0..tracks.len() {
+        let mut total = 0;
+        for (end, track) in tracks.iter().enumerate().skip(start) {
+            let Some(length) = track.length else {
+                break;
+            };
+            total += length;
+            let deviation = total.abs_diff(file_ms);
+            if end == start || f64::from(deviation) > f64::from(total) * MAX_DEVIATION {
+                continue;
+            }
+            let closer = best.as_ref().is_none_or(|(best_deviation, range)| {
+                deviation < *best_deviation
+                    || (deviation == *best_deviation && end + 1 - start > range.len())
+            });
+            if closer {
+                best = Some((deviation, start..end + 1));
+            }
+        }
+    }
+    best.map(|(_, range)| range)
+}
+
+/// Chapters laid back to back from the track lengths, starting with the first
+/// of `tracks`; the last one runs to the end of the file. `None` if a track
+/// has no length on MusicBrainz.
+pub fn plan(tracks: &[Track], file_ms: u32) -> Option<Vec<ChapterMark>> {
+    let mut chapters = Vec::with_capacity(tracks.len());
+    let mut start_ms = 0;
+    for track in tracks {
+        let end_ms = start_ms + track.length?;
+        chapters.push(ChapterMark {
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            start_ms,
+            end_ms,
+        });
+        start_ms = end_ms;
+    }
+    // Chapters past the end of the file can't be played; the last one that
+    // starts in it ends with the file
+    chapters.retain(|c| c.start_ms < file_ms);
+    if let Some(last) = chapters.last_mut() {
+        last.end_ms = file_ms;
+    }
+    Some(chapters)
+}
+
+/// Tag `file_path` as the whole album, with a CHAP frame per chapter and a
+/// CTOC listing them in order. Chapters already in the file are replaced.
+pub fn write_album_rip(
+    file_path: &Path,
+    album: &Album,
+    chapters: &[ChapterMark],
+    cover_art: Option<&[u8]>,
+    settings: &TagSettings,
+) -> Result<()> {
+    let data = TagData {
+        title: album.title.clone(),
+        artist: album.artist.clone(),
+        artists: vec![album.artist.clone()],
+        album: album.title.clone(),
+        album_artist: album.artist.clone(),
+        album_artist_sort: album
+            .album_artist_sort
+            .clone()
+            .filter(|_| settings.write_album_artist_sort),
+        track_number: 1,
+        total_tracks: 1,
+        disc: None,
+        disc_subtitle: None,
+        genres: album.genres.clone(),
+        date: album.date.clone(),
+        compilation: settings.write_compilation && album.is_compilation,
+        album_id: album.id.clone(),
+        track_id: String::new(),
+        recording_id: String::new(),
+        album_artist_id: album.album_artist_id.clone(),
+    };
+    tagger::writer_for(file_path, settings)?.write(
+        file_path,
+        &data,
+        tagger::Artwork::cover(cover_art),
+    )?;
+
+    let mut tag = tagger::read_tag(file_path).map_err(|source| Error::TagWriteError {
+        path: file_path.to_path_buf(),
+        source,
+    })?;
+    // From an earlier run that tagged the file as a single track
+    tag.remove_extended_text(Some("MusicBrainz Release Track Id"), None);
+    tag.remove_extended_text(Some("MusicBrainz Recording Id"), None);
+    tag.remove("CHAP");
+    tag.remove("CTOC");
+    let ids: Vec<String> = (1..=chapters.len()).map(|i| format!("ch{}", i)).collect();
+    for (id, chapter) in ids.iter().zip(chapters) {
+        tag.add_frame(Chapter {
+            element_id: id.clone(),
+            start_time: chapter.start_ms,
+            end_time: chapter.end_ms,
+            // No byte offsets; players use the times
+            start_offset: u32::MAX,
+            end_offset: u32::MAX,
+            frames: vec![
+                Frame::text("TIT2", &chapter.title),
+                Frame::text("TPE1", &chapter.artist),
+            ],
+        });
+    }
+    tag.add_frame(TableOfContents {
+        element_id: TOC_ID.to_string(),
+        top_level: true,
+        ordered: true,
+        elements: ids,
+        frames: vec![Frame::text("TIT2", &album.title)],
+    });
+    tag.write_to_path(file_path, settings.version)
+        .map_err(|source| Error::TagWriteError {
+            path: file_path.to_path_buf(),
+            source,
+        })
+}
@@ -0,0 +1,139 @@
+// src/check.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+use crate::audio;
+use crate::events::{Event, EventSink};
+use crate::maintenance::{collect_tree, display_name};
+use crate::symbols;
+use crate::tagger::{read_tag, stored_checksum, write_checksum};
+
+/// Store audio checksums after tagging. Files that already carry one keep
+/// it; if their audio no longer matches it, that is reported, since tagging
+/// never touches the audio.
+pub fn store(files: &[PathBuf], events: &dyn EventSink) -> Result<()> {
+    let mut stored = 0;
+    let pb = crate::progress::bar(files.len(), "Hashing audio");
+    for file in files {
+        pb.inc(1);
+        let checksum = audio::stream_hash(file)?;
+        match read_tag(file).ok().as_ref().and_then(stored_checksum) {
+            Some(existing) if existing == checksum => {}
+            Some(_) => {
+                let message = format!(
+                    "{}: audio no longer matches its stored checksum",
+                    file.display()
+                );
+                pb.suspend(|| println!("{} {}", symbols::warn().bright_yellow(), message));
+                events.emit(Event::Warning { message });
+            }
+            None => {
+                write_checksum(file, &checksum)
+                    .with_context(|| format!("Failed to store checksum in {}", file.display()))?;
+                stored += 1;
+            }
+        }
+    }
+    pb.finish_and_clear();
+    if stored > 0 {
+        println!(
+            "{} Stored audio checksums for {} file(s)",
+            symbols::ok().bright_green(),
+            stored
+        );
+    }
+    Ok(())
+}
+
+/// Re-hash every MP3 under `path` and compare against the stored checksums.
+/// With `add`, files without one get it stored. Fails when any file's audio
+/// has changed.
+pub fn run(path: &Path, add: bool) -> Result<()> {
+    let files = collect_tree(path)?;
+    if files.is_empty() {
+        anyhow::bail!("No MP3 files found in {}", path.display());
+    }
+
+    let mut changed = Vec::new();
+    let mut unreadable = Vec::new();
+    let mut missing = Vec::new();
+    let mut verified = 0;
+    let pb = crate::progress::bar(files.len(), "Verifying audio");
+    for file in &files {
+        pb.inc(1);
+        let checksum = match audio::stream_hash(file) {
+            Ok(checksum) => checksum,
+            Err(e) => {
+                unreadable.push((file, e.to_string()));
+                continue;
+            }
+        };
+        match read_tag(file).ok().as_ref().and_then(stored_checksum) {
+            Some(stored) if stored == checksum => verified += 1,
+            Some(_) => changed.push(file),
+            None if add => {
+                write_checksum(file, &checksum)
+                    .with_context(|| format!("Failed to store checksum in {}", file.display()))?;
+                missing.push(file);
+            }
+            None => missing.push(file),
+        }
+    }
+    pb.finish_and_clear();
+
+    for file in &changed {
+        println!(
+            "  {} {} {}",
+            symbols::warn().bright_red(),
+            display_name(file).bright_white(),
+            "(audio changed)".bright_red()
+        );
+    }
+    for (file, error) in &unreadable {
+        println!(
+            "  {} {} {}",
+            symbols::warn().bright_yellow(),
+            display_name(file).bright_white(),
+            format!("({})", error).bright_black()
+        );
+    }
+    if !changed.is_empty() || !unreadable.is_empty() {
+        println!();
+    }
+
+    println!(
+        "{} {} of {} file(s) verified",
+        symbols::ok().bright_green(),
+        verified,
+        files.len()
+    );
+    if !missing.is_empty() {
+        let note = if add {
+            format!(
+                "Stored checksums for {} file(s) that had none",
+                missing.len()
+            )
+        } else {
+            format!(
+                "{} file(s) have no checksum (add them with --add)",
+                missing.len()
+            )
+        };
+        println!("{} {}", symbols::warn().bright_yellow(), note);
+    }
+    if !unreadable.is_empty() {
+        println!(
+            "{} {} file(s) could not be read",
+            symbols::warn().bright_yellow(),
+            unreadable.len()
+        );
+    }
+    if !changed.is_empty() {
+        anyhow::bail!(
+            "{} file(s) no longer match their stored checksum",
+            changed.len()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+// src/config.rs
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::art_picker::SmallArt;
+use crate::musicbrainz::{MusicBrainzClient, MusicBrainzClientBuilder};
+use crate::profile::{Profile, TagSettings};
+
+const CONFIG_FILE: &str = "config.toml";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Preferences saved by `setup`. Command-line flags win over these.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Email or URL sent with MusicBrainz requests, as MusicBrainz asks apps to
+    pub contact: Option<String>,
+    /// Largest cover art to download, in pixels per side, unless --profile says otherwise
+    pub art_size: Option<u32>,
+    /// Smallest cover art worth embedding, in pixels per side
+    pub min_art_size: Option<u32>,
+    /// What to do with art below `min_art_size`
+    pub small_art: SmallArt,
+    /// Path template for --organize
+    pub template: Option<String>,
+    /// Library root for --organize given without one
+    pub library: Option<PathBuf>,
+    /// Release countries to list first when choosing a release, most preferred first
+    pub countries: Vec<String>,
+    /// Media formats to list first when choosing a release, most preferred first
+    pub formats: Vec<String>,
+}
+
+/// `musictagger_rs/config.toml` in the user's config directory
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("musictagger_rs").join(CONFIG_FILE))
+}
+
+/// The saved config, or `None` if there is none yet
+pub fn load() -> Result<Option<Config>> {
+    let Some(path) = path() else {
+        return Ok(None);
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let config = toml::from_str(&contents).with_context(|| {
+        format!(
+            "Invalid config {}; fix it or run `musictagger_rs setup` to replace it",
+            path.display()
+        )
+    })?;
+    Ok(Some(config))
+}
+
+pub fn save(config: &Config) -> Result<PathBuf> {
+    let path = path().context("Could not determine the config directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let contents = toml::to_string(config).context("Failed to serialize config")?;
+    std::fs::write(&path, contents)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Make `config` the one `get` returns for the rest of the run
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// The config for this run; empty until `init`
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// A MusicBrainz client that sends the configured contact
+pub fn musicbrainz_client() -> MusicBrainzClient {
+    musicbrainz_builder()
+        .build()
+        .expect("Failed to create HTTP client")
+}
+
+/// Where downloaded cover art is kept between runs
+pub fn art_cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("musictagger_rs").join("art"))
+}
+
+/// Builder for `musicbrainz_client`, for callers that need more settings
+pub fn musicbrainz_builder() -> MusicBrainzClientBuilder {
+    let mut builder = MusicBrainzClient::builder();
+    if let Some(dir) = art_cache_dir() {
+        builder = builder.art_cache(dir);
+    }
+    match &get().contact {
+        Some(contact) => builder.contact(contact),
+        None => builder,
+    }
+}
+
+/// Tag settings for `profile`, or the defaults with the configured art size
+pub fn tag_settings(profile: Option<Profile>) -> TagSettings {
+    match profile {
+        Some(profile) => profile.settings(),
+        None => TagSettings {
+            max_art_size: get()
+                .art_size
+                .unwrap_or(TagSettings::default().max_art_size),
+            ..TagSettings::default()
+        },
+    }
+}
@@ -0,0 +1,325 @@
+// src/conflicts.rs
+use anyhow::Result;
+use clap::ValueEnum;
+use colored::Colorize;
+use dialoguer::{Input, Select};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::dedupe::normalize;
+use crate::matcher::FileMatch;
+use crate::musicbrainz::{Album, Track};
+use crate::symbols;
+use crate::tagger::read_existing_tags;
+
+/// What to do where a file's existing tag differs from MusicBrainz
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConflictMode {
+    /// Write the MusicBrainz value
+    Overwrite,
+    /// Ask for each field: keep yours, take MusicBrainz, or edit
+    Ask,
+    /// Keep the existing value
+    Keep,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Genre,
+}
+
+impl Field {
+    fn name(self) -> &'static str {
+        match self {
+            Field::Title => "title",
+            Field::Artist => "artist",
+            Field::Album => "album",
+            Field::AlbumArtist => "album artist",
+            Field::Genre => "genre",
+        }
+    }
+
+    /// Album fields are written to every file, so one answer covers them all
+    fn is_album_wide(self) -> bool {
+        matches!(self, Field::Album | Field::AlbumArtist | Field::Genre)
+    }
+}
+
+/// An existing value that differs from MusicBrainz by more than case,
+/// accents, or punctuation
+pub struct Conflict {
+    field: Field,
+    /// Indexes into the matches holding `yours`
+    files: Vec<usize>,
+    yours: String,
+    theirs: String,
+}
+
+/// Album-wide values kept or edited for some files only, by the ID of the
+/// track each file was matched to. The release itself is left as it is, so
+/// one file's answer doesn't end up in every file.
+#[derive(Debug, Default)]
+pub struct AlbumOverrides(HashMap<String, AlbumValues>);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct AlbumValues {
+    title: Option<String>,
+    artist: Option<String>,
+    genres: Option<Vec<String>>,
+}
+
+impl AlbumOverrides {
+    /// The release as it gets written to the file holding `track`
+    pub fn album_for<'a>(&self, album: &'a Album, track: &Track) -> Cow<'a, Album> {
+        let Some(values) = self.0.get(&track.id) else {
+            return Cow::Borrowed(album);
+        };
+        let mut album = album.clone();
+        if let Some(title) = &values.title {
+            album.title = title.clone();
+        }
+        if let Some(artist) = &values.artist {
+            album.artist = artist.clone();
+        }
+        if let Some(genres) = &values.genres {
+            album.genres = genres.clone();
+        }
+        Cow::Owned(album)
+    }
+
+    /// Whether the two tracks' files get the same album values
+    pub fn same(&self, a: &Track, b: &Track) -> bool {
+        self.0.get(&a.id) == self.0.get(&b.id)
+    }
+}
+
+/// Compare each matched file's existing title, artist, album, album artist,
+/// and genre against what would be written
+pub fn find(matches: &[FileMatch], album: &Album) -> Vec<Conflict> {
+    let mut conflicts: Vec<Conflict> = Vec::new();
+    let album_genres = album.genres.join("; ");
+    for (i, m) in matches.iter().enumerate() {
+        let existing = read_existing_tags(&m.file_path);
+        let fields = [
+            (Field::Title, existing.title, m.track.title.clone()),
+            (Field::Artist, existing.artist, m.track.artist.clone()),
+            (Field::Album, existing.album, album.title.clone()),
+            (
+                Field::AlbumArtist,
+                existing.album_artist,
+                album.artist.clone(),
+            ),
+            (Field::Genre, existing.genre, album_genres.clone()),
+        ];
+        for (field, yours, theirs) in fields {
+            let Some(yours) = yours.filter(|v| !v.trim().is_empty()) else {
+                continue;
+            };
+            // Nothing gets written over a field MusicBrainz has no value for
+            if theirs.is_empty() || same_value(field, &yours, &theirs, m) {
+                continue;
+            }
+            let shared = field.is_album_wide().then(|| {
+                conflicts
+                    .iter_mut()
+                    .find(|c| c.field == field && c.yours == yours)
+            });
+            match shared.flatten() {
+                Some(conflict) => conflict.files.push(i),
+                None => conflicts.push(Conflict {
+                    field,
+                    files: vec![i],
+                    yours,
+                    theirs,
+                }),
+            }
+        }
+    }
+    conflicts
+}
+
+fn same_value(field: Field, yours: &str, theirs: &str, file_match: &FileMatch) -> bool {
+    match field {
+        // "A; B" or "A/B" from a tag that stores several artists
+        Field::Artist => {
+            normalize(yours) == normalize(theirs)
+                || normalize(yours) == normalize(&file_match.track.artists.concat())
+        }
+        Field::Genre => {
+            let set = |value: &str| {
+                let mut genres: Vec<String> = value.split("; ").map(normalize).collect();
+                genres.sort();
+                genres
+            };
+            set(yours) == set(theirs)
+        }
+        _ => normalize(yours) == normalize(theirs),
+    }
+}
+
+/// List the conflicts without resolving them, for dry runs
+pub fn print(conflicts: &[Conflict], matches: &[FileMatch]) {
+    if conflicts.is_empty() {
+        return;
+    }
+    println!(
+        "{} {} existing value(s) differ from MusicBrainz:",
+        symbols::warn().bright_yellow(),
+        conflicts.len()
+    );
+    for conflict in conflicts {
+        println!(
+            "  {} {}: {} {} {}",
+            location(conflict, matches).bright_white(),
+            conflict.field.name(),
+            conflict.yours.bright_cyan(),
+            symbols::arrow(),
+            conflict.theirs
+        );
+    }
+    println!();
+}
+
+/// Settle every conflict by `mode`. Titles and artists are changed on the
+/// matched tracks; album-wide values are returned as overrides for just the
+/// files that had them.
+pub fn resolve(
+    mode: ConflictMode,
+    conflicts: &[Conflict],
+    matches: &mut [FileMatch],
+) -> Result<AlbumOverrides> {
+    let mut overrides = AlbumOverrides::default();
+    if conflicts.is_empty() || mode == ConflictMode::Overwrite {
+        return Ok(overrides);
+    }
+
+    let mut rest = (mode == ConflictMode::Keep).then_some(Choice::Yours);
+    if mode == ConflictMode::Ask {
+        println!(
+            "{} {} existing value(s) differ from MusicBrainz",
+            symbols::warn().bright_yellow(),
+            conflicts.len()
+        );
+        println!();
+    }
+
+    let mut kept = 0;
+    for conflict in conflicts {
+        let choice = match rest {
+            Some(choice) => choice,
+            None => {
+                let choice = ask(conflict, matches)?;
+                if matches!(choice, Choice::AllYours | Choice::AllTheirs) {
+                    rest = Some(choice);
+                }
+                choice
+            }
+        };
+        let value = match choice {
+            Choice::Yours | Choice::AllYours => conflict.yours.clone(),
+            Choice::Theirs | Choice::AllTheirs => continue,
+            Choice::Edit => Input::new()
+                .with_prompt("  Value")
+                .default(conflict.yours.clone())
+                .interact_text()?,
+        };
+        apply(conflict, value, matches, &mut overrides);
+        kept += 1;
+    }
+
+    println!(
+        "{} Kept or edited {} of {} differing value(s)",
+        symbols::ok().bright_green(),
+        kept,
+        conflicts.len()
+    );
+    Ok(overrides)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Choice {
+    Yours,
+    Theirs,
+    Edit,
+    AllYours,
+    AllTheirs,
+}
+
+fn ask(conflict: &Conflict, matches: &[FileMatch]) -> Result<Choice> {
+    println!(
+        "{} {}",
+        location(conflict, matches).bright_white(),
+        format!("({})", conflict.field.name()).bright_black()
+    );
+    println!("  Yours:       {}", conflict.yours.bright_cyan());
+    println!("  MusicBrainz: {}", conflict.theirs.bright_white());
+    let choice = Select::new()
+        .items(&[
+            "Keep mine",
+            "Take MusicBrainz",
+            "Edit",
+            "Keep mine for all remaining",
+            "Take MusicBrainz for all remaining",
+        ])
+        .default(0)
+        .interact()?;
+    println!();
+    Ok(match choice {
+        0 => Choice::Yours,
+        1 => Choice::Theirs,
+        2 => Choice::Edit,
+        3 => Choice::AllYours,
+        _ => Choice::AllTheirs,
+    })
+}
+
+fn apply(
+    conflict: &Conflict,
+    value: String,
+    matches: &mut [FileMatch],
+    overrides: &mut AlbumOverrides,
+) {
+    for &i in &conflict.files {
+        let track = &mut matches[i].track;
+        match conflict.field {
+            Field::Title => track.title = value.clone(),
+            Field::Artist => {
+                // A kept artist replaces the individual MusicBrainz credits
+                track.artists = vec![value.clone()];
+                track.artist = value.clone();
+            }
+            Field::Album => {
+                overrides.0.entry(track.id.clone()).or_default().title = Some(value.clone())
+            }
+            Field::AlbumArtist => {
+                overrides.0.entry(track.id.clone()).or_default().artist = Some(value.clone())
+            }
+            Field::Genre => {
+                overrides.0.entry(track.id.clone()).or_default().genres = Some(
+                    value
+                        .split("; ")
+                        .map(|g| g.trim().to_string())
+                        .filter(|g| !g.is_empty())
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+/// The file a conflict is in, or how many files share it
+fn location(conflict: &Conflict, matches: &[FileMatch]) -> String {
+    match conflict.files.as_slice() {
+        [i] => matches[*i]
+            .file_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        files => format!("{} files", files.len()),
+    }
+}
@@ -0,0 +1,135 @@
+// src/credits.rs
+use clap::ValueEnum;
+
+use crate::musicbrainz::{Album, Credit, Track};
+
+/// Where featured artists end up when tags are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum FeatPlacement {
+    /// Leave artist and title as MusicBrainz has them
+    #[default]
+    Keep,
+    /// Main artists in the artist field, "(feat. X)" appended to the title
+    Title,
+    /// Full credit ("A feat. X") in the artist field, "(feat. X)" removed from the title
+    Artist,
+}
+
+const FEAT_MARKERS: &[&str] = &["feat.", "feat ", "ft.", "featuring"];
+
+pub fn apply_to_album(placement: FeatPlacement, album: &mut Album) {
+    if placement == FeatPlacement::Keep {
+        return;
+    }
+
+    for track in &mut album.tracks {
+        match placement {
+            FeatPlacement::Keep => {}
+            FeatPlacement::Title => move_to_title(track),
+            FeatPlacement::Artist => move_to_artist(track),
+        }
+    }
+}
+
+fn move_to_title(track: &mut Track) {
+    let (main, featured) = split_credits(&track.credits);
+    if featured.is_empty() {
+        return;
+    }
+
+    track.artist = join_credits(main);
+    track.artists = main.iter().map(|c| c.name.clone()).collect();
+
+    if find_feat_suffix(&track.title).is_none() {
+        let names: Vec<&str> = featured.iter().map(|c| c.name.as_str()).collect();
+        track.title = format!("{} (feat. {})", track.title, join_names(&names));
+    }
+}
+
+fn move_to_artist(track: &mut Track) {
+    let (main, featured) = split_credits(&track.credits);
+
+    if let Some((range, names)) = find_feat_suffix(&track.title) {
+        // Title carries the credit ("Song (feat. X)") - move it over unless the
+        // artist credit already names the featured artists
+        let base = format!(
+            "{}{}",
+            &track.title[..range.start],
+            &track.title[range.end..]
+        )
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+        if featured.is_empty() {
+            track.artist = format!("{} feat. {}", join_credits(main), names);
+            track.artists.push(names);
+        } else {
+            track.artist = join_credits(&track.credits);
+        }
+        track.title = base;
+    } else if !featured.is_empty() {
+        track.artist = join_credits(&track.credits);
+    }
+}
+
+/// Split the credit list at the first "feat."-style join phrase
+fn split_credits(credits: &[Credit]) -> (&[Credit], &[Credit]) {
+    let split = credits
+        .iter()
+        .position(|c| is_feat_marker(&c.join_phrase))
+        .map(|idx| idx + 1)
+        .unwrap_or(credits.len());
+
+    credits.split_at(split)
+}
+
+/// Render credits as displayed, dropping the trailing join phrase
+fn join_credits(credits: &[Credit]) -> String {
+    let mut result = String::new();
+    for (i, credit) in credits.iter().enumerate() {
+        result.push_str(&credit.name);
+        if i + 1 < credits.len() {
+            result.push_str(&credit.join_phrase);
+        }
+    }
+    result
+}
+
+fn join_names(names: &[&str]) -> String {
+    match names {
+        [] => String::new(),
+        [only] => only.to_string(),
+        [rest @ .., last] => format!("{} & {}", rest.join(", "), last),
+    }
+}
+
+fn is_feat_marker(join_phrase: &str) -> bool {
+    let lower = join_phrase.to_lowercase();
+    FEAT_MARKERS.iter().any(|m| lower.contains(m.trim_end()))
+}
+
+/// Find a "(feat. X)" / "[ft. X]" group; returns its byte range and the featured names
+fn find_feat_suffix(title: &str) -> Option<(std::ops::Range<usize>, String)> {
+    for (start, open) in title.char_indices().filter(|(_, c)| *c == '(' || *c == '[') {
+        let inner = &title[start + 1..];
+        let marker = FEAT_MARKERS.iter().find(|m| {
+            inner
+                .get(..m.len())
+                .map(|prefix| prefix.eq_ignore_ascii_case(m))
+                .unwrap_or(false)
+        });
+
+        if let Some(marker) = marker {
+            let close = if open == '(' { ')' } else { ']' };
+            let names_start = start + 1 + marker.len();
+            let (names_end, group_end) = match title[names_start..].find(close) {
+                Some(i) => (names_start + i, names_start + i + 1),
+                None => (title.len(), title.len()),
+            };
+            let names = title[names_start..names_end].trim().to_string();
+            return Some((start..group_end, names));
+        }
+    }
+
+    None
+}
@@ -0,0 +1,368 @@
+// src/cue.rs
+use std::path::{Path, PathBuf};
+
+use crate::chapters::ChapterMark;
+use crate::error::{Error, Result};
+use crate::events::{Event, EventSink};
+use crate::matcher::{FileMatch, MatchReport};
+use crate::musicbrainz::{Album, Track};
+
+/// CUE times count frames of 1/75 s
+const FRAMES_PER_SECOND: u32 = 75;
+
+/// A file this close to its track's length agrees with the pairing
+const DURATION_TOLERANCE_MS: u32 = 3000;
+
+/// Confidence of a pairing the sheet's title or the file's length disagrees
+/// with while the other agrees, and of one nothing agrees with
+const PARTLY_CONFIRMED: f64 = 0.75;
+const UNCONFIRMED: f64 = 0.4;
+
+/// The parts of a CUE sheet that say which audio goes where
+#[derive(Debug, Clone, Default)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub files: Vec<CueFile>,
+}
+
+/// A `FILE` entry and the tracks in it
+#[derive(Debug, Clone)]
+pub struct CueFile {
+    /// As written in the sheet, relative to the sheet's folder
+    pub name: String,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// Where `INDEX 01` puts the track in its file
+    pub start_ms: u32,
+}
+
+/// Parse a CUE sheet, ignoring commands that don't describe the tracklist. A
+/// track without a readable `INDEX 01` is an error, since where it starts
+/// can't be known.
+pub fn parse(text: &str) -> Result<CueSheet> {
+    let mut sheet = CueSheet::default();
+    // The track still waiting for its INDEX 01
+    let mut unindexed: Option<u32> = None;
+    let missing_index =
+        |number: u32| Error::InvalidCue(format!("track {} has no INDEX 01 time", number));
+    for line in text.lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                if let Some(number) = unindexed {
+                    return Err(missing_index(number));
+                }
+                sheet.files.push(CueFile {
+                    name: file_name(rest),
+                    tracks: Vec::new(),
+                })
+            }
+            "TRACK" => {
+                if let Some(number) = unindexed {
+                    return Err(missing_index(number));
+                }
+                let number = rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(0);
+                if let Some(file) = sheet.files.last_mut() {
+                    file.tracks.push(CueTrack {
+                        number,
+                        title: None,
+                        performer: None,
+                        start_ms: 0,
+                    });
+                    unindexed = Some(number);
+                }
+            }
+            "TITLE" | "PERFORMER" => {
+                let value = unquote(rest);
+                let is_title = command.eq_ignore_ascii_case("TITLE");
+                match sheet.files.last_mut().and_then(|f| f.tracks.last_mut()) {
+                    Some(track) if is_title => track.title = Some(value),
+                    Some(track) => track.performer = Some(value),
+                    None if is_title => sheet.title = Some(value),
+                    None => sheet.performer = Some(value),
+                }
+            }
+            "INDEX" => {
+                let mut parts = rest.split_whitespace();
+                if parts.next() != Some("01") {
+                    continue;
+                }
+                let start_ms = parts.next().and_then(parse_time);
+                if let (Some(start_ms), Some(track)) = (
+                    start_ms,
+                    sheet.files.last_mut().and_then(|f| f.tracks.last_mut()),
+                ) {
+                    track.start_ms = start_ms;
+                    unindexed = None;
+                }
+            }
+            _ => {}
+        }
+    }
+    match unindexed {
+        Some(number) => Err(missing_index(number)),
+        None => Ok(sheet),
+    }
+}
+
+/// The first CUE sheet in `dir`, by name, with its path
+pub fn find(dir: &Path) -> Result<Option<(PathBuf, CueSheet)>> {
+    let mut sheets: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|source| Error::Io {
+            path: dir.to_path_buf(),
+            source,
+        })?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("cue"))
+        })
+        .collect();
+    sheets.sort();
+    let Some(path) = sheets.into_iter().next() else {
+        return Ok(None);
+    };
+    let bytes = std::fs::read(&path).map_err(|source| Error::Io {
+        path: path.clone(),
+        source,
+    })?;
+    // Older rippers write Latin-1; the tracklist structure survives either way
+    let sheet = parse(&String::from_utf8_lossy(&bytes)).map_err(|e| match e {
+        Error::InvalidCue(reason) => Error::InvalidCue(format!("{}: {}", path.display(), reason)),
+        e => e,
+    })?;
+    Ok(Some((path, sheet)))
+}
+
+impl CueSheet {
+    /// The sheet's entry for `file`, comparing names without extension, since
+    /// sheets often still name the lossless source
+    pub fn entry_for(&self, file: &Path) -> Option<&CueFile> {
+        self.files.iter().find(|entry| same_stem(&entry.name, file))
+    }
+}
+
+/// Match files to tracks in the sheet's order, when it lists one track per
+/// file. Every file the sheet names is paired with the release track at the
+/// same place; the rest are left unmatched. A pairing that neither the
+/// sheet's title nor the file's length bears out gets a lower confidence.
+/// `None` if the sheet doesn't describe per-track files or names none of
+/// `files`.
+pub fn match_by_cue(
+    sheet: &CueSheet,
+    files: &[PathBuf],
+    album: &Album,
+    events: &dyn EventSink,
+) -> Option<MatchReport> {
+    if sheet.files.len() < 2 || sheet.files.iter().any(|f| f.tracks.len() != 1) {
+        return None;
+    }
+    let in_order = sheet.files.len() == album.tracks.len();
+
+    let mut report = MatchReport::default();
+    let mut used_tracks: Vec<usize> = Vec::new();
+    for (i, entry) in sheet.files.iter().enumerate() {
+        let Some(file) = files.iter().find(|f| same_stem(&entry.name, f)) else {
+            continue;
+        };
+        let number = entry.tracks[0].number;
+        let index = if in_order {
+            Some(i)
+        } else {
+            track_at(&album.tracks, number)
+        };
+        let Some(index) = index.filter(|i| !used_tracks.contains(i)) else {
+            continue;
+        };
+        used_tracks.push(index);
+        let track = &album.tracks[index];
+        let confidence = confirm(&entry.tracks[0], file, track);
+        events.emit(Event::FileMatched {
+            file: file.clone(),
+            disc: track.disc_number,
+            track: track.position,
+            title: track.title.clone(),
+            confidence,
+        });
+        report.matches.push(FileMatch {
+            file_path: file.clone(),
+            track: track.clone(),
+            confidence,
+            duration_resolved: false,
+        });
+    }
+    if report.matches.is_empty() {
+        return None;
+    }
+
+    report
+        .matches
+        .sort_by_key(|m| (m.track.disc_number, m.track.position));
+    report.unmatched_files = files
+        .iter()
+        .filter(|f| !report.matches.iter().any(|m| &m.file_path == *f))
+        .cloned()
+        .collect();
+    report.unmatched_tracks = album
+        .tracks
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !used_tracks.contains(i))
+        .map(|(_, track)| track.clone())
+        .collect();
+    Some(report)
+}
+
+/// How far the sheet's title and the file's length bear out pairing `file`
+/// with `track`. What can't be checked counts for nothing either way.
+fn confirm(cue_track: &CueTrack, file: &Path, track: &Track) -> f64 {
+    let key = |title: &str| -> String {
+        title
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    let title = cue_track.title.as_deref().map(|title| {
+        let (sheet, release) = (key(title), key(&track.title));
+        !sheet.is_empty() && (sheet.contains(&release) || release.contains(&sheet))
+    });
+    let length = track.length.and_then(|length| {
+        let file_ms = crate::audio::probe(file)?.duration_ms;
+        Some(file_ms.abs_diff(length) <= DURATION_TOLERANCE_MS)
+    });
+    let checks = [title, length];
+    if !checks.contains(&Some(false)) {
+        1.0
+    } else if checks.contains(&Some(true)) {
+        PARTLY_CONFIRMED
+    } else {
+        UNCONFIRMED
+    }
+}
+
+/// Chapters at the sheet's track boundaries in a single-file rip, titled
+/// from the release when it has the same number of tracks
+pub fn chapters(entry: &CueFile, tracks: &[Track], file_ms: u32) -> Vec<ChapterMark> {
+    let same_tracklist = entry.tracks.len() == tracks.len();
+    entry
+        .tracks
+        .iter()
+        .enumerate()
+        .map(|(i, cue_track)| {
+            let release_track = same_tracklist.then(|| &tracks[i]);
+            let end_ms = entry
+                .tracks
+                .get(i + 1)
+                .map(|next| next.start_ms)
+                .unwrap_or(file_ms);
+            ChapterMark {
+                title: release_track
+                    .map(|t| t.title.clone())
+                    .or_else(|| cue_track.title.clone())
+                    .unwrap_or_else(|| format!("Track {}", cue_track.number)),
+                artist: release_track
+                    .map(|t| t.artist.clone())
+                    .or_else(|| cue_track.performer.clone())
+                    .unwrap_or_default(),
+                start_ms: cue_track.start_ms,
+                end_ms,
+            }
+        })
+        .collect()
+}
+
+/// A CUE sheet for `file_name` with a track at each chapter, as written by `--write-cue`
+pub fn render(album: &Album, file_name: &str, chapters: &[ChapterMark]) -> String {
+    let mut lines = vec![format!("REM MUSICBRAINZ_ALBUMID {}", album.id)];
+    if let Some(date) = &album.date {
+        lines.push(format!("REM DATE {}", date));
+    }
+    lines.push(format!("PERFORMER {}", quote(&album.artist)));
+    lines.push(format!("TITLE {}", quote(&album.title)));
+    lines.push(format!("FILE {} MP3", quote(file_name)));
+    for (i, chapter) in chapters.iter().enumerate() {
+        lines.push(format!("  TRACK {:02} AUDIO", i + 1));
+        lines.push(format!("    TITLE {}", quote(&chapter.title)));
+        if !chapter.artist.is_empty() {
+            lines.push(format!("    PERFORMER {}", quote(&chapter.artist)));
+        }
+        lines.push(format!("    INDEX 01 {}", format_time(chapter.start_ms)));
+    }
+    let mut text = lines.join("\r\n");
+    text.push_str("\r\n");
+    text
+}
+
+/// The track numbered `number`, if exactly one has that position (so only
+/// on single-disc releases or a single selected disc)
+fn track_at(tracks: &[Track], number: u32) -> Option<usize> {
+    let mut found = tracks
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.position == number);
+    let (index, _) = found.next()?;
+    found.next().is_none().then_some(index)
+}
+
+fn same_stem(name: &str, file: &Path) -> bool {
+    let stem = |path: &Path| {
+        path.file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    };
+    // Sheets written on Windows use backslashes
+    let name = name.replace('\\', "/");
+    stem(Path::new(&name)) == stem(file)
+}
+
+/// `"My Song.wav" WAVE` or `My Song.wav WAVE` → `My Song.wav`
+fn file_name(rest: &str) -> String {
+    if rest.starts_with('"') {
+        return unquote(rest);
+    }
+    match rest.rsplit_once(char::is_whitespace) {
+        Some((name, _file_type)) => name.trim().to_string(),
+        None => rest.to_string(),
+    }
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    match value.strip_prefix('"') {
+        Some(inner) => inner.split('"').next().unwrap_or_default().to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// CUE has no escape for quotes, so they become apostrophes
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "'"))
+}
+
+/// Milliseconds to `mm:ss:ff`
+fn format_time(ms: u32) -> String {
+    let seconds = ms / 1000;
+    let frames = ms % 1000 * FRAMES_PER_SECOND / 1000;
+    format!("{:02}:{:02}:{:02}", seconds / 60, seconds % 60, frames)
+}
+
+/// `mm:ss:ff` to milliseconds
+fn parse_time(time: &str) -> Option<u32> {
+    let mut parts = time.split(':').map(|p| p.parse::<u32>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    Some((minutes * 60 + seconds) * 1000 + frames * 1000 / FRAMES_PER_SECOND)
+}
@@ -0,0 +1,319 @@
+// src/dedupe.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use dialoguer::{Confirm, Select};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::audio::{self, AudioInfo};
+use crate::index::LibraryIndex;
+use crate::symbols;
+
+/// Copies matched only by artist and title must be this close in length
+const DURATION_TOLERANCE_MS: u32 = 3000;
+
+struct Copy {
+    path: PathBuf,
+    info: Option<AudioInfo>,
+    size: u64,
+}
+
+impl Copy {
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(info) = &self.info {
+            if let Some(kbps) = info.bitrate_kbps {
+                parts.push(format!("{} kbps", kbps));
+            }
+            if let Some(rate) = info.sample_rate {
+                parts.push(format!("{:.1} kHz", rate as f64 / 1000.0));
+            }
+            let seconds = info.duration_ms / 1000;
+            parts.push(format!("{}:{:02}", seconds / 60, seconds % 60));
+        }
+        parts.push(format!("{:.1} MB", self.size as f64 / (1024.0 * 1024.0)));
+        parts.join(", ")
+    }
+}
+
+enum Action {
+    Quarantine,
+    Delete,
+}
+
+/// Find copies of the same recording in the library index, best first, and let
+/// the user delete or quarantine the others
+pub fn run(scan: Option<&Path>, quarantine: Option<&Path>, dry_run: bool) -> Result<()> {
+    let index = LibraryIndex::open()?;
+    if let Some(root) = scan {
+        let (total, refreshed) = crate::query::scan_tree(&index, root)?;
+        println!(
+            "{} Indexed {} file(s), {} updated",
+            symbols::ok().bright_green(),
+            total,
+            refreshed
+        );
+    }
+
+    // Recording MBIDs are authoritative; otherwise fall back on artist and title
+    let mut candidates: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for file in index.files()? {
+        if !file.path.exists() {
+            index.forget(&file.path)?;
+            continue;
+        }
+        let key = match (&file.recording_id, &file.artist, &file.title) {
+            (Some(id), _, _) if !id.is_empty() => format!("recording:{}", id),
+            (_, Some(artist), Some(title)) => {
+                format!("tags:{}\u{1f}{}", normalize(artist), normalize(title))
+            }
+            _ => continue,
+        };
+        candidates.entry(key).or_default().push(file.path);
+    }
+    candidates.retain(|_, paths| paths.len() > 1);
+
+    let probing: usize = candidates.values().map(Vec::len).sum();
+    let pb = crate::progress::bar(probing, "Reading audio");
+    let mut groups = Vec::new();
+    for (key, paths) in candidates {
+        let copies: Vec<Copy> = paths
+            .into_iter()
+            .map(|path| {
+                pb.inc(1);
+                Copy {
+                    info: audio::probe(&path),
+                    size: std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                    path,
+                }
+            })
+            .collect();
+        if key.starts_with("recording:") {
+            groups.push(copies);
+        } else {
+            groups.extend(split_by_duration(copies));
+        }
+    }
+    pb.finish_and_clear();
+
+    for group in &mut groups {
+        rank(group);
+    }
+
+    if groups.is_empty() {
+        println!("{} No duplicates found", symbols::ok().bright_green());
+        return Ok(());
+    }
+
+    println!(
+        "{} Found {} group(s) of duplicates",
+        symbols::ok().bright_green(),
+        groups.len()
+    );
+    println!();
+
+    let mut removed = 0;
+    for (i, group) in groups.iter().enumerate() {
+        print_group(i + 1, groups.len(), group);
+        if dry_run {
+            continue;
+        }
+
+        let Some((keep, action)) = ask(group, quarantine)? else {
+            println!("{}", "Stopped.".bright_yellow());
+            break;
+        };
+        let Some(keep) = keep else {
+            println!();
+            continue;
+        };
+
+        for (j, copy) in group.iter().enumerate() {
+            if j == keep {
+                continue;
+            }
+            match (&action, quarantine) {
+                (Action::Quarantine, Some(dir)) => {
+                    let destination = quarantine_path(dir, &copy.path);
+                    crate::organize::move_file(&copy.path, &destination)?;
+                    println!(
+                        "  {} {}",
+                        symbols::arrow().bright_cyan(),
+                        destination.display().to_string().bright_black()
+                    );
+                }
+                _ => {
+                    std::fs::remove_file(&copy.path)
+                        .with_context(|| format!("Failed to delete {}", copy.path.display()))?;
+                    println!(
+                        "  {} {}",
+                        symbols::fail().bright_red(),
+                        copy.path.display().to_string().bright_black()
+                    );
+                }
+            }
+            index.forget(&copy.path)?;
+            removed += 1;
+        }
+        println!();
+    }
+
+    if dry_run {
+        println!(
+            "{}",
+            "Dry run - no files were deleted or moved.".bright_yellow()
+        );
+    } else {
+        println!(
+            "{} Removed {} duplicate(s)",
+            symbols::ok().bright_green(),
+            removed
+        );
+    }
+
+    Ok(())
+}
+
+/// Which copy to keep (`None` to skip the group) and what to do with the rest.
+/// Returns `None` when the user stops.
+fn ask(group: &[Copy], quarantine: Option<&Path>) -> Result<Option<(Option<usize>, Action)>> {
+    let mut items = Vec::new();
+    if quarantine.is_some() {
+        items.push("Keep the best, quarantine the others");
+    }
+    items.extend([
+        "Keep the best, delete the others",
+        "Choose which copy to keep",
+        "Skip",
+        "Stop",
+    ]);
+
+    let choice = Select::new()
+        .with_prompt("What should happen to this group?")
+        .items(&items)
+        .default(0)
+        .interact()?;
+
+    let (keep, action) = match items[choice] {
+        "Keep the best, quarantine the others" => (0, Action::Quarantine),
+        "Keep the best, delete the others" => (0, Action::Delete),
+        "Choose which copy to keep" => {
+            let paths: Vec<String> = group.iter().map(|c| c.path.display().to_string()).collect();
+            let keep = Select::new()
+                .with_prompt("Keep which copy?")
+                .items(&paths)
+                .default(0)
+                .interact()?;
+            let action = if quarantine.is_some() {
+                Action::Quarantine
+            } else {
+                Action::Delete
+            };
+            (keep, action)
+        }
+        "Skip" => return Ok(Some((None, Action::Delete))),
+        _ => return Ok(None),
+    };
+
+    if matches!(action, Action::Delete)
+        && !Confirm::new()
+            .with_prompt(format!("Permanently delete {} file(s)?", group.len() - 1))
+            .default(false)
+            .interact()?
+    {
+        return Ok(Some((None, action)));
+    }
+
+    Ok(Some((Some(keep), action)))
+}
+
+/// Best copy first: highest bitrate, then sample rate, then file size
+fn rank(group: &mut [Copy]) {
+    group.sort_by_key(|c| {
+        let bitrate = c.info.and_then(|i| i.bitrate_kbps).unwrap_or(0);
+        let sample_rate = c.info.and_then(|i| i.sample_rate).unwrap_or(0);
+        std::cmp::Reverse((bitrate, sample_rate, c.size))
+    });
+}
+
+/// Split copies that share artist and title into runs of similar length, so a
+/// live version or remix with the same name isn't treated as a duplicate. Each
+/// run is measured from its shortest copy, so small gaps can't chain together.
+fn split_by_duration(mut copies: Vec<Copy>) -> Vec<Vec<Copy>> {
+    copies.retain(|c| c.info.is_some());
+    copies.sort_by_key(|c| c.info.map(|i| i.duration_ms));
+
+    let mut groups: Vec<Vec<Copy>> = Vec::new();
+    let mut first_duration = 0;
+    for copy in copies {
+        let duration = copy.info.map(|i| i.duration_ms).unwrap_or(0);
+        match groups.last_mut() {
+            Some(group) if duration - first_duration <= DURATION_TOLERANCE_MS => group.push(copy),
+            _ => {
+                first_duration = duration;
+                groups.push(vec![copy]);
+            }
+        }
+    }
+    groups.retain(|g| g.len() > 1);
+    groups
+}
+
+fn print_group(number: usize, total: usize, group: &[Copy]) {
+    println!(
+        "{} {}",
+        format!("[{}/{}]", number, total).bright_black(),
+        group[0]
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .bright_white()
+    );
+    for (i, copy) in group.iter().enumerate() {
+        let marker = if i == 0 {
+            symbols::star().bright_green()
+        } else {
+            " ".normal()
+        };
+        println!(
+            "  {} {} {}",
+            marker,
+            copy.path.display(),
+            format!("({})", copy.describe()).bright_black()
+        );
+    }
+}
+
+/// `dir/<album folder>/<file name>`, numbered if a quarantined file already has that name
+fn quarantine_path(dir: &Path, file: &Path) -> PathBuf {
+    let folder = file
+        .parent()
+        .and_then(|p| p.file_name())
+        .unwrap_or_default();
+    let name = file.file_name().unwrap_or_default();
+    let mut destination = dir.join(folder).join(name);
+
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = file
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut n = 2;
+    while destination.exists() {
+        destination = dir
+            .join(folder)
+            .join(format!("{} ({}){}", stem, n, extension));
+        n += 1;
+    }
+    destination
+}
+
+/// Case, accents, and punctuation don't make a different recording
+pub fn normalize(value: &str) -> String {
+    deunicode::deunicode(value)
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
@@ -0,0 +1,315 @@
+// src/doctor.rs
+use anyhow::Result;
+use colored::Colorize;
+use id3::{Tag, TagLike, Version};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::provider::MetadataProvider;
+use crate::symbols;
+use crate::tagger::extended_text;
+
+struct FileInfo {
+    path: PathBuf,
+    version: Option<Version>,
+    title: Option<String>,
+    album: Option<String>,
+    album_artist: Option<String>,
+    disc: u32,
+    track: Option<u32>,
+    has_art: bool,
+    album_id: Option<String>,
+    track_id: Option<String>,
+    recording_id: Option<String>,
+}
+
+/// Check an album folder for common tagging problems and print a report
+pub async fn run(path: &Path) -> Result<()> {
+    let files = crate::maintenance::collect_tree(path)?;
+    if files.is_empty() {
+        anyhow::bail!("No MP3 files found in {}", path.display());
+    }
+
+    println!("{}", "Album Doctor".bright_cyan().bold());
+    println!(
+        "{} Checking {} MP3 file(s)",
+        symbols::ok().bright_green(),
+        files.len()
+    );
+    println!();
+
+    let pb = crate::progress::bar(files.len(), "Reading tags");
+    let infos: Vec<FileInfo> = files
+        .into_iter()
+        .map(|file| {
+            pb.inc(1);
+            read_info(file)
+        })
+        .collect();
+    pb.finish_and_clear();
+    let mut problems = 0;
+
+    problems += check_untagged(&infos);
+    problems += check_versions(&infos);
+    problems += check_consistency(&infos, "album", |i| i.album.clone());
+    problems += check_consistency(&infos, "album artist", |i| i.album_artist.clone());
+    problems += check_art(&infos);
+    problems += check_duplicates(&infos);
+    problems += check_missing_tracks(&infos).await;
+
+    println!();
+    if problems == 0 {
+        println!(
+            "{} {}",
+            symbols::ok().bright_green(),
+            "No problems found".bright_green()
+        );
+    } else {
+        println!(
+            "{} Found {} problem{}",
+            symbols::warn().bright_yellow(),
+            problems,
+            if problems == 1 { "" } else { "s" }
+        );
+    }
+
+    Ok(())
+}
+
+fn read_info(path: PathBuf) -> FileInfo {
+    let tag = Tag::read_from_path(&path).ok();
+
+    FileInfo {
+        version: tag.as_ref().map(|t| t.version()),
+        title: tag.as_ref().and_then(|t| t.title().map(str::to_string)),
+        album: tag.as_ref().and_then(|t| t.album().map(str::to_string)),
+        album_artist: tag
+            .as_ref()
+            .and_then(|t| t.album_artist().map(str::to_string)),
+        disc: tag.as_ref().and_then(|t| t.disc()).unwrap_or(1),
+        track: tag.as_ref().and_then(|t| t.track()),
+        has_art: tag
+            .as_ref()
+            .map(|t| t.pictures().count() > 0)
+            .unwrap_or(false),
+        album_id: tag
+            .as_ref()
+            .and_then(|t| extended_text(t, "MusicBrainz Album Id")),
+        track_id: tag
+            .as_ref()
+            .and_then(|t| extended_text(t, "MusicBrainz Release Track Id")),
+        recording_id: tag
+            .as_ref()
+            .and_then(|t| extended_text(t, "MusicBrainz Recording Id")),
+        path,
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn heading(text: &str) {
+    println!(
+        "{} {}",
+        symbols::warn().bright_yellow(),
+        text.bright_yellow()
+    );
+}
+
+fn check_untagged(infos: &[FileInfo]) -> usize {
+    let untagged: Vec<&FileInfo> = infos.iter().filter(|i| i.version.is_none()).collect();
+    if untagged.is_empty() {
+        return 0;
+    }
+
+    heading(&format!("{} file(s) have no ID3 tag", untagged.len()));
+    for info in untagged {
+        println!("    {}", file_name(&info.path).bright_white());
+    }
+    1
+}
+
+fn check_versions(infos: &[FileInfo]) -> usize {
+    let mut by_version: BTreeMap<String, usize> = BTreeMap::new();
+    for version in infos.iter().filter_map(|i| i.version) {
+        *by_version.entry(version.to_string()).or_default() += 1;
+    }
+
+    if by_version.len() <= 1 {
+        return 0;
+    }
+
+    heading("Mixed ID3 versions");
+    for (version, count) in by_version {
+        println!("    {}: {} file(s)", version, count);
+    }
+    1
+}
+
+fn check_consistency(
+    infos: &[FileInfo],
+    field: &str,
+    value: impl Fn(&FileInfo) -> Option<String>,
+) -> usize {
+    let mut values: BTreeMap<String, usize> = BTreeMap::new();
+    let mut missing = 0;
+    for info in infos {
+        match value(info) {
+            Some(v) => *values.entry(v).or_default() += 1,
+            None => missing += 1,
+        }
+    }
+
+    if values.len() <= 1 && missing == 0 {
+        return 0;
+    }
+    if values.is_empty() {
+        heading(&format!("No file has an {} tag", field));
+        return 1;
+    }
+
+    heading(&format!("Inconsistent {} values", field));
+    for (v, count) in values {
+        println!("    \"{}\": {} file(s)", v, count);
+    }
+    if missing > 0 {
+        println!("    (missing): {} file(s)", missing);
+    }
+    1
+}
+
+fn check_art(infos: &[FileInfo]) -> usize {
+    let without: Vec<&FileInfo> = infos.iter().filter(|i| !i.has_art).collect();
+    if without.is_empty() {
+        return 0;
+    }
+
+    if without.len() == infos.len() {
+        heading("No file has embedded cover art");
+    } else {
+        heading(&format!("{} file(s) are missing cover art", without.len()));
+        for info in without {
+            println!("    {}", file_name(&info.path).bright_white());
+        }
+    }
+    1
+}
+
+fn check_duplicates(infos: &[FileInfo]) -> usize {
+    let mut groups: BTreeMap<String, Vec<&FileInfo>> = BTreeMap::new();
+
+    for info in infos {
+        let key = if let Some(recording_id) = &info.recording_id {
+            format!("recording {}", recording_id)
+        } else if let Some(track) = info.track {
+            format!("disc {} track {}", info.disc, track)
+        } else if let Some(title) = &info.title {
+            format!("title \"{}\"", title.to_lowercase())
+        } else {
+            continue;
+        };
+        groups.entry(key).or_default().push(info);
+    }
+
+    let duplicates: Vec<_> = groups.into_iter().filter(|(_, g)| g.len() > 1).collect();
+    if duplicates.is_empty() {
+        return 0;
+    }
+
+    heading(&format!("{} duplicate track(s)", duplicates.len()));
+    for (key, group) in duplicates {
+        println!("    {}:", key.bright_white());
+        for info in group {
+            println!("      {}", file_name(&info.path));
+        }
+    }
+    1
+}
+
+async fn check_missing_tracks(infos: &[FileInfo]) -> usize {
+    let album_ids: HashSet<&str> = infos.iter().filter_map(|i| i.album_id.as_deref()).collect();
+    if album_ids.is_empty() {
+        return 0;
+    }
+
+    let mut problems = 0;
+    if album_ids.len() > 1 {
+        heading(&format!(
+            "Files are tagged with {} different releases",
+            album_ids.len()
+        ));
+        problems += 1;
+    }
+
+    // Start every fetch up front; the shared rate limiter spaces the requests
+    // while earlier releases are being checked
+    let mut album_ids: Vec<&str> = album_ids.into_iter().collect();
+    album_ids.sort_unstable();
+    let client = crate::config::musicbrainz_client();
+    let fetches: Vec<_> = album_ids
+        .iter()
+        .map(|id| {
+            let client = client.clone();
+            let id = id.to_string();
+            tokio::spawn(async move { client.get_release(&id).await })
+        })
+        .collect();
+
+    for (album_id, fetch) in album_ids.into_iter().zip(fetches) {
+        let result = match fetch.await {
+            Ok(result) => result.map_err(anyhow::Error::from),
+            Err(e) => Err(anyhow::anyhow!("Fetch task failed: {}", e)),
+        };
+        let album = match result {
+            Ok(album) => album,
+            Err(e) => {
+                println!(
+                    "{} Could not fetch release {} to check for missing tracks: {}",
+                    symbols::warn().bright_yellow(),
+                    album_id,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let present: HashSet<&str> = infos
+            .iter()
+            .filter(|i| i.album_id.as_deref() == Some(album_id))
+            .filter_map(|i| i.track_id.as_deref())
+            .collect();
+
+        let missing: Vec<_> = album
+            .tracks
+            .iter()
+            .filter(|t| !present.contains(t.id.as_str()))
+            .collect();
+
+        if missing.is_empty() {
+            continue;
+        }
+
+        heading(&format!(
+            "{} of {} tracks missing from \"{}\"",
+            missing.len(),
+            album.tracks.len(),
+            album.title
+        ));
+        for track in missing {
+            if album.media_count > 1 {
+                println!(
+                    "    Disc {} Track {} - {}",
+                    track.disc_number, track.position, track.title
+                );
+            } else {
+                println!("    Track {} - {}", track.position, track.title);
+            }
+        }
+        problems += 1;
+    }
+
+    problems
+}
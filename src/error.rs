@@ -0,0 +1,87 @@
+// src/error.rs
+use std::path::PathBuf;
+
+/// Failures the library reports, grouped so callers can react per class
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The request never got a response (DNS, connection, timeout)
+    #[error("Network request failed")]
+    NetworkError(#[from] reqwest::Error),
+
+    /// The service answered but has nothing under this ID
+    #[error("{0} not found")]
+    NotFound(String),
+
+    /// Still throttled after retrying with backoff
+    #[error("{0} is rate limiting requests; try again later")]
+    RateLimited(&'static str),
+
+    /// Any other unsuccessful HTTP status
+    #[error("{service} API error {status}: {body}")]
+    ApiError {
+        service: &'static str,
+        status: reqwest::StatusCode,
+        body: String,
+    },
+
+    #[error("Failed to parse {what}")]
+    ParseError {
+        what: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// A release export holds no release, or several and none was picked
+    #[error("Expected one release in the export, found {0}")]
+    ReleaseCount(usize),
+
+    #[error("Failed to process cover art image")]
+    ImageError(#[from] image::ImageError),
+
+    /// A download answered with something else, such as a placeholder page
+    #[error("{0} did not return an image")]
+    NotAnImage(String),
+
+    #[error("Failed to write tags to {}", path.display())]
+    TagWriteError {
+        path: PathBuf,
+        #[source]
+        source: id3::Error,
+    },
+
+    #[error("No tag writer for .{0} files")]
+    UnsupportedFormat(String),
+
+    #[error("Invalid exclude pattern: {pattern}")]
+    InvalidPattern {
+        pattern: String,
+        #[source]
+        source: glob::PatternError,
+    },
+
+    /// The caller's cancellation token fired
+    #[error("Cancelled")]
+    Cancelled,
+
+    /// Replaying recorded responses, and none was recorded for this URL
+    #[cfg(feature = "fixtures")]
+    #[error("No recorded response for {0}")]
+    MissingFixture(String),
+
+    /// A CUE sheet whose tracklist can't be relied on
+    #[error("Invalid CUE sheet {0}")]
+    InvalidCue(String),
+
+    /// A damaged tag's size is wrong and no MPEG frame could be found after it
+    #[error("Could not find where the audio starts in {}", .0.display())]
+    NoAudio(PathBuf),
+
+    #[error("Failed to read {}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,55 @@
+// src/events.rs
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+/// Something the library did or noticed while working. Frontends subscribe to
+/// these instead of parsing the CLI's output.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The input folders were scanned for MP3 files
+    FilesScanned { root: PathBuf, files: usize },
+    /// A file was assigned to a release track
+    FileMatched {
+        file: PathBuf,
+        disc: u32,
+        track: u32,
+        title: String,
+        confidence: f64,
+    },
+    /// Tags were written to a file
+    TagWritten { file: PathBuf },
+    /// Writing a file's tags failed and the batch moved on to the next file
+    TagFailed { file: PathBuf, error: String },
+    /// Cover art was downloaded (and resized if needed)
+    ArtDownloaded { release_id: String, bytes: usize },
+    /// Something worth telling the user that didn't stop the work
+    Warning { message: String },
+}
+
+/// Receives events. Implemented for closures and for `mpsc::Sender<Event>`, so
+/// a frontend can either handle events inline or read them from a channel.
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: Event);
+}
+
+impl<F: Fn(Event) + Send + Sync> EventSink for F {
+    fn emit(&self, event: Event) {
+        self(event)
+    }
+}
+
+impl EventSink for Sender<Event> {
+    fn emit(&self, event: Event) {
+        // A receiver that went away just isn't listening anymore
+        let _ = self.send(event);
+    }
+}
+
+/// Sink that drops every event
+pub struct NoEvents;
+
+impl EventSink for NoEvents {
+    fn emit(&self, _event: Event) {}
+}
@@ -0,0 +1,99 @@
+// src/exclude.rs
+use glob::{MatchOptions, Pattern};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+
+/// Per-folder ignore file, one glob per line (`#` starts a comment)
+pub const IGNORE_FILE: &str = ".mbtagignore";
+
+struct Rule {
+    pattern: Pattern,
+    /// Pattern contains a `/`, so it is matched against the path relative to the root
+    anchored: bool,
+    /// Pattern ended in `/`, so it only matches directories
+    dir_only: bool,
+}
+
+/// Files and folders skipped during discovery, from `--exclude` and `.mbtagignore`.
+/// The default excludes nothing.
+#[derive(Default)]
+pub struct ExcludeRules {
+    rules: Vec<Rule>,
+}
+
+impl ExcludeRules {
+    /// Combine command-line patterns with the ignore file in `root`, if any
+    pub fn load(root: &Path, patterns: &[String]) -> Result<Self> {
+        let mut lines: Vec<String> = patterns.to_vec();
+
+        let ignore_file = root.join(IGNORE_FILE);
+        if root.is_dir() && ignore_file.is_file() {
+            let contents = std::fs::read_to_string(&ignore_file).map_err(|source| Error::Io {
+                path: ignore_file.clone(),
+                source,
+            })?;
+            lines.extend(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+
+        let rules = lines
+            .iter()
+            .map(|line| {
+                let dir_only = line.ends_with('/');
+                let trimmed = line.trim_end_matches('/').trim_start_matches('/');
+                let pattern = Pattern::new(trimmed).map_err(|source| Error::InvalidPattern {
+                    pattern: line.clone(),
+                    source,
+                })?;
+                Ok(Rule {
+                    pattern,
+                    anchored: trimmed.contains('/'),
+                    dir_only,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Whether `path` (somewhere below `root`) should be skipped
+    pub fn is_excluded(&self, path: &Path, root: &Path, is_dir: bool) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let options = MatchOptions {
+            case_sensitive: false,
+            require_literal_separator: true,
+            ..Default::default()
+        };
+
+        self.rules.iter().any(|rule| {
+            if rule.dir_only && !is_dir {
+                return false;
+            }
+            if rule.anchored {
+                rule.pattern.matches_with(&relative, options)
+            } else {
+                rule.pattern.matches_with(&name, options)
+            }
+        })
+    }
+}
@@ -0,0 +1,111 @@
+// src/extra_art.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use id3::frame::PictureType;
+use std::path::{Path, PathBuf};
+
+use crate::art;
+use crate::profile::TagSettings;
+use crate::symbols;
+
+/// Names accepted before the `=` in `--art`. The front cover comes from the
+/// release, so it isn't among them.
+const TYPES: [(&str, PictureType); 11] = [
+    ("back", PictureType::CoverBack),
+    ("media", PictureType::Media),
+    ("booklet", PictureType::Leaflet),
+    ("artist", PictureType::Artist),
+    ("band", PictureType::Band),
+    ("conductor", PictureType::Conductor),
+    ("composer", PictureType::Composer),
+    ("illustration", PictureType::Illustration),
+    ("logo", PictureType::BandLogo),
+    ("publisher", PictureType::PublisherLogo),
+    ("other", PictureType::Other),
+];
+
+/// A local image from `--art TYPE=FILE`
+#[derive(Debug, Clone)]
+pub struct ExtraArt {
+    pub picture_type: PictureType,
+    pub path: PathBuf,
+}
+
+/// Parse `TYPE=FILE`, e.g. `back=back.png`
+pub fn parse(arg: &str) -> Result<ExtraArt, String> {
+    let (name, path) = arg
+        .split_once('=')
+        .ok_or_else(|| "expected TYPE=FILE, e.g. back=back.png".to_string())?;
+    let name = name.trim().to_lowercase();
+    let picture_type = TYPES
+        .iter()
+        .find(|(type_name, _)| *type_name == name)
+        .map(|(_, picture_type)| *picture_type)
+        .ok_or_else(|| {
+            let names: Vec<&str> = TYPES.iter().map(|(type_name, _)| *type_name).collect();
+            format!("unknown picture type '{}' (use {})", name, names.join(", "))
+        })?;
+    if path.trim().is_empty() {
+        return Err(format!("no file given for {}", name));
+    }
+    Ok(ExtraArt {
+        picture_type,
+        path: PathBuf::from(path.trim()),
+    })
+}
+
+/// Read the images, ready to embed. Relative paths not found from the
+/// working directory are looked up in `album_dir`. Images that can't be made
+/// to fit the embed limit are left out with a warning, as are images of a
+/// type given again later, since a file holds one picture per type.
+pub fn load(
+    arts: &[ExtraArt],
+    album_dir: &Path,
+    settings: &TagSettings,
+) -> Result<Vec<(PictureType, Vec<u8>)>> {
+    let mut pictures = Vec::new();
+    for (i, extra) in arts.iter().enumerate() {
+        if let Some(later) = arts[i + 1..]
+            .iter()
+            .find(|later| later.picture_type == extra.picture_type)
+        {
+            println!(
+                "{} {} is given more than once; {} replaces {}",
+                symbols::warn().bright_yellow(),
+                extra.picture_type,
+                later.path.display(),
+                extra.path.display()
+            );
+            continue;
+        }
+        let path = if extra.path.is_relative() && !extra.path.exists() {
+            album_dir.join(&extra.path)
+        } else {
+            extra.path.clone()
+        };
+        let image =
+            std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if art::dimensions(&image).is_none() {
+            anyhow::bail!("{} is not an image", path.display());
+        }
+        let Some(fitted) = art::fit_to_bytes(&image, settings.max_embed_bytes)?.0 else {
+            println!(
+                "{} {} ({:.1} KB) can't be made to fit {:.1} KB; not embedding it",
+                symbols::warn().bright_yellow(),
+                path.display(),
+                image.len() as f64 / 1024.0,
+                settings.max_embed_bytes as f64 / 1024.0
+            );
+            continue;
+        };
+        println!(
+            "{} {}: {} ({:.1} KB)",
+            symbols::ok().bright_green(),
+            extra.picture_type,
+            path.display(),
+            fitted.len() as f64 / 1024.0
+        );
+        pictures.push((extra.picture_type, fitted));
+    }
+    Ok(pictures)
+}
@@ -0,0 +1,267 @@
+// src/filename.rs
+use anyhow::Result;
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Which filesystem's naming rules generated paths must satisfy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FilenameMode {
+    /// Safe on NTFS/FAT/SMB shares: no `\ / : * ? " < > |`, no trailing dots or spaces
+    Windows,
+    /// Only `/` and NUL are forbidden
+    Posix,
+}
+
+impl Default for FilenameMode {
+    fn default() -> Self {
+        if cfg!(windows) {
+            FilenameMode::Windows
+        } else {
+            FilenameMode::Posix
+        }
+    }
+}
+
+/// How characters that are illegal in filenames get replaced
+#[derive(Debug, Clone, Default)]
+pub struct FilenameRules {
+    pub mode: FilenameMode,
+    /// Transliterate non-ASCII characters ("Motörhead" → "Motorhead")
+    pub ascii: bool,
+    overrides: HashMap<char, String>,
+}
+
+const WINDOWS_ILLEGAL: &[char] = &['\\', '/', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Device names Windows refuses as file or directory names, with or without an extension
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Longest single path component (bytes on POSIX, UTF-16 units on Windows)
+const MAX_COMPONENT: usize = 255;
+
+/// Classic Windows MAX_PATH, minus the terminating NUL
+const WINDOWS_MAX_PATH: usize = 259;
+
+/// Components are never truncated below this length when fitting a path
+const MIN_COMPONENT: usize = 16;
+
+impl FilenameRules {
+    /// Build rules from `CHAR=REPLACEMENT` pairs (an empty replacement deletes the character)
+    pub fn new(mode: FilenameMode, replacements: &[String], ascii: bool) -> Result<Self> {
+        let mut overrides = HashMap::new();
+
+        for pair in replacements {
+            let (from, to) = pair.split_once('=').ok_or_else(|| {
+                anyhow::anyhow!("Invalid replacement '{}' (expected CHAR=REPLACEMENT)", pair)
+            })?;
+            let mut chars = from.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                anyhow::bail!(
+                    "Invalid replacement '{}': left side must be one character",
+                    pair
+                );
+            };
+            if to.contains(|r: char| is_illegal(mode, r)) {
+                anyhow::bail!(
+                    "Invalid replacement '{}': replacement is itself illegal",
+                    pair
+                );
+            }
+            overrides.insert(c, to.to_string());
+        }
+
+        Ok(Self {
+            mode,
+            ascii,
+            overrides,
+        })
+    }
+
+    /// Make a single path component safe for the configured filesystem. The
+    /// `--replace` overrides see the name as written, so they win over
+    /// transliteration ("ö=oe" gives "oe", not "o").
+    pub fn sanitize(&self, component: &str) -> String {
+        let mut result = String::with_capacity(component.len());
+        let mut run = String::new();
+        for c in component.chars() {
+            match self.overrides.get(&c) {
+                Some(replacement) => {
+                    self.push_run(&mut result, &run);
+                    run.clear();
+                    result.push_str(replacement);
+                }
+                None => run.push(c),
+            }
+        }
+        self.push_run(&mut result, &run);
+
+        result = truncate(&result, MAX_COMPONENT);
+
+        if self.mode == FilenameMode::Windows {
+            // Windows silently strips trailing dots and spaces, which breaks lookups
+            let trimmed = result.trim_end_matches(['.', ' ']);
+            if trimmed.len() != result.len() {
+                result = trimmed.to_string();
+            }
+
+            // "CON" and "con.txt" are both reserved; "con_.txt" is not
+            if is_reserved(&result) {
+                let stem_end = result.find('.').unwrap_or(result.len());
+                result.insert(stem_end, '_');
+            }
+        }
+
+        result
+    }
+
+    /// Append text without overrides of its own, transliterated if asked and
+    /// with illegal characters replaced
+    fn push_run(&self, result: &mut String, run: &str) {
+        let transliterated;
+        let run = if self.ascii {
+            transliterated = deunicode::deunicode_with_tofu(run, "_");
+            transliterated.as_str()
+        } else {
+            run
+        };
+        for c in run.chars() {
+            // Transliteration can produce a character with an override, as
+            // a full-width colon becomes ':'
+            if let Some(replacement) = self.overrides.get(&c) {
+                result.push_str(replacement);
+            } else if is_illegal(self.mode, c) {
+                result.push_str(default_replacement(c));
+            } else {
+                result.push(c);
+            }
+        }
+    }
+
+    /// Join `relative` and `extension` onto `root`, shortening components so the
+    /// full path stays within MAX_PATH in Windows mode. The file name is shortened
+    /// first, then the longest directory names.
+    pub fn fit_path(&self, root: &Path, relative: &Path, extension: &str) -> PathBuf {
+        let mut components: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        let suffix = format!(".{}", extension);
+
+        // The extension must fit inside the file name's component limit too
+        if let Some(last) = components.last_mut() {
+            *last = truncate(last, MAX_COMPONENT - suffix.len());
+        }
+
+        if self.mode == FilenameMode::Windows {
+            let root_len = utf16_len(&root.to_string_lossy()) + 1;
+            let total = |components: &[String]| {
+                root_len
+                    + components.iter().map(|c| utf16_len(c) + 1).sum::<usize>()
+                    + utf16_len(&suffix)
+                    - 1
+            };
+
+            while total(&components) > WINDOWS_MAX_PATH {
+                let excess = total(&components) - WINDOWS_MAX_PATH;
+                let last = components.len() - 1;
+
+                // Prefer the file name, then the longest remaining component
+                let target = if utf16_len(&components[last]) > MIN_COMPONENT {
+                    last
+                } else {
+                    match (0..last)
+                        .filter(|&i| utf16_len(&components[i]) > MIN_COMPONENT)
+                        .max_by_key(|&i| utf16_len(&components[i]))
+                    {
+                        Some(i) => i,
+                        None => break,
+                    }
+                };
+
+                let current = utf16_len(&components[target]);
+                let keep = current.saturating_sub(excess).max(MIN_COMPONENT);
+                let shortened = self.sanitize(&truncate_utf16(&components[target], keep));
+                if utf16_len(&shortened) >= current {
+                    break;
+                }
+                components[target] = shortened;
+            }
+        }
+
+        let mut path = root.to_path_buf();
+        let last = components.pop().unwrap_or_default();
+        path.extend(components);
+        path.push(format!("{}{}", last, suffix));
+        path
+    }
+}
+
+/// Path suitable for filesystem calls. On Windows, paths longer than MAX_PATH
+/// get the `\\?\` extended-length prefix so deep library folders still work.
+pub fn io_path(path: &Path) -> PathBuf {
+    if cfg!(windows) && path.is_absolute() && utf16_len(&path.to_string_lossy()) > WINDOWS_MAX_PATH
+    {
+        let display = path.to_string_lossy();
+        if !display.starts_with(r"\\?\") {
+            return PathBuf::from(format!(r"\\?\{}", display));
+        }
+    }
+    path.to_path_buf()
+}
+
+fn is_reserved(component: &str) -> bool {
+    let stem = component.split('.').next().unwrap_or(component).trim_end();
+    WINDOWS_RESERVED
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+}
+
+fn utf16_len(s: &str) -> usize {
+    s.encode_utf16().count()
+}
+
+/// Truncate to at most `max_bytes` bytes on a character boundary
+fn truncate(s: &str, max_bytes: usize) -> String {
+    if s.len() <= max_bytes {
+        return s.to_string();
+    }
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s[..end].trim_end().to_string()
+}
+
+/// Truncate to at most `max_units` UTF-16 code units on a character boundary
+fn truncate_utf16(s: &str, max_units: usize) -> String {
+    let mut units = 0;
+    let mut end = 0;
+    for (i, c) in s.char_indices() {
+        units += c.len_utf16();
+        if units > max_units {
+            break;
+        }
+        end = i + c.len_utf8();
+    }
+    s[..end].trim_end().to_string()
+}
+
+fn is_illegal(mode: FilenameMode, c: char) -> bool {
+    match mode {
+        FilenameMode::Windows => WINDOWS_ILLEGAL.contains(&c) || c.is_control(),
+        FilenameMode::Posix => c == '/' || c == '\0',
+    }
+}
+
+fn default_replacement(c: char) -> &'static str {
+    match c {
+        ':' | '/' | '\\' | '|' => "-",
+        '"' => "'",
+        '*' | '<' | '>' => "_",
+        _ => "",
+    }
+}
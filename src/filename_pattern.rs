@@ -0,0 +1,169 @@
+// src/filename_pattern.rs
+use anyhow::Result;
+
+/// Metadata fields a pattern can capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Artist,
+    Title,
+    Track,
+    Disc,
+    Album,
+    AlbumArtist,
+    Year,
+    /// Matches anything and is discarded
+    Skip,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().as_str() {
+            "artist" => Field::Artist,
+            "title" => Field::Title,
+            "track" => Field::Track,
+            "disc" => Field::Disc,
+            "album" => Field::Album,
+            "albumartist" => Field::AlbumArtist,
+            "year" => Field::Year,
+            "skip" => Field::Skip,
+            _ => return None,
+        })
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Field::Track | Field::Disc | Field::Year)
+    }
+}
+
+#[derive(Debug)]
+enum Part {
+    Literal(String),
+    Field(Field),
+}
+
+/// Values parsed from one filename
+#[derive(Debug, Default)]
+pub struct ParsedName {
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub track: Option<u32>,
+    pub disc: Option<u32>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub year: Option<i32>,
+}
+
+/// A filename pattern such as `%track%. %artist% - %title%`, matched against
+/// the file name without its extension
+#[derive(Debug)]
+pub struct FilenamePattern {
+    parts: Vec<Part>,
+}
+
+impl FilenamePattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = pattern;
+        while let Some(start) = rest.find('%') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let after = &rest[start + 1..];
+            let Some(end) = after.find('%') else {
+                anyhow::bail!("Unterminated field in pattern '{}'", pattern);
+            };
+            let name = &after[..end];
+            let Some(field) = Field::from_name(name) else {
+                anyhow::bail!(
+                    "Unknown field '%{}%' in pattern. Use %artist%, %title%, %track%, %disc%, %album%, %albumartist%, %year%, or %skip%",
+                    name
+                );
+            };
+            if matches!(parts.last(), Some(Part::Field(_))) {
+                anyhow::bail!(
+                    "Fields in pattern '{}' must be separated by some text",
+                    pattern
+                );
+            }
+            parts.push(Part::Field(field));
+            rest = &after[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        if !parts.iter().any(|p| matches!(p, Part::Field(_))) {
+            anyhow::bail!("Pattern '{}' contains no fields", pattern);
+        }
+        Ok(Self { parts })
+    }
+
+    /// Parse a file name (extension is stripped); `None` if it doesn't fit the pattern
+    pub fn parse_name(&self, filename: &str) -> Option<ParsedName> {
+        let stem = match filename.rsplit_once('.') {
+            Some((stem, _)) if !stem.is_empty() => stem,
+            _ => filename,
+        };
+
+        let mut captures = Vec::new();
+        if !match_parts(&self.parts, stem, &mut captures) {
+            return None;
+        }
+
+        let mut parsed = ParsedName::default();
+        for (field, value) in captures {
+            let value = value.trim();
+            let text = Some(value.to_string());
+            match field {
+                Field::Artist => parsed.artist = text,
+                Field::Title => parsed.title = text,
+                Field::Album => parsed.album = text,
+                Field::AlbumArtist => parsed.album_artist = text,
+                Field::Track => parsed.track = value.parse().ok(),
+                Field::Disc => parsed.disc = value.parse().ok(),
+                Field::Year => parsed.year = value.parse().ok(),
+                Field::Skip => {}
+            }
+        }
+        Some(parsed)
+    }
+}
+
+/// Match `parts` against all of `text`. Each field takes the shortest span
+/// that lets the rest of the pattern match, so separators that also appear in
+/// a title ("A - B - C") go to the last field.
+fn match_parts<'a>(parts: &[Part], text: &'a str, captures: &mut Vec<(Field, &'a str)>) -> bool {
+    let Some((first, rest)) = parts.split_first() else {
+        return text.is_empty();
+    };
+
+    match first {
+        Part::Literal(literal) => text
+            .strip_prefix(literal.as_str())
+            .is_some_and(|remaining| match_parts(rest, remaining, captures)),
+        Part::Field(field) => {
+            let ends: Vec<usize> = match rest.first() {
+                None => vec![text.len()],
+                Some(Part::Literal(next)) => {
+                    text.match_indices(next.as_str()).map(|(i, _)| i).collect()
+                }
+                // Adjacent fields are rejected when parsing
+                Some(Part::Field(_)) => return false,
+            };
+            for end in ends {
+                let value = &text[..end];
+                if value.trim().is_empty()
+                    || (field.is_numeric() && !value.trim().chars().all(|c| c.is_ascii_digit()))
+                {
+                    continue;
+                }
+                captures.push((*field, value));
+                if match_parts(rest, &text[end..], captures) {
+                    return true;
+                }
+                captures.pop();
+            }
+            false
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// src/fixtures.rs
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+/// Set to a directory to record every MusicBrainz and Cover Art Archive response
+pub const RECORD_ENV: &str = "MUSICTAGGER_RECORD";
+/// Set to a directory of recorded responses to answer requests from it instead
+/// of the network
+pub const REPLAY_ENV: &str = "MUSICTAGGER_REPLAY";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Send requests as usual and save each response
+    Record,
+    /// Answer requests from saved responses; nothing goes over the network
+    Replay,
+}
+
+/// A directory of recorded HTTP responses, one pair of files per request URL:
+/// `<name>.json` with the URL and status, and `<name>.body` with the body
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+    dir: PathBuf,
+    mode: Mode,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Meta {
+    url: String,
+    status: u16,
+}
+
+impl Fixtures {
+    pub fn record(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            mode: Mode::Record,
+        }
+    }
+
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            mode: Mode::Replay,
+        }
+    }
+
+    /// Fixtures named by `MUSICTAGGER_REPLAY` or `MUSICTAGGER_RECORD`, replay
+    /// winning if both are set
+    pub fn from_env() -> Option<Self> {
+        if let Some(dir) = std::env::var_os(REPLAY_ENV) {
+            Some(Self::replay(dir))
+        } else {
+            std::env::var_os(RECORD_ENV).map(Self::record)
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The recorded status and body for `url`
+    pub(crate) fn load(&self, url: &str) -> Result<(reqwest::StatusCode, Vec<u8>)> {
+        let meta_path = self.path(url, "json");
+        if !meta_path.exists() {
+            return Err(Error::MissingFixture(url.to_string()));
+        }
+        let meta: Meta =
+            serde_json::from_slice(&read(&meta_path)?).map_err(|source| Error::ParseError {
+                what: meta_path.display().to_string(),
+                source,
+            })?;
+        let body = read(&self.path(url, "body"))?;
+        let status = reqwest::StatusCode::from_u16(meta.status)
+            .unwrap_or(reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+        Ok((status, body))
+    }
+
+    /// Save the response to `url`, replacing an earlier recording
+    pub(crate) fn save(&self, url: &str, status: reqwest::StatusCode, body: &[u8]) -> Result<()> {
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| Error::Io { path, source }
+        };
+        std::fs::create_dir_all(&self.dir).map_err(io_error(&self.dir))?;
+
+        let meta = Meta {
+            url: url.to_string(),
+            status: status.as_u16(),
+        };
+        let meta_path = self.path(url, "json");
+        let json = serde_json::to_vec_pretty(&meta).expect("fixture metadata serializes");
+        std::fs::write(&meta_path, json).map_err(io_error(&meta_path))?;
+        let body_path = self.path(url, "body");
+        std::fs::write(&body_path, body).map_err(io_error(&body_path))
+    }
+
+    /// `<host>-<hash of the URL>.<extension>`, so recordings stay browsable
+    /// by service
+    fn path(&self, url: &str, extension: &str) -> PathBuf {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_else(|| "request".to_string());
+        self.dir.join(format!(
+            "{}-{:016x}.{}",
+            host,
+            fnv1a(url.as_bytes()),
+            extension
+        ))
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// 64-bit FNV-1a; unlike `DefaultHasher` it is the same on every Rust
+/// version, so fixture names don't change under a toolchain upgrade
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
@@ -0,0 +1,67 @@
+// src/genre.rs
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// User-supplied genre rules, loaded from a TOML file:
+///
+/// ```toml
+/// blacklist = ["seen live", "favorites"]
+///
+/// [map]
+/// "alt rock" = "Alternative Rock"
+/// "hip hop" = "Hip-Hop"
+/// ```
+#[derive(Deserialize, Debug, Default)]
+pub struct GenreRules {
+    #[serde(default)]
+    map: HashMap<String, String>,
+    #[serde(default)]
+    blacklist: Vec<String>,
+}
+
+impl GenreRules {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read genre map {}", path.display()))?;
+        let rules: GenreRules = toml::from_str(&text)
+            .with_context(|| format!("Failed to parse genre map {}", path.display()))?;
+
+        // Keys are matched case-insensitively
+        Ok(Self {
+            map: rules.map.into_iter().map(|(k, v)| (fold(&k), v)).collect(),
+            blacklist: rules.blacklist.into_iter().map(|g| fold(&g)).collect(),
+        })
+    }
+
+    /// Map, filter, and de-duplicate genres, preserving their order
+    pub fn apply(&self, genres: &[String]) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+
+        for genre in genres {
+            let key = fold(genre);
+            if key.is_empty() || self.blacklist.contains(&key) {
+                continue;
+            }
+
+            let mapped = self.map.get(&key).cloned().unwrap_or_else(|| genre.clone());
+            let mapped_key = fold(&mapped);
+            if self.blacklist.contains(&mapped_key) {
+                continue;
+            }
+
+            if !result.iter().any(|g| fold(g) == mapped_key) {
+                result.push(mapped);
+            }
+        }
+
+        result
+    }
+}
+
+/// The one case-folding genres are compared with, for the map, the blacklist,
+/// and duplicates alike
+fn fold(genre: &str) -> String {
+    genre.trim().to_lowercase()
+}
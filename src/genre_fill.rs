@@ -0,0 +1,156 @@
+// src/genre_fill.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use id3::{Tag, TagLike};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::genre::GenreRules;
+use crate::profile::TagSettings;
+use crate::provider::MetadataProvider;
+use crate::symbols;
+use crate::tagger::{extended_text, read_tag, set_genres};
+
+/// Files sharing a release, plus the artist to fall back on if the release has no genres
+struct ReleaseFiles {
+    artist_id: Option<String>,
+    files: Vec<PathBuf>,
+}
+
+/// Fill empty genres across a library from MusicBrainz, looked up by the
+/// embedded release MBID (or the artist's genres if the release has none).
+/// Only TCON is written.
+pub async fn run(
+    path: &Path,
+    rules: &GenreRules,
+    settings: &TagSettings,
+    dry_run: bool,
+) -> Result<()> {
+    let files = crate::maintenance::collect_tree(path)?;
+    if files.is_empty() {
+        anyhow::bail!("No MP3 files found in {}", path.display());
+    }
+
+    let mut releases: BTreeMap<String, ReleaseFiles> = BTreeMap::new();
+    let mut without_release = 0;
+    let pb = crate::progress::bar(files.len(), "Reading tags");
+    for file in files {
+        pb.inc(1);
+        let Ok(tag) = Tag::read_from_path(&file) else {
+            continue;
+        };
+        if tag.genre().is_some_and(|g| !g.trim().is_empty()) {
+            continue;
+        }
+        let Some(album_id) = extended_text(&tag, "MusicBrainz Album Id") else {
+            without_release += 1;
+            continue;
+        };
+        let artist_id = extended_text(&tag, "MusicBrainz Album Artist Id")
+            .or_else(|| extended_text(&tag, "MusicBrainz Artist Id"));
+        let entry = releases.entry(album_id).or_insert_with(|| ReleaseFiles {
+            artist_id: None,
+            files: Vec::new(),
+        });
+        entry.artist_id = entry.artist_id.take().or(artist_id);
+        entry.files.push(file);
+    }
+    pb.finish_and_clear();
+
+    let needing: usize = releases.values().map(|r| r.files.len()).sum();
+    if needing == 0 {
+        println!(
+            "{} No tagged files are missing a genre",
+            symbols::ok().bright_green()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} {} file(s) in {} release(s) have no genre",
+        symbols::ok().bright_green(),
+        needing,
+        releases.len()
+    );
+    if without_release > 0 {
+        println!(
+            "  {} file(s) without a MusicBrainz Album Id were skipped",
+            without_release
+        );
+    }
+    println!();
+
+    let client = crate::config::musicbrainz_client();
+    let mut artist_genres: HashMap<String, Vec<String>> = HashMap::new();
+    let mut filled = 0;
+    for (album_id, release) in &releases {
+        let album = match client.get_release(album_id).await {
+            Ok(album) => album,
+            Err(e) => {
+                println!("  {} {}: {}", symbols::warn().bright_yellow(), album_id, e);
+                continue;
+            }
+        };
+
+        let mut genres = rules.apply(&album.genres);
+        if genres.is_empty() {
+            if let Some(artist_id) = &release.artist_id {
+                if !artist_genres.contains_key(artist_id) {
+                    let fetched = client
+                        .get_artist_genres(artist_id)
+                        .await
+                        .unwrap_or_default();
+                    artist_genres.insert(artist_id.clone(), fetched);
+                }
+                genres = rules.apply(&artist_genres[artist_id]);
+            }
+        }
+
+        if genres.is_empty() {
+            println!(
+                "  {} {} {}",
+                symbols::fail().bright_red(),
+                album.title.bright_white(),
+                "(no genres on MusicBrainz)".bright_black()
+            );
+            continue;
+        }
+
+        if !dry_run {
+            for file in &release.files {
+                let mut tag = read_tag(file)
+                    .with_context(|| format!("Failed to read tags from {}", file.display()))?;
+                set_genres(&mut tag, &genres, settings.multi_value);
+                tag.write_to_path(file, tag.version())
+                    .with_context(|| format!("Failed to write tags to {}", file.display()))?;
+            }
+        }
+        filled += release.files.len();
+        println!(
+            "  {} {} {} {}",
+            symbols::ok().bright_green(),
+            album.title.bright_white(),
+            genres.join(", ").bright_cyan(),
+            format!("({} file(s))", release.files.len()).bright_black()
+        );
+    }
+
+    println!();
+    if dry_run {
+        println!(
+            "{} {} of {} file(s) would get a genre.",
+            "Dry run -".bright_yellow(),
+            filled,
+            needing
+        );
+    } else {
+        println!(
+            "{} Filled the genre of {} of {} file(s)",
+            symbols::ok().bright_green(),
+            filled,
+            needing
+        );
+    }
+
+    Ok(())
+}
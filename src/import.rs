@@ -0,0 +1,238 @@
+// src/import.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use id3::frame::Comment;
+use id3::{Tag, TagLike, Version};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::symbols;
+use crate::tagger::read_tag;
+
+/// One row of an import file. Either `path` or `mbid` identifies the target file;
+/// every other field that is present overwrites the corresponding tag.
+/// Manual mode templates use the same format.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ImportRecord {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mbid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_tracks: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disc: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Apply tag values from a JSON or CSV file to the MP3 files under `root`
+pub fn run(root: &Path, source: &Path, dry_run: bool) -> Result<()> {
+    let records = read_records(source)?;
+    println!(
+        "{} Loaded {} record(s) from {}",
+        symbols::ok().bright_green(),
+        records.len(),
+        source.display()
+    );
+
+    // Only scan embedded IDs when some records are keyed by MBID
+    let mbid_index = if records.iter().any(|r| r.path.is_none() && r.mbid.is_some()) {
+        build_mbid_index(root)?
+    } else {
+        HashMap::new()
+    };
+    println!();
+
+    let mut applied = 0;
+    let mut missing = 0;
+
+    for record in &records {
+        let target = match resolve_target(root, record, &mbid_index) {
+            Some(target) => target,
+            None => {
+                let key = record
+                    .path
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+                    .or_else(|| record.mbid.clone())
+                    .unwrap_or_else(|| "(no path or mbid)".to_string());
+                println!("  {} {}", symbols::fail().bright_red(), key.bright_black());
+                missing += 1;
+                continue;
+            }
+        };
+
+        let mut tag = read_tag(&target).unwrap_or_else(|_| Tag::new());
+        let changes = apply_record(&mut tag, record);
+
+        let file_name = target
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        println!(
+            "  {} {} {}",
+            symbols::ok().bright_green(),
+            file_name.bright_white(),
+            format!("({})", changes.join(", ")).bright_black()
+        );
+
+        if !dry_run && !changes.is_empty() {
+            tag.write_to_path(&target, Version::Id3v24)
+                .with_context(|| format!("Failed to write tags to {}", target.display()))?;
+        }
+        applied += 1;
+    }
+
+    println!();
+    if dry_run {
+        println!("{}", "Dry run - no files were modified.".bright_yellow());
+    }
+    println!(
+        "{} Applied {} record(s), {} not found",
+        symbols::ok().bright_green(),
+        applied,
+        missing
+    );
+
+    Ok(())
+}
+
+pub fn read_records(source: &Path) -> Result<Vec<ImportRecord>> {
+    let is_csv = source
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("csv"))
+        .unwrap_or(false);
+
+    if is_csv {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(source)
+            .with_context(|| format!("Failed to open {}", source.display()))?;
+
+        reader
+            .deserialize()
+            .collect::<std::result::Result<Vec<ImportRecord>, _>>()
+            .context("Failed to parse CSV import file")
+    } else {
+        let text = std::fs::read_to_string(source)
+            .with_context(|| format!("Failed to read {}", source.display()))?;
+        serde_json::from_str(&text).context("Failed to parse JSON import file")
+    }
+}
+
+/// Map embedded track and recording MBIDs to file paths
+fn build_mbid_index(root: &Path) -> Result<HashMap<String, PathBuf>> {
+    let mut index = HashMap::new();
+
+    let files = crate::maintenance::collect_tree(root)?;
+    let pb = crate::progress::bar(files.len(), "Indexing MusicBrainz IDs");
+    for file in files {
+        pb.inc(1);
+        let Ok(tag) = Tag::read_from_path(&file) else {
+            continue;
+        };
+        for text in tag.extended_texts() {
+            if text.description == "MusicBrainz Release Track Id"
+                || text.description == "MusicBrainz Recording Id"
+            {
+                index.insert(text.value.to_lowercase(), file.clone());
+            }
+        }
+    }
+    pb.finish_and_clear();
+
+    Ok(index)
+}
+
+fn resolve_target(
+    root: &Path,
+    record: &ImportRecord,
+    mbid_index: &HashMap<String, PathBuf>,
+) -> Option<PathBuf> {
+    if let Some(path) = &record.path {
+        let full = if path.is_absolute() {
+            path.clone()
+        } else {
+            root.join(path)
+        };
+        return full.is_file().then_some(full);
+    }
+
+    record
+        .mbid
+        .as_ref()
+        .and_then(|mbid| mbid_index.get(&mbid.trim().to_lowercase()))
+        .cloned()
+}
+
+fn apply_record(tag: &mut Tag, record: &ImportRecord) -> Vec<&'static str> {
+    let mut changes = Vec::new();
+
+    if let Some(title) = non_empty(&record.title) {
+        tag.set_title(title);
+        changes.push("title");
+    }
+    if let Some(artist) = non_empty(&record.artist) {
+        tag.set_artist(artist);
+        changes.push("artist");
+    }
+    if let Some(album) = non_empty(&record.album) {
+        tag.set_album(album);
+        changes.push("album");
+    }
+    if let Some(album_artist) = non_empty(&record.album_artist) {
+        tag.set_album_artist(album_artist);
+        changes.push("album artist");
+    }
+    if let Some(track) = record.track {
+        tag.set_track(track);
+        changes.push("track");
+    }
+    if let Some(total_tracks) = record.total_tracks {
+        tag.set_total_tracks(total_tracks);
+        changes.push("total tracks");
+    }
+    if let Some(disc) = record.disc {
+        tag.set_disc(disc);
+        changes.push("disc");
+    }
+    if let Some(year) = record.year {
+        tag.set_year(year);
+        changes.push("year");
+    }
+    if let Some(genre) = non_empty(&record.genre) {
+        tag.set_genre(genre);
+        changes.push("genre");
+    }
+    if let Some(comment) = non_empty(&record.comment) {
+        tag.remove_comment(Some(""), None);
+        tag.add_frame(Comment {
+            lang: "eng".to_string(),
+            description: String::new(),
+            text: comment.to_string(),
+        });
+        changes.push("comment");
+    }
+
+    changes
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|s| !s.is_empty())
+}
@@ -0,0 +1,252 @@
+// src/index.rs
+use anyhow::{Context, Result};
+use id3::{Tag, TagLike};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::tagger::extended_text;
+
+const INDEX_FILE: &str = "library.db";
+
+/// Columns added after the first release, in the order they were introduced
+const METADATA_COLUMNS: &[(&str, &str)] = &[
+    ("artist", "TEXT"),
+    ("album_artist", "TEXT"),
+    ("album", "TEXT"),
+    ("title", "TEXT"),
+    ("genre", "TEXT"),
+    ("year", "INTEGER"),
+    ("track", "INTEGER"),
+    ("disc", "INTEGER"),
+    ("has_art", "INTEGER"),
+    ("has_lyrics", "INTEGER"),
+    ("has_replaygain", "INTEGER"),
+];
+
+/// The identifying fields of an indexed file
+pub struct IndexedFile {
+    pub path: PathBuf,
+    pub recording_id: Option<String>,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Persistent record of scanned files (size, mtime, embedded MBIDs, basic tags,
+/// last-tagged release) so library scans can skip files that haven't changed
+/// and queries don't have to re-read every tag
+pub struct LibraryIndex {
+    conn: Connection,
+}
+
+impl LibraryIndex {
+    /// Open the index under the user's data directory, creating it if needed
+    pub fn open() -> Result<Self> {
+        let dir = dirs::data_dir()
+            .context("Could not determine the data directory")?
+            .join("musictagger_rs");
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        Self::open_at(&dir.join(INDEX_FILE))
+    }
+
+    fn open_at(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open library index {}", path.display()))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS files (
+                path TEXT PRIMARY KEY,
+                size INTEGER NOT NULL,
+                mtime INTEGER NOT NULL,
+                album_id TEXT,
+                track_id TEXT,
+                recording_id TEXT,
+                tagged_release TEXT,
+                tagged_at TEXT
+            )",
+        )
+        .context("Failed to initialize library index")?;
+        migrate(&conn).context("Failed to upgrade library index")?;
+        Ok(Self { conn })
+    }
+
+    /// Whether the file's size and mtime still match the last scan
+    pub fn is_unchanged(&self, path: &Path) -> Result<bool> {
+        let Some((size, mtime)) = file_stamp(path) else {
+            return Ok(false);
+        };
+        let stored: Option<(i64, i64)> = self
+            .conn
+            .query_row(
+                "SELECT size, mtime FROM files WHERE path = ?1",
+                params![key(path)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(stored == Some((size, mtime)))
+    }
+
+    /// Re-read a file's tags and store its current size, mtime, and MBIDs
+    pub fn refresh(&self, path: &Path) -> Result<()> {
+        let (size, mtime) = file_stamp(path)
+            .with_context(|| format!("Failed to read metadata of {}", path.display()))?;
+        let tag = Tag::read_from_path(path).ok();
+        let text = |name: &str| tag.as_ref().and_then(|t| extended_text(t, name));
+
+        let album_id = text("MusicBrainz Album Id");
+        let track_id = text("MusicBrainz Release Track Id");
+        let recording_id = text("MusicBrainz Recording Id");
+
+        let tag = tag.as_ref();
+        let field = |get: fn(&Tag) -> Option<&str>| tag.and_then(get).map(str::to_string);
+        let year = tag.and_then(|t| {
+            t.year()
+                .or_else(|| t.date_released().map(|d| d.year))
+                .or_else(|| t.date_recorded().map(|d| d.year))
+        });
+        let has_art = tag.is_some_and(|t| t.pictures().next().is_some());
+        let has_lyrics = tag.is_some_and(|t| t.lyrics().any(|l| !l.text.trim().is_empty()));
+        let has_replaygain = tag.is_some_and(|t| {
+            t.extended_texts()
+                .any(|e| e.description.eq_ignore_ascii_case("REPLAYGAIN_TRACK_GAIN"))
+        });
+
+        self.conn.execute(
+            "INSERT INTO files (path, size, mtime, album_id, track_id, recording_id,
+                artist, album_artist, album, title, genre, year, track, disc,
+                has_art, has_lyrics, has_replaygain)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+             ON CONFLICT(path) DO UPDATE SET
+                size = ?2, mtime = ?3, album_id = ?4, track_id = ?5, recording_id = ?6,
+                artist = ?7, album_artist = ?8, album = ?9, title = ?10, genre = ?11,
+                year = ?12, track = ?13, disc = ?14,
+                has_art = ?15, has_lyrics = ?16, has_replaygain = ?17",
+            params![
+                key(path),
+                size,
+                mtime,
+                album_id,
+                track_id,
+                recording_id,
+                field(|t| t.artist()),
+                field(|t| t.album_artist()),
+                field(|t| t.album()),
+                field(|t| t.title()),
+                field(|t| t.genre()),
+                year,
+                tag.and_then(|t| t.track()),
+                tag.and_then(|t| t.disc()),
+                has_art,
+                has_lyrics,
+                has_replaygain,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Remember which release a file was tagged from. The stored size and mtime
+    /// are left alone, so the next scan still sees the file as changed.
+    pub fn record_tagged(&self, path: &Path, release_id: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO files (path, size, mtime, tagged_release, tagged_at)
+             VALUES (?1, -1, -1, ?2, ?3)
+             ON CONFLICT(path) DO UPDATE SET tagged_release = ?2, tagged_at = ?3",
+            params![key(path), release_id, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Drop a file that no longer exists
+    pub fn forget(&self, path: &Path) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM files WHERE path = ?1", params![key(path)])?;
+        Ok(())
+    }
+
+    /// Indexed paths matching an SQL condition on the `files` table, in path order
+    pub fn select(&self, condition: &str, values: &[Value]) -> Result<Vec<PathBuf>> {
+        let sql = format!("SELECT path FROM files WHERE {} ORDER BY path", condition);
+        let mut statement = self.conn.prepare(&sql)?;
+        let paths = statement
+            .query_map(params_from_iter(values), |row| row.get::<_, String>(0))?
+            .filter_map(|p| p.ok())
+            .map(PathBuf::from)
+            .collect();
+        Ok(paths)
+    }
+
+    /// Every indexed file, in path order
+    pub fn files(&self) -> Result<Vec<IndexedFile>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT path, recording_id, artist, title FROM files ORDER BY path")?;
+        let files = statement
+            .query_map([], |row| {
+                Ok(IndexedFile {
+                    path: PathBuf::from(row.get::<_, String>(0)?),
+                    recording_id: row.get(1)?,
+                    artist: row.get(2)?,
+                    title: row.get(3)?,
+                })
+            })?
+            .filter_map(|f| f.ok())
+            .collect();
+        Ok(files)
+    }
+
+    /// Indexed paths directly inside `dir`
+    pub fn files_in(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let dir = &dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let prefix = format!("{}{}", key(dir), std::path::MAIN_SEPARATOR);
+        let mut statement = self
+            .conn
+            .prepare("SELECT path FROM files WHERE substr(path, 1, length(?1)) = ?1")?;
+        let paths = statement
+            .query_map(params![prefix], |row| row.get::<_, String>(0))?
+            .filter_map(|p| p.ok())
+            .map(PathBuf::from)
+            .filter(|p| p.parent() == Some(dir))
+            .collect();
+        Ok(paths)
+    }
+}
+
+/// Add the metadata columns to an index created by an older version. Existing
+/// rows are marked stale so the next scan fills them in.
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version >= 1 {
+        return Ok(());
+    }
+
+    let mut statement = conn.prepare("SELECT name FROM pragma_table_info('files')")?;
+    let existing: Vec<String> = statement
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (name, kind) in METADATA_COLUMNS {
+        if !existing.iter().any(|c| c == name) {
+            conn.execute_batch(&format!("ALTER TABLE files ADD COLUMN {} {}", name, kind))?;
+        }
+    }
+    conn.execute_batch("UPDATE files SET size = -1, mtime = -1; PRAGMA user_version = 1")
+}
+
+/// Paths are stored canonicalized so the same file is found however it was reached
+fn key(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+fn file_stamp(path: &Path) -> Option<(i64, i64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let mtime = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_nanos() as i64;
+    Some((metadata.len() as i64, mtime))
+}
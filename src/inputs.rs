@@ -0,0 +1,124 @@
+// src/inputs.rs
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::exclude::ExcludeRules;
+use crate::matcher::find_mp3_files;
+
+/// The MP3 files selected by one or more `--path` arguments
+pub struct Inputs {
+    /// The single path given, or the folder all paths have in common.
+    /// Unmatched files and source cleanup are relative to it.
+    pub root: PathBuf,
+    pub files: Vec<PathBuf>,
+}
+
+/// Expand directories, individual files, and glob patterns into one candidate
+/// set. Directories are searched `max_depth` levels deep and honor `excludes`
+/// and their own `.mbtagignore`.
+pub fn resolve(args: &[PathBuf], excludes: &[String], max_depth: usize) -> Result<Inputs> {
+    let mut resolved = Vec::new();
+    for arg in args {
+        if arg.exists() {
+            resolved.push(arg.canonicalize().context("Failed to resolve path")?);
+            continue;
+        }
+
+        let pattern = arg.to_string_lossy();
+        if !pattern.contains(['*', '?', '[']) {
+            anyhow::bail!("Path does not exist: {}", arg.display());
+        }
+
+        let mut matched = glob::glob(&pattern)
+            .with_context(|| format!("Invalid glob pattern: {}", pattern))?
+            .filter_map(|p| p.ok())
+            .filter(|p| p.is_dir() || is_mp3(p))
+            .collect::<Vec<_>>();
+        if matched.is_empty() {
+            anyhow::bail!("No files match {}", pattern);
+        }
+        matched.sort();
+        for path in matched {
+            resolved.push(path.canonicalize().context("Failed to resolve path")?);
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+    for path in &resolved {
+        if path.is_dir() {
+            let exclude = ExcludeRules::load(path, excludes)?;
+            for file in find_mp3_files(path, &exclude, max_depth)? {
+                if seen.insert(file.clone()) {
+                    files.push(file);
+                }
+            }
+        } else if path.is_file() {
+            if path.extension().is_none() {
+                anyhow::bail!("File has no extension: {}", path.display());
+            }
+            if !is_mp3(path) {
+                anyhow::bail!("File must be an MP3: {}", path.display());
+            }
+            if seen.insert(path.clone()) {
+                files.push(path.clone());
+            }
+        } else {
+            anyhow::bail!("Path must be a file or directory: {}", path.display());
+        }
+    }
+
+    let root = match resolved.as_slice() {
+        [single] => single.clone(),
+        _ => common_ancestor(&resolved).context("Paths have no common folder")?,
+    };
+
+    Ok(Inputs { root, files })
+}
+
+/// Read newline-separated paths from a list file, or from stdin when `source` is `-`
+pub fn read_file_list(source: &Path) -> Result<Vec<PathBuf>> {
+    let contents = if source == Path::new("-") {
+        let mut contents = String::new();
+        std::io::stdin()
+            .read_to_string(&mut contents)
+            .context("Failed to read file list from stdin")?;
+        contents
+    } else {
+        std::fs::read_to_string(source)
+            .with_context(|| format!("Failed to read file list {}", source.display()))?
+    };
+
+    Ok(contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Deepest folder containing every path; a directory counts as its own folder
+fn common_ancestor(paths: &[PathBuf]) -> Option<PathBuf> {
+    let folder = |p: &PathBuf| {
+        if p.is_dir() {
+            Some(p.clone())
+        } else {
+            p.parent().map(Path::to_path_buf)
+        }
+    };
+
+    let mut common = folder(paths.first()?)?;
+    for path in &paths[1..] {
+        while !path.starts_with(&common) {
+            common = common.parent()?.to_path_buf();
+        }
+    }
+    Some(common)
+}
+
+fn is_mp3(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("mp3"))
+        .unwrap_or(false)
+}
@@ -0,0 +1,146 @@
+// src/junk.rs
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use glob::{MatchOptions, Pattern};
+use std::path::{Path, PathBuf};
+
+use crate::symbols;
+
+/// Files left behind by OSes, rippers, and scene releases
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    "Thumbs.db",
+    "desktop.ini",
+    ".DS_Store",
+    "._*",
+    "*.nfo",
+    "*.sfv",
+    "*.url",
+    "AlbumArtSmall.jpg",
+    "AlbumArt_*_Small.jpg",
+];
+
+/// Without a configured minimum art size, images at or below this size (in
+/// both dimensions) count as junk thumbnails
+pub const DEFAULT_MIN_ART_SIZE: u32 = 300;
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif"];
+
+/// What to do with junk found in an album folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum JunkAction {
+    /// Delete junk files
+    Delete,
+    /// Move junk into `_junk/` under the library root
+    Move,
+}
+
+pub struct JunkOptions {
+    pub action: JunkAction,
+    patterns: Vec<Pattern>,
+    min_art_size: Option<u32>,
+}
+
+impl JunkOptions {
+    /// Build options from glob patterns; an empty list uses DEFAULT_PATTERNS.
+    /// Images smaller than `min_art_size` on both sides are junk too, or
+    /// without one, images no larger than DEFAULT_MIN_ART_SIZE.
+    pub fn new(action: JunkAction, patterns: &[String], min_art_size: Option<u32>) -> Result<Self> {
+        let patterns = if patterns.is_empty() {
+            DEFAULT_PATTERNS.iter().map(|p| p.to_string()).collect()
+        } else {
+            patterns.to_vec()
+        };
+
+        let patterns = patterns
+            .iter()
+            .map(|p| Pattern::new(p).with_context(|| format!("Invalid junk pattern: {}", p)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            action,
+            patterns,
+            min_art_size,
+        })
+    }
+
+    pub fn is_junk(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy()) else {
+            return false;
+        };
+
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+        if self.patterns.iter().any(|p| p.matches_with(&name, options)) {
+            return true;
+        }
+
+        match self.min_art_size {
+            Some(min_size) => is_tiny_image(path, min_size.saturating_sub(1)),
+            None => is_tiny_image(path, DEFAULT_MIN_ART_SIZE),
+        }
+    }
+
+    /// Delete or relocate junk files in `dir`; returns how many were handled
+    pub fn clean_dir(&self, dir: &Path, junk_root: &Path, dry_run: bool) -> Result<usize> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Ok(0);
+        };
+
+        let junk: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && self.is_junk(p))
+            .collect();
+
+        for file in &junk {
+            let name = file.file_name().unwrap_or_default();
+            match self.action {
+                JunkAction::Delete => {
+                    println!(
+                        "  {} {}",
+                        symbols::fail().bright_red(),
+                        file.display().to_string().bright_black()
+                    );
+                    if !dry_run {
+                        std::fs::remove_file(file)
+                            .with_context(|| format!("Failed to delete {}", file.display()))?;
+                    }
+                }
+                JunkAction::Move => {
+                    let folder = dir.file_name().unwrap_or_default();
+                    // Album folders of the same name share a junk folder
+                    let destination =
+                        crate::organize::unique_path(&junk_root.join(folder).join(name));
+                    println!(
+                        "  {} {}",
+                        symbols::arrow().bright_cyan(),
+                        destination.display().to_string().bright_black()
+                    );
+                    if !dry_run {
+                        crate::organize::move_file(file, &destination)?;
+                    }
+                }
+            }
+        }
+
+        Ok(junk.len())
+    }
+}
+
+/// Low-resolution cover thumbnails (e.g. 75×75 "AlbumArtSmall") that are not worth keeping
+pub fn is_tiny_image(path: &Path, min_size: u32) -> bool {
+    let is_image = path
+        .extension()
+        .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+        .unwrap_or(false);
+    if !is_image {
+        return false;
+    }
+
+    image::image_dimensions(path)
+        .map(|(w, h)| w <= min_size && h <= min_size)
+        .unwrap_or(false)
+}
@@ -0,0 +1,51 @@
+//! Match MP3 files to MusicBrainz releases and write ID3 tags.
+//!
+//! The `musictagger_rs` binary is a thin CLI over this library. The library
+//! never prints; everything it learns is returned to the caller.
+//!
+//! ```no_run
+//! use musictagger_rs::events::NoEvents;
+//! use musictagger_rs::exclude::ExcludeRules;
+//! use musictagger_rs::matcher::{find_mp3_files, match_files};
+//! use musictagger_rs::musicbrainz::MusicBrainzClient;
+//! use musictagger_rs::profile::TagSettings;
+//! use musictagger_rs::provider::MetadataProvider;
+//! use musictagger_rs::tagger::{tag_files, Artwork};
+//! use musictagger_rs::CancellationToken;
+//! use std::path::Path;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let client = MusicBrainzClient::new();
+//! let album = client
+//!     .get_release("f5093c06-23e3-404f-aeaa-40f72885ee3a")
+//!     .await?;
+//!
+//! let files = find_mp3_files(Path::new("Downloads/album"), &ExcludeRules::default(), 3)?;
+//! let report = match_files(&files, &album, &NoEvents, &CancellationToken::new()).await?;
+//! for file in &report.unmatched_files {
+//!     eprintln!("no track for {}", file.display());
+//! }
+//!
+//! let art = Artwork::default();
+//! tag_files(&report.matches, &album, art, &TagSettings::default(), &NoEvents)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod art;
+pub mod audio;
+pub mod chapters;
+pub mod cue;
+pub mod error;
+pub mod events;
+pub mod exclude;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+pub mod matcher;
+pub mod musicbrainz;
+pub mod profile;
+pub mod provider;
+pub mod repair;
+pub mod tagger;
+
+pub use tokio_util::sync::CancellationToken;
@@ -0,0 +1,225 @@
+// src/lyrics.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use id3::frame::Lyrics;
+use id3::{Tag, TagLike};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::matcher::get_mp3_duration;
+use crate::symbols;
+use crate::tagger::read_tag;
+
+const LRCLIB_API: &str = "https://lrclib.net/api/get";
+const USER_AGENT: &str = concat!("musictagger_rs/", env!("CARGO_PKG_VERSION"));
+/// LRCLIB has no published limit; stay well clear of hammering it
+const REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+/// ISO 639-2 code ID3 uses for an unknown language
+const UNKNOWN_LANGUAGE: &str = "XXX";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LrclibResponse {
+    #[serde(default)]
+    instrumental: bool,
+    plain_lyrics: Option<String>,
+}
+
+enum Lookup {
+    Found(String),
+    Instrumental,
+    NotFound,
+}
+
+struct Candidate {
+    path: PathBuf,
+    artist: String,
+    title: String,
+    album: Option<String>,
+}
+
+/// Add unsynchronized lyrics (USLT) to tagged files that have none, looked up
+/// on LRCLIB by artist, title, album, and duration
+pub async fn run(path: &Path, dry_run: bool) -> Result<()> {
+    let files = crate::maintenance::collect_tree(path)?;
+    if files.is_empty() {
+        anyhow::bail!("No MP3 files found in {}", path.display());
+    }
+
+    let mut candidates = Vec::new();
+    let mut untagged = 0;
+    let pb = crate::progress::bar(files.len(), "Reading tags");
+    for file in files {
+        pb.inc(1);
+        let Ok(tag) = Tag::read_from_path(&file) else {
+            untagged += 1;
+            continue;
+        };
+        if tag.lyrics().any(|l| !l.text.trim().is_empty()) {
+            continue;
+        }
+        match (tag.artist(), tag.title()) {
+            (Some(artist), Some(title)) => candidates.push(Candidate {
+                artist: artist.to_string(),
+                title: title.to_string(),
+                album: tag.album().map(str::to_string),
+                path: file,
+            }),
+            _ => untagged += 1,
+        }
+    }
+    pb.finish_and_clear();
+
+    if candidates.is_empty() {
+        println!(
+            "{} No tagged files are missing lyrics",
+            symbols::ok().bright_green()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} Looking up lyrics for {} file(s)",
+        symbols::ok().bright_green(),
+        candidates.len()
+    );
+    if untagged > 0 {
+        println!(
+            "  {} file(s) without an artist and title were skipped",
+            untagged
+        );
+    }
+    println!();
+
+    let client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(Duration::from_secs(30))
+        .build()
+        .context("Failed to create HTTP client")?;
+
+    let (mut hits, mut instrumental, mut misses, mut errors) = (0, 0, 0, 0);
+    let pb = crate::progress::bar(candidates.len(), "Fetching lyrics");
+    for (i, candidate) in candidates.iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(REQUEST_INTERVAL).await;
+        }
+        let name = display_name(&candidate.path);
+        pb.set_message(name.clone());
+
+        let lookup = match fetch(&client, candidate).await {
+            Ok(lookup) => lookup,
+            Err(e) => {
+                pb.suspend(|| println!("  {} {}: {}", symbols::warn().bright_yellow(), name, e));
+                errors += 1;
+                pb.inc(1);
+                continue;
+            }
+        };
+
+        match lookup {
+            Lookup::Found(text) => {
+                if !dry_run {
+                    write_lyrics(&candidate.path, &text)?;
+                }
+                pb.suspend(|| {
+                    println!("  {} {}", symbols::ok().bright_green(), name.bright_white())
+                });
+                hits += 1;
+            }
+            Lookup::Instrumental => {
+                pb.suspend(|| {
+                    println!(
+                        "  {} {} {}",
+                        symbols::arrow().bright_cyan(),
+                        name,
+                        "(instrumental)".bright_black()
+                    )
+                });
+                instrumental += 1;
+            }
+            Lookup::NotFound => {
+                pb.suspend(|| println!("  {} {}", symbols::fail().bright_red(), name));
+                misses += 1;
+            }
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    println!();
+    let verb = if dry_run { "Would add" } else { "Added" };
+    println!(
+        "{} {} lyrics to {} file(s); {} not found, {} instrumental, {} failed",
+        symbols::ok().bright_green(),
+        verb,
+        hits,
+        misses,
+        instrumental,
+        errors
+    );
+    if dry_run {
+        println!("{}", "Dry run - no files were modified".bright_yellow());
+    }
+
+    Ok(())
+}
+
+async fn fetch(client: &reqwest::Client, candidate: &Candidate) -> Result<Lookup> {
+    let mut params = vec![
+        ("artist_name", candidate.artist.clone()),
+        ("track_name", candidate.title.clone()),
+    ];
+    if let Some(album) = &candidate.album {
+        params.push(("album_name", album.clone()));
+    }
+    // LRCLIB matches within a couple of seconds of the given duration
+    if let Some(ms) = get_mp3_duration(&candidate.path) {
+        params.push(("duration", (ms / 1000).to_string()));
+    }
+
+    let response = client
+        .get(LRCLIB_API)
+        .query(&params)
+        .send()
+        .await
+        .context("Failed to reach LRCLIB")?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(Lookup::NotFound);
+    }
+    if !response.status().is_success() {
+        anyhow::bail!("LRCLIB returned status: {}", response.status());
+    }
+
+    let body: LrclibResponse = response
+        .json()
+        .await
+        .context("Failed to parse LRCLIB response")?;
+
+    if body.instrumental {
+        return Ok(Lookup::Instrumental);
+    }
+    Ok(match body.plain_lyrics {
+        Some(text) if !text.trim().is_empty() => Lookup::Found(text),
+        _ => Lookup::NotFound,
+    })
+}
+
+fn write_lyrics(path: &Path, text: &str) -> Result<()> {
+    let mut tag =
+        read_tag(path).with_context(|| format!("Failed to read tags from {}", path.display()))?;
+    tag.add_frame(Lyrics {
+        lang: UNKNOWN_LANGUAGE.to_string(),
+        description: String::new(),
+        text: text.to_string(),
+    });
+    tag.write_to_path(path, tag.version())
+        .with_context(|| format!("Failed to write tags to {}", path.display()))
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
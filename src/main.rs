@@ -1,35 +1,136 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use colored::Colorize;
+use dialoguer::Confirm;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::sync::Arc;
 
+mod art_picker;
+mod artify;
+mod browser;
+mod casing;
+mod check;
+mod config;
+mod conflicts;
+mod credits;
+mod dedupe;
+mod doctor;
+mod extra_art;
+mod filename;
+mod filename_pattern;
+mod genre;
+mod genre_fill;
+mod import;
+mod index;
+mod inputs;
+mod junk;
+mod lyrics;
+mod maintenance;
+mod manual_edit;
 mod manual_mode;
-mod matcher;
-mod musicbrainz;
-mod tagger;
+mod marker;
+mod missing;
+mod mixed_albums;
+mod nfo;
+mod normalize;
+mod notify;
+mod organize;
+mod permissions;
+mod pin;
+mod playlist;
+mod progress;
+mod quality;
+mod query;
+mod rename;
+mod replaygain;
+mod report;
+mod results;
+mod review;
+mod search;
+mod selection;
+mod serve;
+mod session_log;
+mod setup;
+mod sidecar;
+mod stats;
+mod symbols;
+mod unmatched;
 mod updater;
 
+use musictagger_rs::{
+    art, audio, chapters, cue, error, events, exclude, matcher, musicbrainz, profile, provider,
+    repair, tagger,
+};
+
+use error::Error as MbError;
+use events::{Event, EventSink};
 use matcher::match_files;
-use musicbrainz::MusicBrainzClient;
-use tagger::tag_files;
+use musictagger_rs::CancellationToken;
+use provider::MetadataProvider;
 
 #[derive(Parser)]
 #[command(name = "musictagger_rs")]
 #[command(about = "Tag MP3 files with MusicBrainz metadata", long_about = None)]
-#[command(version)]
+#[command(version, args_override_self = true)]
+#[command(group(clap::ArgGroup::new("release").multiple(true).args(["album_id", "release_json"])))]
 struct Cli {
-    /// Path to MP3 file or directory containing MP3 files
-    #[arg(short, long)]
-    path: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// MP3 files, directories, or glob patterns to tag (repeatable)
+    #[arg(short, long = "path", num_args = 1..)]
+    paths: Vec<PathBuf>,
+
+    /// Read newline-separated paths from a file, or from stdin with `-`
+    #[arg(long, value_name = "LIST")]
+    files: Option<PathBuf>,
 
     /// MusicBrainz Release (Album) ID (required unless --manual is set)
     #[arg(short, long)]
     album_id: Option<String>,
 
+    /// Tag from a saved MusicBrainz release response (or JSON dump excerpt)
+    /// instead of fetching the release; with several releases in the file,
+    /// --album-id picks one
+    #[arg(long, value_name = "FILE")]
+    release_json: Option<PathBuf>,
+
+    /// Tag a single file as this MusicBrainz recording (ID or URL) instead of a whole release
+    #[arg(long)]
+    recording_id: Option<String>,
+
+    /// With --recording-id, also take the album fields and cover art from the
+    /// recording's earliest official release
+    #[arg(long, requires = "recording_id")]
+    with_release: bool,
+
     /// Manual tagging mode - enter metadata for each file interactively
     #[arg(short, long)]
     manual: bool,
 
+    /// Enter manual mode metadata for all tracks at once in $EDITOR
+    #[arg(long, requires = "manual")]
+    editor: bool,
+
+    /// Parse manual mode suggestions from filenames, e.g. "%track%. %artist% - %title%"
+    #[arg(long, requires = "manual")]
+    pattern: Option<String>,
+
+    /// Pre-fill manual mode from a template saved with --save-template
+    #[arg(long, value_name = "FILE", requires = "manual")]
+    from_template: Option<PathBuf>,
+
+    /// Save the manual mode entries to a JSON template, also usable with `import`
+    #[arg(long, value_name = "FILE", requires = "manual")]
+    save_template: Option<PathBuf>,
+
+    /// Rename files from their existing tags by the --template's file name, or
+    /// move them into a library with --organize; nothing is looked up or written
+    #[arg(long)]
+    rename_only: bool,
+
     /// Dry run - show matches without writing tags
     #[arg(short, long)]
     dry_run: bool,
@@ -38,10 +139,196 @@ struct Cli {
     #[arg(short = 'y', long)]
     yes: bool,
 
+    /// With --yes, overwrite files already tagged from a different release
+    #[arg(long, requires = "yes")]
+    force: bool,
+
+    /// Tag files in folders that have a completion marker
+    #[arg(long)]
+    ignore_markers: bool,
+
+    /// Where existing titles, artists, albums, or genres differ from MusicBrainz: overwrite, ask per field, or keep them
+    #[arg(long, value_enum, default_value = "overwrite")]
+    conflicts: conflicts::ConflictMode,
+
+    /// Write a .mb-tagger.toml marker into the album folder once all files are tagged
+    #[arg(long)]
+    mark: bool,
+
+    /// Write an album.toml pinning the release into the album folder, so later
+    /// runs there tag from it without --album-id
+    #[arg(long)]
+    pin: bool,
+
+    /// Clear the read-only bit on files before tagging them without asking
+    #[arg(long)]
+    make_writable: bool,
+
+    /// Rebuild damaged tags from the frames that still parse instead of skipping those files
+    #[arg(long)]
+    repair: bool,
+
+    /// Decode files of 256 kbps or more in the listing to spot ones upsampled from a low-bitrate source
+    #[arg(long)]
+    check_transcodes: bool,
+
+    /// For a single-file rip, also write a .cue sheet with the release's track titles and start times
+    #[arg(long)]
+    write_cue: bool,
+
+    /// Keep tagging the remaining files when one fails, then list the failures (exit code 3)
+    #[arg(long)]
+    keep_going: bool,
+
+    /// Only match these track numbers, e.g. 1-6,9
+    #[arg(long, value_name = "LIST", requires = "release")]
+    tracks: Option<String>,
+
+    /// Only match tracks from this disc
+    #[arg(long, requires = "release")]
+    disc: Option<u32>,
+
     /// Skip downloading cover art
     #[arg(long)]
     no_cover_art: bool,
 
+    /// Download cover art again instead of using the cached copy
+    #[arg(long, conflicts_with = "no_cover_art")]
+    refresh_art: bool,
+
+    /// Also embed a local image as another picture type, e.g. back=back.png
+    /// or media=label.jpg (repeatable)
+    #[arg(long = "art", value_name = "TYPE=FILE", value_parser = extra_art::parse)]
+    extra_art: Vec<extra_art::ExtraArt>,
+
+    /// Smallest cover art worth embedding, in pixels per side (overrides the config)
+    #[arg(long, value_name = "PX")]
+    min_art_size: Option<u32>,
+
+    /// What to do when the only cover art is below --min-art-size
+    #[arg(long, value_enum)]
+    small_art: Option<art_picker::SmallArt>,
+
+    /// Open the release's MusicBrainz page (or its release-group or artist page) in the browser
+    #[arg(long, value_enum, value_name = "PAGE", num_args = 0..=1, default_missing_value = "release")]
+    open: Option<browser::Page>,
+
+    /// Skip files and folders matching this glob (repeatable, adds to .mbtagignore)
+    #[arg(long = "exclude", value_name = "GLOB")]
+    excludes: Vec<String>,
+
+    /// Adjust tag version, frames, and art size for a specific player
+    #[arg(long, value_enum)]
+    profile: Option<profile::Profile>,
+
+    /// Largest cover art to embed, e.g. 500K or 2M; bigger art is re-encoded
+    /// or shrunk, or saved as cover.jpg instead
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_embed_bytes: Option<usize>,
+
+    /// TOML file with genre mappings and a blacklist applied before writing
+    #[arg(long)]
+    genre_map: Option<PathBuf>,
+
+    /// Move tagged files into <LIBRARY_ROOT> using --template [default: the library from `setup`]
+    #[arg(long, value_name = "LIBRARY_ROOT", num_args = 0..=1)]
+    organize: Option<Option<PathBuf>>,
+
+    /// Copy files into the library instead of moving them, leaving the source untouched
+    #[arg(long, requires = "organize")]
+    copy: bool,
+
+    /// Path template for --organize, relative to the library root (without extension)
+    /// [default: the template from `setup`, else "{albumartist}/{album} ({year})/{disc}{track} - {title}"]
+    #[arg(long)]
+    template: Option<String>,
+
+    /// Filename rules for --organize: windows (portable) or posix [default: current platform]
+    #[arg(long, value_enum)]
+    filename_mode: Option<filename::FilenameMode>,
+
+    /// Replace an illegal filename character, e.g. --replace ':= -' (repeatable)
+    #[arg(long = "replace", value_name = "CHAR=REPLACEMENT")]
+    replacements: Vec<String>,
+
+    /// Transliterate non-ASCII characters in generated filenames (tags keep full Unicode)
+    #[arg(long)]
+    ascii_filenames: bool,
+
+    /// Also write each file's tags to a JSON sidecar (<name>.json) next to it
+    #[arg(long)]
+    sidecar: bool,
+
+    /// Write JSON sidecars under --sidecar-dir instead of tagging, leaving the
+    /// files untouched (e.g. on read-only media)
+    #[arg(long, requires = "sidecar_dir", conflicts_with_all = ["organize", "sidecar"])]
+    sidecar_only: bool,
+
+    /// Put sidecars under this directory, in the album's folder layout,
+    /// instead of next to the files (implies --sidecar)
+    #[arg(long, value_name = "DIR")]
+    sidecar_dir: Option<PathBuf>,
+
+    /// Store a checksum of each file's audio stream (tags excluded) for the check command
+    #[arg(long)]
+    checksum: bool,
+
+    /// Delete or move aside junk (Thumbs.db, .DS_Store, scene .nfo, tiny thumbnails) when organizing
+    #[arg(long, value_enum, requires = "organize")]
+    junk: Option<junk::JunkAction>,
+
+    /// Filename glob treated as junk, replacing the default list (repeatable)
+    #[arg(long = "junk-pattern", value_name = "GLOB", requires = "junk")]
+    junk_patterns: Vec<String>,
+
+    /// Move unmatched files into _unmatched/ or list them in unmatched.txt
+    #[arg(long, value_enum)]
+    unmatched: Option<unmatched::UnmatchedAction>,
+
+    /// Write an album.m3u8 playlist next to the tagged files
+    #[arg(long)]
+    playlist: bool,
+
+    /// Write an album.nfo for Kodi/Jellyfin next to the tagged files
+    #[arg(long)]
+    nfo: bool,
+
+    /// Also write an artist.nfo into the parent (artist) folder
+    #[arg(long, requires = "nfo")]
+    artist_nfo: bool,
+
+    /// Casing applied to track titles
+    #[arg(long, value_enum, default_value_t)]
+    title_case: casing::CaseStyle,
+
+    /// Casing applied to album titles
+    #[arg(long, value_enum, default_value_t)]
+    album_case: casing::CaseStyle,
+
+    /// Casing applied to artist names
+    #[arg(long, value_enum, default_value_t)]
+    artist_case: casing::CaseStyle,
+
+    /// Where featured artists are credited: keep, title ("Song (feat. X)"), or artist ("A feat. X")
+    #[arg(long, value_enum, default_value_t)]
+    feat: credits::FeatPlacement,
+
+    /// Show a desktop notification when tagging finishes or fails
+    #[arg(long)]
+    notify: bool,
+
+    /// POST a JSON summary to this URL when tagging finishes or fails
+    #[arg(long, value_name = "URL")]
+    webhook: Option<String>,
+
+    /// Print one JSON object per event (scan, match, download, write) to stderr instead of progress bars
+    #[arg(long)]
+    progress_json: bool,
+
+    /// Keep a log of the run's matches, changes, and warnings (off by default)
+    #[arg(long, value_enum, value_name = "WHERE", default_value_t)]
+    session_log: session_log::Destination,
+
     /// Update to the latest version
     #[arg(long)]
     update: bool,
@@ -49,13 +336,292 @@ struct Cli {
     /// Check for available updates
     #[arg(long)]
     check_update: bool,
+
+    /// Use ASCII status symbols instead of Unicode, for consoles that garble them
+    #[arg(long, global = true)]
+    ascii: bool,
+
+    /// When choosing among releases (search, release groups), list the earliest first
+    #[arg(long, global = true, conflicts_with = "prefer_latest")]
+    prefer_original: bool,
+
+    /// When choosing among releases (search, release groups), list the newest first
+    #[arg(long, global = true)]
+    prefer_latest: bool,
+
+    /// Only offer releases from this year or range, e.g. 1997, 1990..1999, or 2010..
+    #[arg(long, global = true, value_name = "YEAR[..YEAR]")]
+    year: Option<provider::YearRange>,
+
+    /// Release countries to list first, most preferred first, e.g. US,GB,XW (overrides the config)
+    #[arg(
+        long = "country",
+        global = true,
+        value_delimiter = ',',
+        value_name = "CODE"
+    )]
+    countries: Vec<String>,
+
+    /// Media formats to list first, most preferred first, e.g. "Digital Media,CD" (overrides the config)
+    #[arg(
+        long = "media",
+        global = true,
+        value_delimiter = ',',
+        value_name = "FORMAT"
+    )]
+    formats: Vec<String>,
+}
+
+impl Cli {
+    /// How search results and release-group editions are ordered
+    fn ranking(&self) -> provider::ReleaseRanking {
+        let date = if self.prefer_original {
+            provider::DatePreference::Original
+        } else if self.prefer_latest {
+            provider::DatePreference::Latest
+        } else {
+            provider::DatePreference::Relevance
+        };
+        let countries = if self.countries.is_empty() {
+            config::get().countries.clone()
+        } else {
+            self.countries.clone()
+        };
+        let formats = if self.formats.is_empty() {
+            config::get().formats.clone()
+        } else {
+            self.formats.clone()
+        };
+        provider::ReleaseRanking {
+            date,
+            years: self.year.unwrap_or_default(),
+            countries,
+            formats,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Remove embedded pictures from MP3 files in a directory tree
+    RemoveArt {
+        /// MP3 file or directory to process recursively
+        path: PathBuf,
+
+        /// Only remove pictures of this type (repeatable, default: all)
+        #[arg(short = 't', long = "type", value_enum)]
+        types: Vec<maintenance::PictureKind>,
+
+        /// Show what would be removed without writing
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// Embed cover art into tagged files that have none, using their embedded release IDs
+    Artify {
+        /// MP3 file or directory to process recursively
+        path: PathBuf,
+
+        /// Also replace front covers smaller than this many pixels on either side
+        #[arg(long, value_name = "PX")]
+        min_size: Option<u32>,
+
+        /// Adjust tag version, frames, and art size for a specific player
+        #[arg(long, value_enum)]
+        profile: Option<profile::Profile>,
+
+        /// List the releases that would be fetched without writing
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// Fill empty genres from MusicBrainz using the embedded release and artist IDs
+    Genres {
+        /// MP3 file or directory to process recursively
+        path: PathBuf,
+
+        /// TOML file with genre mappings and a blacklist applied before writing
+        #[arg(long)]
+        genre_map: Option<PathBuf>,
+
+        /// Adjust how multiple genres are written for a specific player
+        #[arg(long, value_enum)]
+        profile: Option<profile::Profile>,
+
+        /// Show the genres that would be written without writing
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// Find duplicate recordings in the library index and remove the inferior copies
+    Dedupe {
+        /// Bring the index up to date for this directory first
+        #[arg(long, value_name = "DIR")]
+        scan: Option<PathBuf>,
+
+        /// Move inferior copies here instead of deleting them
+        #[arg(long, value_name = "DIR")]
+        quarantine: Option<PathBuf>,
+
+        /// List the duplicate groups without changing anything
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// List indexed files matching a query, e.g. "artist:radiohead year:2000..2010 missing:art"
+    Query {
+        /// Query terms; a leading ^ or - negates a term
+        #[arg(required = true, allow_hyphen_values = true)]
+        terms: Vec<String>,
+
+        /// Bring the index up to date for this directory before querying
+        #[arg(long, value_name = "DIR")]
+        scan: Option<PathBuf>,
+    },
+
+    /// Search MusicBrainz for releases by album title and print their IDs
+    Search {
+        /// Album title
+        title: String,
+
+        /// Only releases credited to this artist
+        #[arg(short, long)]
+        artist: Option<String>,
+    },
+
+    /// Compute and write ReplayGain for files that lack it
+    Replaygain {
+        /// MP3 file or directory to process recursively
+        path: PathBuf,
+
+        /// Also write the track gain as iTunes Sound Check (iTunNORM) for Apple devices
+        #[arg(long)]
+        sound_check: bool,
+
+        /// Analyze and print the gains without writing
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// Add lyrics from LRCLIB to tagged files that don't have any
+    Lyrics {
+        /// MP3 file or directory to process recursively
+        path: PathBuf,
+
+        /// Look up lyrics without writing them
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// Strip specific ID3 frames (e.g. COMM, PRIV) from MP3 files in a directory tree
+    Strip {
+        /// MP3 file or directory to process recursively
+        path: PathBuf,
+
+        /// Frame ID to remove (repeatable)
+        #[arg(short, long = "frame", required = true)]
+        frames: Vec<String>,
+
+        /// Show what would be removed without writing
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// Rewrite tagged files to one convention (ID3 version, multi-value separator, profile frames) without changing their values
+    Normalize {
+        /// MP3 file or directory to process recursively
+        path: PathBuf,
+
+        /// Follow this player's conventions, including which optional frames it gets
+        #[arg(long, value_enum)]
+        profile: Option<profile::Profile>,
+
+        /// Also remove this frame (repeatable); PRIV and NCON always go
+        #[arg(long = "strip", value_name = "FRAME")]
+        strip: Vec<String>,
+
+        /// Show what would change without writing
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// Regenerate album.m3u8 playlists for every album folder in a library
+    Playlists {
+        /// Library root to scan
+        path: PathBuf,
+
+        /// List album folders without writing playlists
+        #[arg(short, long)]
+        dry_run: bool,
+
+        /// Regenerate playlists even for albums that haven't changed
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Re-hash the audio of checksummed files and report any that changed
+    Check {
+        /// MP3 file or directory to process recursively
+        path: PathBuf,
+
+        /// Store checksums for files that don't have one yet
+        #[arg(long)]
+        add: bool,
+    },
+
+    /// Report problems in an album folder (duplicates, missing tracks, inconsistent tags)
+    Doctor {
+        /// Album directory to check
+        path: PathBuf,
+    },
+
+    /// Report releases in a library with missing tracks, duplicates, or extra files
+    Missing {
+        /// Library root to scan
+        path: PathBuf,
+    },
+
+    /// Summarize a library: formats, tag versions, MBID/art/lyrics coverage, bitrates, years, and genres
+    Stats {
+        /// Library root to scan
+        path: PathBuf,
+    },
+
+    /// Apply tag values from a JSON or CSV file (keyed by path or MBID)
+    Import {
+        /// JSON or CSV file with one record per track
+        file: PathBuf,
+
+        /// Library root that relative paths and MBID lookups resolve against
+        #[arg(short, long, default_value = ".")]
+        path: PathBuf,
+
+        /// Show what would be changed without writing
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// Serve lookup, search, match, and tag requests as JSON-RPC over a local socket
+    Serve {
+        /// Address to listen on
+        #[arg(short, long, default_value = "127.0.0.1:7878")]
+        listen: std::net::SocketAddr,
+
+        /// Adjust tag version, frames, and art size for a specific player
+        #[arg(long, value_enum)]
+        profile: Option<profile::Profile>,
+    },
+
+    /// Set the contact email, art size, rename template, and library root
+    Setup,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    symbols::init(cli.ascii);
 
-    // Handle update commands first (they don't require other arguments)
+    // Handle update commands first (they don't require other arguments or the config)
     // Run in blocking context since self_update is synchronous
     if cli.update {
         return tokio::task::spawn_blocking(updater::update)
@@ -70,75 +636,484 @@ async fn main() -> Result<()> {
             .map(|_| ());
     }
 
+    // Only `setup` and tagging runs open the wizard; other subcommands make do
+    // with the defaults, even when the config file can't be read
+    let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+    let config = match (&cli.command, config::load()) {
+        (Some(Command::Setup), Ok(saved)) => setup::run(saved)?,
+        (Some(Command::Setup), Err(e)) => {
+            println!(
+                "{} {:#}; starting over from the defaults",
+                symbols::warn().bright_yellow(),
+                e
+            );
+            println!();
+            setup::run(Some(config::Config::default()))?
+        }
+        (None, Ok(None)) if interactive && !cli.yes => setup::run(None)?,
+        (None, saved) => saved?.unwrap_or_default(),
+        (Some(_), Ok(saved)) => saved.unwrap_or_default(),
+        (Some(_), Err(e)) => {
+            println!(
+                "{} {:#}; using the defaults",
+                symbols::warn().bright_yellow(),
+                e
+            );
+            println!();
+            config::Config::default()
+        }
+    };
+    config::init(config);
+
+    let ranking = cli.ranking();
+    if let Some(command) = cli.command {
+        return match command {
+            Command::RemoveArt {
+                path,
+                types,
+                dry_run,
+            } => maintenance::remove_art(&path, &types, dry_run),
+            Command::Artify {
+                path,
+                min_size,
+                profile,
+                dry_run,
+            } => {
+                let settings = config::tag_settings(profile);
+                let config = config::get();
+                let min_size = min_size.or(config.min_art_size);
+                artify::run(&path, min_size, config.small_art, dry_run, &settings).await
+            }
+            Command::Genres {
+                path,
+                genre_map,
+                profile,
+                dry_run,
+            } => {
+                let rules = match genre_map {
+                    Some(file) => genre::GenreRules::load(&file)?,
+                    None => genre::GenreRules::default(),
+                };
+                let settings = config::tag_settings(profile);
+                genre_fill::run(&path, &rules, &settings, dry_run).await
+            }
+            Command::Dedupe {
+                scan,
+                quarantine,
+                dry_run,
+            } => dedupe::run(scan.as_deref(), quarantine.as_deref(), dry_run),
+            Command::Query { terms, scan } => query::run(&terms, scan.as_deref()),
+            Command::Search { title, artist } => {
+                search::run(&title, artist.as_deref(), &ranking).await
+            }
+            Command::Replaygain {
+                path,
+                sound_check,
+                dry_run,
+            } => replaygain::run(&path, sound_check, dry_run),
+            Command::Lyrics { path, dry_run } => lyrics::run(&path, dry_run).await,
+            Command::Strip {
+                path,
+                frames,
+                dry_run,
+            } => maintenance::strip_frames(&path, &frames, dry_run),
+            Command::Normalize {
+                path,
+                profile,
+                strip,
+                dry_run,
+            } => {
+                let settings = config::tag_settings(profile);
+                normalize::run(&path, &settings, profile.is_some(), &strip, dry_run)
+            }
+            Command::Playlists {
+                path,
+                dry_run,
+                force,
+            } => playlist::regenerate_library(&path, dry_run, force),
+            Command::Check { path, add } => check::run(&path, add),
+            Command::Doctor { path } => doctor::run(&path).await,
+            Command::Missing { path } => missing::run(&path).await,
+            Command::Stats { path } => stats::run(&path),
+            Command::Import {
+                file,
+                path,
+                dry_run,
+            } => import::run(&path, &file, dry_run),
+            Command::Serve { listen, profile } => {
+                serve::run(listen, config::tag_settings(profile)).await
+            }
+            Command::Setup => Ok(()),
+        };
+    }
+
+    // An album.toml in the album folder pins the release, options, and matches
+    let pinned = match pin::folder(&cli.paths) {
+        Some(dir) => pin::read(&dir)?.map(|pin| (dir, pin)),
+        None => None,
+    };
+    let cli = match &pinned {
+        Some((_, pin)) if !pin.options.is_empty() => {
+            let cli = with_pinned_options(&pin.options)?;
+            symbols::init(cli.ascii);
+            cli
+        }
+        _ => cli,
+    };
+
+    let notifier = notify::Notifier::new(cli.notify, cli.webhook.clone());
+    let mut summary = notify::RunSummary {
+        dry_run: cli.dry_run,
+        ..Default::default()
+    };
+    let result = run_tagging(cli, pinned, &mut summary).await;
+    notifier.finish(&mut summary, &result).await;
+    if result.is_ok() && summary.failed > 0 {
+        // Some files failed under --keep-going; scripts can tell that apart
+        // from both success (0) and an aborted run (1)
+        std::process::exit(3);
+    }
+    result
+}
+
+/// The command line again, with album.toml's options in front of the given
+/// arguments so that the given ones win
+fn with_pinned_options(options: &[String]) -> Result<Cli> {
+    pin::check_options(options)?;
+    let mut args: Vec<std::ffi::OsString> = std::env::args_os().take(1).collect();
+    args.extend(options.iter().map(Into::into));
+    args.extend(std::env::args_os().skip(1));
+    let cli = Cli::try_parse_from(args)
+        .with_context(|| format!("Invalid options in {}", pin::PIN_FILE))?;
+    if cli.command.is_some() {
+        anyhow::bail!(
+            "Invalid options in {}: a command can't be pinned",
+            pin::PIN_FILE
+        );
+    }
+    Ok(cli)
+}
+
+/// Tag the given files from a MusicBrainz release (or manually), filling in
+/// `summary` as the run progresses
+async fn run_tagging(
+    mut cli: Cli,
+    pinned: Option<(PathBuf, pin::Pin)>,
+    summary: &mut notify::RunSummary,
+) -> Result<()> {
+    let ranking = cli.ranking();
+    let pinned_release = pinned.as_ref().and_then(|(_, pin)| pin.release.clone());
+    let no_mode = cli.album_id.is_none()
+        && cli.release_json.is_none()
+        && cli.recording_id.is_none()
+        && !cli.manual
+        && !cli.rename_only;
+    let use_pinned = no_mode && pinned_release.is_some();
+    if use_pinned {
+        cli.album_id = pinned_release.clone();
+    }
+    let mut paths = cli.paths;
+    if let Some(list) = &cli.files {
+        // Prompts read from stdin too, so a piped list only works unattended
+        if list.as_os_str() == "-" && (cli.manual || !(cli.yes || cli.dry_run)) {
+            anyhow::bail!("--files - reads stdin, so it requires --yes or --dry-run and cannot be used with --manual");
+        }
+        paths.extend(inputs::read_file_list(list)?);
+    }
+
     // For tagging operations, path is required
-    let path = cli
-        .path
-        .context("--path is required for tagging operations")?;
+    if paths.is_empty() {
+        anyhow::bail!("--path or --files is required for tagging operations");
+    }
 
-    // Validate that either album_id or manual mode is specified
-    if cli.album_id.is_none() && !cli.manual {
-        anyhow::bail!("Either --album-id or --manual must be specified");
+    // Validate that exactly one of album_id, recording_id, and manual mode is specified
+    let modes = [
+        cli.album_id.is_some() || cli.release_json.is_some(),
+        cli.recording_id.is_some(),
+        cli.manual,
+        cli.rename_only,
+    ];
+    match modes.iter().filter(|m| **m).count() {
+        0 => anyhow::bail!(
+            "Either --album-id, --release-json, --recording-id, --manual, or --rename-only must be specified"
+        ),
+        1 => {}
+        _ => anyhow::bail!(
+            "Use only one of --album-id or --release-json, --recording-id, --manual, and --rename-only"
+        ),
     }
-    if cli.album_id.is_some() && cli.manual {
-        anyhow::bail!("Cannot use both --album-id and --manual at the same time");
+    if cli.conflicts == conflicts::ConflictMode::Ask && cli.yes {
+        anyhow::bail!("--conflicts ask needs prompts; use keep or overwrite with --yes");
     }
 
     println!("{}", "MusicBrainz MP3 Tagger".bright_cyan().bold());
     println!();
+    if let Some(release) = pinned_release.filter(|_| use_pinned) {
+        println!(
+            "{} Using release {} pinned by {}",
+            symbols::arrow().bright_cyan(),
+            release,
+            pin::PIN_FILE
+        );
+        println!();
+    }
 
-    // Validate and canonicalize path
-    if !path.exists() {
-        anyhow::bail!("Path does not exist: {}", path.display());
+    // Manual mode only looks at the top level of each folder
+    let max_depth = if cli.manual { 1 } else { 3 };
+    let mut inputs = inputs::resolve(&paths, &cli.excludes, max_depth)?;
+    // Marked folders are exactly the ones whose tags can be trusted for renaming
+    if !cli.ignore_markers && !cli.rename_only && !inputs.files.is_empty() {
+        inputs.files = marker::skip_marked(inputs.files, &inputs.root);
+        if inputs.files.is_empty() {
+            println!("{}", "Nothing left to tag.".bright_yellow());
+            return Ok(());
+        }
     }
+    let path = inputs.root;
+    summary.files = inputs.files.len();
 
-    let path = path.canonicalize().context("Failed to resolve path")?;
+    if cli.progress_json {
+        progress::hide();
+    }
+    let recorder = Arc::new(session_log::Recorder::new(cli.progress_json));
+    let events: &dyn EventSink = recorder.as_ref();
+    events.emit(Event::FilesScanned {
+        root: path.clone(),
+        files: inputs.files.len(),
+    });
 
-    // Accept both files and directories
-    if !path.is_file() && !path.is_dir() {
-        anyhow::bail!("Path must be a file or directory: {}", path.display());
+    let mut settings = config::tag_settings(cli.profile);
+    if let Some(max_embed_bytes) = cli.max_embed_bytes {
+        settings.max_embed_bytes = max_embed_bytes;
     }
+    let selection = selection::TrackSelection::new(cli.tracks.as_deref(), cli.disc)?;
+    let genre_rules = cli
+        .genre_map
+        .as_deref()
+        .map(genre::GenreRules::load)
+        .transpose()?;
 
-    // If it's a file, verify it's an MP3
-    if path.is_file() {
-        if let Some(ext) = path.extension() {
-            if !ext.eq_ignore_ascii_case("mp3") {
-                anyhow::bail!("File must be an MP3: {}", path.display());
-            }
-        } else {
-            anyhow::bail!("File has no extension: {}", path.display());
-        }
+    if let Some(recording_arg) = &cli.recording_id {
+        let run = RecordingRun {
+            with_release: cli.with_release,
+            cover_art: !cli.no_cover_art,
+            refresh_art: cli.refresh_art,
+            dry_run: cli.dry_run,
+            yes: cli.yes,
+            make_writable: cli.make_writable,
+            repair: cli.repair,
+            settings: &settings,
+            genre_rules: genre_rules.as_ref(),
+        };
+        return tag_recording(recording_arg, &inputs.files, &run, summary).await;
     }
 
-    // Branch to manual mode if requested
-    if cli.manual {
-        return manual_mode::run(&path, cli.dry_run, cli.yes);
+    let filenames = filename::FilenameRules::new(
+        cli.filename_mode.unwrap_or_default(),
+        &cli.replacements,
+        cli.ascii_filenames,
+    )?;
+    let junk = cli
+        .junk
+        .map(|action| {
+            let min_art_size = cli.min_art_size.or(config::get().min_art_size);
+            junk::JunkOptions::new(action, &cli.junk_patterns, min_art_size)
+        })
+        .transpose()?;
+    let template = cli
+        .template
+        .or_else(|| config::get().template.clone())
+        .unwrap_or_else(|| organize::DEFAULT_TEMPLATE.to_string());
+    let organize_options = match cli.organize {
+        Some(library_root) => Some(organize::OrganizeOptions {
+            library_root: library_root
+                .or_else(|| config::get().library.clone())
+                .context(
+                "--organize needs a library root; pass one or set it with `musictagger_rs setup`",
+            )?,
+            template,
+            filenames,
+            junk,
+            copy: cli.copy,
+            in_place: false,
+            dry_run: cli.dry_run,
+        }),
+        None if cli.rename_only => Some(organize::OrganizeOptions {
+            library_root: path.clone(),
+            template,
+            filenames,
+            junk,
+            copy: false,
+            in_place: true,
+            dry_run: cli.dry_run,
+        }),
+        None => None,
+    };
+
+    if cli.rename_only {
+        let options = organize_options.expect("--rename-only always has options");
+        return rename::run(&inputs.files, &path, &options);
     }
 
-    let album_id = cli.album_id.unwrap();
+    let casing = casing::CasingRules {
+        title: cli.title_case,
+        album: cli.album_case,
+        artist: cli.artist_case,
+    };
+
+    // Branch to manual mode if requested; it can hand a release back to us
+    let (mut album_id, exported) = if cli.manual {
+        let pattern = cli
+            .pattern
+            .as_deref()
+            .map(filename_pattern::FilenamePattern::parse)
+            .transpose()?;
+        let template = cli
+            .from_template
+            .as_deref()
+            .map(import::read_records)
+            .transpose()?;
+        let options = manual_mode::ManualOptions {
+            dry_run: cli.dry_run,
+            yes: cli.yes,
+            settings: &settings,
+            organize: organize_options.as_ref(),
+            playlist: cli.playlist,
+            pattern: pattern.as_ref(),
+            editor: cli.editor,
+            make_writable: cli.make_writable,
+            repair: cli.repair,
+            keep_going: cli.keep_going,
+            events,
+            template: template.as_deref(),
+            save_template: cli.save_template.as_deref(),
+            ranking: &ranking,
+            casing,
+            genre_rules: genre_rules.as_ref(),
+        };
+        let album_id = match manual_mode::run(&path, &inputs.files, &options).await? {
+            manual_mode::ManualOutcome::Done => return Ok(()),
+            manual_mode::ManualOutcome::PartlyFailed(failed) => {
+                summary.failed = failed;
+                return Ok(());
+            }
+            manual_mode::ManualOutcome::Release(release_id) => release_id,
+        };
+        println!(
+            "{} Switching to MusicBrainz release {}",
+            symbols::arrow().bright_cyan(),
+            album_id
+        );
+        println!();
+        (album_id, None)
+    } else if let Some(json) = &cli.release_json {
+        let wanted = cli
+            .album_id
+            .as_deref()
+            .map(release_id_from_arg)
+            .transpose()?;
+        let album = read_release_json(json, wanted.as_deref())?;
+        (album.id.clone(), Some(album))
+    } else {
+        (release_id_from_arg(&cli.album_id.unwrap())?, None)
+    };
+    summary.release_id = Some(album_id.clone());
 
     // List all files in the directory or single file
-    if path.is_dir() {
+    if paths.len() == 1 && path.is_dir() {
         println!("{}", "Files in directory:".bright_white());
-        list_directory_contents(&path)?;
+        list_directory_contents(&path, cli.check_transcodes)?;
         println!();
     } else {
-        println!("{}", "Target file:".bright_white());
-        list_single_file(&path)?;
+        println!("{}", "Target files:".bright_white());
+        for file in &inputs.files {
+            list_single_file(file, cli.check_transcodes)?;
+        }
         println!();
     }
 
     // Initialize MusicBrainz client
+    let mb_events = Arc::clone(&recorder);
+    let mb_client = config::musicbrainz_builder()
+        .refresh_art(cli.refresh_art)
+        .events(move |event| mb_events.emit(event))
+        .build()
+        .context("Failed to create HTTP client")?;
+    // A release from --release-json is tagged without going online
+    let offline = exported.is_some();
+    let fetched = match exported {
+        Some(album) => Ok(album),
+        None => {
+            println!(
+                "{}",
+                "Fetching album metadata from MusicBrainz...".bright_yellow()
+            );
+            mb_client.get_release(&album_id).await
+        }
+    };
+    let mut album = match fetched {
+        Ok(album) => album,
+        // Only worth the extra requests once the lookup has already failed
+        Err(MbError::NotFound(_)) => match mb_client.entity_kind(&album_id).await {
+            // Any of its releases will do; let the user pick the edition
+            Ok(Some("release-group")) => {
+                album_id =
+                    search::choose_from_release_group(&mb_client, &album_id, &ranking, !cli.yes)
+                        .await?;
+                summary.release_id = Some(album_id.clone());
+                mb_client
+                    .get_release(&album_id)
+                    .await
+                    .context("Failed to fetch album from MusicBrainz")?
+            }
+            kind => {
+                let hint = match kind {
+                    Ok(Some(kind)) => {
+                        let url = browser::entity_url(kind, &album_id);
+                        if cli.open.is_some() {
+                            browser::open(&url);
+                        }
+                        format!(
+                            "{} is a MusicBrainz {}, not a release. Open {} and copy the ID of one of its releases",
+                            album_id, kind, url
+                        )
+                    }
+                    _ => format!(
+                        "Release {} does not exist on MusicBrainz. Find the right one with: musictagger_rs search \"<album>\" --artist \"<artist>\"",
+                        album_id
+                    ),
+                };
+                anyhow::bail!(hint);
+            }
+        },
+        Err(e @ MbError::NetworkError(_)) => {
+            return Err(e).context("Could not reach MusicBrainz; check your connection");
+        }
+        Err(e) => return Err(e).context("Failed to fetch album from MusicBrainz"),
+    };
+
+    if let Some(rules) = &genre_rules {
+        album.genres = rules.apply(&album.genres);
+    }
+
+    credits::apply_to_album(cli.feat, &mut album);
+    summary.album = Some(album.title.clone());
+    summary.artist = Some(album.artist.clone());
+
+    if !casing.is_noop() {
+        casing.apply_to_album(&mut album);
+    }
+
+    let release_track_count = album.tracks.len();
+    if !selection.is_all() {
+        selection.apply(&mut album)?;
+    }
+
     println!(
-        "{}",
-        "Fetching album metadata from MusicBrainz...".bright_yellow()
+        "{} {}",
+        symbols::ok().bright_green(),
+        "Album found:".bright_white()
     );
-    let mb_client = MusicBrainzClient::new();
-    let album = mb_client
-        .get_release(&album_id)
-        .await
-        .context("Failed to fetch album from MusicBrainz")?;
-
-    println!("{} {}", "✓".bright_green(), "Album found:".bright_white());
     println!(
         "  {} by {}",
         album.title.bright_cyan(),
@@ -147,47 +1122,166 @@ async fn main() -> Result<()> {
     if album.media_count > 1 {
         println!(
             "  {} discs, {} total tracks",
-            album.media_count,
-            album.tracks.len()
+            album.media_count, release_track_count
         );
     } else {
-        println!("  {} tracks", album.tracks.len());
+        println!("  {} tracks", release_track_count);
+    }
+    if !album.genres.is_empty() {
+        println!("  Genre: {}", album.genres.join(", "));
+    }
+    if album.tracks.len() < release_track_count {
+        println!(
+            "  Selected {} of {} tracks",
+            album.tracks.len(),
+            release_track_count
+        );
+    }
+    if let Some(page) = cli.open {
+        browser::open_page(&album, page);
     }
     println!();
 
     // Fetch cover art
-    let cover_art = if !cli.no_cover_art {
+    let min_art_size = cli.min_art_size.or(config::get().min_art_size);
+    let small_art = cli.small_art.unwrap_or(config::get().small_art);
+    let (cover_art, folder_art) = if !cli.no_cover_art && !offline {
         println!("{}", "Fetching cover art...".bright_yellow());
-        match mb_client.get_cover_art(&album_id).await {
-            Ok(art) => {
+        match art_picker::cover_art(&mb_client, &album_id, settings.max_art_size, !cli.yes).await {
+            Ok(None) => {
+                println!("{}", "No cover art chosen".bright_yellow());
+                println!();
+                (None, None)
+            }
+            Ok(Some(art)) => {
                 println!(
-                    "{} Cover art downloaded ({:.1} KB)",
-                    "✓".bright_green(),
+                    "{} Cover art ready ({:.1} KB)",
+                    symbols::ok().bright_green(),
                     art.len() as f64 / 1024.0
                 );
-                println!();
-                Some(art)
+                let too_small = min_art_size
+                    .and_then(|min| art_picker::below_minimum(&art, min).map(|dims| (min, dims)));
+                if let Some((min, (width, height))) = too_small {
+                    let message = format!(
+                        "Cover art is only {}x{}, below the {} px minimum; {} needs better art",
+                        width, height, min, album.title
+                    );
+                    println!("{} {}", symbols::warn().bright_yellow(), message);
+                    events.emit(Event::Warning { message });
+                }
+                if too_small.is_some() && small_art == art_picker::SmallArt::Skip {
+                    println!("{}", "Not embedding it".bright_yellow());
+                    println!();
+                    (None, None)
+                } else {
+                    let fitted = fit_cover_art(art, &settings);
+                    println!();
+                    fitted
+                }
             }
             Err(e) => {
                 println!(
                     "{} {}: {}",
-                    "⚠".bright_yellow(),
+                    symbols::warn().bright_yellow(),
                     "Could not fetch cover art".bright_yellow(),
                     e
                 );
                 println!();
-                None
+                (None, None)
             }
         }
     } else {
         println!("{}", "Skipping cover art download".bright_yellow());
         println!();
+        (None, None)
+    };
+    let disc_art = if cli.no_cover_art || offline {
+        BTreeMap::new()
+    } else {
+        art_picker::disc_art(&mb_client, &album, &settings, min_art_size, small_art).await
+    };
+    if !disc_art.is_empty() {
+        println!();
+    }
+
+    let extra_pictures = if cli.extra_art.is_empty() {
+        Vec::new()
+    } else {
+        let album_dir = if path.is_dir() {
+            path.as_path()
+        } else {
+            path.parent().unwrap_or(&path)
+        };
+        let pictures = extra_art::load(&cli.extra_art, album_dir, &settings)?;
+        println!();
+        pictures
+    };
+
+    let cue_sheet = if path.is_dir() {
+        cue::find(&path)?
+    } else {
         None
     };
+    if let Some((cue_path, _)) = &cue_sheet {
+        println!(
+            "{} Using CUE sheet {}",
+            symbols::arrow().bright_cyan(),
+            cue_path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        println!();
+    }
+
+    // One long file holding the whole tracklist gets chapters rather than
+    // being tagged as a single track
+    if let [file] = inputs.files.as_slice() {
+        let rip = ChapterRip {
+            file,
+            cue: cue_sheet
+                .as_ref()
+                .and_then(|(_, sheet)| sheet.entry_for(file))
+                .filter(|entry| entry.tracks.len() > 1),
+            album: &album,
+            cover_art: cover_art.as_deref(),
+            folder_art: folder_art.as_deref(),
+            settings: &settings,
+            write_cue: cli.write_cue,
+        };
+        if tag_album_rip(&rip, cli.dry_run, cli.yes, summary)? {
+            return Ok(());
+        }
+    }
+
+    // A folder holding several albums would otherwise match the wrong tracks
+    let files = mixed_albums::check(&inputs.files, &album, !cli.yes).await?;
 
     // Find and match MP3 files
     println!("{}", "Matching files to tracks...".bright_yellow());
-    let matches = match_files(&path, &album)?;
+    if !files.is_empty() {
+        report::print_tracks(&album);
+    }
+    // A CUE sheet naming each file gives the exact track order
+    let cue_report = cue_sheet
+        .as_ref()
+        .and_then(|(_, sheet)| cue::match_by_cue(sheet, &files, &album, events));
+    let mut match_report = match cue_report {
+        Some(match_report) => {
+            report::print_cue_report(&album, &match_report);
+            match_report
+        }
+        None => {
+            let match_report =
+                match_files(&files, &album, events, &CancellationToken::new()).await?;
+            if !files.is_empty() {
+                report::print_match_report(&album, &match_report);
+            }
+            match_report
+        }
+    };
+    if let Some((dir, pin)) = &pinned {
+        pin::apply_matches(pin, dir, &album, &mut match_report)?;
+    }
+    let mut matches = match_report.matches;
+    summary.matched = matches.len();
 
     if matches.is_empty() {
         println!(
@@ -204,7 +1298,7 @@ async fn main() -> Result<()> {
     println!();
     println!(
         "{} Matched {} of {} files",
-        "✓".bright_green(),
+        symbols::ok().bright_green(),
         matches.len(),
         album.tracks.len()
     );
@@ -246,7 +1340,8 @@ async fn main() -> Result<()> {
                 format!("{:.0}%", m.confidence * 100.0).color(confidence_color)
             );
             println!(
-                "   → Track {}: {} - {}",
+                "   {} Track {}: {} - {}",
+                symbols::arrow(),
                 m.track.position,
                 m.track.artist.bright_white(),
                 m.track.title.bright_white()
@@ -274,7 +1369,8 @@ async fn main() -> Result<()> {
                 format!("{:.0}%", m.confidence * 100.0).color(confidence_color)
             );
             println!(
-                "   → Track {}: {} - {}",
+                "   {} Track {}: {} - {}",
+                symbols::arrow(),
                 m.track.position,
                 m.track.artist.bright_white(),
                 m.track.title.bright_white()
@@ -283,41 +1379,946 @@ async fn main() -> Result<()> {
         }
     }
 
-    if cli.dry_run {
-        println!("{}", "Dry run - no files were modified.".bright_yellow());
-        return Ok(());
-    }
-
-    // Confirm with user
-    if !cli.yes {
-        use dialoguer::Confirm;
-        let confirmed = Confirm::new()
-            .with_prompt("Do you want to apply these tags?")
-            .default(false)
-            .interact()?;
-
-        if !confirmed {
-            println!("{}", "Aborted.".bright_yellow());
-            return Ok(());
-        }
+    // Files already tagged from another release are usually a different
+    // edition, and overwriting them by accident is hard to undo
+    let other_releases = tagged_from_other_releases(&matches, &album_id);
+    if !other_releases.is_empty() {
+        let count: usize = other_releases.iter().map(|(_, n)| n).sum();
+        println!(
+            "{} {}",
+            symbols::warn().bright_red(),
+            format!(
+                "{} file(s) are already tagged from a different MusicBrainz release:",
+                count
+            )
+            .bright_red()
+            .bold()
+        );
+        for (release_id, n) in &other_releases {
+            println!(
+                "    {} ({} file(s))",
+                browser::entity_url("release", release_id),
+                n
+            );
+        }
+        println!(
+            "  Tagging replaces those tags with this release ({})",
+            album_id
+        );
+        println!();
+
+        if !cli.dry_run {
+            if cli.yes {
+                if !cli.force {
+                    anyhow::bail!(
+                        "Refusing to overwrite tags from a different release without --force"
+                    );
+                }
+            } else if !Confirm::new()
+                .with_prompt("Overwrite them?")
+                .default(false)
+                .interact()?
+            {
+                println!("{}", "Aborted.".bright_yellow());
+                return Ok(());
+            }
+        }
     }
 
-    // Apply tags
+    // Extra copies of matched tracks are handled apart from unmatched files
+    let is_duplicate =
+        |file: &PathBuf| match_report.duplicates.iter().any(|d| d.file_path == *file);
+    // Files that did not end up matched to any track
+    let unmatched_files: Vec<PathBuf> = if cli.unmatched.is_some() && path.is_dir() {
+        inputs
+            .files
+            .iter()
+            .filter(|f| !matches.iter().any(|m| &m.file_path == *f) && !is_duplicate(f))
+            .cloned()
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut album_overrides = conflicts::AlbumOverrides::default();
+    let mut organize_plan = plan_organize(&matches, &album, &album_overrides);
+
+    if cli.dry_run {
+        if cli.conflicts != conflicts::ConflictMode::Overwrite {
+            conflicts::print(&conflicts::find(&matches, &album), &matches);
+        }
+        if let Some(action) = cli.unmatched {
+            unmatched::handle(action, &unmatched_files, &path, true)?;
+            println!();
+        }
+        if let Some(options) = &organize_options {
+            organize::organize(&organize_plan, &path, options)?;
+            println!();
+        }
+        println!("{}", "Dry run - no files were modified.".bright_yellow());
+        return Ok(());
+    }
+
+    // Confirm with user, who may correct individual matches first
+    if !cli.yes {
+        if !review::confirm(&mut matches, &album)? {
+            println!("{}", "Aborted.".bright_yellow());
+            return Ok(());
+        }
+        organize_plan = plan_organize(&matches, &album, &album_overrides);
+    }
+
+    // After review, so values edited there are what gets compared
+    if cli.conflicts != conflicts::ConflictMode::Overwrite {
+        let conflicts = conflicts::find(&matches, &album);
+        if !conflicts.is_empty() {
+            println!();
+            album_overrides = conflicts::resolve(cli.conflicts, &conflicts, &mut matches)?;
+            organize_plan = plan_organize(&matches, &album, &album_overrides);
+        }
+    }
+
+    if cli.sidecar_only {
+        println!();
+        let files: Vec<PathBuf> = matches.iter().map(|m| m.file_path.clone()).collect();
+        let root = organize::common_dir(&files).unwrap_or_else(|| path.clone());
+        write_sidecars(
+            &matches,
+            &files,
+            &root,
+            cli.sidecar_dir.as_deref(),
+            &album,
+            &album_overrides,
+            &settings,
+        )?;
+        summary.tagged = matches.len();
+        return Ok(());
+    }
+
+    // Unless corrected into a match during review
+    let duplicates: Vec<&matcher::Duplicate> = match_report
+        .duplicates
+        .iter()
+        .filter(|d| !matches.iter().any(|m| m.file_path == d.file_path))
+        .collect();
+    if !organize_options.as_ref().is_some_and(|o| o.copy) {
+        unmatched::offer_duplicates(&duplicates, !cli.yes)?;
+    }
+
+    // Copy → original, so results show the file the user pointed at
+    let mut copied_from: Vec<(PathBuf, PathBuf)> = Vec::new();
+    // In copy mode the files are copied first and only the copies get tagged,
+    // so the source folder is left exactly as it was
+    let copied_paths = match &organize_options {
+        Some(options) if options.copy => {
+            println!();
+            let copied = organize::organize(&organize_plan, &path, options)?;
+            // Files that were not copied (destination exists) keep their source
+            // path and must not be tagged, unless they are the destination
+            // already: those are in the library and tagged where they are
+            let in_library = |(file, values): &(PathBuf, organize::TemplateValues)| {
+                organize::destination(file, values, &path, options).is_ok_and(|d| d == *file)
+            };
+            matches = matches
+                .into_iter()
+                .zip(&copied)
+                .zip(&organize_plan)
+                .filter(|((m, copy), planned)| m.file_path != **copy || in_library(planned))
+                .map(|((mut m, copy), _)| {
+                    if m.file_path != *copy {
+                        copied_from.push((copy.clone(), m.file_path.clone()));
+                    }
+                    m.file_path = copy.clone();
+                    m
+                })
+                .collect();
+            Some(copied)
+        }
+        _ => None,
+    };
+
+    // Check before writing so one locked file doesn't stop the run halfway
     println!();
+    let targets: Vec<PathBuf> = matches.iter().map(|m| m.file_path.clone()).collect();
+    let mut skipped = permissions::check_writable(&targets, cli.make_writable, !cli.yes)?;
+    let writable: Vec<PathBuf> = targets
+        .into_iter()
+        .filter(|file| !skipped.contains(file))
+        .collect();
+    skipped.extend(check_tags(&writable, cli.repair, settings.version)?);
+    let mut file_results: Vec<results::FileResult> = matches
+        .iter()
+        .filter(|m| skipped.contains(&m.file_path))
+        .map(|m| results::FileResult::new(m, &album, "skipped"))
+        .collect();
+    if !skipped.is_empty() {
+        matches.retain(|m| !skipped.contains(&m.file_path));
+        organize_plan.retain(|(file, _)| !skipped.contains(file));
+    }
+
+    // Apply tags
     println!("{}", "Writing tags...".bright_yellow());
-    tag_files(&matches, &album, cover_art)?;
+    let before: Vec<results::Snapshot> = matches
+        .iter()
+        .map(|m| results::snapshot(&m.file_path))
+        .collect();
+    let art_to_embed = EmbeddedArt {
+        cover: cover_art.as_deref(),
+        discs: &disc_art,
+        extra: &extra_pictures,
+    };
+    let failures = write_all_tags(
+        &matches,
+        &album,
+        &album_overrides,
+        &art_to_embed,
+        &settings,
+        cli.keep_going,
+        events,
+    )?;
+    for (m, before) in matches.iter().zip(&before) {
+        let mut result = results::FileResult::new(m, &album, "tagged");
+        match failures.iter().find(|(f, _)| *f == m.file_path) {
+            Some((_, error)) => {
+                result.status = "failed";
+                result.error = Some(error.root_cause().to_string());
+            }
+            None => {
+                result.changed = results::changed_fields(before, &results::snapshot(&m.file_path));
+                result.art_embedded = cover_art.is_some()
+                    || disc_art.contains_key(&m.track.disc_number)
+                    || !extra_pictures.is_empty();
+            }
+        }
+        file_results.push(result);
+    }
+    // Failed files stay where they are, untouched by organizing and the index
+    let failed = |file: &PathBuf| failures.iter().any(|(f, _)| f == file);
+    matches.retain(|m| !failed(&m.file_path));
+    organize_plan.retain(|(file, _)| !failed(file));
+    summary.tagged = matches.len();
+    summary.failed = failures.len();
+
+    println!();
+    print_outcome(matches.len(), skipped.len(), failures.len());
+
+    if cli.checksum {
+        let files: Vec<PathBuf> = matches.iter().map(|m| m.file_path.clone()).collect();
+        check::store(&files, events)?;
+    }
+
+    // Set aside before organizing so leftovers stay with the source folder
+    if let Some(action) = cli.unmatched {
+        println!();
+        unmatched::handle(action, &unmatched_files, &path, false)?;
+    }
+
+    let sources: Vec<PathBuf> = organize_plan.iter().map(|(file, _)| file.clone()).collect();
+    let final_paths = match (&organize_options, copied_paths) {
+        (_, Some(copied)) => copied,
+        (Some(options), None) => {
+            println!();
+            organize::organize(&organize_plan, &path, options)?
+        }
+        (None, None) => organize_plan.into_iter().map(|(file, _)| file).collect(),
+    };
+
+    for result in &mut file_results {
+        if let Some((copy, original)) = copied_from.iter().find(|(copy, _)| *copy == result.file) {
+            result.file = original.clone();
+            result.renamed_to = Some(copy.clone());
+        } else if let Some((_, moved)) = sources
+            .iter()
+            .zip(&final_paths)
+            .find(|(source, moved)| **source == result.file && source != moved)
+        {
+            result.renamed_to = Some(moved.clone());
+        }
+    }
+
+    if let Some(art) = &folder_art {
+        save_folder_art(art, &final_paths)?;
+    }
+    save_disc_art(&disc_art, &matches, &sources, &final_paths)?;
+
+    if cli.sidecar || cli.sidecar_dir.is_some() {
+        // Where each tagged file ended up after organizing
+        let files: Vec<PathBuf> = matches
+            .iter()
+            .map(|m| {
+                sources
+                    .iter()
+                    .position(|source| *source == m.file_path)
+                    .map(|i| final_paths[i].clone())
+                    .unwrap_or_else(|| m.file_path.clone())
+            })
+            .collect();
+        let root = organize::common_dir(&files).unwrap_or_else(|| path.clone());
+        write_sidecars(
+            &matches,
+            &files,
+            &root,
+            cli.sidecar_dir.as_deref(),
+            &album,
+            &album_overrides,
+            &settings,
+        )?;
+    }
+
+    record_in_index(&final_paths, &album.id);
+
+    if cli.playlist {
+        write_playlist(&final_paths)?;
+    }
+
+    if cli.nfo {
+        if let Some(album_dir) = organize::common_dir(&final_paths) {
+            let nfo_path = nfo::write_album_nfo(&album_dir, &album)?;
+            println!(
+                "{} Wrote {}",
+                symbols::ok().bright_green(),
+                nfo_path.display()
+            );
+
+            if cli.artist_nfo {
+                if let Some(artist_dir) = album_dir.parent() {
+                    let nfo_path = nfo::write_artist_nfo(artist_dir, &album)?;
+                    println!(
+                        "{} Wrote {}",
+                        symbols::ok().bright_green(),
+                        nfo_path.display()
+                    );
+                }
+            }
+        }
+    }
+
+    if cli.mark && skipped.is_empty() && failures.is_empty() {
+        if let Some(album_dir) = organize::common_dir(&final_paths) {
+            let marker_path = marker::write(&album_dir, &marker::Marker::new(&album.id))?;
+            println!(
+                "{} Wrote {}",
+                symbols::ok().bright_green(),
+                marker_path.display()
+            );
+        }
+    }
+
+    if cli.pin {
+        if let Some(album_dir) = organize::common_dir(&final_paths) {
+            // Options already pinned are kept; matches become this run's
+            let mut pin = pinned.map(|(_, pin)| pin).unwrap_or_default();
+            pin.release = Some(album.id.clone());
+            let matched: Vec<(PathBuf, &musicbrainz::Track)> = matches
+                .iter()
+                .map(|m| {
+                    let file = sources
+                        .iter()
+                        .position(|source| *source == m.file_path)
+                        .map_or_else(|| m.file_path.clone(), |i| final_paths[i].clone());
+                    (file, &m.track)
+                })
+                .collect();
+            pin::record_matches(&mut pin, &album_dir, &album, &matched);
+            let pin_path = pin::write(&album_dir, &pin)?;
+            println!(
+                "{} Wrote {}",
+                symbols::ok().bright_green(),
+                pin_path.display()
+            );
+        }
+    }
 
+    file_results.sort_by(|a, b| a.file.cmp(&b.file));
+
+    let session = session_log::Session {
+        album: &album,
+        source: &path,
+        results: &file_results,
+        unmatched_files: &match_report.unmatched_files,
+        unmatched_tracks: &match_report.unmatched_tracks,
+        recorder: &recorder,
+    };
+    let album_dir = organize::common_dir(&final_paths).unwrap_or_else(|| path.clone());
+    match session_log::write(cli.session_log, &session, &album_dir) {
+        Ok(Some(log_path)) => println!(
+            "{} Logged the session to {}",
+            symbols::ok().bright_green(),
+            log_path.display()
+        ),
+        Ok(None) => {}
+        Err(e) => println!(
+            "{} Could not write the session log: {:#}",
+            symbols::warn().bright_yellow(),
+            e
+        ),
+    }
     println!();
+    results::print_table(
+        &file_results,
+        organize_options.as_ref().map(|o| o.library_root.as_path()),
+    );
+    summary.results = file_results;
+
+    Ok(())
+}
+
+/// A single MP3 that may hold the whole release
+struct ChapterRip<'a> {
+    file: &'a PathBuf,
+    /// The CUE sheet's tracks for the file, if it lists several
+    cue: Option<&'a cue::CueFile>,
+    album: &'a musicbrainz::Album,
+    cover_art: Option<&'a [u8]>,
+    folder_art: Option<&'a [u8]>,
+    settings: &'a profile::TagSettings,
+    write_cue: bool,
+}
+
+/// If the file is long enough to hold every track, offer to tag it as the
+/// album with a chapter per track. Returns whether the run is done.
+fn tag_album_rip(
+    rip: &ChapterRip,
+    dry_run: bool,
+    yes: bool,
+    summary: &mut notify::RunSummary,
+) -> Result<bool> {
+    let Some(file_ms) = audio::probe(rip.file).map(|info| info.duration_ms) else {
+        return Ok(false);
+    };
+    let all_tracks = &rip.album.tracks;
+    let span = match rip.cue {
+        Some(_) => 0..all_tracks.len(),
+        None => match chapters::album_rip_tracks(file_ms, all_tracks) {
+            Some(span) => span,
+            None => return Ok(false),
+        },
+    };
+    let tracks = &all_tracks[span.clone()];
+    let name = rip.file.file_name().unwrap_or_default().to_string_lossy();
+    let held = if tracks.len() == all_tracks.len() {
+        "the whole tracklist".to_string()
+    } else {
+        format!(
+            "tracks {} to {}",
+            tracks[0].position,
+            tracks[tracks.len() - 1].position
+        )
+    };
     println!(
-        "{} {}",
-        "✓".bright_green(),
-        "Successfully tagged all files!".bright_green().bold()
+        "{} {} ({}) looks like a rip of {} in one file",
+        symbols::arrow().bright_cyan(),
+        name.bright_cyan(),
+        report::format_duration(file_ms),
+        held
+    );
+    let marks = match rip.cue {
+        // The sheet knows where each track really starts
+        Some(entry) => Some(cue::chapters(entry, tracks, file_ms)),
+        None => chapters::plan(tracks, file_ms),
+    };
+    let Some(marks) = marks else {
+        println!(
+            "  {} Some tracks have no length on MusicBrainz, so chapters can't be placed",
+            symbols::warn().bright_yellow()
+        );
+        println!();
+        return Ok(false);
+    };
+    for (i, mark) in marks.iter().enumerate() {
+        println!(
+            "  {:>2}. {}  {}",
+            i + 1,
+            report::format_duration(mark.start_ms).bright_black(),
+            mark.title
+        );
+    }
+    println!();
+
+    if dry_run {
+        println!("{}", "Dry run - no chapters written".bright_yellow());
+        return Ok(true);
+    }
+    let accepted = yes
+        || Confirm::new()
+            .with_prompt("Tag the file as the album with these chapters?")
+            .default(true)
+            .interact()?;
+    if !accepted {
+        println!();
+        return Ok(false);
+    }
+
+    summary.matched = 1;
+    chapters::write_album_rip(rip.file, rip.album, &marks, rip.cover_art, rip.settings)?;
+    summary.tagged = 1;
+    println!(
+        "{} Tagged {} with {} chapters",
+        symbols::ok().bright_green(),
+        name,
+        marks.len()
+    );
+    if rip.write_cue {
+        write_cue_sheet(rip, &marks)?;
+    }
+    let files = [rip.file.clone()];
+    if let Some(art) = rip.folder_art {
+        save_folder_art(art, &files)?;
+    }
+    record_in_index(&files, &rip.album.id);
+    Ok(true)
+}
+
+/// Write `<file>.cue` next to the rip, leaving an existing sheet alone
+fn write_cue_sheet(rip: &ChapterRip, marks: &[chapters::ChapterMark]) -> Result<()> {
+    let cue_path = rip.file.with_extension("cue");
+    if cue_path.exists() {
+        println!(
+            "{} {} already exists; left it as it is",
+            symbols::warn().bright_yellow(),
+            cue_path.display()
+        );
+        return Ok(());
+    }
+    let file_name = rip.file.file_name().unwrap_or_default().to_string_lossy();
+    std::fs::write(&cue_path, cue::render(rip.album, &file_name, marks))
+        .with_context(|| format!("Failed to write {}", cue_path.display()))?;
+    println!(
+        "{} Wrote {}",
+        symbols::ok().bright_green(),
+        cue_path.display()
     );
+    Ok(())
+}
 
+/// Write a JSON sidecar for each match; `files` is where each one is now
+fn write_sidecars(
+    matches: &[matcher::FileMatch],
+    files: &[PathBuf],
+    root: &std::path::Path,
+    dir: Option<&std::path::Path>,
+    album: &musicbrainz::Album,
+    overrides: &conflicts::AlbumOverrides,
+    settings: &profile::TagSettings,
+) -> Result<()> {
+    for (file_match, file) in matches.iter().zip(files) {
+        let sidecar_path = sidecar::path_for(file, root, dir);
+        let album = overrides.album_for(album, &file_match.track);
+        sidecar::write(file_match, file, &sidecar_path, &album, settings)?;
+    }
+    println!(
+        "{} Wrote {} sidecar(s) to {}",
+        symbols::ok().bright_green(),
+        matches.len(),
+        dir.unwrap_or(root).display()
+    );
     Ok(())
 }
 
-fn list_single_file(path: &PathBuf) -> Result<()> {
+/// Save each disc's own art in its folder, when the disc has one to itself
+fn save_disc_art(
+    disc_art: &BTreeMap<u32, Vec<u8>>,
+    matches: &[matcher::FileMatch],
+    sources: &[PathBuf],
+    final_paths: &[PathBuf],
+) -> Result<()> {
+    if disc_art.is_empty() {
+        return Ok(());
+    }
+    let disc_files = |disc: Option<u32>| -> Vec<PathBuf> {
+        sources
+            .iter()
+            .zip(final_paths)
+            .filter(|(source, _)| {
+                matches.iter().any(|m| {
+                    &m.file_path == *source && disc.is_none_or(|d| m.track.disc_number == d)
+                })
+            })
+            .map(|(_, path)| path.clone())
+            .collect()
+    };
+    let album_dir = organize::common_dir(&disc_files(None));
+    for (disc, art) in disc_art {
+        let files = disc_files(Some(*disc));
+        // In a flat folder the disc's cover would stand in for the release's
+        if files.is_empty() || organize::common_dir(&files) == album_dir {
+            continue;
+        }
+        save_folder_art(art, &files)?;
+    }
+    Ok(())
+}
+
+/// Find files whose existing tag can't be read, so they aren't overwritten
+/// with an empty one. With `repair` their tags are rebuilt from the frames
+/// that still parse; otherwise they are returned to be skipped.
+fn check_tags(files: &[PathBuf], repair: bool, version: id3::Version) -> Result<Vec<PathBuf>> {
+    let damaged: Vec<(&PathBuf, String)> = files
+        .iter()
+        .filter_map(|file| repair::tag_problem(file).map(|problem| (file, problem)))
+        .collect();
+    if damaged.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    println!(
+        "{} {} file(s) have damaged tags",
+        symbols::warn().bright_yellow(),
+        damaged.len()
+    );
+    let mut skipped = Vec::new();
+    for (file, problem) in damaged {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        if !repair {
+            println!(
+                "  {} {} {}",
+                symbols::fail().bright_red(),
+                name,
+                format!("({})", problem).bright_black()
+            );
+            skipped.push(file.clone());
+            continue;
+        }
+        match repair::repair_tag(file, version) {
+            Ok(report) if report.lost == 0 => println!(
+                "  {} {}: recovered {} frame(s)",
+                symbols::ok().bright_green(),
+                name,
+                report.recovered
+            ),
+            Ok(report) => println!(
+                "  {} {}: recovered {} frame(s), {} unreadable",
+                symbols::warn().bright_yellow(),
+                name,
+                report.recovered,
+                report.lost
+            ),
+            Err(e) => {
+                println!(
+                    "  {} {} {}",
+                    symbols::fail().bright_red(),
+                    name,
+                    format!("({:#})", anyhow::Error::from(e)).bright_black()
+                );
+                skipped.push(file.clone());
+            }
+        }
+    }
+    if !repair {
+        println!("  Skipping them; rerun with --repair to keep what can be salvaged");
+    }
+    println!();
+    Ok(skipped)
+}
+
+/// One line saying how many files were tagged, skipped, and failed
+fn print_outcome(tagged: usize, skipped: usize, failed: usize) {
+    if skipped == 0 && failed == 0 {
+        println!(
+            "{} {}",
+            symbols::ok().bright_green(),
+            "Successfully tagged all files!".bright_green().bold()
+        );
+        return;
+    }
+
+    let mut line = format!("Tagged {} file(s)", tagged);
+    if skipped > 0 {
+        line.push_str(&format!(", skipped {}", skipped));
+    }
+    if failed > 0 {
+        line.push_str(&format!(", {} failed", failed));
+    }
+    println!("{} {}", symbols::warn().bright_yellow(), line);
+}
+
+/// Release IDs other than `album_id` that matched files are already tagged
+/// from, with how many files carry each
+fn tagged_from_other_releases(
+    matches: &[matcher::FileMatch],
+    album_id: &str,
+) -> Vec<(String, usize)> {
+    let mut releases: Vec<(String, usize)> = Vec::new();
+    for m in matches {
+        let Ok(tag) = tagger::read_tag(&m.file_path) else {
+            continue;
+        };
+        let Some(existing) = tagger::extended_text(&tag, "MusicBrainz Album Id") else {
+            continue;
+        };
+        if existing.eq_ignore_ascii_case(album_id) {
+            continue;
+        }
+        match releases.iter_mut().find(|(id, _)| *id == existing) {
+            Some((_, n)) => *n += 1,
+            None => releases.push((existing, 1)),
+        }
+    }
+    releases
+}
+
+/// Parse a byte count such as `800000`, `500K`, or `2M` (binary units)
+fn parse_size(value: &str) -> std::result::Result<usize, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 1024),
+        Some('M') => (&value[..value.len() - 1], 1024 * 1024),
+        _ => (value, 1),
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{}' is not a size like 800000, 500K, or 2M", value))?;
+    if number <= 0.0 {
+        return Err("size must be greater than zero".to_string());
+    }
+    Ok((number * multiplier as f64) as usize)
+}
+
+/// Make cover art fit the embed size limit, reporting what that took. Returns
+/// the art to embed and, if it can't be made to fit, the original to save
+/// next to the files instead.
+fn fit_cover_art(
+    art: Vec<u8>,
+    settings: &profile::TagSettings,
+) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+    let kb = |bytes: usize| format!("{:.1} KB", bytes as f64 / 1024.0);
+    let limit = kb(settings.max_embed_bytes);
+    match art::fit_to_bytes(&art, settings.max_embed_bytes) {
+        Ok((embed, art::Fit::Unchanged)) => (embed, None),
+        Ok((Some(embed), art::Fit::Reencoded { quality })) => {
+            println!(
+                "{} Re-encoded cover art to fit {} ({} {} {}, JPEG quality {})",
+                symbols::ok().bright_green(),
+                limit,
+                kb(art.len()),
+                symbols::arrow(),
+                kb(embed.len()),
+                quality
+            );
+            (Some(embed), None)
+        }
+        Ok((Some(embed), art::Fit::Downscaled { width, height })) => {
+            println!(
+                "{} Shrank cover art to {}x{} to fit {} ({} {} {})",
+                symbols::ok().bright_green(),
+                width,
+                height,
+                limit,
+                kb(art.len()),
+                symbols::arrow(),
+                kb(embed.len())
+            );
+            (Some(embed), None)
+        }
+        _ => {
+            println!(
+                "{} Cover art ({}) can't be made to fit {}; it will be saved next to the files instead of embedded",
+                symbols::warn().bright_yellow(),
+                kb(art.len()),
+                limit
+            );
+            (None, Some(art))
+        }
+    }
+}
+
+/// Save art too large to embed as cover.jpg (or .png) in every folder of
+/// `files` that doesn't have a cover image yet
+fn save_folder_art(art: &[u8], files: &[PathBuf]) -> Result<()> {
+    let extension = match image::guess_format(art) {
+        Ok(image::ImageFormat::Png) => "png",
+        _ => "jpg",
+    };
+    let mut dirs: Vec<&std::path::Path> = files.iter().filter_map(|f| f.parent()).collect();
+    dirs.sort();
+    dirs.dedup();
+
+    let mut saved = 0;
+    for dir in dirs {
+        if has_cover_image(dir) {
+            continue;
+        }
+        let cover_path = dir.join(format!("cover.{}", extension));
+        std::fs::write(&cover_path, art)
+            .with_context(|| format!("Failed to write {}", cover_path.display()))?;
+        saved += 1;
+    }
+    if saved > 0 {
+        println!(
+            "{} Saved cover art as cover.{} in {} folder(s)",
+            symbols::ok().bright_green(),
+            extension,
+            saved
+        );
+    }
+    Ok(())
+}
+
+fn has_cover_image(dir: &std::path::Path) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    entries.filter_map(|e| e.ok()).any(|entry| {
+        let path = entry.path();
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        matches!(stem.as_str(), "cover" | "folder" | "front")
+            && matches!(extension.as_str(), "jpg" | "jpeg" | "png")
+    })
+}
+
+/// Art for `write_all_tags` to embed
+struct EmbeddedArt<'a> {
+    /// The release's front cover
+    cover: Option<&'a [u8]>,
+    /// Front covers of their own, by disc number
+    discs: &'a BTreeMap<u32, Vec<u8>>,
+    /// Pictures of other types from `--art`
+    extra: &'a [(id3::frame::PictureType, Vec<u8>)],
+}
+
+/// Tag every match with a progress bar. With `keep_going`, files that fail
+/// are returned instead of stopping the batch.
+fn write_all_tags(
+    matches: &[matcher::FileMatch],
+    album: &musicbrainz::Album,
+    overrides: &conflicts::AlbumOverrides,
+    art: &EmbeddedArt,
+    settings: &profile::TagSettings,
+    keep_going: bool,
+    events: &dyn EventSink,
+) -> Result<Vec<(PathBuf, anyhow::Error)>> {
+    let pb = progress::bar(matches.len(), "Writing tags");
+    let on_event = |event: Event| {
+        events.emit(event.clone());
+        if let Event::TagWritten { file } | Event::TagFailed { file, .. } = event {
+            pb.set_message(
+                file.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+            );
+            pb.inc(1);
+        }
+    };
+
+    let mut failures = Vec::new();
+    // Discs with art of their own get it instead of the release cover
+    for disc in matches.chunk_by(|a, b| a.track.disc_number == b.track.disc_number) {
+        let cover = art
+            .discs
+            .get(&disc[0].track.disc_number)
+            .map(Vec::as_slice)
+            .or(art.cover);
+        let pictures = tagger::Artwork {
+            cover,
+            extra: art.extra,
+        };
+        // Files with kept album values are written with those
+        for run in disc.chunk_by(|a, b| overrides.same(&a.track, &b.track)) {
+            let album = overrides.album_for(album, &run[0].track);
+            if keep_going {
+                failures.extend(
+                    tagger::tag_files_keep_going(run, &album, pictures, settings, &on_event)
+                        .into_iter()
+                        .map(|(file, e)| (file, e.into())),
+                );
+            } else {
+                tagger::tag_files(run, &album, pictures, settings, &on_event)?;
+            }
+        }
+    }
+
+    pb.finish_with_message("Complete");
+    Ok(failures)
+}
+
+/// One line on how the batch went, plus a table of the files that failed
+fn print_results(tagged: usize, skipped: usize, failures: &[(PathBuf, anyhow::Error)]) {
+    print_outcome(tagged, skipped, failures.len());
+    if failures.is_empty() {
+        return;
+    }
+
+    let names: Vec<String> = failures
+        .iter()
+        .map(|(file, _)| {
+            file.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    println!();
+    println!(
+        "    {}  {}",
+        format!("{:<width$}", "File").bold(),
+        "Error".bold()
+    );
+    for (name, (_, error)) in names.iter().zip(failures) {
+        println!(
+            "  {} {:<width$}  {}",
+            symbols::fail().bright_red(),
+            name,
+            error.root_cause().to_string().bright_red()
+        );
+    }
+}
+
+fn plan_organize(
+    matches: &[matcher::FileMatch],
+    album: &musicbrainz::Album,
+    overrides: &conflicts::AlbumOverrides,
+) -> Vec<(PathBuf, organize::TemplateValues)> {
+    matches
+        .iter()
+        .map(|m| {
+            let album = overrides.album_for(album, &m.track);
+            (
+                m.file_path.clone(),
+                organize::TemplateValues::from_track(&album, &m.track),
+            )
+        })
+        .collect()
+}
+
+/// Remember the release each file was tagged from; the index is a cache, so
+/// failures only warn
+fn record_in_index(files: &[PathBuf], release_id: &str) {
+    let result = index::LibraryIndex::open().and_then(|index| {
+        files
+            .iter()
+            .try_for_each(|file| index.record_tagged(file, release_id))
+    });
+    if let Err(e) = result {
+        println!(
+            "{} Could not update the library index: {}",
+            symbols::warn().bright_yellow(),
+            e
+        );
+    }
+}
+
+fn write_playlist(files: &[PathBuf]) -> Result<()> {
+    if let Some(playlist_path) = playlist::write_album_playlist(files)? {
+        println!(
+            "{} Wrote playlist {}",
+            symbols::ok().bright_green(),
+            playlist_path.display()
+        );
+    }
+    Ok(())
+}
+
+fn list_single_file(path: &PathBuf, check_transcodes: bool) -> Result<()> {
     use std::fs;
 
     let file_name = path
@@ -327,17 +2328,19 @@ fn list_single_file(path: &PathBuf) -> Result<()> {
     let metadata = fs::metadata(path).context("Failed to read file metadata")?;
     let size_str = format_file_size(metadata.len());
 
+    let info = audio::probe(path);
     println!(
         "  {} {} {}",
-        "♪".bright_cyan(),
+        symbols::note().bright_cyan(),
         file_name.bright_white(),
-        format!("({})", size_str).bright_black()
+        format!("({}{})", size_str, describe_audio(path, info.as_ref())).bright_black()
     );
+    print_quality_warnings(path, info.as_ref(), check_transcodes);
 
     Ok(())
 }
 
-fn list_directory_contents(path: &PathBuf) -> Result<()> {
+fn list_directory_contents(path: &PathBuf, check_transcodes: bool) -> Result<()> {
     use std::fs;
 
     let mut entries: Vec<_> = fs::read_dir(path)
@@ -355,6 +2358,7 @@ fn list_directory_contents(path: &PathBuf) -> Result<()> {
 
     let mut mp3_count = 0;
     let mut other_count = 0;
+    let mut doubtful_count = 0;
 
     for entry in entries {
         let path = entry.path();
@@ -369,12 +2373,17 @@ fn list_directory_contents(path: &PathBuf) -> Result<()> {
             let size_str = format_file_size(size);
 
             if extension.eq_ignore_ascii_case("mp3") {
+                let info = audio::probe(&path);
                 println!(
                     "  {} {} {}",
-                    "♪".bright_cyan(),
+                    symbols::note().bright_cyan(),
                     file_name_str.bright_white(),
-                    format!("({})", size_str).bright_black()
+                    format!("({}{})", size_str, describe_audio(&path, info.as_ref()))
+                        .bright_black()
                 );
+                if print_quality_warnings(&path, info.as_ref(), check_transcodes) {
+                    doubtful_count += 1;
+                }
                 mp3_count += 1;
             } else {
                 println!(
@@ -386,7 +2395,11 @@ fn list_directory_contents(path: &PathBuf) -> Result<()> {
                 other_count += 1;
             }
         } else if path.is_dir() {
-            println!("  {} {}/", "📁".bright_blue(), file_name_str.bright_blue());
+            println!(
+                "  {} {}/",
+                symbols::folder().bright_blue(),
+                file_name_str.bright_blue()
+            );
         }
     }
 
@@ -399,10 +2412,297 @@ fn list_directory_contents(path: &PathBuf) -> Result<()> {
         other_count,
         if other_count == 1 { "" } else { "s" }
     );
+    if doubtful_count > 0 {
+        println!(
+            "  {} {} file{} with quality warnings; check the rip is worth tagging",
+            symbols::warn().bright_yellow(),
+            doubtful_count,
+            if doubtful_count == 1 { "" } else { "s" }
+        );
+    }
 
     Ok(())
 }
 
+/// How `--recording-id` tags its file
+struct RecordingRun<'a> {
+    with_release: bool,
+    cover_art: bool,
+    refresh_art: bool,
+    dry_run: bool,
+    yes: bool,
+    make_writable: bool,
+    repair: bool,
+    settings: &'a profile::TagSettings,
+    genre_rules: Option<&'a genre::GenreRules>,
+}
+
+/// Tag one loose file as a recording, plus its canonical release with
+/// `--with-release`
+async fn tag_recording(
+    arg: &str,
+    files: &[PathBuf],
+    run: &RecordingRun<'_>,
+    summary: &mut notify::RunSummary,
+) -> Result<()> {
+    let [file] = files else {
+        anyhow::bail!(
+            "--recording-id tags a single file, but {} were given",
+            files.len()
+        );
+    };
+    let recording_id = recording_id_from_arg(arg)?;
+
+    println!(
+        "{}",
+        "Fetching recording from MusicBrainz...".bright_yellow()
+    );
+    let client = config::musicbrainz_builder()
+        .refresh_art(run.refresh_art)
+        .build()
+        .context("Failed to create HTTP client")?;
+    let recording = client
+        .get_recording(&recording_id)
+        .await
+        .context("Failed to fetch recording")?;
+    println!("{} Recording found:", symbols::ok().bright_green());
+    println!(
+        "  {} by {}",
+        recording.title.bright_white(),
+        recording.artist
+    );
+    if let Some(length) = recording.length {
+        println!("  {}", report::format_duration(length));
+        let file_ms = audio::probe(file).map(|info| info.duration_ms);
+        if let Some(file_ms) = file_ms.filter(|ms| ms.abs_diff(length) > 10_000) {
+            println!(
+                "  {} The file is {} long; is it the right recording?",
+                symbols::warn().bright_yellow(),
+                report::format_duration(file_ms)
+            );
+        }
+    }
+    println!();
+
+    // With a release, the file is tagged like any track of it
+    let mut release = None;
+    if run.with_release {
+        match recording.canonical_release() {
+            Some(canonical) => {
+                let mut album = client
+                    .get_release(&canonical.id)
+                    .await
+                    .context("Failed to fetch the recording's release")?;
+                let track = album
+                    .tracks
+                    .iter()
+                    .find(|t| t.recording_id == recording.id)
+                    .cloned()
+                    .context("The recording's release doesn't list it")?;
+                if let Some(rules) = run.genre_rules {
+                    album.genres = rules.apply(&album.genres);
+                }
+                println!(
+                    "{} From release {}{} (track {} of {})",
+                    symbols::ok().bright_green(),
+                    album.title.bright_white(),
+                    album
+                        .date
+                        .as_deref()
+                        .map(|date| format!(", {}", date))
+                        .unwrap_or_default(),
+                    track.position,
+                    album.total_tracks
+                );
+                println!();
+                release = Some((album, track));
+            }
+            None => {
+                println!(
+                    "{} The recording is on no release; only its own fields will be written",
+                    symbols::warn().bright_yellow()
+                );
+                println!();
+            }
+        }
+    }
+    summary.release_id = release.as_ref().map(|(album, _)| album.id.clone());
+    summary.album = release.as_ref().map(|(album, _)| album.title.clone());
+    summary.artist = Some(recording.artist.clone());
+    summary.matched = 1;
+
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    if run.dry_run {
+        println!(
+            "{} {} would be tagged as {} by {}",
+            "Dry run -".bright_yellow(),
+            name,
+            recording.title,
+            recording.artist
+        );
+        return Ok(());
+    }
+    let accepted = run.yes
+        || Confirm::new()
+            .with_prompt(format!("Tag {} as this recording?", name))
+            .default(true)
+            .interact()?;
+    if !accepted {
+        println!("{}", "Tagging cancelled.".bright_yellow());
+        return Ok(());
+    }
+
+    // The same checks as for an album, so a locked file or a damaged tag
+    // isn't overwritten with an empty one
+    let targets = std::slice::from_ref(file);
+    let mut skipped = permissions::check_writable(targets, run.make_writable, !run.yes)?;
+    if skipped.is_empty() {
+        skipped = check_tags(targets, run.repair, run.settings.version)?;
+    }
+    if !skipped.is_empty() {
+        print_outcome(0, skipped.len(), 0);
+        return Ok(());
+    }
+
+    match &release {
+        Some((album, track)) => {
+            let art = if run.cover_art {
+                match art_picker::cover_art(&client, &album.id, run.settings.max_art_size, false)
+                    .await
+                {
+                    Ok(Some(art)) => fit_cover_art(art, run.settings).0,
+                    Ok(None) => None,
+                    Err(e) => {
+                        println!(
+                            "{} {}: {}",
+                            symbols::warn().bright_yellow(),
+                            "Could not fetch cover art".bright_yellow(),
+                            e
+                        );
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            let art = tagger::Artwork::cover(art.as_deref());
+            tagger::write_tags(file, track, album, art, run.settings)?;
+            record_in_index(std::slice::from_ref(file), &album.id);
+        }
+        None => tagger::write_recording(file, &recording, run.settings)?,
+    }
+    summary.tagged = 1;
+    println!("{} Tagged {}", symbols::ok().bright_green(), name);
+    Ok(())
+}
+
+/// The recording ID in `--recording-id`, which may also be a pasted MusicBrainz URL
+fn recording_id_from_arg(arg: &str) -> Result<String> {
+    mbid_from_arg(arg, "recording")
+}
+
+/// The release in the `--release-json` file, `release_id` if it holds several
+fn read_release_json(
+    path: &std::path::Path,
+    release_id: Option<&str>,
+) -> Result<musicbrainz::Album> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    match musicbrainz::release_from_json(&text, release_id) {
+        Err(MbError::ReleaseCount(count)) if count > 1 => anyhow::bail!(
+            "{} holds {} releases; pick one with --album-id",
+            path.display(),
+            count
+        ),
+        result => {
+            result.with_context(|| format!("Could not read a release from {}", path.display()))
+        }
+    }
+}
+
+/// The release ID in `--album-id`, which may also be a pasted MusicBrainz URL
+fn release_id_from_arg(arg: &str) -> Result<String> {
+    mbid_from_arg(arg, "release")
+}
+
+/// An MBID of `wanted` kind, given bare or as a MusicBrainz URL
+fn mbid_from_arg(arg: &str, wanted: &str) -> Result<String> {
+    let arg = arg.trim();
+    let id = match arg.split_once("musicbrainz.org/") {
+        Some((_, rest)) => {
+            let mut segments = rest.split(['/', '?', '#']);
+            let kind = segments.next().unwrap_or_default();
+            let id = segments.next().unwrap_or_default();
+            if kind != wanted {
+                anyhow::bail!(
+                    "That is a MusicBrainz {} URL; open it and copy the URL of one of its {}s",
+                    kind,
+                    wanted
+                );
+            }
+            id
+        }
+        None => arg,
+    };
+
+    if !musicbrainz::is_mbid(id) {
+        let hint = if wanted == "release" {
+            ". Find one with: musictagger_rs search \"<album>\" --artist \"<artist>\""
+        } else {
+            ""
+        };
+        anyhow::bail!(
+            "'{}' is not a MusicBrainz {} ID. IDs look like 76df3287-6cda-33eb-8e9a-044b5e15ffdd, the end of the {} URL{}",
+            arg,
+            wanted,
+            wanted,
+            hint
+        );
+    }
+    Ok(id.to_lowercase())
+}
+
+/// ", 3:25, 320 kbps CBR, 44.1 kHz" (plus ", mono" and the encoder) or empty
+/// if the file can't be probed
+fn describe_audio(path: &std::path::Path, info: Option<&audio::AudioInfo>) -> String {
+    let Some(info) = info else {
+        return String::new();
+    };
+
+    let seconds = info.duration_ms / 1000;
+    let mut description = format!(", {}:{:02}", seconds / 60, seconds % 60);
+    if let Some(kbps) = info.bitrate_kbps {
+        let mode = if info.vbr { "VBR" } else { "CBR" };
+        description.push_str(&format!(", {} kbps {}", kbps, mode));
+    }
+    if let Some(rate) = info.sample_rate {
+        description.push_str(&format!(", {:.1} kHz", rate as f64 / 1000.0));
+    }
+    if info.channels == Some(1) {
+        description.push_str(", mono");
+    }
+    if let Some(encoder) = audio::encoder(path) {
+        description.push_str(&format!(", {}", encoder));
+    }
+    description
+}
+
+/// Print the file's quality warnings under its listing line; true if there were any
+fn print_quality_warnings(
+    path: &std::path::Path,
+    info: Option<&audio::AudioInfo>,
+    check_transcodes: bool,
+) -> bool {
+    let Some(info) = info else {
+        return false;
+    };
+    let warnings = quality::warnings(path, info, check_transcodes);
+    for warning in &warnings {
+        println!("      {} {}", symbols::warn().bright_yellow(), warning);
+    }
+    !warnings.is_empty()
+}
+
 fn format_file_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
@@ -0,0 +1,230 @@
+// src/maintenance.rs
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use id3::frame::PictureType;
+use id3::TagLike;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::symbols;
+use crate::tagger::read_tag;
+
+/// Picture types that can be selected for removal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PictureKind {
+    Front,
+    Back,
+    Leaflet,
+    Media,
+    Artist,
+    Icon,
+    Other,
+}
+
+impl PictureKind {
+    fn matches(self, picture_type: PictureType) -> bool {
+        match self {
+            PictureKind::Front => picture_type == PictureType::CoverFront,
+            PictureKind::Back => picture_type == PictureType::CoverBack,
+            PictureKind::Leaflet => picture_type == PictureType::Leaflet,
+            PictureKind::Media => picture_type == PictureType::Media,
+            PictureKind::Artist => matches!(
+                picture_type,
+                PictureType::LeadArtist | PictureType::Artist | PictureType::Band
+            ),
+            PictureKind::Icon => {
+                matches!(picture_type, PictureType::Icon | PictureType::OtherIcon)
+            }
+            PictureKind::Other => {
+                matches!(picture_type, PictureType::Other | PictureType::Undefined(_))
+            }
+        }
+    }
+}
+
+/// Remove embedded pictures from every MP3 under `path`.
+///
+/// An empty `kinds` slice removes all pictures regardless of type.
+pub fn remove_art(path: &Path, kinds: &[PictureKind], dry_run: bool) -> Result<()> {
+    let files = collect_tree(path)?;
+    println!(
+        "{} Scanning {} MP3 file(s)",
+        symbols::ok().bright_green(),
+        files.len()
+    );
+    println!();
+
+    let mut changed = 0;
+    for file in &files {
+        let mut tag = match read_tag(file) {
+            Ok(tag) => tag,
+            Err(_) => continue,
+        };
+
+        let before = tag.pictures().count();
+        let selected: Vec<PictureType> = tag
+            .pictures()
+            .map(|p| p.picture_type)
+            .filter(|t| kinds.is_empty() || kinds.iter().any(|k| k.matches(*t)))
+            .collect();
+
+        if selected.is_empty() {
+            continue;
+        }
+
+        for picture_type in &selected {
+            tag.remove_picture_by_type(*picture_type);
+        }
+        let removed = before - tag.pictures().count();
+
+        println!(
+            "  {} {} {}",
+            symbols::fail().bright_red(),
+            display_name(file).bright_white(),
+            format!("({} picture(s))", removed).bright_black()
+        );
+
+        if !dry_run {
+            tag.write_to_path(file, tag.version())
+                .with_context(|| format!("Failed to write tags to {}", file.display()))?;
+        }
+        changed += 1;
+    }
+
+    print_summary(changed, files.len(), dry_run);
+    Ok(())
+}
+
+/// Remove all frames with the given IDs (e.g. `COMM`, `PRIV`) from every MP3 under `path`.
+pub fn strip_frames(path: &Path, frame_ids: &[String], dry_run: bool) -> Result<()> {
+    let frame_ids = parse_frame_ids(frame_ids)?;
+
+    let files = collect_tree(path)?;
+    println!(
+        "{} Scanning {} MP3 file(s)",
+        symbols::ok().bright_green(),
+        files.len()
+    );
+    println!();
+
+    let mut changed = 0;
+    for file in &files {
+        let mut tag = match read_tag(file) {
+            Ok(tag) => tag,
+            Err(_) => continue,
+        };
+
+        let mut removed = Vec::new();
+        for id in &frame_ids {
+            let count = tag.remove(id).len();
+            if count > 0 {
+                removed.push(format!("{}x{}", id, count));
+            }
+        }
+
+        if removed.is_empty() {
+            continue;
+        }
+
+        println!(
+            "  {} {} {}",
+            symbols::fail().bright_red(),
+            display_name(file).bright_white(),
+            format!("({})", removed.join(", ")).bright_black()
+        );
+
+        if !dry_run {
+            tag.write_to_path(file, tag.version())
+                .with_context(|| format!("Failed to write tags to {}", file.display()))?;
+        }
+        changed += 1;
+    }
+
+    print_summary(changed, files.len(), dry_run);
+    Ok(())
+}
+
+/// Frame IDs given on the command line, uppercased and checked
+pub fn parse_frame_ids(frame_ids: &[String]) -> Result<Vec<String>> {
+    let frame_ids: Vec<String> = frame_ids.iter().map(|id| id.to_uppercase()).collect();
+    for id in &frame_ids {
+        if id.len() != 4 || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            anyhow::bail!(
+                "Invalid frame ID: {} (expected four characters, e.g. COMM)",
+                id
+            );
+        }
+    }
+    Ok(frame_ids)
+}
+
+/// Recursively collect all MP3 files under `path` (or `path` itself if it is a file)
+pub fn collect_tree(path: &Path) -> Result<Vec<PathBuf>> {
+    // `-` reads a list of files from stdin, e.g. piped from `query`
+    if path == Path::new("-") {
+        let mut files: Vec<PathBuf> = crate::inputs::read_file_list(path)?
+            .into_iter()
+            .filter(|p| {
+                p.is_file()
+                    && p.extension()
+                        .map(|ext| ext.eq_ignore_ascii_case("mp3"))
+                        .unwrap_or(false)
+            })
+            .collect();
+        files.sort();
+        files.dedup();
+        return Ok(files);
+    }
+
+    if !path.exists() {
+        anyhow::bail!("Path does not exist: {}", path.display());
+    }
+
+    let pb = crate::progress::spinner("Scanning for MP3 files");
+    let mut files: Vec<PathBuf> = WalkDir::new(path)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            e.path()
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("mp3"))
+                .unwrap_or(false)
+        })
+        .map(|e| {
+            pb.inc(1);
+            e.path().to_path_buf()
+        })
+        .collect();
+    pb.finish_and_clear();
+
+    files.sort();
+    Ok(files)
+}
+
+pub fn display_name(file: &Path) -> String {
+    file.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| file.display().to_string())
+}
+
+pub fn print_summary(changed: usize, total: usize, dry_run: bool) {
+    println!();
+    if dry_run {
+        println!(
+            "{} {} of {} file(s) would be modified.",
+            "Dry run -".bright_yellow(),
+            changed,
+            total
+        );
+    } else {
+        println!(
+            "{} Modified {} of {} file(s)",
+            symbols::ok().bright_green(),
+            changed,
+            total
+        );
+    }
+}
@@ -0,0 +1,109 @@
+// src/manual_edit.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use dialoguer::{Confirm, Editor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::symbols;
+
+const HEADER: &str = "\
+# Edit the album and track values below, then save and close the editor.
+# Leave `cover` or `comment` empty for none; separate several genres with \";\".
+# Delete a [[track]] entry to leave that file untouched.
+# Closing without saving aborts.
+
+";
+
+/// Everything manual mode asks for, as one editable TOML document
+#[derive(Serialize, Deserialize)]
+pub struct AlbumDraft {
+    pub album: AlbumFields,
+    #[serde(rename = "track", default)]
+    pub tracks: Vec<TrackDraft>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AlbumFields {
+    pub title: String,
+    pub artist: String,
+    pub year: i32,
+    /// Several genres separated by ";"
+    #[serde(default)]
+    pub genre: String,
+    /// Path or http(s) URL of an image, empty for none
+    #[serde(default)]
+    pub cover: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TrackDraft {
+    /// Path relative to the input folder; identifies the file, not editable
+    pub file: String,
+    pub track: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disc: Option<u32>,
+    pub artist: String,
+    pub title: String,
+    /// Empty removes an existing comment
+    #[serde(default)]
+    pub comment: String,
+}
+
+/// Open the draft in $VISUAL/$EDITOR and read it back, re-opening it after
+/// mistakes. Returns `None` if the user closes without saving or gives up.
+pub fn edit(draft: &AlbumDraft) -> Result<Option<AlbumDraft>> {
+    let mut text = format!(
+        "{}{}",
+        HEADER,
+        toml::to_string(draft).context("Failed to prepare the track list")?
+    );
+
+    loop {
+        let Some(edited) = Editor::new()
+            .extension(".toml")
+            .edit(&text)
+            .context("Failed to run the editor; set $EDITOR")?
+        else {
+            return Ok(None);
+        };
+
+        match parse(&edited, draft) {
+            Ok(parsed) => return Ok(Some(parsed)),
+            Err(e) => {
+                println!("{} {:#}", symbols::warn().bright_yellow(), e);
+                let again = Confirm::new()
+                    .with_prompt("Edit again?")
+                    .default(true)
+                    .interact()?;
+                if !again {
+                    return Ok(None);
+                }
+                text = edited;
+            }
+        }
+    }
+}
+
+fn parse(text: &str, original: &AlbumDraft) -> Result<AlbumDraft> {
+    let parsed: AlbumDraft = toml::from_str(text).context("Invalid TOML")?;
+
+    let expected: HashSet<&str> = original.tracks.iter().map(|t| t.file.as_str()).collect();
+    let mut seen = HashSet::new();
+    for track in &parsed.tracks {
+        if !expected.contains(track.file.as_str()) {
+            anyhow::bail!("Unknown file '{}'; file names can't be changed", track.file);
+        }
+        if !seen.insert(track.file.as_str()) {
+            anyhow::bail!("'{}' is listed more than once", track.file);
+        }
+        if track.title.trim().is_empty() {
+            anyhow::bail!("'{}' has an empty title", track.file);
+        }
+    }
+    if parsed.tracks.is_empty() {
+        anyhow::bail!("No tracks left to tag");
+    }
+
+    Ok(parsed)
+}
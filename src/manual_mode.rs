@@ -1,37 +1,161 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::Datelike;
 use colored::Colorize;
-use dialoguer::Input;
+use dialoguer::{Confirm, Input, MultiSelect, Select, Sort};
+use id3::frame::{Comment, Content};
+use id3::{Tag, TagLike};
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+
+use crate::casing::{self, CasingRules};
+use crate::events::{Event, EventSink};
+use crate::filename_pattern::{FilenamePattern, ParsedName};
+use crate::genre::GenreRules;
+use crate::import::ImportRecord;
+use crate::manual_edit::{AlbumDraft, AlbumFields, TrackDraft};
+use crate::organize::{OrganizeOptions, TemplateValues};
+use crate::profile::TagSettings;
+use crate::provider::{MetadataProvider, ReleaseRanking};
+use crate::symbols;
 
 pub struct ManualTrackInfo {
     pub file_path: PathBuf,
     pub title: String,
     pub artist: String,
     pub track_number: u32,
+    pub disc_number: Option<u32>,
+    /// `None` leaves any existing comment alone; an empty string removes it
+    pub comment: Option<String>,
 }
 
 pub struct ManualAlbum {
     pub title: String,
     pub artist: String,
+    pub year: i32,
+    pub genres: Vec<String>,
     pub tracks: Vec<ManualTrackInfo>,
     pub cover_art: Option<Vec<u8>>,
+    /// Art too large to embed, saved next to the files instead
+    pub folder_art: Option<Vec<u8>>,
+}
+
+/// How a manual tagging run behaves
+pub struct ManualOptions<'a> {
+    pub dry_run: bool,
+    pub yes: bool,
+    pub settings: &'a TagSettings,
+    pub organize: Option<&'a OrganizeOptions>,
+    pub playlist: bool,
+    /// Parse suggestions from filenames with this instead of the built-in heuristics
+    pub pattern: Option<&'a FilenamePattern>,
+    /// Enter everything in $EDITOR at once instead of prompting per track
+    pub editor: bool,
+    /// Clear the read-only bit on files without asking
+    pub make_writable: bool,
+    /// Rebuild damaged tags instead of skipping those files
+    pub repair: bool,
+    /// Tag the remaining files when one fails instead of stopping
+    pub keep_going: bool,
+    /// Receives a write event per file
+    pub events: &'a dyn EventSink,
+    /// Values from an earlier session, ahead of every other suggestion
+    pub template: Option<&'a [ImportRecord]>,
+    /// Write the entered values here once the user is done
+    pub save_template: Option<&'a Path>,
+    /// Order and year range for MusicBrainz search results
+    pub ranking: &'a ReleaseRanking,
+    /// Casing for the entered titles and artists
+    pub casing: CasingRules,
+    /// Genre mappings and blacklist for the entered genres
+    pub genre_rules: Option<&'a GenreRules>,
+}
+
+/// Album values, as suggested or as entered
+struct AlbumInfo {
+    title: String,
+    artist: String,
+    year: i32,
+    /// Several genres separated by ";"
+    genre: String,
+}
+
+/// Values suggested for one file from the pattern, its tags, or its name
+struct TrackSuggestion {
+    /// `None` falls back to the album artist
+    artist: Option<String>,
+    title: String,
+    track: u32,
+    disc: Option<u32>,
+    comment: Option<String>,
+}
+
+/// How a manual run ended
+pub enum ManualOutcome {
+    /// Tagged, dry run, or aborted; nothing left to do
+    Done,
+    /// The user found the album on MusicBrainz; tag from this release instead
+    Release(String),
+    /// Tagged, but this many files failed under `--keep-going`
+    PartlyFailed(usize),
 }
 
-pub fn run(path: &Path, dry_run: bool, yes: bool) -> Result<()> {
+pub async fn run(
+    path: &Path,
+    files: &[PathBuf],
+    options: &ManualOptions<'_>,
+) -> Result<ManualOutcome> {
+    let ManualOptions {
+        dry_run,
+        yes,
+        settings,
+        organize,
+        playlist,
+        pattern,
+        editor,
+        make_writable,
+        repair,
+        keep_going,
+        events,
+        template,
+        save_template,
+        ranking,
+        casing,
+        genre_rules,
+    } = *options;
+
     println!("{}", "Manual Tagging Mode".bright_cyan().bold());
     println!();
 
-    // Collect MP3 files
-    let files = collect_mp3_files(path)?;
+    let mut files = files.to_vec();
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
     if files.is_empty() {
         anyhow::bail!("No MP3 files found in directory");
     }
 
-    println!("{} Found {} MP3 file(s)", "✓".bright_green(), files.len());
+    println!(
+        "{} Found {} MP3 file(s)",
+        symbols::ok().bright_green(),
+        files.len()
+    );
     println!();
 
-    // Try to get album info from existing tags of first file
+    let parsed: Vec<Option<ParsedName>> = files
+        .iter()
+        .map(|f| pattern.and_then(|p| p.parse_name(&file_name(f))))
+        .collect();
+    if pattern.is_some() {
+        let unmatched = parsed.iter().filter(|p| p.is_none()).count();
+        if unmatched > 0 {
+            println!(
+                "{} {} file name(s) don't match --pattern; their suggestions come from tags",
+                symbols::warn().bright_yellow(),
+                unmatched
+            );
+            println!();
+        }
+    }
+    let first_parsed = parsed.iter().flatten().next();
+
+    // Try to get album info from the pattern, then existing tags of first file
     let first_file_tags = crate::tagger::read_existing_tags(&files[0]);
 
     let dir_name = path
@@ -39,13 +163,336 @@ pub fn run(path: &Path, dry_run: bool, yes: bool) -> Result<()> {
         .map(|s| s.to_string_lossy().to_string())
         .unwrap_or_else(|| "Unknown Album".to_string());
 
-    let default_album = first_file_tags.album.unwrap_or(dir_name);
-    let default_album_artist = first_file_tags
-        .album_artist
+    let default_album = first_parsed
+        .and_then(|p| p.album.clone())
+        .or(first_file_tags.album)
+        .unwrap_or(dir_name);
+    let default_album_artist = first_parsed
+        .and_then(|p| p.album_artist.clone())
+        .or(first_file_tags.album_artist)
         .unwrap_or_else(|| "Various Artists".to_string());
+    let year = first_parsed
+        .and_then(|p| p.year)
+        .or(first_file_tags.year)
+        .unwrap_or_else(|| chrono::Utc::now().year());
+
+    // Prompt in track order, which isn't always the order the file names sort in
+    let mut entries: Vec<(PathBuf, TrackSuggestion)> = files
+        .iter()
+        .zip(parsed)
+        .enumerate()
+        .map(|(i, (file, parsed))| {
+            let mut suggestion = suggest(file, parsed, i, pattern.is_some());
+            if let Some(record) = template.and_then(|t| template_record(t, path, file)) {
+                apply_template_record(&mut suggestion, record);
+            }
+            (file.clone(), suggestion)
+        })
+        .collect();
+    entries.sort_by_key(|(_, s)| (s.disc.unwrap_or(1), s.track));
+    let (files, suggestions): (Vec<PathBuf>, Vec<TrackSuggestion>) = entries.into_iter().unzip();
+    let mut defaults = AlbumInfo {
+        title: default_album,
+        artist: default_album_artist,
+        year,
+        genre: first_file_tags.genre.unwrap_or_default(),
+    };
+    if let Some(record) = template.and_then(|t| t.first()) {
+        if let Some(album) = &record.album {
+            defaults.title = album.clone();
+        }
+        if let Some(artist) = &record.album_artist {
+            defaults.artist = artist.clone();
+        }
+        if let Some(year) = record.year {
+            defaults.year = year;
+        }
+        if let Some(genre) = &record.genre {
+            defaults.genre = genre.clone();
+        }
+    }
+
+    let mut album = if editor {
+        match edit_album(path, &files, suggestions, &defaults, settings).await? {
+            Some(album) => album,
+            None => {
+                println!("{}", "Aborted.".bright_yellow());
+                return Ok(ManualOutcome::Done);
+            }
+        }
+    } else {
+        prompt_album(path, &files, suggestions, &defaults, settings).await?
+    };
+
+    sort_tracks(&mut album.tracks);
+    if !casing.is_noop() {
+        apply_casing(&casing, &mut album);
+    }
+    if let Some(rules) = genre_rules {
+        album.genres = rules.apply(&album.genres);
+    }
+
+    // Show the summary until the user is happy with the track list
+    loop {
+        print_summary(&album);
+        if yes {
+            break;
+        }
+        let proceed = if dry_run {
+            "Continue"
+        } else {
+            "Apply these tags"
+        };
+        let items = [
+            proceed,
+            "Change a track number",
+            "Reorder tracks",
+            "Exclude files",
+            "Search MusicBrainz for this album",
+            "Abort",
+        ];
+        let choice = Select::new()
+            .with_prompt("What next?")
+            .items(&items)
+            .default(0)
+            .interact()?;
+        println!();
+        match items[choice] {
+            "Change a track number" => change_track_number(&mut album.tracks)?,
+            "Reorder tracks" => reorder_tracks(&mut album.tracks)?,
+            "Exclude files" => exclude_tracks(&mut album.tracks)?,
+            "Search MusicBrainz for this album" => {
+                if let Some(release_id) = search_musicbrainz(&album, ranking).await? {
+                    return Ok(ManualOutcome::Release(release_id));
+                }
+            }
+            "Abort" => {
+                println!("{}", "Aborted.".bright_yellow());
+                return Ok(ManualOutcome::Done);
+            }
+            _ => break,
+        }
+        if album.tracks.is_empty() {
+            println!("{}", "No files left to tag.".bright_yellow());
+            return Ok(ManualOutcome::Done);
+        }
+    }
+
+    if let Some(template_path) = save_template {
+        write_template(template_path, path, &album)?;
+        println!(
+            "{} Saved template {}",
+            symbols::ok().bright_green(),
+            template_path.display()
+        );
+        println!();
+    }
+
+    let mut organize_plan: Vec<_> = album
+        .tracks
+        .iter()
+        .map(|track| (track.file_path.clone(), template_values(&album, track)))
+        .collect();
+
+    if dry_run {
+        if let Some(options) = organize {
+            crate::organize::organize(&organize_plan, path, options)?;
+            println!();
+        }
+        println!("{}", "Dry run - no files were modified.".bright_yellow());
+        return Ok(ManualOutcome::Done);
+    }
+
+    // In copy mode only the copies are tagged, plus files already where the
+    // template puts them
+    let copied_paths = match organize {
+        Some(options) if options.copy => {
+            println!();
+            let copied = crate::organize::organize(&organize_plan, path, options)?;
+            let in_library = |(file, values): &(PathBuf, crate::organize::TemplateValues)| {
+                crate::organize::destination(file, values, path, options).is_ok_and(|d| d == *file)
+            };
+            album.tracks = album
+                .tracks
+                .into_iter()
+                .zip(&copied)
+                .zip(&organize_plan)
+                .filter(|((track, copy), planned)| track.file_path != **copy || in_library(planned))
+                .map(|((mut track, copy), _)| {
+                    track.file_path = copy.clone();
+                    track
+                })
+                .collect();
+            Some(copied)
+        }
+        _ => None,
+    };
 
-    let (album_title, album_artist, cover_art) =
-        prompt_album_info(&default_album, &default_album_artist, path)?;
+    println!();
+    let targets: Vec<PathBuf> = album.tracks.iter().map(|t| t.file_path.clone()).collect();
+    let mut skipped = crate::permissions::check_writable(&targets, make_writable, !yes)?;
+    let writable: Vec<PathBuf> = targets
+        .into_iter()
+        .filter(|file| !skipped.contains(file))
+        .collect();
+    skipped.extend(crate::check_tags(&writable, repair, settings.version)?);
+    if !skipped.is_empty() {
+        album.tracks.retain(|t| !skipped.contains(&t.file_path));
+        organize_plan.retain(|(file, _)| !skipped.contains(file));
+    }
+
+    // Write tags
+    println!("{}", "Writing tags...".bright_yellow());
+    let failures = tag_files(&album, settings, keep_going, events)?;
+    let failed = |file: &PathBuf| failures.iter().any(|(f, _)| f == file);
+    album.tracks.retain(|t| !failed(&t.file_path));
+    organize_plan.retain(|(file, _)| !failed(file));
+
+    println!();
+    crate::print_results(album.tracks.len(), skipped.len(), &failures);
+
+    let final_paths = match (organize, copied_paths) {
+        (_, Some(copied)) => copied,
+        (Some(options), None) => {
+            println!();
+            crate::organize::organize(&organize_plan, path, options)?
+        }
+        (None, None) => organize_plan.into_iter().map(|(file, _)| file).collect(),
+    };
+
+    if let Some(art) = &album.folder_art {
+        crate::save_folder_art(art, &final_paths)?;
+    }
+
+    if playlist {
+        if let Some(playlist_path) = crate::playlist::write_album_playlist(&final_paths)? {
+            println!(
+                "{} Wrote playlist {}",
+                symbols::ok().bright_green(),
+                playlist_path.display()
+            );
+        }
+    }
+
+    Ok(if failures.is_empty() {
+        ManualOutcome::Done
+    } else {
+        ManualOutcome::PartlyFailed(failures.len())
+    })
+}
+
+/// An explicit pattern wins over existing tags; the built-in filename
+/// heuristics are only a fallback behind them
+fn suggest(
+    file_path: &Path,
+    parsed: Option<ParsedName>,
+    index: usize,
+    has_pattern: bool,
+) -> TrackSuggestion {
+    let filename = file_name(file_path);
+    let existing_tags = crate::tagger::read_existing_tags(file_path);
+    let parsed = parsed.unwrap_or_default();
+    let (filename_artist, filename_title) = if has_pattern {
+        (None, filename_stem(&filename))
+    } else {
+        parse_filename(&filename)
+    };
+
+    TrackSuggestion {
+        artist: parsed.artist.or(existing_tags.artist).or(filename_artist),
+        title: parsed
+            .title
+            .or(existing_tags.title)
+            .unwrap_or(filename_title),
+        track: parsed
+            .track
+            .or(existing_tags.track)
+            .or_else(|| leading_number(&filename))
+            .unwrap_or((index + 1) as u32),
+        disc: parsed.disc.or(existing_tags.disc),
+        comment: existing_tags.comment,
+    }
+}
+
+/// The template record for `file`: same path relative to the album folder,
+/// or failing that the same file name, so a template survives a move
+fn template_record<'a>(
+    template: &'a [ImportRecord],
+    path: &Path,
+    file: &Path,
+) -> Option<&'a ImportRecord> {
+    let relative = Path::new(&relative_path(path, file)).to_path_buf();
+    template
+        .iter()
+        .find(|r| r.path.as_deref() == Some(relative.as_path()))
+        .or_else(|| {
+            template
+                .iter()
+                .find(|r| r.path.as_deref().and_then(Path::file_name) == file.file_name())
+        })
+}
+
+fn apply_template_record(suggestion: &mut TrackSuggestion, record: &ImportRecord) {
+    if let Some(artist) = &record.artist {
+        suggestion.artist = Some(artist.clone());
+    }
+    if let Some(title) = &record.title {
+        suggestion.title = title.clone();
+    }
+    if let Some(track) = record.track {
+        suggestion.track = track;
+    }
+    if record.disc.is_some() {
+        suggestion.disc = record.disc;
+    }
+    if record.comment.is_some() {
+        suggestion.comment = record.comment.clone();
+    }
+}
+
+/// Save the album as import records, one per track, with paths relative to the album folder
+fn write_template(template_path: &Path, path: &Path, album: &ManualAlbum) -> Result<()> {
+    let genre = (!album.genres.is_empty()).then(|| album.genres.join("; "));
+    let records: Vec<ImportRecord> = album
+        .tracks
+        .iter()
+        .map(|track| ImportRecord {
+            path: Some(PathBuf::from(relative_path(path, &track.file_path))),
+            title: Some(track.title.clone()),
+            artist: Some(track.artist.clone()),
+            album: Some(album.title.clone()),
+            album_artist: Some(album.artist.clone()),
+            track: Some(track.track_number),
+            total_tracks: Some(album.tracks.len() as u32),
+            disc: track.disc_number,
+            year: Some(album.year),
+            genre: genre.clone(),
+            comment: track.comment.clone().filter(|c| !c.is_empty()),
+            ..Default::default()
+        })
+        .collect();
+
+    let json = serde_json::to_string_pretty(&records)?;
+    std::fs::write(template_path, json)
+        .with_context(|| format!("Failed to write {}", template_path.display()))
+}
+
+/// Ask for the album, then each track in turn
+async fn prompt_album(
+    path: &Path,
+    files: &[PathBuf],
+    suggestions: Vec<TrackSuggestion>,
+    defaults: &AlbumInfo,
+    settings: &TagSettings,
+) -> Result<ManualAlbum> {
+    let (info, cover_art) = prompt_album_info(defaults, path, settings).await?;
+    let (cover_art, folder_art) = cover_art
+        .map(|art| crate::fit_cover_art(art, settings))
+        .unwrap_or_default();
+    let with_comments = Confirm::new()
+        .with_prompt("  Add comments to individual tracks?")
+        .default(suggestions.iter().any(|s| s.comment.is_some()))
+        .interact()?;
     println!();
 
     // Process each file
@@ -54,153 +501,499 @@ pub fn run(path: &Path, dry_run: bool, yes: bool) -> Result<()> {
         "{}",
         "(Press Enter to accept suggested value)".bright_black()
     );
+    println!(
+        "{}",
+        "(Add /all to use an artist or comment for all remaining tracks; /album uses the album artist)"
+            .bright_black()
+    );
     println!();
 
+    // Values the user applied to every remaining track
+    let mut all_artist: Option<String> = None;
+    let mut all_comment: Option<String> = None;
     let mut tracks = Vec::new();
-    for (i, file_path) in files.iter().enumerate() {
-        let filename = file_path
-            .file_name()
-            .map(|s| s.to_string_lossy().to_string())
-            .unwrap_or_default();
-
+    for (i, (file_path, suggestion)) in files.iter().zip(suggestions).enumerate() {
         println!(
             "{} {}",
             format!("[{}/{}]", i + 1, files.len()).bright_black(),
-            filename.bright_cyan()
+            file_name(file_path).bright_cyan()
         );
+        print_file_context(file_path);
 
-        // Read existing tags from file
-        let existing_tags = crate::tagger::read_existing_tags(file_path);
-
-        // Parse filename as fallback
-        let (filename_artist, filename_title) = parse_filename(&filename);
-
-        // Prefer existing tags, then filename parsing, then album artist
-        let default_artist = existing_tags
-            .artist
-            .or(filename_artist)
-            .unwrap_or_else(|| album_artist.clone());
-
-        let default_title = existing_tags.title.unwrap_or(filename_title);
-
-        let artist: String = Input::new()
-            .with_prompt("  Artist")
-            .default(default_artist)
+        let track_number: u32 = Input::new()
+            .with_prompt("  Track")
+            .default(suggestion.track)
             .interact_text()?;
 
+        let artist = match &all_artist {
+            Some(artist) => {
+                println!("  Artist: {} {}", artist, "(all tracks)".bright_black());
+                artist.clone()
+            }
+            None => {
+                let suggested = suggestion.artist.unwrap_or_else(|| info.artist.clone());
+                let input: String = Input::new()
+                    .with_prompt("  Artist")
+                    .default(suggested.clone())
+                    .interact_text()?;
+                if input.trim() == "/album" {
+                    all_artist = Some(info.artist.clone());
+                    info.artist.clone()
+                } else if let Some(artist) = apply_to_all(&input, &suggested) {
+                    all_artist = Some(artist.clone());
+                    artist
+                } else {
+                    input
+                }
+            }
+        };
+
         let title: String = Input::new()
             .with_prompt("  Title")
-            .default(default_title)
+            .default(suggestion.title)
             .interact_text()?;
 
+        let comment = match &all_comment {
+            _ if !with_comments => None,
+            Some(comment) => {
+                println!("  Comment: {} {}", comment, "(all tracks)".bright_black());
+                Some(comment.clone())
+            }
+            None => {
+                let suggested = suggestion.comment.unwrap_or_default();
+                let input: String = Input::new()
+                    .with_prompt("  Comment")
+                    .default(suggested.clone())
+                    .allow_empty(true)
+                    .interact_text()?;
+                if let Some(comment) = apply_to_all(&input, &suggested) {
+                    all_comment = Some(comment.clone());
+                    Some(comment)
+                } else {
+                    Some(input)
+                }
+            }
+        };
+
         tracks.push(ManualTrackInfo {
             file_path: file_path.clone(),
             title,
             artist,
-            track_number: (i + 1) as u32,
+            track_number,
+            disc_number: suggestion.disc,
+            comment,
         });
 
         println!();
     }
 
-    let album = ManualAlbum {
-        title: album_title,
-        artist: album_artist,
+    Ok(ManualAlbum {
+        genres: split_genres(&info.genre),
+        title: info.title,
+        artist: info.artist,
+        year: info.year,
         tracks,
         cover_art,
+        folder_art,
+    })
+}
+
+/// Search MusicBrainz with the album and artist as entered and let the user
+/// pick a release. `None` if nothing was found or chosen.
+async fn search_musicbrainz(
+    album: &ManualAlbum,
+    ranking: &ReleaseRanking,
+) -> Result<Option<String>> {
+    println!("{}", "Searching MusicBrainz...".bright_yellow());
+    // Compilations are credited to Various Artists, which matches too much
+    let artist = Some(album.artist.as_str()).filter(|a| *a != "Various Artists");
+    let client = crate::config::musicbrainz_client();
+    let mut releases = match client.search_releases(&album.title, artist).await {
+        Ok(releases) => releases,
+        Err(e) => {
+            println!(
+                "{} Search failed: {}",
+                symbols::warn().bright_yellow(),
+                describe_error(e)
+            );
+            println!();
+            return Ok(None);
+        }
     };
+    ranking.apply(&mut releases);
+    if releases.is_empty() {
+        println!(
+            "{} No releases found for \"{}\"",
+            symbols::warn().bright_yellow(),
+            album.title
+        );
+        println!();
+        return Ok(None);
+    }
 
-    // Show summary
+    let labels: Vec<String> = releases.iter().map(crate::search::label).collect();
+    let choice = Select::new()
+        .with_prompt(format!(
+            "Tag these {} file(s) from which release? (Esc to go back)",
+            album.tracks.len()
+        ))
+        .items(&labels)
+        .default(0)
+        .interact_opt()?;
+    println!();
+    Ok(choice.map(|i| releases[i].id.clone()))
+}
+
+/// The error and its root cause; the full chain repeats itself for network errors
+fn describe_error(e: crate::error::Error) -> String {
+    let e = anyhow::Error::from(e);
+    match e.chain().nth(1) {
+        Some(_) => format!("{}: {}", e, e.root_cause()),
+        None => e.to_string(),
+    }
+}
+
+/// Length, quality, and every tag the file already has, so the user can see
+/// what they're replacing
+fn print_file_context(file_path: &Path) {
+    let audio = crate::describe_audio(file_path, crate::audio::probe(file_path).as_ref());
+    if let Some(audio) = audio.strip_prefix(", ") {
+        println!("  {}", audio.bright_black());
+    }
+
+    let Ok(tag) = Tag::read_from_path(file_path) else {
+        println!("  {}", "No existing tags".bright_black());
+        return;
+    };
+    for frame in tag.frames() {
+        let (label, value) = match frame.content() {
+            Content::ExtendedText(text) => (text.description.clone(), text.value.clone()),
+            Content::Comment(comment) if comment.description.is_empty() => {
+                ("Comment".to_string(), comment.text.clone())
+            }
+            Content::Comment(comment) => (comment.description.clone(), comment.text.clone()),
+            Content::Lyrics(lyrics) => (
+                "Lyrics".to_string(),
+                format!("{} line(s)", lyrics.text.lines().count()),
+            ),
+            Content::Picture(picture) => (
+                format!("Picture ({})", picture.picture_type),
+                format!(
+                    "{}, {:.1} KB",
+                    picture.mime_type,
+                    picture.data.len() as f64 / 1024.0
+                ),
+            ),
+            content => match content.text() {
+                Some(text) => (frame_label(frame.id(), frame.name()), text.to_string()),
+                None => continue,
+            },
+        };
+        println!(
+            "  {}",
+            format!("{}: {}", label, truncate(&value.replace('\0', "; "), 70)).bright_black()
+        );
+    }
+}
+
+/// Short names for the frames people care about; id3's names are the spec's
+fn frame_label(id: &str, name: &str) -> String {
+    let label = match id {
+        "TIT2" => "Title",
+        "TPE1" => "Artist",
+        "TPE2" => "Album Artist",
+        "TALB" => "Album",
+        "TRCK" => "Track",
+        "TPOS" => "Disc",
+        "TYER" | "TDRC" => "Year",
+        "TCON" => "Genre",
+        "TCOM" => "Composer",
+        "TSOP" => "Artist Sort",
+        "TSO2" => "Album Artist Sort",
+        _ => name,
+    };
+    label.to_string()
+}
+
+fn truncate(value: &str, max_chars: usize) -> String {
+    if value.chars().count() <= max_chars {
+        value.to_string()
+    } else {
+        let cut: String = value.chars().take(max_chars - 1).collect();
+        format!("{}{}", cut, symbols::ellipsis())
+    }
+}
+
+/// The value to use for every remaining track if `input` ends in "/all":
+/// whatever was typed before it, or the suggestion if nothing was
+fn apply_to_all(input: &str, suggested: &str) -> Option<String> {
+    let value = input.trim().strip_suffix("/all")?.trim();
+    Some(if value.is_empty() {
+        suggested.to_string()
+    } else {
+        value.to_string()
+    })
+}
+
+/// Write the suggestions to a TOML file, open it in the user's editor, and
+/// build the album from what comes back
+async fn edit_album(
+    path: &Path,
+    files: &[PathBuf],
+    suggestions: Vec<TrackSuggestion>,
+    defaults: &AlbumInfo,
+    settings: &TagSettings,
+) -> Result<Option<ManualAlbum>> {
+    let relative = |file: &Path| relative_path(path, file);
+
+    let draft = AlbumDraft {
+        album: AlbumFields {
+            title: defaults.title.clone(),
+            artist: defaults.artist.clone(),
+            year: defaults.year,
+            genre: defaults.genre.clone(),
+            cover: find_cover_art_in_dir(path)
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        },
+        tracks: files
+            .iter()
+            .zip(suggestions)
+            .map(|(file, suggestion)| TrackDraft {
+                file: relative(file),
+                track: suggestion.track,
+                disc: suggestion.disc,
+                artist: suggestion.artist.unwrap_or_else(|| defaults.artist.clone()),
+                title: suggestion.title,
+                comment: suggestion.comment.unwrap_or_default(),
+            })
+            .collect(),
+    };
+
+    println!("{}", "Waiting for the editor to close...".bright_yellow());
+    let Some(edited) = crate::manual_edit::edit(&draft)? else {
+        return Ok(None);
+    };
+
+    let tracks = edited
+        .tracks
+        .into_iter()
+        .filter_map(|track| {
+            let file_path = files.iter().find(|f| relative(f) == track.file)?;
+            Some(ManualTrackInfo {
+                file_path: file_path.clone(),
+                title: track.title,
+                artist: track.artist,
+                track_number: track.track,
+                disc_number: track.disc,
+                comment: Some(track.comment),
+            })
+        })
+        .collect();
+
+    println!();
+    let (cover_art, folder_art) = load_cover_art(&edited.album.cover, settings)
+        .await
+        .map(|art| crate::fit_cover_art(art, settings))
+        .unwrap_or_default();
+    Ok(Some(ManualAlbum {
+        cover_art,
+        folder_art,
+        title: edited.album.title,
+        artist: edited.album.artist,
+        year: edited.album.year,
+        genres: split_genres(&edited.album.genre),
+        tracks,
+    }))
+}
+
+fn print_summary(album: &ManualAlbum) {
     println!("{}", "Summary:".bright_white().bold());
     println!(
         "  Album: {} by {}",
         album.title.bright_cyan(),
         album.artist.bright_cyan()
     );
+    println!("  Year: {}", album.year);
+    if !album.genres.is_empty() {
+        println!("  Genre: {}", album.genres.join(", "));
+    }
     if album.cover_art.is_some() {
         println!("  Cover art: {}", "Yes".bright_green());
+    } else if album.folder_art.is_some() {
+        println!(
+            "  Cover art: {}",
+            "Folder only (too large to embed)".bright_yellow()
+        );
     } else {
         println!("  Cover art: {}", "None".bright_yellow());
     }
     println!();
     for track in &album.tracks {
         println!(
-            "  {}. {} - {}",
-            track.track_number,
+            "  {}. {} - {} {}",
+            track_label(track),
             track.artist.bright_white(),
-            track.title.bright_white()
+            track.title.bright_white(),
+            format!("({})", file_name(&track.file_path)).bright_black()
         );
+        if let Some(comment) = track.comment.as_deref().filter(|c| !c.is_empty()) {
+            println!("     {}", comment.bright_black());
+        }
     }
     println!();
+}
 
-    if dry_run {
-        println!("{}", "Dry run - no files were modified.".bright_yellow());
-        return Ok(());
+/// "3", or "2-3" when the disc is known
+fn track_label(track: &ManualTrackInfo) -> String {
+    match track.disc_number {
+        Some(disc) => format!("{}-{}", disc, track.track_number),
+        None => track.track_number.to_string(),
     }
+}
 
-    // Confirm
-    if !yes {
-        use dialoguer::Confirm;
-        let confirmed = Confirm::new()
-            .with_prompt("Apply these tags?")
-            .default(false)
-            .interact()?;
+fn describe_track(track: &ManualTrackInfo) -> String {
+    format!(
+        "{}. {} - {} ({})",
+        track_label(track),
+        track.artist,
+        track.title,
+        file_name(&track.file_path)
+    )
+}
 
-        if !confirmed {
-            println!("{}", "Aborted.".bright_yellow());
-            return Ok(());
-        }
+/// The same casing tagging from MusicBrainz applies
+fn apply_casing(casing: &CasingRules, album: &mut ManualAlbum) {
+    album.title = casing::apply(casing.album, &album.title);
+    album.artist = casing::apply(casing.artist, &album.artist);
+    for track in &mut album.tracks {
+        track.title = casing::apply(casing.title, &track.title);
+        track.artist = casing::apply(casing.artist, &track.artist);
     }
+}
 
-    // Write tags
-    println!();
-    println!("{}", "Writing tags...".bright_yellow());
-    crate::tagger::tag_files_manual(&album)?;
+fn sort_tracks(tracks: &mut [ManualTrackInfo]) {
+    tracks.sort_by_key(|t| (t.disc_number.unwrap_or(1), t.track_number));
+}
 
+fn change_track_number(tracks: &mut [ManualTrackInfo]) -> Result<()> {
+    let labels: Vec<String> = tracks.iter().map(describe_track).collect();
+    let Some(i) = Select::new()
+        .with_prompt("Which track? (Esc to cancel)")
+        .items(&labels)
+        .default(0)
+        .interact_opt()?
+    else {
+        return Ok(());
+    };
+
+    tracks[i].track_number = Input::new()
+        .with_prompt("  Track")
+        .default(tracks[i].track_number)
+        .interact_text()?;
     println!();
-    println!(
-        "{} {}",
-        "✓".bright_green(),
-        "Successfully tagged all files!".bright_green().bold()
-    );
+    sort_tracks(tracks);
+    Ok(())
+}
+
+/// Let the user put the tracks in order, then number each disc from 1 in that order
+fn reorder_tracks(tracks: &mut Vec<ManualTrackInfo>) -> Result<()> {
+    let labels: Vec<String> = tracks.iter().map(describe_track).collect();
+    let Some(order) = Sort::new()
+        .with_prompt("Move tracks with Space and the arrow keys, Enter when done")
+        .items(&labels)
+        .interact_opt()?
+    else {
+        return Ok(());
+    };
+
+    let mut remaining: Vec<Option<ManualTrackInfo>> = tracks.drain(..).map(Some).collect();
+    tracks.extend(order.into_iter().filter_map(|i| remaining[i].take()));
 
+    let mut numbers = std::collections::HashMap::new();
+    for track in tracks.iter_mut() {
+        let number = numbers.entry(track.disc_number).or_insert(0);
+        *number += 1;
+        track.track_number = *number;
+    }
     Ok(())
 }
 
-fn collect_mp3_files(path: &Path) -> Result<Vec<PathBuf>> {
-    let mut files: Vec<PathBuf> = WalkDir::new(path)
-        .max_depth(1)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| {
-            e.path()
-                .extension()
-                .map(|ext| ext.eq_ignore_ascii_case("mp3"))
-                .unwrap_or(false)
-        })
-        .map(|e| e.path().to_path_buf())
-        .collect();
+/// Drop files from the album; they're left untouched
+fn exclude_tracks(tracks: &mut Vec<ManualTrackInfo>) -> Result<()> {
+    let labels: Vec<String> = tracks.iter().map(describe_track).collect();
+    let Some(excluded) = MultiSelect::new()
+        .with_prompt("Select files to leave untouched (Space to select, Enter when done)")
+        .items(&labels)
+        .interact_opt()?
+    else {
+        return Ok(());
+    };
 
-    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
-    Ok(files)
+    let mut i = 0;
+    tracks.retain(|_| {
+        i += 1;
+        !excluded.contains(&(i - 1))
+    });
+    Ok(())
+}
+
+fn template_values(album: &ManualAlbum, track: &ManualTrackInfo) -> TemplateValues {
+    TemplateValues {
+        title: track.title.clone(),
+        artist: track.artist.clone(),
+        album: album.title.clone(),
+        album_artist: album.artist.clone(),
+        year: Some(album.year),
+        track: Some(track.track_number),
+        disc: track.disc_number,
+        total_tracks: Some(album.tracks.len() as u32),
+        disc_tracks: album
+            .tracks
+            .iter()
+            .filter(|t| t.disc_number == track.disc_number)
+            .map(|t| t.track_number)
+            .max(),
+        genre: album.genres.first().cloned(),
+        ..Default::default()
+    }
 }
 
-fn prompt_album_info(
-    default_album: &str,
-    default_artist: &str,
+fn split_genres(genre: &str) -> Vec<String> {
+    genre
+        .split(';')
+        .map(str::trim)
+        .filter(|g| !g.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+async fn prompt_album_info(
+    defaults: &AlbumInfo,
     path: &Path,
-) -> Result<(String, String, Option<Vec<u8>>)> {
+    settings: &TagSettings,
+) -> Result<(AlbumInfo, Option<Vec<u8>>)> {
     println!("{}", "Album Information:".bright_white().bold());
 
-    let album_title: String = Input::new()
+    let title: String = Input::new()
         .with_prompt("  Album Title")
-        .default(default_album.to_string())
+        .default(defaults.title.clone())
         .interact_text()?;
 
-    let album_artist: String = Input::new()
+    let artist: String = Input::new()
         .with_prompt("  Album Artist")
-        .default(default_artist.to_string())
+        .default(defaults.artist.clone())
+        .interact_text()?;
+
+    let year: i32 = Input::new()
+        .with_prompt("  Year")
+        .default(defaults.year)
+        .interact_text()?;
+
+    let genre: String = Input::new()
+        .with_prompt("  Genre (separate several with ;)")
+        .default(defaults.genre.clone())
+        .allow_empty(true)
         .interact_text()?;
 
     // Look for existing cover art in directory
@@ -211,41 +1004,81 @@ fn prompt_album_info(
         .unwrap_or_default();
 
     let cover_path: String = Input::new()
-        .with_prompt("  Cover Art (path to image, or leave empty)")
+        .with_prompt("  Cover Art (path or URL to image, or leave empty)")
         .default(default_cover_str)
         .allow_empty(true)
         .interact_text()?;
 
-    let cover_art = if cover_path.is_empty() {
+    let cover_art = load_cover_art(&cover_path, settings).await;
+    let info = AlbumInfo {
+        title,
+        artist,
+        year,
+        genre,
+    };
+
+    Ok((info, cover_art))
+}
+
+/// Read the image at `cover_path`, or download it if it's an http(s) URL,
+/// warning and returning `None` if that fails
+async fn load_cover_art(cover_path: &str, settings: &TagSettings) -> Option<Vec<u8>> {
+    let cover_path = cover_path.trim();
+    if cover_path.is_empty() {
         None
+    } else if cover_path.starts_with("http://") || cover_path.starts_with("https://") {
+        let client = crate::config::musicbrainz_client();
+        match client
+            .download_image(cover_path, settings.max_art_size)
+            .await
+        {
+            Ok(data) => {
+                println!(
+                    "  {} Downloaded cover art ({:.1} KB)",
+                    symbols::ok().bright_green(),
+                    data.len() as f64 / 1024.0
+                );
+                Some(data)
+            }
+            Err(e) => {
+                println!(
+                    "  {} Could not download cover art: {}",
+                    symbols::warn().bright_yellow(),
+                    describe_error(e)
+                );
+                None
+            }
+        }
     } else {
-        let cover_path = Path::new(&cover_path);
+        let cover_path = Path::new(cover_path);
         if cover_path.exists() {
             match std::fs::read(cover_path) {
                 Ok(data) => {
                     println!(
                         "  {} Loaded cover art ({:.1} KB)",
-                        "✓".bright_green(),
+                        symbols::ok().bright_green(),
                         data.len() as f64 / 1024.0
                     );
                     Some(data)
                 }
                 Err(e) => {
-                    println!("  {} Could not read cover art: {}", "⚠".bright_yellow(), e);
+                    println!(
+                        "  {} Could not read cover art: {}",
+                        symbols::warn().bright_yellow(),
+                        e
+                    );
                     None
                 }
             }
         } else {
             println!(
                 "  {} Cover art file not found: {}",
-                "⚠".bright_yellow(),
+                symbols::warn().bright_yellow(),
                 cover_path.display()
             );
             None
         }
-    };
-
-    Ok((album_title, album_artist, cover_art))
+    }
 }
 
 fn find_cover_art_in_dir(path: &Path) -> Option<PathBuf> {
@@ -285,6 +1118,28 @@ fn find_cover_art_in_dir(path: &Path) -> Option<PathBuf> {
     None
 }
 
+/// `file` relative to the album folder, or as given if it lies elsewhere
+fn relative_path(path: &Path, file: &Path) -> String {
+    file.strip_prefix(path)
+        .unwrap_or(file)
+        .to_string_lossy()
+        .to_string()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn filename_stem(filename: &str) -> String {
+    filename
+        .strip_suffix(".mp3")
+        .or_else(|| filename.strip_suffix(".MP3"))
+        .unwrap_or(filename)
+        .to_string()
+}
+
 fn parse_filename(filename: &str) -> (Option<String>, String) {
     // Remove extension
     let name = filename
@@ -311,6 +1166,20 @@ fn parse_filename(filename: &str) -> (Option<String>, String) {
     (None, name.trim().to_string())
 }
 
+/// Track number at the start of a file name such as "07 - Title.mp3"
+fn leading_number(filename: &str) -> Option<u32> {
+    let digits: String = filename
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    // Longer runs are more likely a year or catalog number
+    if digits.len() > 3 {
+        return None;
+    }
+    digits.parse().ok().filter(|&n| n > 0)
+}
+
 fn strip_track_number(name: &str) -> &str {
     let name = name.trim();
 
@@ -350,3 +1219,95 @@ fn strip_track_number(name: &str) -> &str {
 
     name
 }
+
+/// With `keep_going`, files that fail are returned instead of stopping the batch
+fn tag_files(
+    album: &ManualAlbum,
+    settings: &TagSettings,
+    keep_going: bool,
+    events: &dyn EventSink,
+) -> Result<Vec<(PathBuf, anyhow::Error)>> {
+    let pb = crate::progress::bar(album.tracks.len(), "Writing tags");
+
+    let total_tracks = album.tracks.len() as u32;
+
+    let mut failures = Vec::new();
+    for track in &album.tracks {
+        pb.set_message(format!(
+            "{}",
+            track.file_path.file_name().unwrap().to_string_lossy()
+        ));
+
+        let result = write_manual_tags(
+            &track.file_path,
+            track,
+            album,
+            total_tracks,
+            album.year,
+            settings,
+        )
+        .with_context(|| format!("Failed to write tags to {}", track.file_path.display()));
+        match result {
+            Ok(()) => events.emit(Event::TagWritten {
+                file: track.file_path.clone(),
+            }),
+            Err(e) if keep_going => {
+                events.emit(Event::TagFailed {
+                    file: track.file_path.clone(),
+                    error: format!("{:#}", e),
+                });
+                failures.push((track.file_path.clone(), e));
+            }
+            Err(e) => return Err(e),
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("Complete");
+    Ok(failures)
+}
+
+fn write_manual_tags(
+    file_path: &Path,
+    track: &ManualTrackInfo,
+    album: &ManualAlbum,
+    total_tracks: u32,
+    year: i32,
+    settings: &TagSettings,
+) -> Result<()> {
+    let mut tag = crate::tagger::read_tag(file_path).unwrap_or_else(|_| Tag::new());
+
+    tag.set_title(&track.title);
+    tag.set_artist(&track.artist);
+    tag.set_album(&album.title);
+    tag.set_album_artist(&album.artist);
+    tag.set_track(track.track_number);
+    tag.set_total_tracks(total_tracks);
+    if let Some(disc) = track.disc_number {
+        tag.set_disc(disc);
+    }
+    tag.set_year(year);
+    crate::tagger::set_genres(&mut tag, &album.genres, settings.multi_value);
+
+    if let Some(comment) = &track.comment {
+        tag.remove_comment(Some(""), None);
+        if !comment.is_empty() {
+            tag.add_frame(Comment {
+                lang: "eng".to_string(),
+                description: String::new(),
+                text: comment.clone(),
+            });
+        }
+    }
+
+    // Add cover art if provided
+    if let Some(image_data) = &album.cover_art {
+        crate::tagger::add_cover_art(&mut tag, image_data);
+    }
+
+    tag.write_to_path(file_path, settings.version)
+        .context("Failed to write ID3 tag")?;
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+// src/marker.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::symbols;
+
+/// Written into an album folder once all of its files are tagged
+pub const MARKER_FILE: &str = ".mb-tagger.toml";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Marker {
+    pub release_id: String,
+    /// RFC 3339, UTC
+    pub tagged_at: String,
+    pub version: String,
+    /// Command-line arguments of the run that tagged the folder
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+impl Marker {
+    pub fn new(release_id: &str) -> Self {
+        Marker {
+            release_id: release_id.to_string(),
+            tagged_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            options: std::env::args().skip(1).collect(),
+        }
+    }
+}
+
+pub fn write(dir: &Path, marker: &Marker) -> Result<PathBuf> {
+    let path = dir.join(MARKER_FILE);
+    let contents = toml::to_string(marker).context("Failed to serialize marker")?;
+    std::fs::write(&path, contents)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// The folder's marker, if it has a readable one
+pub fn read(dir: &Path) -> Option<Marker> {
+    let contents = std::fs::read_to_string(dir.join(MARKER_FILE)).ok()?;
+    toml::from_str(&contents).ok()
+}
+
+/// Drop files in marked folders under `root` (the folder itself counts, so
+/// disc subfolders of a marked album are skipped too), reporting each folder
+pub fn skip_marked(files: Vec<PathBuf>, root: &Path) -> Vec<PathBuf> {
+    let root = if root.is_dir() {
+        root
+    } else {
+        root.parent().unwrap_or(root)
+    };
+    let mut marked: HashMap<PathBuf, Option<Marker>> = HashMap::new();
+    let mut kept = Vec::new();
+    for file in files {
+        let marked_dir = file
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(root))
+            .find(|dir| marked.contains_key(*dir) || dir.join(MARKER_FILE).is_file())
+            .map(Path::to_path_buf);
+        match marked_dir {
+            Some(dir) => {
+                marked.entry(dir.clone()).or_insert_with(|| read(&dir));
+            }
+            None => kept.push(file),
+        }
+    }
+    if marked.is_empty() {
+        return kept;
+    }
+
+    let mut dirs: Vec<_> = marked.into_iter().collect();
+    dirs.sort_by(|a, b| a.0.cmp(&b.0));
+    println!(
+        "{} Skipping {} folder(s) already tagged (use --ignore-markers to tag them again):",
+        symbols::warn().bright_yellow(),
+        dirs.len()
+    );
+    for (dir, marker) in dirs {
+        let detail = match marker {
+            Some(marker) => format!(
+                "(release {}, {})",
+                marker.release_id,
+                marker.tagged_at.split('T').next().unwrap_or_default()
+            ),
+            None => format!("(unreadable {})", MARKER_FILE),
+        };
+        println!("  {} {}", dir.display(), detail.bright_black());
+    }
+    println!();
+    kept
+}
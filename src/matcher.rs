@@ -1,99 +1,249 @@
 // src/matcher.rs
-use anyhow::Result;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
 use walkdir::WalkDir;
 
+use crate::audio::AudioInfo;
+use crate::error::{Error, Result};
+use crate::events::{Event, EventSink};
+use crate::exclude::ExcludeRules;
 use crate::musicbrainz::{Album, Track};
 
-#[derive(Debug)]
+/// Releases with at least this many tracks get candidate pruning before fuzzy scoring
+const PREINDEX_MIN_TRACKS: usize = 60;
+
+/// Tracks kept per file after pruning
+const MAX_CANDIDATES: usize = 24;
+
+/// Largest bonus a duration match can add; name scores closer than this are ambiguous
+const DURATION_MARGIN: i64 = 80;
+
+/// On compilations, for the artist in an "Artist - Title" file name agreeing
+/// with the track's credit, or naming someone else
+const ARTIST_BONUS: i64 = 80;
+const ARTIST_PENALTY: i64 = -150;
+
+/// Matches below this confidence are set aside rather than tagged
+pub const MIN_CONFIDENCE: f64 = 0.15;
+
+/// Folder that holds files set aside as unmatched; never searched
+pub const UNMATCHED_DIR: &str = "_unmatched";
+
+/// Folder that holds extra copies of matched tracks; never searched
+pub const DUPLICATES_DIR: &str = "_duplicates";
+
+/// Copies of a track must be this close in length; a longer or shorter file
+/// with the same name is a different version
+const DUPLICATE_DURATION_TOLERANCE_MS: u32 = 3000;
+
+/// A file paired with the release track it will be tagged as
+#[derive(Debug, Serialize, Deserialize)]
 pub struct FileMatch {
     pub file_path: PathBuf,
     pub track: Track,
+    /// 0.0 – 1.0, derived from the match score
     pub confidence: f64,
+    /// Other tracks share this one's title, and the file's length moved it
+    /// to this one from the track its name pointed to
+    #[serde(default)]
+    pub duration_resolved: bool,
 }
 
-pub fn match_files(path: &Path, album: &Album) -> Result<Vec<FileMatch>> {
-    let mp3_files = find_mp3_files(path)?;
+/// How a file/track pair's score was made up
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ScoreBreakdown {
+    /// Best fuzzy or word-overlap similarity of the file name to the title
+    pub name: i64,
+    /// Bonus for matching version qualifiers ("Live", "Remix"), penalty for mismatches
+    pub qualifiers: i64,
+    /// Bonus for a file duration close to the track's; 0 if either is unknown
+    pub duration: i64,
+    /// On compilations, bonus or penalty for the artist in the file name
+    /// agreeing with the track's; 0 elsewhere
+    pub artist: i64,
+}
 
-    if mp3_files.is_empty() {
-        return Ok(Vec::new());
+impl ScoreBreakdown {
+    pub fn total(&self) -> i64 {
+        self.name + self.qualifiers + self.duration + self.artist
     }
 
-    println!("Album tracks from MusicBrainz:");
-
-    if album.media_count > 1 {
-        let mut current_disc = 0;
-        for track in &album.tracks {
-            if track.disc_number != current_disc {
-                current_disc = track.disc_number;
-                let disc_info = if let Some(title) = &track.disc_title {
-                    format!(" - {}", title)
-                } else {
-                    String::new()
-                };
-                println!("\n  Disc {}{}:", current_disc, disc_info);
-            }
-            let duration = track
-                .length
-                .map(|ms| format!(" ({})", format_duration(ms)))
-                .unwrap_or_default();
-            println!("    {}. {}{}", track.position, track.title, duration);
-        }
-    } else {
-        for track in &album.tracks {
-            let duration = track
-                .length
-                .map(|ms| format!(" ({})", format_duration(ms)))
-                .unwrap_or_default();
-            println!("  {}. {}{}", track.position, track.title, duration);
-        }
+    /// 0.0 – 1.0; a total of 200 or more counts as certain
+    pub fn confidence(&self) -> f64 {
+        (self.total() as f64 / 200.0).clamp(0.0, 1.0)
     }
-    println!();
+}
 
-    let matcher = SkimMatcherV2::default();
+/// One file/track pairing, in the order the greedy assignment made it
+#[derive(Debug)]
+pub struct Assignment {
+    pub file_path: PathBuf,
+    /// Index into `Album::tracks`
+    pub track_index: usize,
+    pub score: i64,
+    pub confidence: f64,
+    pub breakdown: ScoreBreakdown,
+    /// Moved to another track sharing its title because of its length
+    pub duration_resolved: bool,
+}
 
-    // PHASE 1: Score all possible file-to-track combinations
-    println!("Computing all possible matches...");
+/// A file/track pair that was considered, whether or not it was assigned
+#[derive(Debug)]
+pub struct PairScore {
+    pub file_path: PathBuf,
+    /// Index into `Album::tracks`
+    pub track_index: usize,
+    pub breakdown: ScoreBreakdown,
+}
+
+/// An extra copy of a matched track, left out of the matches rather than
+/// reported as unmatched
+#[derive(Debug, Clone, Serialize)]
+pub struct Duplicate {
+    /// The copy that isn't tagged
+    pub file_path: PathBuf,
+    /// The copy matched to the track instead, the better of the two
+    pub kept: PathBuf,
+    /// Index into `Album::tracks`
+    pub track_index: usize,
+}
 
-    #[derive(Debug, Clone)]
-    struct PossibleMatch {
-        file_idx: usize,
-        track_idx: usize,
-        score: i64,
-        confidence: f64,
+/// Everything `match_files` found out, for callers to render or act on
+#[derive(Debug, Default)]
+pub struct MatchReport {
+    /// Matches to tag, sorted by disc and track
+    pub matches: Vec<FileMatch>,
+    /// Matches below MIN_CONFIDENCE, which are not included in `matches`
+    pub low_confidence: Vec<FileMatch>,
+    /// Every pairing in assignment order, including low-confidence ones
+    pub assignments: Vec<Assignment>,
+    /// Every pair with a positive score, highest first
+    pub candidates: Vec<PairScore>,
+    pub unmatched_files: Vec<PathBuf>,
+    pub unmatched_tracks: Vec<Track>,
+    /// Files that lost their track to another copy of it in the same folder
+    pub duplicates: Vec<Duplicate>,
+    /// Durations (ms) of the files that had to be probed
+    pub durations: HashMap<PathBuf, u32>,
+}
+
+/// Match files to the release's tracks by file name (and duration where names
+/// are ambiguous). Every track and file is used at most once; pairs are
+/// assigned greedily, highest score first. Files that score the same against
+/// several tracks sharing a title are then paired with them by closest
+/// duration. A file left over because another copy of its track in the same
+/// folder took it is a duplicate; of the copies, the one with the highest
+/// bitrate is matched. Returns `Error::Cancelled` if `cancel` fires before the
+/// matches are assigned.
+pub async fn match_files(
+    mp3_files: &[PathBuf],
+    album: &Album,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Result<MatchReport> {
+    let mut report = MatchReport::default();
+    if mp3_files.is_empty() {
+        return Ok(report);
     }
 
-    let mut all_possible_matches: Vec<PossibleMatch> = Vec::new();
+    let matcher = SkimMatcherV2::default();
 
-    for (file_idx, file) in mp3_files.iter().enumerate() {
-        let file_duration = get_mp3_duration(file);
+    // PHASE 1: Score all possible file-to-track combinations
+    // Big box sets: prune file/track pairs by shared title trigrams first
+    let title_index =
+        (album.tracks.len() >= PREINDEX_MIN_TRACKS).then(|| TitleIndex::new(&album.tracks));
+    let candidates: Vec<Vec<usize>> = mp3_files
+        .iter()
+        .map(|file| match &title_index {
+            Some(index) => index.candidates(file),
+            None => (0..album.tracks.len()).collect(),
+        })
+        .collect();
 
-        for (track_idx, track) in album.tracks.iter().enumerate() {
-            if let Some((_, confidence, score)) =
-                score_match(file, track, &matcher, file_duration, &album.artist)
-            {
-                all_possible_matches.push(PossibleMatch {
+    // Compilation files named "Artist - Title"; only artists that are credited
+    // somewhere on the release count, so "Title - Artist" names are left alone
+    let file_artists: Vec<Option<String>> = mp3_files
+        .iter()
+        .map(|file| {
+            let artist = filename_artist(file).filter(|_| album.is_compilation)?;
+            // "05. Artist" has a track number in front, "50 Cent" doesn't
+            [artist.as_str(), without_track_number(&artist)]
+                .iter()
+                .find(|artist| album.tracks.iter().any(|t| same_artist(artist, &t.artist)))
+                .map(|artist| artist.to_string())
+        })
+        .collect();
+
+    let score_file = |file_idx: usize, file_duration: Option<u32>| {
+        candidates[file_idx]
+            .iter()
+            .filter_map(|&track_idx| {
+                let track = &album.tracks[track_idx];
+                score_match(
+                    &mp3_files[file_idx],
+                    track,
+                    &matcher,
+                    file_duration,
+                    &album.artist,
+                    file_artists[file_idx].as_deref(),
+                )
+                .map(|breakdown| PossibleMatch {
                     file_idx,
                     track_idx,
-                    score,
-                    confidence,
-                });
-            }
+                    score: breakdown.total(),
+                    confidence: breakdown.confidence(),
+                    breakdown,
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Names alone settle most files; decoding audio is only worth it where
+    // the duration bonus could still change the outcome
+    let mut all_possible_matches: Vec<PossibleMatch> = (0..mp3_files.len())
+        .flat_map(|i| score_file(i, None))
+        .collect();
+
+    let mut durations: Vec<Option<u32>> = vec![None; mp3_files.len()];
+    let ambiguous = ambiguous_files(&all_possible_matches);
+    if !ambiguous.is_empty() {
+        let paths: Vec<PathBuf> = ambiguous.iter().map(|&i| mp3_files[i].clone()).collect();
+        let probed = probe_durations(&paths, cancel).await?;
+        for (&file_idx, duration) in ambiguous.iter().zip(probed) {
+            durations[file_idx] = duration;
+        }
+
+        all_possible_matches.retain(|m| !ambiguous.contains(&m.file_idx));
+        for &file_idx in &ambiguous {
+            all_possible_matches.extend(score_file(file_idx, durations[file_idx]));
         }
     }
 
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+
     // PHASE 2: Sort by score (highest first)
-    all_possible_matches.sort_by(|a, b| b.score.cmp(&a.score));
+    all_possible_matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+
+    report.candidates = all_possible_matches
+        .iter()
+        .map(|m| PairScore {
+            file_path: mp3_files[m.file_idx].clone(),
+            track_index: m.track_idx,
+            breakdown: m.breakdown,
+        })
+        .collect();
 
     // PHASE 3: Greedily assign matches, preventing conflicts
-    let mut matched_files: std::collections::HashSet<usize> = std::collections::HashSet::new();
-    let mut matched_tracks: std::collections::HashSet<usize> = std::collections::HashSet::new();
+    let mut matched_files: HashSet<usize> = HashSet::new();
+    let mut matched_tracks: HashSet<usize> = HashSet::new();
     let mut final_matches: Vec<FileMatch> = Vec::new();
 
-    println!("\nAssigning matches (highest confidence first)...");
-
     for possible in all_possible_matches {
         // Skip if either file or track already matched
         if matched_files.contains(&possible.file_idx)
@@ -105,117 +255,165 @@ pub fn match_files(path: &Path, album: &Album) -> Result<Vec<FileMatch>> {
         let file = &mp3_files[possible.file_idx];
         let track = &album.tracks[possible.track_idx];
 
-        let file_name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
-
-        let file_duration = get_mp3_duration(file);
-        let file_dur_str = file_duration
-            .map(|ms| format!(" [file: {}]", format_duration(ms)))
-            .unwrap_or_default();
-        let track_dur_str = track
-            .length
-            .map(|ms| format!(" [track: {}]", format_duration(ms)))
-            .unwrap_or_default();
-
-        if album.media_count > 1 {
-            println!(
-                "  ✓ {} -> Disc {} Track {} - {} (score: {}, confidence: {}%){}{}",
-                file_name,
-                track.disc_number,
-                track.position,
-                track.title,
-                possible.score,
-                (possible.confidence * 100.0) as i32,
-                file_dur_str,
-                track_dur_str
-            );
-        } else {
-            println!(
-                "  ✓ {} -> Track {} - {} (score: {}, confidence: {}%){}{}",
-                file_name,
-                track.position,
-                track.title,
-                possible.score,
-                (possible.confidence * 100.0) as i32,
-                file_dur_str,
-                track_dur_str
-            );
-        }
-
         matched_files.insert(possible.file_idx);
         matched_tracks.insert(possible.track_idx);
 
+        report.assignments.push(Assignment {
+            file_path: file.clone(),
+            track_index: possible.track_idx,
+            score: possible.score,
+            confidence: possible.confidence,
+            breakdown: possible.breakdown,
+            duration_resolved: false,
+        });
         final_matches.push(FileMatch {
             file_path: file.clone(),
             track: track.clone(),
             confidence: possible.confidence,
+            duration_resolved: false,
         });
     }
 
-    println!();
-
-    // Report unmatched files
-    if matched_files.len() < mp3_files.len() {
-        println!("Unmatched files:");
-        for (idx, file) in mp3_files.iter().enumerate() {
-            if !matched_files.contains(&idx) {
-                let file_name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
-                let duration = get_mp3_duration(file)
-                    .map(|ms| format!(" ({})", format_duration(ms)))
-                    .unwrap_or_default();
-                println!("  ✗ {}{}", file_name, duration);
+    // PHASE 3b: Tracks sharing a title ("Interlude", "Untitled") can't be told
+    // apart by name; let the files' lengths decide
+    for group in same_title_groups(&album.tracks) {
+        let paired = pair_by_duration(&group, mp3_files, album, &durations, &report);
+        for (assignment, _, _) in &paired {
+            matched_tracks.remove(&report.assignments[*assignment].track_index);
+        }
+        for (assignment, track_index, breakdown) in paired {
+            matched_tracks.insert(track_index);
+            let assignment = &mut report.assignments[assignment];
+            if assignment.track_index == track_index {
+                // The lengths agree with the names
+                continue;
+            }
+            assignment.track_index = track_index;
+            assignment.score = breakdown.total();
+            assignment.confidence = breakdown.confidence();
+            assignment.breakdown = breakdown;
+            assignment.duration_resolved = true;
+            if let Some(m) = final_matches
+                .iter_mut()
+                .find(|m| m.file_path == assignment.file_path)
+            {
+                m.track = album.tracks[track_index].clone();
+                m.confidence = breakdown.confidence();
+                m.duration_resolved = true;
             }
         }
-        println!();
-    }
-
-    // Report unmatched tracks
-    if matched_tracks.len() < album.tracks.len() {
-        println!("Unmatched tracks:");
-        for (idx, track) in album.tracks.iter().enumerate() {
-            if !matched_tracks.contains(&idx) {
-                let duration = track
-                    .length
-                    .map(|ms| format!(" ({})", format_duration(ms)))
-                    .unwrap_or_default();
-
-                if album.media_count > 1 {
-                    println!(
-                        "  ✗ Disc {} Track {} - {}{}",
-                        track.disc_number, track.position, track.title, duration
-                    );
+    }
+
+    // PHASE 4: Of several copies of a track, keep the best
+    let groups = duplicate_groups(mp3_files, &matched_files, &report);
+    if !groups.is_empty() {
+        // Each group's matched file first, then its extra copies
+        let files: Vec<PathBuf> = groups
+            .iter()
+            .flat_map(|group| {
+                std::iter::once(report.assignments[group.assignment].file_path.clone())
+                    .chain(group.copies.iter().map(|(i, _)| mp3_files[*i].clone()))
+            })
+            .collect();
+        let mut infos = probe_copies(files, cancel).await?.into_iter();
+
+        for group in groups {
+            let assignment = &mut report.assignments[group.assignment];
+            let mut kept = (assignment.file_path.clone(), infos.next().flatten());
+            let mut set_aside = Vec::new();
+            for (file_idx, breakdown) in group.copies {
+                let copy = (mp3_files[file_idx].clone(), infos.next().flatten());
+                let same_length = match (kept.1, copy.1) {
+                    (Some(a), Some(b)) => {
+                        a.duration_ms.abs_diff(b.duration_ms) <= DUPLICATE_DURATION_TOLERANCE_MS
+                    }
+                    _ => true,
+                };
+                if !same_length {
+                    continue;
+                }
+                matched_files.insert(file_idx);
+                if copy_quality(&copy.0, copy.1) > copy_quality(&kept.0, kept.1) {
+                    if let Some(m) = final_matches.iter_mut().find(|m| m.file_path == kept.0) {
+                        m.file_path = copy.0.clone();
+                        m.confidence = breakdown.confidence();
+                    }
+                    assignment.file_path = copy.0.clone();
+                    assignment.score = breakdown.total();
+                    assignment.confidence = breakdown.confidence();
+                    assignment.breakdown = breakdown;
+                    set_aside.push(std::mem::replace(&mut kept, copy).0);
                 } else {
-                    println!("  ✗ Track {} - {}{}", track.position, track.title, duration);
+                    set_aside.push(copy.0);
                 }
             }
+            for file_path in set_aside {
+                report.duplicates.push(Duplicate {
+                    file_path,
+                    kept: kept.0.clone(),
+                    track_index: assignment.track_index,
+                });
+            }
         }
-        println!();
     }
 
+    report.unmatched_files = mp3_files
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !matched_files.contains(idx))
+        .map(|(_, file)| file.clone())
+        .collect();
+    report.unmatched_tracks = album
+        .tracks
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !matched_tracks.contains(idx))
+        .map(|(_, track)| track.clone())
+        .collect();
+    report.durations = mp3_files
+        .iter()
+        .zip(durations)
+        .filter_map(|(file, duration)| Some((file.clone(), duration?)))
+        .collect();
+
     // Sort final matches by disc number, then track position
     final_matches.sort_by_key(|m| (m.track.disc_number, m.track.position));
 
-    // Filter out very low confidence matches
-    let filtered_matches: Vec<FileMatch> = final_matches
+    // Set aside very low confidence matches
+    let (matches, low_confidence): (Vec<_>, Vec<_>) = final_matches
         .into_iter()
-        .filter(|m| {
-            if m.confidence < 0.15 {
-                println!(
-                    "⚠ Skipping very low confidence match: {} -> {} ({}%)",
-                    m.file_path.file_name().unwrap().to_string_lossy(),
-                    m.track.title,
-                    (m.confidence * 100.0) as i32
-                );
-                false
-            } else {
-                true
-            }
-        })
-        .collect();
+        .partition(|m| m.confidence >= MIN_CONFIDENCE);
+    for m in &matches {
+        events.emit(Event::FileMatched {
+            file: m.file_path.clone(),
+            disc: m.track.disc_number,
+            track: m.track.position,
+            title: m.track.title.clone(),
+            confidence: m.confidence,
+        });
+    }
+    for m in &low_confidence {
+        events.emit(Event::Warning {
+            message: format!(
+                "Skipping very low confidence match: {} -> {}",
+                m.file_path.display(),
+                m.track.title
+            ),
+        });
+    }
+    report.matches = matches;
+    report.low_confidence = low_confidence;
 
-    Ok(filtered_matches)
+    Ok(report)
 }
 
-fn find_mp3_files(path: &Path) -> Result<Vec<PathBuf>> {
+/// MP3 files in `path` (or `path` itself if it is one), searching `max_depth`
+/// levels and skipping excluded entries and the `_unmatched` folder
+pub fn find_mp3_files(
+    path: &Path,
+    exclude: &ExcludeRules,
+    max_depth: usize,
+) -> Result<Vec<PathBuf>> {
     let mut mp3_files = Vec::new();
 
     if path.is_file() {
@@ -230,9 +428,14 @@ fn find_mp3_files(path: &Path) -> Result<Vec<PathBuf>> {
 
     for entry in WalkDir::new(path)
         .min_depth(0)
-        .max_depth(3)
+        .max_depth(max_depth)
         .follow_links(false)
         .into_iter()
+        .filter_entry(|e| {
+            e.file_name() != UNMATCHED_DIR
+                && e.file_name() != DUPLICATES_DIR
+                && !exclude.is_excluded(e.path(), path, e.file_type().is_dir())
+        })
         .filter_map(|e| e.ok())
     {
         let entry_path = entry.path();
@@ -249,20 +452,402 @@ fn find_mp3_files(path: &Path) -> Result<Vec<PathBuf>> {
     Ok(mp3_files)
 }
 
-fn get_mp3_duration(file_path: &Path) -> Option<u32> {
-    mp3_duration::from_path(file_path)
-        .ok()
-        .map(|duration| duration.as_millis() as u32)
+/// Duration of every file, probed once and in parallel since decoding is slow.
+/// Stops between files once `cancel` fires.
+pub async fn probe_durations(
+    files: &[PathBuf],
+    cancel: &CancellationToken,
+) -> Result<Vec<Option<u32>>> {
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let chunk_size = files.len().div_ceil(threads).max(1);
+
+    let handles: Vec<_> = files
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let chunk = chunk.to_vec();
+            let cancel = cancel.clone();
+            let len = chunk.len();
+            let handle = tokio::task::spawn_blocking(move || {
+                chunk
+                    .iter()
+                    .map(|f| {
+                        if cancel.is_cancelled() {
+                            None
+                        } else {
+                            get_mp3_duration(f)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            });
+            (len, handle)
+        })
+        .collect();
+
+    let mut durations = Vec::with_capacity(files.len());
+    for (len, handle) in handles {
+        let chunk = tokio::select! {
+            _ = cancel.cancelled() => return Err(Error::Cancelled),
+            // A decoder panic loses that chunk's durations, not the alignment
+            result = handle => result.unwrap_or_else(|_| vec![None; len]),
+        };
+        durations.extend(chunk);
+    }
+    Ok(durations)
+}
+
+/// Duration in milliseconds, or `None` if the file can't be probed
+pub fn get_mp3_duration(file_path: &Path) -> Option<u32> {
+    crate::audio::probe(file_path).map(|info| info.duration_ms)
+}
+
+/// A scored file/track pair waiting for the greedy assignment
+#[derive(Debug, Clone)]
+struct PossibleMatch {
+    file_idx: usize,
+    track_idx: usize,
+    score: i64,
+    confidence: f64,
+    breakdown: ScoreBreakdown,
+}
+
+/// Files whose best track, or whose track's best file, is within
+/// DURATION_MARGIN of the runner-up on name score alone
+fn ambiguous_files(matches: &[PossibleMatch]) -> Vec<usize> {
+    let mut by_file: HashMap<usize, Vec<&PossibleMatch>> = HashMap::new();
+    let mut by_track: HashMap<usize, Vec<&PossibleMatch>> = HashMap::new();
+    for m in matches {
+        by_file.entry(m.file_idx).or_default().push(m);
+        by_track.entry(m.track_idx).or_default().push(m);
+    }
+
+    let mut ambiguous = HashSet::new();
+    for group in by_file.values_mut().chain(by_track.values_mut()) {
+        group.sort_by_key(|m| std::cmp::Reverse(m.score));
+        let best = group[0].score;
+        if group.len() > 1 && best - group[1].score < DURATION_MARGIN {
+            ambiguous.extend(
+                group
+                    .iter()
+                    .take_while(|m| best - m.score < DURATION_MARGIN)
+                    .map(|m| m.file_idx),
+            );
+        }
+    }
+
+    let mut ambiguous: Vec<usize> = ambiguous.into_iter().collect();
+    ambiguous.sort_unstable();
+    ambiguous
+}
+
+/// Indexes of tracks whose titles differ only in case and punctuation, in
+/// groups of two or more
+fn same_title_groups(tracks: &[Track]) -> Vec<Vec<usize>> {
+    let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, track) in tracks.iter().enumerate() {
+        let key: String = track
+            .title
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect();
+        groups.entry(key).or_default().push(i);
+    }
+    groups.into_values().filter(|g| g.len() > 1).collect()
+}
+
+/// New tracks for the files assigned within a group of same-titled tracks,
+/// as (assignment, track, score). Files whose name starts with one of the
+/// group's track numbers keep their track; the rest take the group's tracks
+/// that are theirs or still free, pairing lengths as closely as possible in
+/// total. Empty when a length is unknown or there is nothing to choose between.
+fn pair_by_duration(
+    group: &[usize],
+    mp3_files: &[PathBuf],
+    album: &Album,
+    durations: &[Option<u32>],
+    report: &MatchReport,
+) -> Vec<(usize, usize, ScoreBreakdown)> {
+    let score = |file: &Path, track_index: usize| {
+        report
+            .candidates
+            .iter()
+            .find(|c| c.file_path == file && c.track_index == track_index)
+            .map(|c| c.breakdown)
+    };
+
+    let mut files: Vec<(usize, u32)> = Vec::new();
+    for (i, assignment) in report.assignments.iter().enumerate() {
+        if !group.contains(&assignment.track_index) {
+            continue;
+        }
+        let numbered = leading_number(&assignment.file_path)
+            .is_some_and(|n| group.iter().any(|&t| album.tracks[t].position == n));
+        let scored = group
+            .iter()
+            .all(|&t| score(&assignment.file_path, t).is_some());
+        let duration = mp3_files
+            .iter()
+            .position(|f| *f == assignment.file_path)
+            .and_then(|file_idx| durations[file_idx]);
+        if let (false, true, Some(duration)) = (numbered, scored, duration) {
+            files.push((i, duration));
+        }
+    }
+
+    let taken: Vec<usize> = report
+        .assignments
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !files.iter().any(|(f, _)| f == i))
+        .map(|(_, a)| a.track_index)
+        .collect();
+    let open: Vec<(usize, u32)> = group
+        .iter()
+        .filter(|t| !taken.contains(t))
+        .filter_map(|&t| Some((t, album.tracks[t].length?)))
+        .collect();
+    if files.is_empty()
+        || open.len() < 2
+        || open.len() < group.iter().filter(|t| !taken.contains(t)).count()
+    {
+        return Vec::new();
+    }
+
+    let file_lengths: Vec<u32> = files.iter().map(|(_, d)| *d).collect();
+    let track_lengths: Vec<u32> = open.iter().map(|(_, l)| *l).collect();
+    closest_pairs(&file_lengths, &track_lengths)
+        .into_iter()
+        .filter_map(|(f, t)| {
+            let (assignment, track_index) = (files[f].0, open[t].0);
+            let breakdown = score(&report.assignments[assignment].file_path, track_index)?;
+            Some((assignment, track_index, breakdown))
+        })
+        .collect()
+}
+
+/// Pair each file length with a different track length so the differences
+/// add up to as little as possible, as (file, track) positions. Sorted by
+/// length, the best pairs never cross, so a table over both lists finds them.
+fn closest_pairs(files: &[u32], tracks: &[u32]) -> Vec<(usize, usize)> {
+    let mut file_order: Vec<usize> = (0..files.len()).collect();
+    file_order.sort_by_key(|&i| files[i]);
+    let mut track_order: Vec<usize> = (0..tracks.len()).collect();
+    track_order.sort_by_key(|&i| tracks[i]);
+    let (n, m) = (files.len(), tracks.len());
+    if n > m {
+        return Vec::new();
+    }
+
+    // cost[i][j]: the least total difference pairing the i shortest files
+    // among the j shortest tracks
+    let mut cost = vec![vec![u64::MAX; m + 1]; n + 1];
+    cost[0].fill(0);
+    for i in 1..=n {
+        for j in i..=m {
+            let diff = files[file_order[i - 1]].abs_diff(tracks[track_order[j - 1]]);
+            let pair = cost[i - 1][j - 1].saturating_add(u64::from(diff));
+            cost[i][j] = pair.min(cost[i][j - 1]);
+        }
+    }
+
+    let mut pairs = Vec::with_capacity(n);
+    let (mut i, mut j) = (n, m);
+    while i > 0 {
+        if j > i && cost[i][j] == cost[i][j - 1] {
+            j -= 1;
+        } else {
+            pairs.push((file_order[i - 1], track_order[j - 1]));
+            i -= 1;
+            j -= 1;
+        }
+    }
+    pairs
+}
+
+/// A matched track and the leftover files that are copies of it
+struct DuplicateGroup {
+    /// Index into `MatchReport::assignments`
+    assignment: usize,
+    /// File index and score against the track of each extra copy
+    copies: Vec<(usize, ScoreBreakdown)>,
+}
+
+/// Leftover files whose best track went to a file in the same folder that
+/// scored about as well; names alone can't tell such files apart
+fn duplicate_groups(
+    mp3_files: &[PathBuf],
+    matched_files: &HashSet<usize>,
+    report: &MatchReport,
+) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for (file_idx, file) in mp3_files.iter().enumerate() {
+        if matched_files.contains(&file_idx) {
+            continue;
+        }
+        // Candidates are sorted highest first
+        let Some(best) = report.candidates.iter().find(|c| c.file_path == *file) else {
+            continue;
+        };
+        let Some(assignment) = report
+            .assignments
+            .iter()
+            .position(|a| a.track_index == best.track_index)
+        else {
+            continue;
+        };
+        let matched = &report.assignments[assignment];
+        if matched.file_path.parent() != file.parent()
+            || best.breakdown.confidence() < MIN_CONFIDENCE
+            || matched.score - best.breakdown.total() >= DURATION_MARGIN
+        {
+            continue;
+        }
+        match groups.iter_mut().find(|g| g.assignment == assignment) {
+            Some(group) => group.copies.push((file_idx, best.breakdown)),
+            None => groups.push(DuplicateGroup {
+                assignment,
+                copies: vec![(file_idx, best.breakdown)],
+            }),
+        }
+    }
+    groups
+}
+
+/// Stream properties of each copy, read on a blocking thread
+async fn probe_copies(
+    files: Vec<PathBuf>,
+    cancel: &CancellationToken,
+) -> Result<Vec<Option<AudioInfo>>> {
+    let len = files.len();
+    let handle = tokio::task::spawn_blocking(move || {
+        files
+            .iter()
+            .map(|f| crate::audio::probe(f))
+            .collect::<Vec<_>>()
+    });
+    tokio::select! {
+        _ = cancel.cancelled() => Err(Error::Cancelled),
+        result = handle => Ok(result.unwrap_or_else(|_| vec![None; len])),
+    }
+}
+
+/// Better copies sort higher: bitrate, then sample rate, then file size
+fn copy_quality(file: &Path, info: Option<AudioInfo>) -> (u32, u32, u64) {
+    let size = std::fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+    match info {
+        Some(info) => (
+            info.bitrate_kbps.unwrap_or(0),
+            info.sample_rate.unwrap_or(0),
+            size,
+        ),
+        None => (0, 0, size),
+    }
+}
+
+/// Trigram index over track titles, used to find the few tracks worth fuzzy
+/// scoring against a file instead of trying every track
+struct TitleIndex {
+    postings: HashMap<String, Vec<usize>>,
+    positions: HashMap<u32, Vec<usize>>,
+    track_count: usize,
+}
+
+impl TitleIndex {
+    fn new(tracks: &[Track]) -> Self {
+        let mut postings: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut positions: HashMap<u32, Vec<usize>> = HashMap::new();
+
+        for (idx, track) in tracks.iter().enumerate() {
+            let (base, _) = extract_qualifiers(&track.title.to_lowercase());
+            for trigram in trigrams(&base) {
+                let entry = postings.entry(trigram).or_default();
+                if entry.last() != Some(&idx) {
+                    entry.push(idx);
+                }
+            }
+            positions.entry(track.position).or_default().push(idx);
+        }
+
+        Self {
+            postings,
+            positions,
+            track_count: tracks.len(),
+        }
+    }
+
+    /// Tracks sharing the most title trigrams with the file name, plus tracks
+    /// whose number matches the file's leading number. Falls back to every
+    /// track when nothing overlaps, so odd file names still get scored.
+    fn candidates(&self, file: &Path) -> Vec<usize> {
+        let file_name = file
+            .file_stem()
+            .map(|s| clean_filename(&s.to_string_lossy()))
+            .unwrap_or_default();
+
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        let mut seen = HashSet::new();
+        for trigram in trigrams(&file_name) {
+            if !seen.insert(trigram.clone()) {
+                continue;
+            }
+            for &idx in self.postings.get(&trigram).into_iter().flatten() {
+                *shared.entry(idx).or_default() += 1;
+            }
+        }
+
+        let mut ranked: Vec<(usize, usize)> = shared.into_iter().collect();
+        ranked.sort_by_key(|&(idx, count)| (std::cmp::Reverse(count), idx));
+        let mut candidates: Vec<usize> = ranked
+            .into_iter()
+            .take(MAX_CANDIDATES)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        let leading_number = file_name
+            .split(' ')
+            .next()
+            .and_then(|n| n.parse::<u32>().ok());
+        if let Some(number) = leading_number {
+            // "105" may mean disc 1 track 5
+            for position in [number, number % 100] {
+                for &idx in self.positions.get(&position).into_iter().flatten() {
+                    if !candidates.contains(&idx) {
+                        candidates.push(idx);
+                    }
+                }
+            }
+        }
+
+        if candidates.is_empty() {
+            return (0..self.track_count).collect();
+        }
+        candidates
+    }
+}
+
+fn trigrams(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect();
+    chars
+        .windows(3)
+        .filter(|w| !w.iter().any(|c| c.is_whitespace()))
+        .map(|w| w.iter().collect())
+        .collect()
 }
 
 /// Score a single file-track pairing
-fn score_match<'a>(
+fn score_match(
     file_path: &Path,
-    track: &'a Track,
+    track: &Track,
     matcher: &SkimMatcherV2,
     file_duration: Option<u32>,
     album_artist: &str,
-) -> Option<(&'a Track, f64, i64)> {
+    file_artist: Option<&str>,
+) -> Option<ScoreBreakdown> {
     let file_name = file_path.file_stem()?.to_string_lossy().to_lowercase();
 
     let (base_name, file_qualifiers) = extract_qualifiers(&file_name);
@@ -394,14 +979,84 @@ fn score_match<'a>(
         0
     };
 
-    let total_score = base_score + qualifier_score + duration_score;
+    let artist_score = match file_artist {
+        Some(artist) if same_artist(artist, &track.artist) => ARTIST_BONUS,
+        Some(_) => ARTIST_PENALTY,
+        None => 0,
+    };
 
-    if total_score > 0 {
-        let confidence = (total_score as f64 / 200.0).clamp(0.0, 1.0);
-        Some((track, confidence, total_score))
-    } else {
-        None
+    let breakdown = ScoreBreakdown {
+        name: base_score,
+        qualifiers: qualifier_score,
+        duration: duration_score,
+        artist: artist_score,
+    };
+
+    (breakdown.total() > 0).then_some(breakdown)
+}
+
+/// The artist in a file named "Artist - Title" or "05 - Artist - Title"
+fn filename_artist(file_path: &Path) -> Option<String> {
+    let stem = file_path.file_stem()?.to_string_lossy().replace('_', " ");
+    let mut parts: Vec<&str> = stem
+        .split(" - ")
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    // A track number of its own ("05 - ")
+    if parts
+        .first()
+        .is_some_and(|p| p.chars().all(|c| c.is_ascii_digit() || c == '-'))
+    {
+        parts.remove(0);
     }
+    (parts.len() >= 2).then(|| parts[0].to_string())
+}
+
+/// The track number a file name starts with, as in "07 Interlude" or "7. Interlude"
+fn leading_number(file_path: &Path) -> Option<u32> {
+    let stem = file_path.file_stem()?.to_string_lossy();
+    let digits: String = stem.chars().take_while(char::is_ascii_digit).collect();
+    let rest = &stem[digits.len()..];
+    if !rest.is_empty() && !rest.starts_with(['.', ')', ' ', '-', '_']) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// "05. Artist" → "Artist"
+fn without_track_number(name: &str) -> &str {
+    let rest = name.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.len() == name.len() || !rest.starts_with(['.', ')', ' ']) {
+        return name;
+    }
+    rest.trim_start_matches(['.', ')', ' '])
+}
+
+/// Whether two artist names are the same, allowing for "feat." credits and
+/// "&" spelled out
+fn same_artist(a: &str, b: &str) -> bool {
+    let normalize = |name: &str| {
+        let name = name.to_lowercase().replace('&', " and ");
+        let words: Vec<String> = name
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect();
+        let words = match words.first().map(String::as_str) {
+            Some("the") => &words[1..],
+            _ => &words[..],
+        };
+        words.join(" ")
+    };
+    let (a, b) = (normalize(a), normalize(b));
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    let contains = |long: &str, short: &str| {
+        short.len() >= 3 && format!(" {} ", long).contains(&format!(" {} ", short))
+    };
+    a == b || contains(&a, &b) || contains(&b, &a)
 }
 
 /// Extract qualifiers (text in parentheses) and return (base_name, qualifiers)
@@ -526,10 +1181,3 @@ fn clean_filename(filename: &str) -> String {
 
     cleaned.trim().to_string()
 }
-
-fn format_duration(ms: u32) -> String {
-    let total_seconds = ms / 1000;
-    let minutes = total_seconds / 60;
-    let seconds = total_seconds % 60;
-    format!("{}:{:02}", minutes, seconds)
-}
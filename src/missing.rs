@@ -0,0 +1,221 @@
+// src/missing.rs
+use anyhow::Result;
+use colored::Colorize;
+use id3::Tag;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::musicbrainz::{Album, Track};
+use crate::provider::MetadataProvider;
+use crate::symbols;
+use crate::tagger::extended_text;
+
+struct TaggedFile {
+    path: PathBuf,
+    track_id: Option<String>,
+}
+
+/// Compare every release found in the library's tags against its MusicBrainz
+/// tracklist and report missing tracks, duplicates, and extra files
+pub async fn run(path: &Path) -> Result<()> {
+    let files = crate::maintenance::collect_tree(path)?;
+    if files.is_empty() {
+        anyhow::bail!("No MP3 files found in {}", path.display());
+    }
+
+    let mut releases: BTreeMap<String, Vec<TaggedFile>> = BTreeMap::new();
+    let mut untagged = 0;
+    let pb = crate::progress::bar(files.len(), "Reading tags");
+    for file in files {
+        pb.inc(1);
+        let tag = Tag::read_from_path(&file).ok();
+        let text = |name: &str| tag.as_ref().and_then(|t| extended_text(t, name));
+        match text("MusicBrainz Album Id") {
+            Some(album_id) => releases.entry(album_id).or_default().push(TaggedFile {
+                track_id: text("MusicBrainz Release Track Id"),
+                path: file,
+            }),
+            None => untagged += 1,
+        }
+    }
+    pb.finish_and_clear();
+
+    if releases.is_empty() {
+        anyhow::bail!("No files with a MusicBrainz Album Id in {}", path.display());
+    }
+
+    println!(
+        "{} Checking {} release(s) against MusicBrainz",
+        symbols::ok().bright_green(),
+        releases.len()
+    );
+    if untagged > 0 {
+        println!(
+            "  {} file(s) without a MusicBrainz Album Id were skipped",
+            untagged
+        );
+    }
+    println!();
+
+    // Start every fetch up front; the shared rate limiter spaces the requests
+    let client = crate::config::musicbrainz_client();
+    let fetches: Vec<_> = releases
+        .keys()
+        .map(|id| {
+            let client = client.clone();
+            let id = id.clone();
+            tokio::spawn(async move { client.get_release(&id).await })
+        })
+        .collect();
+
+    let pb = crate::progress::bar(releases.len(), "Fetching releases");
+    let mut incomplete = 0;
+    let mut failed = 0;
+    for ((album_id, files), fetch) in releases.iter().zip(fetches) {
+        let result = match fetch.await {
+            Ok(result) => result.map_err(anyhow::Error::from),
+            Err(e) => Err(anyhow::anyhow!("Fetch task failed: {}", e)),
+        };
+        pb.inc(1);
+
+        let album = match result {
+            Ok(album) => album,
+            Err(e) => {
+                pb.suspend(|| {
+                    println!(
+                        "{} Could not fetch release {}: {}",
+                        symbols::warn().bright_yellow(),
+                        album_id,
+                        e
+                    )
+                });
+                failed += 1;
+                continue;
+            }
+        };
+
+        let report = compare(&album, files);
+        if !report.is_complete() {
+            incomplete += 1;
+            pb.suspend(|| print_report(&album, &report, path));
+        }
+    }
+    pb.finish_and_clear();
+
+    let checked = releases.len() - failed;
+    if checked == 0 {
+        anyhow::bail!("Could not fetch any of the releases from MusicBrainz");
+    }
+    if incomplete == 0 {
+        println!(
+            "{} All {} release(s) are complete",
+            symbols::ok().bright_green(),
+            checked
+        );
+    } else {
+        println!(
+            "{} {} of {} release(s) have missing, duplicate, or extra files",
+            symbols::warn().bright_yellow(),
+            incomplete,
+            checked
+        );
+    }
+
+    Ok(())
+}
+
+struct ReleaseReport<'a> {
+    missing: Vec<&'a Track>,
+    duplicates: Vec<(&'a Track, Vec<&'a Path>)>,
+    /// Files tagged with this release whose track isn't on it (or has no track ID)
+    extra: Vec<&'a Path>,
+}
+
+impl ReleaseReport<'_> {
+    fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.duplicates.is_empty() && self.extra.is_empty()
+    }
+}
+
+fn compare<'a>(album: &'a Album, files: &'a [TaggedFile]) -> ReleaseReport<'a> {
+    let mut by_track: BTreeMap<&str, Vec<&Path>> = BTreeMap::new();
+    let mut extra = Vec::new();
+    for file in files {
+        match &file.track_id {
+            Some(id) if album.tracks.iter().any(|t| &t.id == id) => {
+                by_track.entry(id).or_default().push(&file.path)
+            }
+            _ => extra.push(file.path.as_path()),
+        }
+    }
+
+    let mut missing = Vec::new();
+    let mut duplicates = Vec::new();
+    for track in &album.tracks {
+        match by_track.remove(track.id.as_str()) {
+            None => missing.push(track),
+            Some(paths) if paths.len() > 1 => duplicates.push((track, paths)),
+            Some(_) => {}
+        }
+    }
+
+    ReleaseReport {
+        missing,
+        duplicates,
+        extra,
+    }
+}
+
+fn print_report(album: &Album, report: &ReleaseReport, root: &Path) {
+    println!(
+        "{} {} by {} ({})",
+        symbols::warn().bright_yellow(),
+        album.title.bright_cyan(),
+        album.artist.bright_cyan(),
+        album.id
+    );
+
+    if !report.missing.is_empty() {
+        println!(
+            "    Missing {} of {} tracks:",
+            report.missing.len(),
+            album.tracks.len()
+        );
+        for track in &report.missing {
+            println!("      {}", describe_track(album, track));
+        }
+    }
+
+    for (track, paths) in &report.duplicates {
+        println!("    Duplicate {}:", describe_track(album, track));
+        for path in paths {
+            println!("      {}", relative(path, root));
+        }
+    }
+
+    if !report.extra.is_empty() {
+        println!("    Not on this release:");
+        for path in &report.extra {
+            println!("      {}", relative(path, root));
+        }
+    }
+    println!();
+}
+
+fn describe_track(album: &Album, track: &Track) -> String {
+    if album.media_count > 1 {
+        format!(
+            "Disc {} Track {} - {}",
+            track.disc_number, track.position, track.title
+        )
+    } else {
+        format!("Track {} - {}", track.position, track.title)
+    }
+}
+
+fn relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
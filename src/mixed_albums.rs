@@ -0,0 +1,154 @@
+// src/mixed_albums.rs
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Confirm;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::matcher::probe_durations;
+use crate::musicbrainz::Album;
+use crate::symbols;
+use crate::CancellationToken;
+
+/// Files running this much longer than the release suggest extra albums
+const DURATION_FACTOR: f64 = 1.5;
+
+/// Look for signs that `files` hold more than one album: several different
+/// album tags, or far more running time than the release has. If the tags
+/// tell which files belong to `album`, offer to match only those. Returns the
+/// files to match.
+pub async fn check(files: &[PathBuf], album: &Album, interactive: bool) -> Result<Vec<PathBuf>> {
+    // Album tags as written, and their files, by normalized title
+    let mut groups: BTreeMap<String, (Vec<String>, Vec<PathBuf>)> = BTreeMap::new();
+    let mut untagged = Vec::new();
+    for file in files {
+        let tags = crate::tagger::read_existing_tags(file);
+        let Some(title) = tags.album.filter(|a| !a.trim().is_empty()) else {
+            untagged.push(file.clone());
+            continue;
+        };
+        let (titles, group) = groups.entry(album_key(&title)).or_default();
+        if !titles.contains(&title) {
+            titles.push(title);
+        }
+        group.push(file.clone());
+    }
+
+    // A title with nothing left to compare (e.g. only punctuation) can't vouch
+    // for any group
+    let release_key = album_key(&album.title);
+    let belongs = |key: &str| {
+        !key.is_empty()
+            && !release_key.is_empty()
+            && (key.contains(&release_key) || release_key.contains(key))
+    };
+    let foreign = groups.keys().filter(|key| !belongs(key)).count();
+    let mixed_tags = groups.len() > 1 && foreign > 0;
+
+    // Only worth probing when there are more files than tracks, and the tags
+    // don't already say every file is from this album (extra running time
+    // then means another edition, not another album)
+    let mut running_times = None;
+    if files.len() > album.tracks.len() && (foreign > 0 || !untagged.is_empty()) {
+        let release_ms: Option<u64> = album.tracks.iter().map(|t| t.length.map(u64::from)).sum();
+        if let Some(release_ms) = release_ms.filter(|&ms| ms > 0) {
+            let files_ms: u64 = probe_durations(files, &CancellationToken::new())
+                .await?
+                .into_iter()
+                .flatten()
+                .map(u64::from)
+                .sum();
+            if files_ms as f64 > release_ms as f64 * DURATION_FACTOR {
+                running_times = Some((files_ms, release_ms));
+            }
+        }
+    }
+
+    if !mixed_tags && running_times.is_none() {
+        return Ok(files.to_vec());
+    }
+
+    println!(
+        "{} {}",
+        symbols::warn().bright_yellow(),
+        "These files seem to hold more than one album:".bright_yellow()
+    );
+    for (key, (titles, group)) in &groups {
+        let line = format!(
+            "{} file(s) tagged \"{}\"",
+            group.len(),
+            titles.join("\", \"")
+        );
+        if belongs(key) {
+            println!("    {}", line.bright_green());
+        } else {
+            println!("    {}", line);
+        }
+    }
+    if !untagged.is_empty() && !groups.is_empty() {
+        println!("    {} file(s) without an album tag", untagged.len());
+    }
+    if let Some((files_ms, release_ms)) = running_times {
+        println!(
+            "    {} file(s) run {} in total; the release runs {}",
+            files.len(),
+            crate::report::format_duration(files_ms.min(u64::from(u32::MAX)) as u32),
+            crate::report::format_duration(release_ms.min(u64::from(u32::MAX)) as u32)
+        );
+    }
+
+    // Without album tags that point at the release there's nothing to narrow to
+    let mut plausible: Vec<PathBuf> = groups
+        .iter()
+        .filter(|(key, _)| belongs(key))
+        .flat_map(|(_, (_, group))| group.iter().cloned())
+        .collect();
+    if plausible.is_empty() || foreign == 0 {
+        println!();
+        return Ok(files.to_vec());
+    }
+    plausible.extend(untagged);
+    // Keep the original (sorted) order for matching and reports
+    plausible.sort_by_key(|file| files.iter().position(|f| f == file));
+
+    if !interactive {
+        println!("  Matching all files; run without --yes to narrow them down");
+        println!();
+        return Ok(files.to_vec());
+    }
+
+    let narrow = Confirm::new()
+        .with_prompt(format!(
+            "Only match the {} file(s) that plausibly belong to \"{}\"?",
+            plausible.len(),
+            album.title
+        ))
+        .default(true)
+        .interact()?;
+    println!();
+    Ok(if narrow { plausible } else { files.to_vec() })
+}
+
+/// Compare album titles ignoring case, punctuation, accents, and disc suffixes
+/// such as "(Disc 2)" or "CD1"
+fn album_key(title: &str) -> String {
+    let lower = deunicode::deunicode(title).to_lowercase();
+    let mut words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let is_number = |w: &str| w.chars().all(|c| c.is_ascii_digit());
+    let n = words.len();
+    if n >= 2 && matches!(words[n - 2], "disc" | "disk" | "cd") && is_number(words[n - 1]) {
+        words.truncate(n - 2);
+    } else if let Some(last) = words.last() {
+        let suffix = ["disc", "disk", "cd"].iter().find_map(|p| {
+            last.strip_prefix(p)
+                .filter(|rest| is_number(rest) && !rest.is_empty())
+        });
+        if suffix.is_some() {
+            words.pop();
+        }
+    }
+    words.concat()
+}
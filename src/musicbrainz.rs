@@ -1,40 +1,148 @@
 // src/musicbrainz.rs
-use anyhow::{Context, Result};
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use crate::error::{Error, Result};
+use crate::events::{Event, EventSink};
+#[cfg(feature = "fixtures")]
+use crate::fixtures::{Fixtures, Mode};
+use crate::provider::{MetadataProvider, ReleaseSummary};
+
 const MB_API_BASE: &str = "https://musicbrainz.org/ws/2";
 const COVERART_API_BASE: &str = "https://coverartarchive.org";
+/// The "Various Artists" artist credited on compilations
+pub const VARIOUS_ARTISTS_ID: &str = "89ad4ac3-39f7-470e-963a-56509c546377";
 const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
 
+/// MusicBrainz allows roughly one request per second. The next free slot is
+/// shared by every client in the process, so concurrent fetches stay within it.
+static NEXT_MB_REQUEST: tokio::sync::Mutex<Option<tokio::time::Instant>> =
+    tokio::sync::Mutex::const_new(None);
+const MB_REQUEST_INTERVAL: Duration = Duration::from_millis(1100);
+
+/// Client for the MusicBrainz web service and the Cover Art Archive
+#[derive(Clone)]
 pub struct MusicBrainzClient {
     client: reqwest::Client,
+    api_base: String,
+    coverart_base: String,
+    max_attempts: u32,
+    retry_backoff: Duration,
+    request_interval: Duration,
+    events: Option<Arc<dyn EventSink>>,
+    art_cache: Option<PathBuf>,
+    refresh_art: bool,
+    /// The last release's Cover Art Archive listing, so the cover and the
+    /// disc art are picked from one request
+    art_listing: Arc<Mutex<Option<ArtListing>>>,
+    #[cfg(feature = "fixtures")]
+    fixtures: Option<Fixtures>,
 }
 
-#[derive(Debug, Clone)]
+/// Configures a `MusicBrainzClient`; every setting defaults to what `new()` uses
+#[derive(Clone)]
+pub struct MusicBrainzClientBuilder {
+    api_base: String,
+    coverart_base: String,
+    user_agent: String,
+    contact: Option<String>,
+    timeout: Duration,
+    connect_timeout: Duration,
+    max_attempts: u32,
+    retry_backoff: Duration,
+    request_interval: Duration,
+    events: Option<Arc<dyn EventSink>>,
+    art_cache: Option<PathBuf>,
+    refresh_art: bool,
+    #[cfg(feature = "fixtures")]
+    fixtures: Option<Fixtures>,
+}
+
+/// A release with its tracks flattened across all media. Serializes to the JSON
+/// object described under "JSON Format" in the README; field names are stable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Album {
     pub id: String,
     pub title: String,
     pub artist: String,
     pub date: Option<String>,
+    #[serde(default)]
+    pub genres: Vec<String>,
     pub tracks: Vec<Track>,
     pub total_tracks: u32,
     pub album_artist_id: Option<String>,
+    pub album_artist_sort: Option<String>,
+    #[serde(default)]
+    pub is_compilation: bool,
     pub media_count: usize,
+    #[serde(default)]
+    pub release_group_id: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+/// A track on a release, with the recording it belongs to
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Track {
     pub id: String,
     pub position: u32,
     pub title: String,
     pub artist: String,
+    #[serde(default)]
+    pub artists: Vec<String>,
+    #[serde(default)]
+    pub credits: Vec<Credit>,
     pub length: Option<u32>, // in milliseconds
     pub recording_id: String,
     pub disc_number: u32,
     pub disc_title: Option<String>,
 }
 
+/// A recording looked up on its own, with the releases it appears on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingInfo {
+    pub id: String,
+    pub title: String,
+    /// Primary credited artist
+    pub artist: String,
+    pub artists: Vec<String>,
+    pub artist_id: Option<String>,
+    pub length: Option<u32>, // in milliseconds
+    pub releases: Vec<RecordingRelease>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingRelease {
+    pub id: String,
+    pub title: String,
+    pub date: Option<String>,
+    /// e.g. `Official`, `Bootleg`
+    pub status: Option<String>,
+}
+
+impl RecordingInfo {
+    /// The release to take album fields from: the earliest official one, or
+    /// the earliest of any when none is official. Undated releases come last.
+    pub fn canonical_release(&self) -> Option<&RecordingRelease> {
+        let earliest = |official: bool| {
+            self.releases
+                .iter()
+                .filter(|r| !official || r.status.as_deref() == Some("Official"))
+                .min_by_key(|r| (r.date.is_none(), r.date.clone()))
+        };
+        earliest(true).or_else(|| earliest(false))
+    }
+}
+
+/// One entry of an artist credit as displayed, e.g. `("Artist A", " feat. ")`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credit {
+    pub name: String,
+    #[serde(default)]
+    pub join_phrase: String,
+}
+
 #[derive(Deserialize, Debug)]
 struct MBRelease {
     id: String,
@@ -43,10 +151,30 @@ struct MBRelease {
     #[serde(rename = "artist-credit")]
     artist_credit: Vec<ArtistCredit>,
     media: Vec<Media>,
+    #[serde(default)]
+    genres: Vec<Genre>,
+    #[serde(rename = "release-group")]
+    release_group: Option<ReleaseGroup>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MBArtist {
+    #[serde(default)]
+    genres: Vec<Genre>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Genre {
+    name: String,
+    #[serde(default)]
+    count: u32,
 }
 
 #[derive(Deserialize, Debug)]
 struct ArtistCredit {
+    name: Option<String>,
+    #[serde(default)]
+    joinphrase: String,
     artist: Artist,
 }
 
@@ -54,6 +182,8 @@ struct ArtistCredit {
 struct Artist {
     id: String,
     name: String,
+    #[serde(rename = "sort-name")]
+    sort_name: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -79,19 +209,118 @@ struct Recording {
     id: String,
 }
 
+#[derive(Deserialize, Debug)]
+struct MBRecording {
+    id: String,
+    title: String,
+    length: Option<u32>,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    releases: Vec<MBRecordingRelease>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MBRecordingRelease {
+    id: String,
+    title: String,
+    date: Option<String>,
+    status: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReleaseGroup {
+    id: String,
+}
+
 #[derive(Deserialize, Debug)]
 struct CoverArtResponse {
     images: Vec<CoverArtImage>,
 }
 
-#[derive(Deserialize, Debug)]
+/// A release id and its Cover Art Archive images
+type ArtListing = (String, Vec<CoverArtImage>);
+
+#[derive(Deserialize, Debug, Clone)]
 struct CoverArtImage {
     front: bool,
     image: String,
     thumbnails: Option<CoverArtThumbnails>,
+    #[serde(default)]
+    types: Vec<String>,
+    #[serde(default)]
+    comment: String,
 }
 
-#[derive(Deserialize, Debug)]
+/// An image that could be a release's front cover
+#[derive(Debug, Clone)]
+pub struct ArtCandidate {
+    pub source: &'static str,
+    /// Whether the source marks it as the front cover
+    pub front: bool,
+    /// e.g. `Front`, `Booklet`
+    pub types: Vec<String>,
+    pub comment: String,
+    /// Sizes to try, preferred first, each marked if it is a thumbnail
+    urls: Vec<(String, bool)>,
+}
+
+/// A candidate's image as downloaded
+#[derive(Debug, Clone)]
+pub struct DownloadedArt {
+    pub bytes: Vec<u8>,
+    /// A reduced copy made by the source rather than the original upload
+    pub thumbnail: bool,
+}
+
+fn art_candidate(img: &CoverArtImage) -> ArtCandidate {
+    // Thumbnails are generated after an upload, so for new art they can be
+    // missing or placeholders for a while; fall back to the original, and
+    // to the small thumbnail only if the original can't be had either
+    let thumbnails = img.thumbnails.as_ref();
+    let mut urls: Vec<(String, bool)> = Vec::new();
+    for (url, thumbnail) in [
+        (thumbnails.and_then(|t| t.large.as_deref()), true),
+        (Some(img.image.as_str()), false),
+        (thumbnails.and_then(|t| t.small.as_deref()), true),
+    ] {
+        let Some(url) = url else {
+            continue;
+        };
+        if !urls.iter().any(|(u, _)| u == url) {
+            urls.push((url.to_string(), thumbnail));
+        }
+    }
+    ArtCandidate {
+        source: "Cover Art Archive",
+        front: img.front,
+        types: img.types.clone(),
+        comment: img.comment.clone(),
+        urls,
+    }
+}
+
+/// The disc an image comment is about: "Disc 2", "CD2 front", "medium #3"
+fn disc_in_comment(comment: &str) -> Option<u32> {
+    let comment = comment.to_lowercase();
+    ["disc", "disk", "cd", "medium"].iter().find_map(|word| {
+        comment.match_indices(word).find_map(|(at, _)| {
+            // "cd" inside another word, e.g. "abcd", doesn't count
+            if comment[..at]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphabetic)
+            {
+                return None;
+            }
+            let rest = comment[at + word.len()..].trim_start_matches([' ', '#', '.']);
+            let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().ok().filter(|disc| *disc > 0)
+        })
+    })
+}
+
+#[derive(Deserialize, Debug, Clone)]
 struct CoverArtThumbnails {
     #[serde(rename = "500")]
     small: Option<String>,
@@ -99,12 +328,200 @@ struct CoverArtThumbnails {
     large: Option<String>,
 }
 
-impl MusicBrainzClient {
-    pub fn new() -> Self {
+#[derive(Deserialize, Debug)]
+struct SearchResponse {
+    releases: Vec<SearchRelease>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchRelease {
+    id: String,
+    title: String,
+    date: Option<String>,
+    country: Option<String>,
+    #[serde(rename = "track-count", default)]
+    track_count: u32,
+    #[serde(default)]
+    score: u32,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    /// Browse results have no track count of their own, only per medium
+    #[serde(default)]
+    media: Vec<SearchMedium>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchMedium {
+    format: Option<String>,
+    #[serde(rename = "track-count", default)]
+    track_count: u32,
+}
+
+impl SearchRelease {
+    fn into_summary(self) -> ReleaseSummary {
+        let track_count = match self.track_count {
+            0 => self.media.iter().map(|m| m.track_count).sum(),
+            n => n,
+        };
+        let mut formats: Vec<String> = Vec::new();
+        for format in self.media.into_iter().filter_map(|m| m.format) {
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        ReleaseSummary {
+            artist: self
+                .artist_credit
+                .iter()
+                .map(|ac| {
+                    format!(
+                        "{}{}",
+                        ac.name.as_deref().unwrap_or(&ac.artist.name),
+                        ac.joinphrase
+                    )
+                })
+                .collect(),
+            id: self.id,
+            title: self.title,
+            date: self.date,
+            country: self.country,
+            formats,
+            track_count,
+            score: self.score,
+        }
+    }
+}
+
+/// Status and body of a finished request
+struct HttpResponse {
+    status: reqwest::StatusCode,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// Wait for the next request slot and reserve it for `interval`
+async fn wait_for_rate_limit(interval: Duration) {
+    let mut next = NEXT_MB_REQUEST.lock().await;
+    if let Some(at) = *next {
+        tokio::time::sleep_until(at).await;
+    }
+    *next = Some(tokio::time::Instant::now() + interval);
+}
+
+impl Default for MusicBrainzClientBuilder {
+    fn default() -> Self {
+        Self {
+            api_base: MB_API_BASE.to_string(),
+            coverart_base: COVERART_API_BASE.to_string(),
+            user_agent: USER_AGENT.to_string(),
+            contact: None,
+            timeout: Duration::from_secs(60),
+            connect_timeout: Duration::from_secs(10),
+            max_attempts: 3,
+            retry_backoff: Duration::from_secs(2),
+            request_interval: MB_REQUEST_INTERVAL,
+            events: None,
+            art_cache: None,
+            refresh_art: false,
+            #[cfg(feature = "fixtures")]
+            fixtures: Fixtures::from_env(),
+        }
+    }
+}
+
+impl MusicBrainzClientBuilder {
+    /// MusicBrainz web service root, e.g. a mirror's `https://host/ws/2`
+    pub fn api_base(mut self, url: impl Into<String>) -> Self {
+        self.api_base = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Cover Art Archive root
+    pub fn coverart_base(mut self, url: impl Into<String>) -> Self {
+        self.coverart_base = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Application name and version sent as the User-Agent
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Contact URL or email appended to the User-Agent, as MusicBrainz asks for
+    pub fn contact(mut self, contact: impl Into<String>) -> Self {
+        self.contact = Some(contact.into());
+        self
+    }
+
+    /// Limit for a whole request, including reading the body
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Attempts per MusicBrainz request (at least 1). Retries wait `backoff`,
+    /// then twice as long for each further attempt.
+    pub fn retries(mut self, max_attempts: u32, backoff: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Minimum spacing between MusicBrainz requests across the process
+    pub fn rate_limit(mut self, interval: Duration) -> Self {
+        self.request_interval = interval;
+        self
+    }
+
+    /// Receive retry warnings and cover art downloads as events
+    pub fn events(mut self, sink: impl EventSink + 'static) -> Self {
+        self.events = Some(Arc::new(sink));
+        self
+    }
+
+    /// Keep downloaded cover art under `dir`, by release and size, and reuse
+    /// it instead of downloading it again
+    pub fn art_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.art_cache = Some(dir.into());
+        self
+    }
+
+    /// Download cover art again even when it is cached, replacing the
+    /// cached copy
+    pub fn refresh_art(mut self, refresh: bool) -> Self {
+        self.refresh_art = refresh;
+        self
+    }
+
+    /// Record responses to, or replay them from, a fixture directory. By
+    /// default `MUSICTAGGER_RECORD` or `MUSICTAGGER_REPLAY` choose one.
+    #[cfg(feature = "fixtures")]
+    pub fn fixtures(mut self, fixtures: Option<Fixtures>) -> Self {
+        self.fixtures = fixtures;
+        self
+    }
+
+    pub fn build(self) -> Result<MusicBrainzClient> {
+        let user_agent = match &self.contact {
+            Some(contact) => format!("{} ( {} )", self.user_agent, contact),
+            None => self.user_agent,
+        };
+
         let client = reqwest::Client::builder()
-            .user_agent(USER_AGENT)
-            .timeout(Duration::from_secs(60))
-            .connect_timeout(Duration::from_secs(10))
+            .user_agent(user_agent)
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
             .pool_idle_timeout(Duration::from_secs(90))
             .pool_max_idle_per_host(1)
             .tcp_keepalive(Duration::from_secs(60))
@@ -116,219 +533,673 @@ impl MusicBrainzClient {
                 );
                 headers
             })
+            .build()?;
+
+        Ok(MusicBrainzClient {
+            client,
+            api_base: self.api_base,
+            coverart_base: self.coverart_base,
+            max_attempts: self.max_attempts,
+            retry_backoff: self.retry_backoff,
+            request_interval: self.request_interval,
+            events: self.events,
+            art_cache: self.art_cache,
+            refresh_art: self.refresh_art,
+            art_listing: Arc::default(),
+            #[cfg(feature = "fixtures")]
+            fixtures: self.fixtures,
+        })
+    }
+}
+
+impl Default for MusicBrainzClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MusicBrainzClient {
+    /// Client for the public MusicBrainz servers with the default settings
+    pub fn new() -> Self {
+        Self::builder()
             .build()
-            .expect("Failed to create HTTP client");
+            .expect("Failed to create HTTP client")
+    }
 
-        Self { client }
+    pub fn builder() -> MusicBrainzClientBuilder {
+        MusicBrainzClientBuilder::default()
     }
 
-    pub async fn get_release(&self, release_id: &str) -> Result<Album> {
-        let url = format!(
-            "{}/release/{}?inc=artist-credits+recordings&fmt=json",
-            MB_API_BASE, release_id
-        );
+    fn emit(&self, event: Event) {
+        if let Some(events) = &self.events {
+            events.emit(event);
+        }
+    }
+
+    fn warn(&self, message: String) {
+        self.emit(Event::Warning { message });
+    }
+
+    /// Replayed responses need no rate limiting or pauses
+    #[cfg(feature = "fixtures")]
+    fn replaying(&self) -> bool {
+        matches!(&self.fixtures, Some(f) if f.mode() == Mode::Replay)
+    }
 
+    #[cfg(not(feature = "fixtures"))]
+    fn replaying(&self) -> bool {
+        false
+    }
+
+    async fn pause(&self, duration: Duration) {
+        if !self.replaying() {
+            tokio::time::sleep(duration).await;
+        }
+    }
+
+    /// Send a request and read the whole response. With fixtures, the
+    /// response is recorded, or answered from a recording.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<HttpResponse> {
+        let request = request.build()?;
+        #[cfg(feature = "fixtures")]
+        let url = request.url().to_string();
+        #[cfg(feature = "fixtures")]
+        if let Some(fixtures) = &self.fixtures {
+            if fixtures.mode() == Mode::Replay {
+                let (status, body) = fixtures.load(&url)?;
+                return Ok(HttpResponse { status, body });
+            }
+        }
+
+        let response = self.client.execute(request).await?;
+        let status = response.status();
+        let body = response.bytes().await?.to_vec();
+        #[cfg(feature = "fixtures")]
+        if let Some(fixtures) = &self.fixtures {
+            fixtures.save(&url, status, &body)?;
+        }
+        Ok(HttpResponse { status, body })
+    }
+
+    /// GET a MusicBrainz endpoint, retrying transient failures and rate limiting.
+    /// `what` names the requested entity in errors.
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &[(&str, &str)],
+        what: &str,
+    ) -> Result<T> {
         let mut attempts = 0;
-        let max_attempts = 3;
+        let max_attempts = self.max_attempts;
 
         loop {
             attempts += 1;
 
             if attempts > 1 {
-                let wait_time = Duration::from_millis(1000 * (2_u64.pow(attempts - 1)));
+                let wait_time = self.retry_backoff * 2_u32.pow(attempts - 2);
                 tokio::time::sleep(wait_time).await;
-            } else {
-                tokio::time::sleep(Duration::from_millis(1100)).await;
+            }
+            if !self.replaying() {
+                wait_for_rate_limit(self.request_interval).await;
             }
 
-            let response = match self
-                .client
-                .get(&url)
-                .header("User-Agent", USER_AGENT)
-                .send()
-                .await
-            {
+            let response = match self.send(self.client.get(url).query(params)).await {
                 Ok(resp) => resp,
-                Err(e) if attempts < max_attempts => {
-                    eprintln!(
-                        "Request failed (attempt {}/{}): {}",
-                        attempts, max_attempts, e
-                    );
+                Err(Error::NetworkError(e)) if attempts < max_attempts => {
+                    self.warn(format!("MusicBrainz request failed ({}), retrying", e));
                     continue;
                 }
-                Err(e) => {
-                    return Err(e).context("Failed to send request to MusicBrainz");
-                }
+                Err(e) => return Err(e),
             };
 
-            let status = response.status();
+            let status = response.status;
 
-            if (status == reqwest::StatusCode::SERVICE_UNAVAILABLE
-                || status == reqwest::StatusCode::TOO_MANY_REQUESTS)
-                && attempts < max_attempts
+            if status == reqwest::StatusCode::SERVICE_UNAVAILABLE
+                || status == reqwest::StatusCode::TOO_MANY_REQUESTS
             {
-                eprintln!(
-                    "Rate limited, retrying... (attempt {}/{})",
-                    attempts, max_attempts
-                );
-                continue;
+                if attempts < max_attempts {
+                    self.warn(format!("MusicBrainz returned {}, retrying", status));
+                    continue;
+                }
+                return Err(Error::RateLimited("MusicBrainz"));
+            }
+
+            // MusicBrainz answers 400 for IDs that aren't valid MBIDs
+            if status == reqwest::StatusCode::NOT_FOUND
+                || status == reqwest::StatusCode::BAD_REQUEST
+            {
+                return Err(Error::NotFound(what.to_string()));
             }
 
             if !status.is_success() {
-                let error_body = response.text().await.unwrap_or_default();
-                anyhow::bail!("MusicBrainz API error {}: {}", status, error_body);
+                return Err(Error::ApiError {
+                    service: "MusicBrainz",
+                    status,
+                    body: response.text(),
+                });
             }
 
-            let text = response
-                .text()
-                .await
-                .context("Failed to read response body")?;
+            return serde_json::from_str(&response.text()).map_err(|source| Error::ParseError {
+                what: format!("MusicBrainz response for {}", what),
+                source,
+            });
+        }
+    }
 
-            let mb_release: MBRelease = serde_json::from_str(&text)
-                .with_context(|| format!("Failed to parse MusicBrainz response. Body: {}", text))?;
+    /// Shrink cover art that exceeds `max_size` pixels. Art within the limit
+    /// is returned byte-for-byte; only the header is read to check. The byte
+    /// size is left to `art::fit_to_bytes` when the art is embedded.
+    fn resize_if_needed(&self, image_data: Vec<u8>, max_size: u32) -> Result<Vec<u8>> {
+        let dimensions = image::io::Reader::new(std::io::Cursor::new(&image_data))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok());
+        let Some((width, height)) = dimensions else {
+            // Not something we can decode; embed it as-is
+            return Ok(image_data);
+        };
 
-            return self.parse_release(mb_release);
+        if width <= max_size && height <= max_size {
+            return Ok(image_data);
         }
+
+        let img = image::load_from_memory(&image_data)?.resize(
+            max_size,
+            max_size,
+            image::imageops::FilterType::Lanczos3,
+        );
+
+        // JPEG has no alpha channel
+        let mut output = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(img.to_rgb8())
+            .write_to(&mut output, image::ImageOutputFormat::Jpeg(90))?;
+
+        Ok(output.into_inner())
     }
 
-    pub async fn get_cover_art(&self, release_id: &str) -> Result<Vec<u8>> {
-        tokio::time::sleep(Duration::from_millis(1100)).await;
+    /// Download an image from any URL, such as artwork linked from a store
+    /// page, and shrink it like Cover Art Archive art
+    pub async fn download_image(&self, url: &str, max_size: u32) -> Result<Vec<u8>> {
+        let image_bytes = self.fetch_image(url, "Image host").await?;
+        self.resize_if_needed(image_bytes, max_size)
+    }
 
-        let url = format!("{}/release/{}", COVERART_API_BASE, release_id);
+    /// GET an image, retrying server errors. Anything that isn't an image,
+    /// such as a placeholder page, is an error rather than something to embed.
+    async fn fetch_image(&self, url: &str, service: &'static str) -> Result<Vec<u8>> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            if attempts > 1 {
+                tokio::time::sleep(self.retry_backoff * 2_u32.pow(attempts - 2)).await;
+            }
+
+            let response = self
+                .send(
+                    self.client
+                        .get(url)
+                        .header(reqwest::header::ACCEPT, "image/*"),
+                )
+                .await?;
+
+            let status = response.status;
+            if (status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+                && attempts < self.max_attempts
+            {
+                self.warn(format!("{} returned {}, retrying", service, status));
+                continue;
+            }
+            if status == reqwest::StatusCode::NOT_FOUND {
+                return Err(Error::NotFound(format!("Image {}", url)));
+            }
+            if !status.is_success() {
+                // Error pages are usually HTML; not worth showing
+                return Err(Error::ApiError {
+                    service,
+                    status,
+                    body: String::new(),
+                });
+            }
 
-        let response = self
-            .client
-            .get(&url)
-            .header("User-Agent", USER_AGENT)
-            .send()
-            .await
-            .context("Failed to request cover art")?;
+            if image::guess_format(&response.body).is_err() {
+                return Err(Error::NotAnImage(url.to_string()));
+            }
+            return Ok(response.body);
+        }
+    }
 
-        if !response.status().is_success() {
-            if response.status() == reqwest::StatusCode::NOT_FOUND {
-                anyhow::bail!("No cover art found for this release");
+    /// Which kind of entity `id` identifies when it isn't a release, so a
+    /// failed release lookup can say what was pasted instead
+    pub async fn entity_kind(&self, id: &str) -> Result<Option<&'static str>> {
+        for kind in ["release-group", "recording", "artist"] {
+            let url = format!("{}/{}/{}?fmt=json", self.api_base, kind, id);
+            match self
+                .get_json::<serde::de::IgnoredAny>(&url, &[], kind)
+                .await
+            {
+                Ok(_) => return Ok(Some(kind)),
+                Err(Error::NotFound(_)) => continue,
+                Err(e) => return Err(e),
             }
-            anyhow::bail!("Cover Art Archive returned status: {}", response.status());
         }
+        Ok(None)
+    }
 
-        let cover_art_response: CoverArtResponse = response
-            .json()
-            .await
-            .context("Failed to parse cover art response")?;
+    /// Every release (edition) in a release group, in MusicBrainz's order
+    pub async fn release_group_releases(&self, group_id: &str) -> Result<Vec<ReleaseSummary>> {
+        let url = format!("{}/release", self.api_base);
+        let params = [
+            ("release-group", group_id),
+            ("inc", "artist-credits+media"),
+            ("limit", "100"),
+            ("fmt", "json"),
+        ];
+        let response: SearchResponse = self
+            .get_json(&url, &params, &format!("Release group {}", group_id))
+            .await?;
+        Ok(response
+            .releases
+            .into_iter()
+            .map(SearchRelease::into_summary)
+            .collect())
+    }
 
-        let front_image = cover_art_response
-            .images
-            .iter()
-            .find(|img| img.front)
-            .or_else(|| cover_art_response.images.first())
-            .context("No images found in response")?;
-
-        let image_url = front_image
-            .thumbnails
-            .as_ref()
-            .and_then(|t| t.large.as_ref().or(t.small.as_ref()))
-            .unwrap_or(&front_image.image);
-
-        tokio::time::sleep(Duration::from_millis(500)).await;
-
-        let image_response = self
-            .client
-            .get(image_url)
-            .header("User-Agent", USER_AGENT)
-            .send()
-            .await
-            .context("Failed to download cover art image")?;
-
-        if !image_response.status().is_success() {
-            anyhow::bail!("Failed to download image: {}", image_response.status());
+    /// A recording with its artists and the releases it is on
+    pub async fn get_recording(&self, recording_id: &str) -> Result<RecordingInfo> {
+        let url = format!(
+            "{}/recording/{}?inc=artist-credits+releases&fmt=json",
+            self.api_base, recording_id
+        );
+        let recording: MBRecording = self
+            .get_json(&url, &[], &format!("Recording {}", recording_id))
+            .await?;
+        let primary = recording.artist_credit.first();
+        Ok(RecordingInfo {
+            id: recording.id,
+            title: recording.title,
+            artist: primary
+                .map(|ac| ac.artist.name.clone())
+                .unwrap_or_else(|| "Unknown Artist".to_string()),
+            artists: recording
+                .artist_credit
+                .iter()
+                .map(|ac| ac.artist.name.clone())
+                .collect(),
+            artist_id: primary.map(|ac| ac.artist.id.clone()),
+            length: recording.length,
+            releases: recording
+                .releases
+                .into_iter()
+                .map(|r| RecordingRelease {
+                    id: r.id,
+                    title: r.title,
+                    date: r.date.filter(|d| !d.is_empty()),
+                    status: r.status,
+                })
+                .collect(),
+        })
+    }
+
+    /// Genres voted on an artist, most-voted first
+    pub async fn get_artist_genres(&self, artist_id: &str) -> Result<Vec<String>> {
+        let url = format!("{}/artist/{}?inc=genres&fmt=json", self.api_base, artist_id);
+        let artist: MBArtist = self
+            .get_json(&url, &[], &format!("Artist {}", artist_id))
+            .await?;
+        Ok(sorted_genres(artist.genres))
+    }
+}
+
+impl MetadataProvider for MusicBrainzClient {
+    async fn get_release(&self, release_id: &str) -> Result<Album> {
+        let url = format!(
+            "{}/release/{}?inc=artist-credits+recordings+genres+release-groups&fmt=json",
+            self.api_base, release_id
+        );
+        let mb_release: MBRelease = self
+            .get_json(&url, &[], &format!("Release {}", release_id))
+            .await?;
+        Ok(parse_release(mb_release))
+    }
+
+    async fn search_releases(
+        &self,
+        title: &str,
+        artist: Option<&str>,
+    ) -> Result<Vec<ReleaseSummary>> {
+        let mut query = format!("release:\"{}\"", escape_query(title));
+        if let Some(artist) = artist {
+            query.push_str(&format!(" AND artist:\"{}\"", escape_query(artist)));
         }
+        let url = format!("{}/release", self.api_base);
+        let params = [("query", query.as_str()), ("limit", "25"), ("fmt", "json")];
 
-        let image_bytes = image_response
-            .bytes()
-            .await
-            .context("Failed to read image bytes")?;
+        let response: SearchResponse = self.get_json(&url, &params, "Release search").await?;
+        Ok(response
+            .releases
+            .into_iter()
+            .map(SearchRelease::into_summary)
+            .collect())
+    }
 
-        self.resize_if_needed(image_bytes.to_vec())
+    async fn get_cover_art(&self, release_id: &str, max_size: u32) -> Result<Vec<u8>> {
+        if let Some(art) = self.cached_cover_art(release_id, max_size) {
+            return Ok(art);
+        }
+        let candidates = self.cover_art_candidates(release_id).await?;
+        let image_bytes = self.fetch_art_candidate(&candidates[0]).await?;
+        let art = self.shrink_art(release_id, image_bytes, max_size)?;
+        self.cache_cover_art(release_id, max_size, &art);
+        Ok(art)
     }
+}
 
-    fn resize_if_needed(&self, image_data: Vec<u8>) -> Result<Vec<u8>> {
-        const MAX_SIZE: u32 = 1200;
-        const MAX_BYTES: usize = 1024 * 1024;
+impl MusicBrainzClient {
+    /// The release's front cover candidates on the Cover Art Archive: the
+    /// image marked as the front cover first, then others of type `Front`.
+    /// Without any, the first image. Never empty.
+    pub async fn cover_art_candidates(&self, release_id: &str) -> Result<Vec<ArtCandidate>> {
+        let all_images = self.cover_art_images(release_id).await?;
 
-        if image_data.len() <= MAX_BYTES {
-            if let Ok(img) = image::load_from_memory(&image_data) {
-                if img.width() <= MAX_SIZE && img.height() <= MAX_SIZE {
-                    return Ok(image_data);
+        // Fronts that name a disc belong to that disc, not the whole release
+        let mut images: Vec<&CoverArtImage> = all_images
+            .iter()
+            .filter(|img| img.front || img.types.iter().any(|t| t == "Front"))
+            .filter(|img| img.front || disc_in_comment(&img.comment).is_none())
+            .collect();
+        // The marked front cover first; the sort is stable
+        images.sort_by_key(|img| !img.front);
+        if images.is_empty() {
+            images.extend(all_images.first());
+        }
+        if images.is_empty() {
+            return Err(Error::NotFound(format!(
+                "Cover art for release {}",
+                release_id
+            )));
+        }
+
+        Ok(images.into_iter().map(art_candidate).collect())
+    }
+
+    /// Images of single discs of a multi-disc release, by disc number: fronts
+    /// or medium pictures whose comment names the disc ("Disc 2", "CD2"). Disc
+    /// fronts win over pictures of the medium.
+    pub async fn disc_art_candidates(&self, release_id: &str) -> Result<Vec<(u32, ArtCandidate)>> {
+        let images = self.cover_art_images(release_id).await?;
+        let mut discs: Vec<(u32, ArtCandidate)> = Vec::new();
+        for wanted in ["Front", "Medium"] {
+            for img in &images {
+                if img.front || !img.types.iter().any(|t| t == wanted) {
+                    continue;
+                }
+                let Some(disc) = disc_in_comment(&img.comment) else {
+                    continue;
+                };
+                if !discs.iter().any(|(d, _)| *d == disc) {
+                    discs.push((disc, art_candidate(img)));
                 }
-            } else {
-                return Ok(image_data);
             }
         }
+        discs.sort_by_key(|(disc, _)| *disc);
+        Ok(discs)
+    }
 
-        let img =
-            image::load_from_memory(&image_data).context("Failed to decode image for resizing")?;
+    /// Everything the Cover Art Archive has for the release. Asking again
+    /// for the same release reuses the listing.
+    async fn cover_art_images(&self, release_id: &str) -> Result<Vec<CoverArtImage>> {
+        if let Ok(listing) = self.art_listing.lock() {
+            if let Some((_, images)) = listing.as_ref().filter(|(id, _)| id == release_id) {
+                return Ok(images.clone());
+            }
+        }
+        self.pause(Duration::from_millis(1100)).await;
 
-        let resized = img.resize(MAX_SIZE, MAX_SIZE, image::imageops::FilterType::Lanczos3);
+        let url = format!("{}/release/{}", self.coverart_base, release_id);
 
-        let mut output = std::io::Cursor::new(Vec::new());
-        resized
-            .write_to(&mut output, image::ImageOutputFormat::Jpeg(90))
-            .context("Failed to encode resized image")?;
+        let response = self.send(self.client.get(&url)).await?;
 
-        Ok(output.into_inner())
+        let status = response.status;
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(Error::NotFound(format!(
+                "Cover art for release {}",
+                release_id
+            )));
+        }
+        if !status.is_success() {
+            return Err(Error::ApiError {
+                service: "Cover Art Archive",
+                status,
+                body: response.text(),
+            });
+        }
+
+        let cover_art_response: CoverArtResponse =
+            serde_json::from_str(&response.text()).map_err(|source| Error::ParseError {
+                what: "Cover Art Archive response".to_string(),
+                source,
+            })?;
+        if let Ok(mut listing) = self.art_listing.lock() {
+            *listing = Some((release_id.to_string(), cover_art_response.images.clone()));
+        }
+        Ok(cover_art_response.images)
     }
 
-    fn parse_release(&self, mb_release: MBRelease) -> Result<Album> {
-        let album_artist = mb_release
-            .artist_credit
-            .first()
-            .map(|ac| ac.artist.name.clone())
-            .unwrap_or_else(|| "Unknown Artist".to_string());
-
-        let album_artist_id = mb_release
-            .artist_credit
-            .first()
-            .map(|ac| ac.artist.id.clone());
-
-        let mut all_tracks = Vec::new();
-        let media_count = mb_release.media.len();
-
-        for (medium_idx, medium) in mb_release.media.into_iter().enumerate() {
-            let disc_number = medium.position.unwrap_or((medium_idx + 1) as u32);
-            let disc_title = medium.title.clone();
-
-            for mb_track in medium.tracks {
-                let track_artist = mb_track
-                    .artist_credit
-                    .as_ref()
-                    .and_then(|ac| ac.first())
-                    .map(|ac| ac.artist.name.clone())
-                    .unwrap_or_else(|| album_artist.clone());
-
-                all_tracks.push(Track {
-                    id: mb_track.id,
-                    position: mb_track.position,
-                    title: mb_track.title,
-                    artist: track_artist,
-                    length: mb_track.length,
-                    recording_id: mb_track.recording.id,
-                    disc_number,
-                    disc_title: disc_title.clone(),
-                });
+    /// Download a candidate as served, trying other sizes if one fails
+    pub async fn fetch_art_candidate(&self, candidate: &ArtCandidate) -> Result<Vec<u8>> {
+        Ok(self.download_art_candidate(candidate).await?.bytes)
+    }
+
+    /// Like `fetch_art_candidate`, also saying whether a thumbnail was served
+    pub async fn download_art_candidate(&self, candidate: &ArtCandidate) -> Result<DownloadedArt> {
+        let mut last_error = None;
+        for (i, (url, thumbnail)) in candidate.urls.iter().enumerate() {
+            self.pause(Duration::from_millis(500)).await;
+            match self.fetch_image(url, candidate.source).await {
+                Ok(bytes) => {
+                    return Ok(DownloadedArt {
+                        bytes,
+                        thumbnail: *thumbnail,
+                    })
+                }
+                Err(e) => {
+                    if i + 1 < candidate.urls.len() {
+                        self.warn(format!("{}; trying another size", e));
+                    }
+                    last_error = Some(e);
+                }
             }
         }
+        Err(last_error.unwrap_or_else(|| Error::NotFound("Cover art image".to_string())))
+    }
+
+    /// Shrink downloaded art to at most `max_size` pixels and report it as
+    /// the release's art
+    pub fn shrink_art(
+        &self,
+        release_id: &str,
+        image_bytes: Vec<u8>,
+        max_size: u32,
+    ) -> Result<Vec<u8>> {
+        let art = self.resize_if_needed(image_bytes, max_size)?;
+        self.emit(Event::ArtDownloaded {
+            release_id: release_id.to_string(),
+            bytes: art.len(),
+        });
+        Ok(art)
+    }
+
+    /// The release's cover art at `max_size` from the art cache, if it was
+    /// stored there before
+    pub fn cached_cover_art(&self, release_id: &str, max_size: u32) -> Option<Vec<u8>> {
+        if self.refresh_art {
+            return None;
+        }
+        let art = std::fs::read(self.art_cache_path(release_id, max_size)?).ok()?;
+        image::guess_format(&art).is_ok().then_some(art)
+    }
 
-        let total_tracks = all_tracks.len() as u32;
-
-        Ok(Album {
-            id: mb_release.id,
-            title: mb_release.title,
-            artist: album_artist,
-            date: mb_release.date,
-            tracks: all_tracks,
-            total_tracks,
-            album_artist_id,
-            media_count,
+    /// Store the art chosen for a release in the art cache. Failing to is
+    /// only worth a warning; the art is downloaded again next time.
+    pub fn cache_cover_art(&self, release_id: &str, max_size: u32, art: &[u8]) {
+        let Some(path) = self.art_cache_path(release_id, max_size) else {
+            return;
+        };
+        // Written aside under a name of this process's own and renamed, so
+        // concurrent runs never read half a file or write into each other's
+        let partial = path.with_extension(format!("{}.partial", std::process::id()));
+        let stored = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&partial, art))
+            .and_then(|_| std::fs::rename(&partial, &path));
+        if let Err(e) = stored {
+            self.warn(format!(
+                "Could not cache cover art in {}: {}",
+                path.display(),
+                e
+            ));
+        }
+    }
+
+    /// `<cache>/<release id>/<max size>.img`. Nothing is cached while fixtures
+    /// are recorded or replayed, or for IDs that aren't MBIDs.
+    fn art_cache_path(&self, release_id: &str, max_size: u32) -> Option<PathBuf> {
+        #[cfg(feature = "fixtures")]
+        if self.fixtures.is_some() {
+            return None;
+        }
+        let dir = self.art_cache.as_ref()?;
+        is_mbid(release_id).then(|| {
+            dir.join(release_id.to_ascii_lowercase())
+                .join(format!("{}.img", max_size))
         })
     }
 }
+
+/// Whether `id` has the shape of a MusicBrainz ID, a hyphenated UUID
+pub fn is_mbid(id: &str) -> bool {
+    let groups: Vec<&str> = id.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// The release in a saved MusicBrainz release response, or in an excerpt of
+/// the JSON data dump (one release per line). With several releases in the
+/// text, `release_id` picks one.
+pub fn release_from_json(text: &str, release_id: Option<&str>) -> Result<Album> {
+    let releases = serde_json::Deserializer::from_str(text)
+        .into_iter::<MBRelease>()
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|source| Error::ParseError {
+            what: "release JSON".to_string(),
+            source,
+        })?;
+    let release = match release_id {
+        Some(id) => releases
+            .into_iter()
+            .find(|r| r.id.eq_ignore_ascii_case(id))
+            .ok_or_else(|| Error::NotFound(format!("Release {}", id)))?,
+        None if releases.len() == 1 => releases.into_iter().next().expect("one release"),
+        None => return Err(Error::ReleaseCount(releases.len())),
+    };
+    Ok(parse_release(release))
+}
+
+fn parse_release(mb_release: MBRelease) -> Album {
+    let album_artist = mb_release
+        .artist_credit
+        .first()
+        .map(|ac| ac.artist.name.clone())
+        .unwrap_or_else(|| "Unknown Artist".to_string());
+
+    let album_artist_id = mb_release
+        .artist_credit
+        .first()
+        .map(|ac| ac.artist.id.clone());
+
+    let album_artist_sort = mb_release
+        .artist_credit
+        .first()
+        .and_then(|ac| ac.artist.sort_name.clone());
+
+    let is_compilation = album_artist_id.as_deref() == Some(VARIOUS_ARTISTS_ID);
+
+    let mut all_tracks = Vec::new();
+    let media_count = mb_release.media.len();
+
+    for (medium_idx, medium) in mb_release.media.into_iter().enumerate() {
+        let disc_number = medium.position.unwrap_or((medium_idx + 1) as u32);
+        let disc_title = medium.title.clone();
+
+        for mb_track in medium.tracks {
+            let track_artist = mb_track
+                .artist_credit
+                .as_ref()
+                .and_then(|ac| ac.first())
+                .map(|ac| ac.artist.name.clone())
+                .unwrap_or_else(|| album_artist.clone());
+
+            let track_artists = mb_track
+                .artist_credit
+                .as_ref()
+                .map(|credits| credits.iter().map(|ac| ac.artist.name.clone()).collect())
+                .unwrap_or_else(|| vec![track_artist.clone()]);
+
+            let credits = mb_track
+                .artist_credit
+                .as_ref()
+                .unwrap_or(&mb_release.artist_credit)
+                .iter()
+                .map(|ac| Credit {
+                    name: ac.name.clone().unwrap_or_else(|| ac.artist.name.clone()),
+                    join_phrase: ac.joinphrase.clone(),
+                })
+                .collect();
+
+            all_tracks.push(Track {
+                id: mb_track.id,
+                position: mb_track.position,
+                title: mb_track.title,
+                artist: track_artist,
+                artists: track_artists,
+                credits,
+                length: mb_track.length,
+                recording_id: mb_track.recording.id,
+                disc_number,
+                disc_title: disc_title.clone(),
+            });
+        }
+    }
+
+    let total_tracks = all_tracks.len() as u32;
+
+    Album {
+        id: mb_release.id,
+        title: mb_release.title,
+        artist: album_artist,
+        date: mb_release.date,
+        genres: sorted_genres(mb_release.genres),
+        tracks: all_tracks,
+        total_tracks,
+        album_artist_id,
+        album_artist_sort,
+        is_compilation,
+        media_count,
+        release_group_id: mb_release.release_group.map(|group| group.id),
+    }
+}
+
+/// Genre names, most-voted first
+fn sorted_genres(mut genres: Vec<Genre>) -> Vec<String> {
+    genres.sort_by_key(|g| std::cmp::Reverse(g.count));
+    genres.into_iter().map(|g| g.name).collect()
+}
+
+/// Escape characters that have a meaning inside a quoted Lucene phrase
+fn escape_query(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
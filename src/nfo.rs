@@ -0,0 +1,150 @@
+// src/nfo.rs
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::musicbrainz::Album;
+
+const COVERART_URL: &str = "https://coverartarchive.org/release";
+const ART_NAMES: &[&str] = &["cover", "folder", "front", "album"];
+const ART_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png"];
+
+/// Write a Kodi/Jellyfin compatible `album.nfo` into `dir`
+pub fn write_album_nfo(dir: &Path, album: &Album) -> Result<PathBuf> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n");
+    xml.push_str("<album>\n");
+    element(&mut xml, 1, "title", &album.title);
+    element(&mut xml, 1, "artistdesc", &album.artist);
+    element(&mut xml, 1, "musicbrainzalbumid", &album.id);
+    for genre in &album.genres {
+        element(&mut xml, 1, "genre", genre);
+    }
+    if let Some(date) = &album.date {
+        if let Some(year) = date.split('-').next() {
+            element(&mut xml, 1, "year", year);
+        }
+        element(&mut xml, 1, "releasedate", date);
+    }
+    if album.is_compilation {
+        element(&mut xml, 1, "compilation", "true");
+    }
+
+    xml.push_str("  <albumArtistCredits>\n");
+    element(&mut xml, 2, "artist", &album.artist);
+    if let Some(artist_id) = &album.album_artist_id {
+        element(&mut xml, 2, "musicBrainzArtistID", artist_id);
+    }
+    xml.push_str("  </albumArtistCredits>\n");
+
+    // Prefer local art next to the files, fall back to the Cover Art Archive
+    match find_local_art(dir) {
+        Some(name) => element_with_attr(&mut xml, 1, "thumb", "aspect=\"thumb\"", &name),
+        None => element_with_attr(
+            &mut xml,
+            1,
+            "thumb",
+            "aspect=\"thumb\"",
+            &format!("{}/{}/front", COVERART_URL, album.id),
+        ),
+    }
+
+    for track in &album.tracks {
+        xml.push_str("  <track>\n");
+        if album.media_count > 1 {
+            element(&mut xml, 2, "disc", &track.disc_number.to_string());
+        }
+        element(&mut xml, 2, "position", &track.position.to_string());
+        element(&mut xml, 2, "title", &track.title);
+        if track.artist != album.artist {
+            element(&mut xml, 2, "artist", &track.artist);
+        }
+        if let Some(ms) = track.length {
+            let seconds = ms / 1000;
+            element(
+                &mut xml,
+                2,
+                "duration",
+                &format!("{}:{:02}", seconds / 60, seconds % 60),
+            );
+        }
+        element(&mut xml, 2, "musicbrainztrackid", &track.recording_id);
+        element(&mut xml, 2, "musicbrainzreleasetrackid", &track.id);
+        xml.push_str("  </track>\n");
+    }
+    xml.push_str("</album>\n");
+
+    let path = dir.join("album.nfo");
+    std::fs::write(&path, xml).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Write an `artist.nfo` for the album artist into `dir` (usually the artist folder)
+pub fn write_artist_nfo(dir: &Path, album: &Album) -> Result<PathBuf> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n");
+    xml.push_str("<artist>\n");
+    element(&mut xml, 1, "name", &album.artist);
+    if let Some(sort_name) = &album.album_artist_sort {
+        element(&mut xml, 1, "sortname", sort_name);
+    }
+    if let Some(artist_id) = &album.album_artist_id {
+        element(&mut xml, 1, "musicBrainzArtistID", artist_id);
+    }
+    for genre in &album.genres {
+        element(&mut xml, 1, "genre", genre);
+    }
+    xml.push_str("</artist>\n");
+
+    let path = dir.join("artist.nfo");
+    std::fs::write(&path, xml).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+fn find_local_art(dir: &Path) -> Option<String> {
+    for name in ART_NAMES {
+        for ext in ART_EXTENSIONS {
+            let file_name = format!("{}.{}", name, ext);
+            if dir.join(&file_name).exists() {
+                return Some(file_name);
+            }
+        }
+    }
+    None
+}
+
+fn element(xml: &mut String, depth: usize, name: &str, value: &str) {
+    let _ = writeln!(
+        xml,
+        "{}<{}>{}</{}>",
+        "  ".repeat(depth),
+        name,
+        escape(value),
+        name
+    );
+}
+
+fn element_with_attr(xml: &mut String, depth: usize, name: &str, attr: &str, value: &str) {
+    let _ = writeln!(
+        xml,
+        "{}<{} {}>{}</{}>",
+        "  ".repeat(depth),
+        name,
+        attr,
+        escape(value),
+        name
+    );
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,254 @@
+// src/normalize.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use id3::{Tag, TagLike, Timestamp, Version};
+use std::path::Path;
+
+use crate::maintenance::{collect_tree, display_name, parse_frame_ids, print_summary};
+use crate::musicbrainz::VARIOUS_ARTISTS_ID;
+use crate::profile::{MultiValue, TagSettings};
+use crate::symbols;
+use crate::tagger::{extended_text, read_tag};
+
+/// Frames no player reads that rippers and stores leave behind: private
+/// vendor data and MusicMatch's leftovers
+const JUNK_FRAMES: [&str; 2] = ["PRIV", "NCON"];
+/// Text fields that hold several values
+const MULTI_VALUE_FRAMES: [(&str, &str); 2] = [("TPE1", "artist"), ("TCON", "genre")];
+/// How other taggers (and our profiles) separate values. " & " is left out,
+/// since it is as often part of a single name.
+const SEPARATORS: [&str; 3] = ["\0", "; ", " / "];
+
+/// Rewrite every tagged MP3 under `path` to one convention: `settings`'
+/// ID3 version and multi-value separator, and with `profile_frames`, only
+/// the optional frames it writes. Junk frames and empty text frames go too.
+/// Values themselves are kept.
+pub fn run(
+    path: &Path,
+    settings: &TagSettings,
+    profile_frames: bool,
+    strip: &[String],
+    dry_run: bool,
+) -> Result<()> {
+    let mut junk: Vec<String> = JUNK_FRAMES.iter().map(|id| id.to_string()).collect();
+    junk.extend(parse_frame_ids(strip)?);
+
+    let files = collect_tree(path)?;
+    println!(
+        "{} Scanning {} MP3 file(s)",
+        symbols::ok().bright_green(),
+        files.len()
+    );
+    println!();
+
+    let mut changed = 0;
+    for file in &files {
+        let mut tag = match read_tag(file) {
+            Ok(tag) => tag,
+            Err(_) => continue,
+        };
+
+        let mut changes = Vec::new();
+        if tag.version() != settings.version {
+            changes.push(format!(
+                "{} {} {}",
+                version_name(tag.version()),
+                symbols::arrow(),
+                version_name(settings.version)
+            ));
+        }
+        changes.extend(remove_junk(&mut tag, &junk));
+        changes.extend(convert_dates(&mut tag, settings.version));
+        changes.extend(rejoin_values(&mut tag, settings));
+        if profile_frames {
+            changes.extend(apply_frame_set(&mut tag, settings));
+        }
+        if changes.is_empty() {
+            continue;
+        }
+
+        println!(
+            "  {} {} {}",
+            symbols::ok().bright_green(),
+            display_name(file).bright_white(),
+            format!("({})", changes.join(", ")).bright_black()
+        );
+
+        if !dry_run {
+            tag.write_to_path(file, settings.version)
+                .with_context(|| format!("Failed to write tags to {}", file.display()))?;
+        }
+        changed += 1;
+    }
+
+    print_summary(changed, files.len(), dry_run);
+    Ok(())
+}
+
+fn version_name(version: Version) -> &'static str {
+    match version {
+        Version::Id3v22 => "ID3v2.2",
+        Version::Id3v23 => "ID3v2.3",
+        Version::Id3v24 => "ID3v2.4",
+    }
+}
+
+/// Drop the `junk` frames and text frames with nothing in them
+fn remove_junk(tag: &mut Tag, junk: &[String]) -> Vec<String> {
+    let mut changes = Vec::new();
+    for id in junk {
+        let count = tag.remove(id).len();
+        if count > 0 {
+            changes.push(format!("removed {}x{}", id, count));
+        }
+    }
+    let empty: Vec<String> = tag
+        .frames()
+        .filter(|f| f.id().starts_with('T') && f.id() != "TXXX")
+        .filter(|f| {
+            f.content()
+                .text()
+                .is_some_and(|t| t.trim_matches(['\0', ' ']).is_empty())
+        })
+        .map(|f| f.id().to_string())
+        .collect();
+    for id in empty {
+        tag.remove(&id);
+        changes.push(format!("removed empty {}", id));
+    }
+    changes
+}
+
+/// Move dates to the frames `version` defines: TDRC and TDOR in ID3v2.4,
+/// TYER/TDAT and TORY in ID3v2.3
+fn convert_dates(tag: &mut Tag, version: Version) -> Vec<String> {
+    let text = |tag: &Tag, id: &str| {
+        tag.get(id)
+            .and_then(|f| f.content().text())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    };
+    let mut changes = Vec::new();
+    match version {
+        Version::Id3v24 => {
+            // TYER stays; it is what ID3v2.3 players fall back on
+            if tag.get("TDRC").is_none() {
+                if let Some(year) = text(tag, "TYER") {
+                    // TDAT is DDMM
+                    let date = match text(tag, "TDAT")
+                        .filter(|d| d.len() == 4 && d.chars().all(|c| c.is_ascii_digit()))
+                    {
+                        Some(ddmm) => format!("{}-{}-{}", year, &ddmm[2..], &ddmm[..2]),
+                        None => year,
+                    };
+                    if let Ok(timestamp) = date.parse::<Timestamp>() {
+                        tag.set_date_recorded(timestamp);
+                        changes.push("date as TDRC".to_string());
+                    }
+                }
+            }
+            for id in ["TDAT", "TIME", "TRDA"] {
+                if !tag.remove(id).is_empty() {
+                    changes.push(format!("removed {}", id));
+                }
+            }
+            if let Some(year) = text(tag, "TORY") {
+                tag.remove("TORY");
+                if tag.get("TDOR").is_none() {
+                    tag.set_text("TDOR", year);
+                }
+                changes.push("original year as TDOR".to_string());
+            }
+        }
+        _ => {
+            if let Some(recorded) = tag.date_recorded() {
+                if tag.get("TYER").is_none() {
+                    tag.set_year(recorded.year);
+                }
+                if let (Some(month), Some(day), None) =
+                    (recorded.month, recorded.day, tag.get("TDAT"))
+                {
+                    tag.set_text("TDAT", format!("{:02}{:02}", day, month));
+                }
+                tag.remove("TDRC");
+                changes.push("date as TYER".to_string());
+            }
+            if let Some(original) = text(tag, "TDOR") {
+                tag.remove("TDOR");
+                if tag.get("TORY").is_none() {
+                    tag.set_text("TORY", original.chars().take(4).collect::<String>());
+                }
+                changes.push("original year as TORY".to_string());
+            }
+        }
+    }
+    changes
+}
+
+/// Write multi-value fields with `settings`' separator. Writing only the
+/// first value would lose the rest, so those fields are left as they are;
+/// so are separate values in ID3v2.3, which can't store them.
+fn rejoin_values(tag: &mut Tag, settings: &TagSettings) -> Vec<String> {
+    let mut changes = Vec::new();
+    for (id, name) in MULTI_VALUE_FRAMES {
+        let Some(text) = tag
+            .get(id)
+            .and_then(|f| f.content().text())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        let values = split_values(&text);
+        if values.len() < 2 {
+            continue;
+        }
+        let (written, how) = match settings.multi_value {
+            MultiValue::Native if settings.version == Version::Id3v24 => {
+                (values.join("\0"), "as separate values".to_string())
+            }
+            MultiValue::Joined(separator) => (
+                values.join(separator),
+                format!("joined with {:?}", separator),
+            ),
+            _ => continue,
+        };
+        if written != text {
+            tag.set_text(id, written);
+            changes.push(format!("{} {}", name, how));
+        }
+    }
+    changes
+}
+
+fn split_values(text: &str) -> Vec<String> {
+    let mut values = vec![text.to_string()];
+    for separator in SEPARATORS {
+        values = values
+            .iter()
+            .flat_map(|v| v.split(separator))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect();
+    }
+    values
+}
+
+/// Keep TCMP and TSO2 only where the profile writes them, and mark
+/// Various Artists releases as compilations when it does
+fn apply_frame_set(tag: &mut Tag, settings: &TagSettings) -> Vec<String> {
+    let mut changes = Vec::new();
+    if !settings.write_compilation {
+        if !tag.remove("TCMP").is_empty() {
+            changes.push("removed TCMP".to_string());
+        }
+    } else if tag.get("TCMP").is_none()
+        && extended_text(tag, "MusicBrainz Album Artist Id").as_deref() == Some(VARIOUS_ARTISTS_ID)
+    {
+        tag.set_text("TCMP", "1");
+        changes.push("added TCMP".to_string());
+    }
+    if !settings.write_album_artist_sort && !tag.remove("TSO2").is_empty() {
+        changes.push("removed TSO2".to_string());
+    }
+    changes
+}
@@ -0,0 +1,146 @@
+// src/notify.rs
+use colored::Colorize;
+use serde::Serialize;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::results::FileResult;
+use crate::symbols;
+
+/// What happened in a tagging run, sent to `--webhook` and summarized in `--notify`
+#[derive(Serialize, Default)]
+pub struct RunSummary {
+    /// `completed`, `partial` (some files failed under `--keep-going`), or `failed`
+    pub status: &'static str,
+    pub error: Option<String>,
+    pub release_id: Option<String>,
+    pub album: Option<String>,
+    pub artist: Option<String>,
+    pub files: usize,
+    pub matched: usize,
+    pub tagged: usize,
+    /// Files that failed to tag under `--keep-going`
+    pub failed: usize,
+    pub dry_run: bool,
+    pub duration_seconds: f64,
+    /// One entry per matched file
+    pub results: Vec<FileResult>,
+}
+
+/// Where to report the end of a run
+pub struct Notifier {
+    desktop: bool,
+    webhook: Option<String>,
+    started: Instant,
+}
+
+impl Notifier {
+    pub fn new(desktop: bool, webhook: Option<String>) -> Self {
+        Notifier {
+            desktop,
+            webhook,
+            started: Instant::now(),
+        }
+    }
+
+    /// Record the run's outcome and send it. Delivery failures only warn, so a
+    /// dead webhook never turns a successful run into a failed one.
+    pub async fn finish(&self, summary: &mut RunSummary, result: &anyhow::Result<()>) {
+        if !self.desktop && self.webhook.is_none() {
+            return;
+        }
+
+        summary.duration_seconds = self.started.elapsed().as_secs_f64();
+        match result {
+            Ok(()) if summary.failed > 0 => summary.status = "partial",
+            Ok(()) => summary.status = "completed",
+            Err(e) => {
+                summary.status = "failed";
+                // The full chain repeats itself for network errors
+                let root = e.root_cause().to_string();
+                let top = e.to_string();
+                summary.error = Some(if root == top {
+                    top
+                } else {
+                    format!("{}: {}", top, root)
+                });
+            }
+        }
+
+        if let Some(url) = &self.webhook {
+            if let Err(e) = post(url, summary).await {
+                println!("{} Webhook failed: {}", symbols::warn().bright_yellow(), e);
+            }
+        }
+        if self.desktop {
+            if let Err(e) = show(summary) {
+                println!(
+                    "{} Desktop notification failed: {}",
+                    symbols::warn().bright_yellow(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+async fn post(url: &str, summary: &RunSummary) -> anyhow::Result<()> {
+    let response = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .build()?
+        .post(url)
+        .json(summary)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        anyhow::bail!("{} returned status: {}", url, response.status());
+    }
+    Ok(())
+}
+
+fn show(summary: &RunSummary) -> anyhow::Result<()> {
+    let album = summary.album.as_deref().unwrap_or("musictagger_rs");
+    let (title, body) = match &summary.error {
+        Some(error) => (format!("Tagging failed: {}", album), error.clone()),
+        None if summary.dry_run => (
+            format!("Dry run finished: {}", album),
+            format!("Matched {} of {} file(s)", summary.matched, summary.files),
+        ),
+        None if summary.failed > 0 => (
+            format!("Tagging finished with errors: {}", album),
+            format!(
+                "Tagged {} of {} file(s), {} failed",
+                summary.tagged, summary.files, summary.failed
+            ),
+        ),
+        None => (
+            format!("Tagging finished: {}", album),
+            format!("Tagged {} of {} file(s)", summary.tagged, summary.files),
+        ),
+    };
+
+    let status = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_string(&body),
+            applescript_string(&title)
+        );
+        Command::new("osascript").args(["-e", &script]).status()
+    } else if cfg!(windows) {
+        anyhow::bail!("not supported on Windows");
+    } else {
+        Command::new("notify-send")
+            .args(["--app-name=musictagger_rs", &title, &body])
+            .status()
+    };
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => anyhow::bail!("notifier exited with {}", status),
+        Err(e) => anyhow::bail!("could not run notifier: {}", e),
+    }
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,617 @@
+// src/organize.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::filename::{io_path, FilenameRules};
+use crate::junk::JunkOptions;
+use crate::musicbrainz::{Album, Track};
+use crate::symbols;
+
+pub const DEFAULT_TEMPLATE: &str = "{albumartist}/{album} ({year})/{disc}{track} - {title}";
+
+const SIDECAR_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif"];
+
+pub struct OrganizeOptions {
+    pub library_root: PathBuf,
+    pub template: String,
+    pub filenames: FilenameRules,
+    pub junk: Option<JunkOptions>,
+    /// Copy instead of move, leaving the source folder untouched
+    pub copy: bool,
+    /// Rename files within their own folders by the template's last segment,
+    /// ignoring the library root
+    pub in_place: bool,
+    pub dry_run: bool,
+}
+
+/// Values available to rename templates
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_artist: String,
+    pub year: Option<i32>,
+    pub track: Option<u32>,
+    pub total_tracks: Option<u32>,
+    /// The highest track number on the track's disc, for `{track:auto}`;
+    /// without it the album's track count is used
+    pub disc_tracks: Option<u32>,
+    pub disc: Option<u32>,
+    pub total_discs: Option<u32>,
+    pub genre: Option<String>,
+}
+
+impl TemplateValues {
+    pub fn from_track(album: &Album, track: &Track) -> Self {
+        Self {
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            album: album.title.clone(),
+            album_artist: album.artist.clone(),
+            year: album
+                .date
+                .as_deref()
+                .and_then(|d| d.split('-').next())
+                .and_then(|y| y.parse().ok()),
+            track: Some(track.position),
+            total_tracks: Some(album.total_tracks),
+            disc_tracks: album
+                .tracks
+                .iter()
+                .filter(|t| t.disc_number == track.disc_number)
+                .map(|t| t.position)
+                .max(),
+            disc: Some(track.disc_number),
+            total_discs: Some(album.media_count as u32),
+            genre: album.genres.first().cloned(),
+        }
+    }
+
+    /// What `auto` pads track numbers to: a disc's own count, so a box set's
+    /// 12-track discs get "05" rather than "005"
+    fn tracks_on_disc(&self) -> Option<u32> {
+        self.disc_tracks.or(self.total_tracks)
+    }
+
+    fn field(&self, name: &str, format: &NumberFormat) -> Option<String> {
+        let multi_disc = self.total_discs.map(|d| d > 1).unwrap_or(false);
+        match name {
+            "title" => Some(self.title.clone()),
+            "artist" => Some(self.artist.clone()),
+            "album" => Some(self.album.clone()),
+            "albumartist" => Some(self.album_artist.clone()),
+            "year" => self.year.map(|y| y.to_string()),
+            "track" => self.track.map(|t| format.pad(t, 2, self.tracks_on_disc())),
+            "totaltracks" => self.total_tracks.map(|t| t.to_string()),
+            // Only shown for multi-disc releases, as a "1-" prefix
+            "disc" => self
+                .disc
+                .filter(|_| multi_disc)
+                .map(|d| format!("{}-", format.pad(d, 1, self.total_discs))),
+            // "1-05" (or "105" when compact) on multi-disc releases, "05" otherwise
+            "disctrack" => self.track.map(|t| {
+                let track = format.pad(t, 2, self.tracks_on_disc());
+                match self.disc.filter(|_| multi_disc) {
+                    Some(d) if format.compact => format!("{}{}", d, track),
+                    Some(d) => format!("{}-{}", d, track),
+                    None => track,
+                }
+            }),
+            "totaldiscs" => self.total_discs.map(|d| d.to_string()),
+            "genre" => self.genre.clone(),
+            _ => None,
+        }
+    }
+}
+
+/// Modifiers for numeric template fields: `{track:3}`, `{track:auto}`, `{disctrack:compact}`
+#[derive(Debug, Default)]
+struct NumberFormat {
+    width: Option<usize>,
+    /// Pad to the number of digits in the total (2 for 12 tracks, 3 for 120);
+    /// for track numbers, the total on their disc
+    auto: bool,
+    compact: bool,
+}
+
+impl NumberFormat {
+    fn parse(name: &str, modifiers: Option<&str>) -> Result<Self> {
+        let mut format = NumberFormat::default();
+        let Some(modifiers) = modifiers else {
+            return Ok(format);
+        };
+
+        if !matches!(name, "track" | "disc" | "disctrack") {
+            anyhow::bail!("Template field {{{}}} does not take modifiers", name);
+        }
+
+        for modifier in modifiers.split(',').map(str::trim) {
+            match modifier {
+                "auto" => format.auto = true,
+                "compact" if name == "disctrack" => format.compact = true,
+                width => {
+                    let width: usize =
+                        width.parse().ok().filter(|w| *w <= 6).with_context(|| {
+                            format!("Unknown modifier '{}' in {{{}:{}}}", width, name, modifiers)
+                        })?;
+                    format.width = Some(width);
+                }
+            }
+        }
+
+        Ok(format)
+    }
+
+    fn pad(&self, number: u32, default_width: usize, total: Option<u32>) -> String {
+        let width = if self.auto {
+            let digits = total.map(|t| t.to_string().len()).unwrap_or(default_width);
+            digits.max(default_width)
+        } else {
+            self.width.unwrap_or(default_width)
+        };
+        format!("{:0width$}", number, width = width)
+    }
+}
+
+/// Render a template into a relative path (without extension)
+pub fn render_template(
+    template: &str,
+    values: &TemplateValues,
+    rules: &FilenameRules,
+) -> Result<PathBuf> {
+    let mut path = PathBuf::new();
+
+    for segment in template.split('/') {
+        let mut rendered = String::new();
+        let mut rest = segment;
+
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find('}')
+                .map(|e| start + e)
+                .with_context(|| format!("Unclosed '{{' in template: {}", template))?;
+            let (name, modifiers) = match rest[start + 1..end].split_once(':') {
+                Some((name, modifiers)) => (name, Some(modifiers)),
+                None => (&rest[start + 1..end], None),
+            };
+            if !is_known_field(name) {
+                anyhow::bail!("Unknown template field: {{{}}}", name);
+            }
+            let format = NumberFormat::parse(name, modifiers)?;
+            if let Some(value) = values.field(name, &format) {
+                rendered.push_str(value.trim());
+            }
+            rest = &rest[end + 1..];
+        }
+        rendered.push_str(rest);
+
+        let cleaned = rules.sanitize(&tidy_component(&rendered));
+        if !cleaned.is_empty() {
+            path.push(cleaned);
+        }
+    }
+
+    if path.as_os_str().is_empty() {
+        anyhow::bail!("Template produced an empty path: {}", template);
+    }
+
+    Ok(path)
+}
+
+fn is_known_field(name: &str) -> bool {
+    matches!(
+        name,
+        "title"
+            | "artist"
+            | "album"
+            | "albumartist"
+            | "year"
+            | "track"
+            | "totaltracks"
+            | "disc"
+            | "disctrack"
+            | "totaldiscs"
+            | "genre"
+    )
+}
+
+/// Drop brackets left empty by missing fields ("Album ()") and collapse whitespace
+fn tidy_component(component: &str) -> String {
+    let mut result = component.to_string();
+    for empty in ["()", "[]", "{}"] {
+        result = result.replace(empty, "");
+    }
+    let result = result.split_whitespace().collect::<Vec<_>>().join(" ");
+    result
+        .trim_matches(|c: char| c == '-' || c.is_whitespace())
+        .to_string()
+}
+
+/// Where the template puts `file`: in its own folder with `in_place`,
+/// otherwise under the library root
+pub fn destination(
+    file: &Path,
+    values: &TemplateValues,
+    source_root: &Path,
+    options: &OrganizeOptions,
+) -> Result<PathBuf> {
+    let extension = file
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| "mp3".to_string());
+    if options.in_place {
+        let name = options.template.rsplit('/').next().unwrap_or_default();
+        let relative = render_template(name, values, &options.filenames)?;
+        let dir = file.parent().unwrap_or(source_root);
+        Ok(options.filenames.fit_path(dir, &relative, &extension))
+    } else {
+        let relative = render_template(&options.template, values, &options.filenames)?;
+        Ok(options
+            .filenames
+            .fit_path(&options.library_root, &relative, &extension))
+    }
+}
+
+/// Move files into the library according to the template, bringing sidecar
+/// art along and removing source directories that end up empty.
+///
+/// Returns the final location of every input file, in input order.
+pub fn organize(
+    files: &[(PathBuf, TemplateValues)],
+    source_root: &Path,
+    options: &OrganizeOptions,
+) -> Result<Vec<PathBuf>> {
+    let root = if options.in_place {
+        source_root
+    } else {
+        &options.library_root
+    };
+    let heading = if options.in_place {
+        "Renaming in"
+    } else if options.copy {
+        "Copying into"
+    } else {
+        "Organizing into"
+    };
+    println!(
+        "{} {}",
+        heading.bright_yellow(),
+        root.display().to_string().bright_white()
+    );
+
+    let mut source_dirs: BTreeSet<PathBuf> = BTreeSet::new();
+    let mut album_dirs: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut destinations = Vec::with_capacity(files.len());
+
+    for (file, values) in files {
+        let destination = destination(file, values, source_root, options)?;
+
+        if let Some(parent) = file.parent() {
+            source_dirs.insert(parent.to_path_buf());
+            if let Some(dest_parent) = destination.parent() {
+                if !album_dirs.iter().any(|(src, _)| src == parent) {
+                    album_dirs.push((parent.to_path_buf(), dest_parent.to_path_buf()));
+                }
+            }
+        }
+        destinations.push(destination);
+    }
+
+    let sources: Vec<&PathBuf> = files.iter().map(|(file, _)| file).collect();
+    let plan = plan_moves(&sources, &destinations, !options.copy);
+
+    let mut moved = 0;
+    let mut final_paths = Vec::with_capacity(files.len());
+    // Files whose destination is still taken by another file being moved (or
+    // by themselves, differing only in case), with the name they wait under
+    let mut parked: Vec<(PathBuf, &PathBuf)> = Vec::new();
+    for ((file, destination), step) in sources.iter().zip(&destinations).zip(&plan) {
+        match step {
+            Step::Stay => {
+                final_paths.push((*file).clone());
+                continue;
+            }
+            Step::Taken => {
+                println!(
+                    "  {} {} {}",
+                    symbols::warn().bright_yellow(),
+                    destination.display(),
+                    "already exists, skipping".bright_yellow()
+                );
+                final_paths.push((*file).clone());
+                continue;
+            }
+            Step::Direct | Step::Parked => {}
+        }
+
+        println!(
+            "  {} {}",
+            symbols::arrow().bright_cyan(),
+            display_relative(destination, root).bright_white()
+        );
+
+        if options.dry_run {
+            final_paths.push((*file).clone());
+        } else if options.copy {
+            copy_file(file, destination)?;
+            final_paths.push(destination.clone());
+        } else if *step == Step::Parked {
+            let parking = parking_path(file);
+            move_file(file, &parking)?;
+            parked.push((parking, destination));
+            final_paths.push(destination.clone());
+        } else {
+            move_file(file, destination)?;
+            final_paths.push(destination.clone());
+        }
+        moved += 1;
+    }
+    // Every file they were waiting on has moved out of the way by now
+    for (parking, destination) in parked {
+        move_file(&parking, destination)?;
+    }
+
+    // Bring cover.jpg and friends along with the album
+    for (source_dir, dest_dir) in &album_dirs {
+        for image in sidecar_images(source_dir) {
+            let Some(name) = image.file_name() else {
+                continue;
+            };
+            if options
+                .junk
+                .as_ref()
+                .is_some_and(|junk| junk.is_junk(&image))
+            {
+                continue;
+            }
+            let destination = dest_dir.join(name);
+            if destination.exists() {
+                continue;
+            }
+            println!(
+                "  {} {}",
+                symbols::arrow().bright_cyan(),
+                display_relative(&destination, root).bright_black()
+            );
+            if options.dry_run {
+                continue;
+            }
+            if options.copy {
+                copy_file(&image, &destination)?;
+            } else {
+                move_file(&image, &destination)?;
+            }
+        }
+    }
+
+    // Nothing is cleaned up behind a copy
+    if options.copy {
+        print_summary(moved, options);
+        return Ok(final_paths);
+    }
+
+    if let Some(junk) = &options.junk {
+        let junk_root = options.library_root.join("_junk");
+        for dir in &source_dirs {
+            junk.clean_dir(dir, &junk_root, options.dry_run)?;
+        }
+    }
+
+    if !options.dry_run {
+        for dir in &source_dirs {
+            remove_empty_dirs(dir, source_root);
+        }
+    }
+
+    print_summary(moved, options);
+    Ok(final_paths)
+}
+
+fn print_summary(count: usize, options: &OrganizeOptions) {
+    let (verb, past) = if options.in_place {
+        ("renamed", "Renamed")
+    } else if options.copy {
+        ("copied", "Copied")
+    } else {
+        ("moved", "Moved")
+    };
+    println!();
+    if options.dry_run {
+        println!(
+            "{} {} file(s) would be {}.",
+            "Dry run -".bright_yellow(),
+            count,
+            verb
+        );
+    } else {
+        println!(
+            "{} {} {} file(s)",
+            symbols::ok().bright_green(),
+            past,
+            count
+        );
+    }
+}
+
+/// What becomes of one file when organizing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// Already where the template puts it
+    Stay,
+    /// Something that stays put, or an earlier file, has the destination
+    Taken,
+    /// Moved straight to its destination
+    Direct,
+    /// Its destination is held by a file that is moving away, or by itself
+    /// under another case; moved there through a temporary name
+    Parked,
+}
+
+/// Decide each file's step up front, so that swapped or chained names and
+/// case-only renames don't trip over files that are about to move. With
+/// `moving` off (copies), sources keep their places.
+fn plan_moves(sources: &[&PathBuf], destinations: &[PathBuf], moving: bool) -> Vec<Step> {
+    // Compared without case, since the filesystem may ignore it
+    let key = |path: &Path| path.to_string_lossy().to_lowercase();
+    let mut plan: Vec<Step> = sources
+        .iter()
+        .zip(destinations)
+        .map(|(file, destination)| {
+            if *file == destination {
+                Step::Stay
+            } else {
+                Step::Direct
+            }
+        })
+        .collect();
+
+    // Skipping one file keeps its place taken, which may skip another
+    loop {
+        let mut changed = false;
+        for i in 0..plan.len() {
+            if matches!(plan[i], Step::Stay | Step::Taken) {
+                continue;
+            }
+            let target = key(&destinations[i]);
+            let claimed = (0..i).any(|j| {
+                matches!(plan[j], Step::Direct | Step::Parked) && key(&destinations[j]) == target
+            });
+            let holder = sources.iter().position(|s| key(s) == target);
+            let step = match holder {
+                _ if claimed => Step::Taken,
+                Some(j) if moving && matches!(plan[j], Step::Direct | Step::Parked) => Step::Parked,
+                Some(_) => Step::Taken,
+                None if destinations[i].exists() => Step::Taken,
+                None => Step::Direct,
+            };
+            if step != plan[i] {
+                plan[i] = step;
+                changed = true;
+            }
+        }
+        if !changed {
+            return plan;
+        }
+    }
+}
+
+/// A free name next to `file` to hold it while its destination clears
+fn parking_path(file: &Path) -> PathBuf {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let mut n = 0;
+    loop {
+        let parking =
+            file.with_file_name(format!(".{}.organize-{}-{}", name, std::process::id(), n));
+        if !parking.exists() {
+            return parking;
+        }
+        n += 1;
+    }
+}
+
+/// Deepest directory containing all files
+pub fn common_dir(files: &[PathBuf]) -> Option<PathBuf> {
+    let mut common = files.first()?.parent()?.to_path_buf();
+    for file in &files[1..] {
+        while !file.starts_with(&common) {
+            common = common.parent()?.to_path_buf();
+        }
+    }
+    Some(common)
+}
+
+fn display_relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+fn sidecar_images(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .filter(|p| {
+            p.extension()
+                .map(|ext| {
+                    SIDECAR_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+                })
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+pub fn move_file(from: &Path, to: &Path) -> Result<()> {
+    let (from, to) = (&io_path(from), &io_path(to));
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+
+    // rename fails across filesystems; fall back to copy + delete
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)
+            .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+        fs::remove_file(from).with_context(|| format!("Failed to remove {}", from.display()))?;
+    }
+
+    Ok(())
+}
+
+/// `path`, or the first of "name (2).ext", "name (3).ext", … that doesn't
+/// exist yet, so a move never replaces a file already there
+pub fn unique_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .expect("some numbered name is free")
+}
+
+/// Copy a file, creating the destination's parent directories
+pub fn copy_file(from: &Path, to: &Path) -> Result<()> {
+    let (from, to) = (&io_path(from), &io_path(to));
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    fs::copy(from, to)
+        .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+    Ok(())
+}
+
+/// Remove `dir` and its parents while they are empty, below `stop_at`; the
+/// folder the user pointed at stays even when everything moved out of it
+fn remove_empty_dirs(dir: &Path, stop_at: &Path) {
+    let mut current = Some(dir);
+
+    while let Some(dir) = current {
+        if dir == stop_at || !dir.starts_with(stop_at) {
+            break;
+        }
+        let is_empty = fs::read_dir(dir)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false);
+        if !is_empty || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+}
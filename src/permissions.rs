@@ -0,0 +1,112 @@
+// src/permissions.rs
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Confirm;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+
+use crate::symbols;
+
+/// Find files that can't be written before any tags are, so one locked file
+/// doesn't stop a run halfway. Read-only files are made writable if the user
+/// agrees or `make_writable` is set. Returns the files that must be skipped.
+pub fn check_writable(
+    files: &[PathBuf],
+    make_writable: bool,
+    interactive: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut read_only = Vec::new();
+    let mut denied: Vec<(PathBuf, String)> = Vec::new();
+    for file in files {
+        match std::fs::metadata(file) {
+            Ok(metadata) if metadata.permissions().readonly() => read_only.push(file.clone()),
+            Ok(_) => {
+                if let Err(e) = open_for_writing(file) {
+                    denied.push((file.clone(), e.to_string()));
+                }
+            }
+            Err(e) => denied.push((file.clone(), e.to_string())),
+        }
+    }
+
+    let any_read_only = !read_only.is_empty();
+    if any_read_only {
+        println!(
+            "{} {} file(s) are read-only",
+            symbols::warn().bright_yellow(),
+            read_only.len()
+        );
+        let fix = make_writable
+            || (interactive
+                && Confirm::new()
+                    .with_prompt("Make them writable?")
+                    .default(true)
+                    .interact()?);
+
+        if fix {
+            let mut fixed = 0;
+            for file in read_only {
+                match clear_read_only(&file).and_then(|()| open_for_writing(&file)) {
+                    Ok(()) => fixed += 1,
+                    Err(e) => denied.push((file, e.to_string())),
+                }
+            }
+            println!(
+                "{} Made {} file(s) writable",
+                symbols::ok().bright_green(),
+                fixed
+            );
+        } else {
+            denied.extend(
+                read_only
+                    .into_iter()
+                    .map(|file| (file, "read-only".to_string())),
+            );
+        }
+    }
+
+    if !denied.is_empty() {
+        println!(
+            "{} Skipping {} file(s) that can't be written:",
+            symbols::warn().bright_yellow(),
+            denied.len()
+        );
+        for (file, reason) in &denied {
+            println!(
+                "  {} {} {}",
+                symbols::fail().bright_red(),
+                file.display(),
+                format!("({})", reason).bright_black()
+            );
+        }
+    }
+
+    if any_read_only || !denied.is_empty() {
+        println!();
+    }
+    Ok(denied.into_iter().map(|(file, _)| file).collect())
+}
+
+/// Opening for writing without truncating checks ownership and ACLs without
+/// touching the file
+fn open_for_writing(file: &Path) -> std::io::Result<()> {
+    OpenOptions::new().write(true).open(file).map(|_| ())
+}
+
+#[cfg(unix)]
+fn clear_read_only(file: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Only the owner gets write access, not everyone as set_readonly(false) would
+    let mut permissions = std::fs::metadata(file)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o200);
+    std::fs::set_permissions(file, permissions)
+}
+
+#[cfg(not(unix))]
+fn clear_read_only(file: &Path) -> std::io::Result<()> {
+    let mut permissions = std::fs::metadata(file)?.permissions();
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    std::fs::set_permissions(file, permissions)
+}
@@ -0,0 +1,247 @@
+// src/pin.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::matcher::{FileMatch, MatchReport};
+use crate::musicbrainz::{Album, Track};
+use crate::symbols;
+
+/// Kept in an album folder to tag it the same way every time
+pub const PIN_FILE: &str = "album.toml";
+
+/// Options a pin may set. A folder can come from anywhere, so it only gets
+/// to change how its own files are tagged: nothing that adds files, moves or
+/// deletes them, skips prompts, or sends data elsewhere.
+const ALLOWED_OPTIONS: &[&str] = &[
+    "tracks",
+    "disc",
+    "exclude",
+    "no-cover-art",
+    "min-art-size",
+    "small-art",
+    "profile",
+    "max-embed-bytes",
+    "conflicts",
+    "title-case",
+    "album-case",
+    "artist-case",
+    "feat",
+    "prefer-original",
+    "prefer-latest",
+    "year",
+    "country",
+    "media",
+    "checksum",
+    "write-cue",
+    "playlist",
+    "nfo",
+    "artist-nfo",
+    "mark",
+    "keep-going",
+];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pin {
+    /// Release MBID or URL, used when no --album-id is given
+    pub release: Option<String>,
+    /// Command-line arguments applied before the ones given, which win
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    /// File name (relative to the folder) to track, "5" or "2-05" for disc 2
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub matches: BTreeMap<String, String>,
+}
+
+/// The folder the arguments name, if they name one: a directory, or files
+/// that all sit in the same directory
+pub fn folder(paths: &[PathBuf]) -> Option<PathBuf> {
+    let dir_of = |path: &PathBuf| {
+        if path.is_dir() {
+            Some(path.clone())
+        } else {
+            path.parent().map(Path::to_path_buf)
+        }
+    };
+    let dir = dir_of(paths.first()?)?;
+    paths
+        .iter()
+        .all(|path| dir_of(path).as_ref() == Some(&dir))
+        .then_some(dir)
+}
+
+/// The folder's pin, if it has one. A pin that can't be read is an error
+/// rather than something to tag without.
+pub fn read(dir: &Path) -> Result<Option<Pin>> {
+    let path = dir.join(PIN_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let pin = toml::from_str(&contents).with_context(|| format!("Invalid {}", path.display()))?;
+    Ok(Some(pin))
+}
+
+pub fn write(dir: &Path, pin: &Pin) -> Result<PathBuf> {
+    let path = dir.join(PIN_FILE);
+    let contents = toml::to_string(pin).context("Failed to serialize album pin")?;
+    std::fs::write(&path, contents)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Fail on the first option the pin isn't allowed to set, naming it
+pub fn check_options(options: &[String]) -> Result<()> {
+    for option in options.iter().filter(|o| o.starts_with('-')) {
+        let key = option
+            .split_once('=')
+            .map_or(option.as_str(), |(key, _)| key);
+        let allowed = key
+            .strip_prefix("--")
+            .is_some_and(|name| ALLOWED_OPTIONS.contains(&name));
+        if !allowed {
+            anyhow::bail!(
+                "{} can't set {}; its options may only change how the files are tagged",
+                PIN_FILE,
+                key
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Replace the pin's matches with the files' final names (relative to `dir`)
+/// and their tracks, so later runs pair them the same way
+pub fn record_matches(pin: &mut Pin, dir: &Path, album: &Album, files: &[(PathBuf, &Track)]) {
+    pin.matches = files
+        .iter()
+        .filter_map(|(file, track)| {
+            let name = file
+                .strip_prefix(dir)
+                .ok()?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let spec = if album.media_count > 1 {
+                format!("{}-{:02}", track.disc_number, track.position)
+            } else {
+                track.position.to_string()
+            };
+            Some((name, spec))
+        })
+        .collect();
+}
+
+/// Put the pinned matches in place of what the matcher found. A file pinned
+/// to a track takes it from whichever file had it, which becomes unmatched.
+pub fn apply_matches(pin: &Pin, dir: &Path, album: &Album, report: &mut MatchReport) -> Result<()> {
+    if pin.matches.is_empty() {
+        return Ok(());
+    }
+    println!(
+        "{}",
+        format!("Matches pinned by {}:", PIN_FILE).bright_white()
+    );
+    for (name, spec) in &pin.matches {
+        let pinned = dir.join(name);
+        let index = track_index(album, spec).with_context(|| {
+            format!(
+                "{} pins {} to track {}, which the release doesn't have",
+                PIN_FILE, name, spec
+            )
+        })?;
+        let found = report
+            .matches
+            .iter()
+            .chain(&report.low_confidence)
+            .map(|m| &m.file_path)
+            .chain(&report.unmatched_files)
+            .chain(report.duplicates.iter().map(|d| &d.file_path))
+            .find(|path| same_file(path, &pinned))
+            .cloned();
+        let Some(file) = found else {
+            println!(
+                "  {} {} {}",
+                symbols::warn().bright_yellow(),
+                name,
+                "(not among the files being tagged)".bright_black()
+            );
+            continue;
+        };
+        let known = |path: &PathBuf| *path == file;
+
+        let track = album.tracks[index].clone();
+        let mut displaced = Vec::new();
+        for list in [&mut report.matches, &mut report.low_confidence] {
+            list.retain(|m| {
+                if known(&m.file_path) {
+                    return false;
+                }
+                if m.track.id == track.id {
+                    displaced.push(m.file_path.clone());
+                    return false;
+                }
+                true
+            });
+        }
+        report.unmatched_files.retain(|f| !known(f));
+        report.unmatched_files.extend(displaced);
+        report.unmatched_tracks.retain(|t| t.id != track.id);
+        report.duplicates.retain(|d| !known(&d.file_path));
+        println!(
+            "  {} {} {} Track {}: {}",
+            symbols::ok().bright_green(),
+            name,
+            symbols::arrow(),
+            spec,
+            track.title
+        );
+        report.matches.push(FileMatch {
+            file_path: file,
+            track,
+            confidence: 1.0,
+            duration_resolved: false,
+        });
+    }
+
+    // A file that lost its track may have left another track without a file
+    for track in &album.tracks {
+        let matched = report.matches.iter().any(|m| m.track.id == track.id);
+        if !matched && !report.unmatched_tracks.iter().any(|t| t.id == track.id) {
+            report.unmatched_tracks.push(track.clone());
+        }
+    }
+    report
+        .matches
+        .sort_by_key(|m| (m.track.disc_number, m.track.position));
+    Ok(())
+}
+
+/// "5" on a single-disc release (or the only track 5), "2-05" for disc 2
+fn track_index(album: &Album, spec: &str) -> Option<usize> {
+    let (disc, position) = match spec.split_once('-') {
+        Some((disc, position)) => (Some(disc.trim().parse::<u32>().ok()?), position),
+        None => (None, spec),
+    };
+    let position: u32 = position.trim().parse().ok()?;
+    let mut found = album
+        .tracks
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.position == position && disc.is_none_or(|d| t.disc_number == d));
+    let (index, _) = found.next()?;
+    // Without a disc, a position on several discs is ambiguous
+    found.next().is_none().then_some(index)
+}
+
+/// Paths from the command line are canonical; pinned ones may not be
+fn same_file(a: &Path, b: &Path) -> bool {
+    a == b
+        || match (a.canonicalize(), b.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+}
@@ -0,0 +1,171 @@
+// src/playlist.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use id3::{Tag, TagLike};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::index::LibraryIndex;
+use crate::matcher::get_mp3_duration;
+use crate::organize::common_dir;
+use crate::symbols;
+
+pub const PLAYLIST_NAME: &str = "album.m3u8";
+
+struct Entry {
+    path: PathBuf,
+    disc: u32,
+    track: u32,
+    title: Option<String>,
+    artist: Option<String>,
+    seconds: Option<u32>,
+}
+
+/// Write an extended M3U playlist (UTF-8, relative paths) for the given files
+/// into their common directory, ordered by disc and track number
+pub fn write_album_playlist(files: &[PathBuf]) -> Result<Option<PathBuf>> {
+    let Some(dir) = common_dir(files) else {
+        return Ok(None);
+    };
+
+    let mut entries: Vec<Entry> = files.iter().map(|f| read_entry(f)).collect();
+    entries.sort_by(|a, b| (a.disc, a.track, &a.path).cmp(&(b.disc, b.track, &b.path)));
+
+    let mut contents = String::from("#EXTM3U\n");
+    for entry in &entries {
+        let label = match (&entry.artist, &entry.title) {
+            (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+            (None, Some(title)) => title.clone(),
+            _ => entry
+                .path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        let seconds = entry.seconds.map(|s| s as i64).unwrap_or(-1);
+        let relative = entry.path.strip_prefix(&dir).unwrap_or(&entry.path);
+
+        contents.push_str(&format!("#EXTINF:{},{}\n", seconds, label));
+        // M3U readers on every platform accept forward slashes
+        contents.push_str(&relative.to_string_lossy().replace('\\', "/"));
+        contents.push('\n');
+    }
+
+    let playlist_path = dir.join(PLAYLIST_NAME);
+    std::fs::write(&playlist_path, contents)
+        .with_context(|| format!("Failed to write {}", playlist_path.display()))?;
+
+    Ok(Some(playlist_path))
+}
+
+/// Regenerate album playlists for every directory containing MP3s under `root`.
+/// Albums whose files are unchanged since the last run are skipped unless `force`.
+pub fn regenerate_library(root: &Path, dry_run: bool, force: bool) -> Result<()> {
+    let files = crate::maintenance::collect_tree(root)?;
+    let index = LibraryIndex::open()?;
+
+    let mut albums: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for file in files {
+        if let Some(parent) = file.parent() {
+            albums.entry(parent.to_path_buf()).or_default().push(file);
+        }
+    }
+
+    println!(
+        "{} Found {} album folder(s)",
+        symbols::ok().bright_green(),
+        albums.len()
+    );
+    println!();
+
+    let mut skipped = 0;
+    for (dir, files) in &albums {
+        let display = match dir.strip_prefix(root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative.display().to_string(),
+            _ => dir.display().to_string(),
+        };
+        if !force && is_up_to_date(&index, dir, files)? {
+            println!(
+                "  {} {} {}",
+                "·".bright_black(),
+                display.bright_black(),
+                "(unchanged)".bright_black()
+            );
+            skipped += 1;
+            continue;
+        }
+
+        if dry_run {
+            println!(
+                "  {} {} ({} tracks)",
+                symbols::arrow().bright_cyan(),
+                display.bright_white(),
+                files.len()
+            );
+            continue;
+        }
+
+        write_album_playlist(files)?;
+        // The index holds canonical paths, so compare against canonical ones
+        let current: HashSet<PathBuf> = files.iter().map(|f| canonical(f)).collect();
+        for stale in index.files_in(dir)? {
+            if !current.contains(&canonical(&stale)) {
+                index.forget(&stale)?;
+            }
+        }
+        for file in files {
+            index.refresh(file)?;
+        }
+        println!(
+            "  {} {} ({} tracks)",
+            symbols::ok().bright_green(),
+            display.bright_white(),
+            files.len()
+        );
+    }
+
+    println!();
+    if dry_run {
+        println!("{}", "Dry run - no playlists were written.".bright_yellow());
+    } else {
+        println!(
+            "{} Wrote {} playlist(s), {} unchanged",
+            symbols::ok().bright_green(),
+            albums.len() - skipped,
+            skipped
+        );
+    }
+
+    Ok(())
+}
+
+/// The playlist exists and no file in the album was added, removed, or changed
+fn is_up_to_date(index: &LibraryIndex, dir: &Path, files: &[PathBuf]) -> Result<bool> {
+    if !dir.join(PLAYLIST_NAME).exists() || index.files_in(dir)?.len() != files.len() {
+        return Ok(false);
+    }
+    for file in files {
+        if !index.is_unchanged(file)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn read_entry(path: &Path) -> Entry {
+    let tag = Tag::read_from_path(path).ok();
+
+    Entry {
+        disc: tag.as_ref().and_then(|t| t.disc()).unwrap_or(1),
+        // Untagged files go last, in filename order
+        track: tag.as_ref().and_then(|t| t.track()).unwrap_or(u32::MAX),
+        title: tag.as_ref().and_then(|t| t.title().map(str::to_string)),
+        artist: tag.as_ref().and_then(|t| t.artist().map(str::to_string)),
+        seconds: get_mp3_duration(path).map(|ms| ms / 1000),
+        path: path.to_path_buf(),
+    }
+}
@@ -0,0 +1,104 @@
+// src/profile.rs
+use clap::ValueEnum;
+use id3::Version;
+
+/// Player ecosystems with known tag-reading quirks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Profile {
+    Plex,
+    Navidrome,
+    Itunes,
+    Foobar,
+    Kodi,
+}
+
+/// How fields with several values (e.g. multiple credited artists) are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiValue {
+    /// Only the first value is written
+    FirstOnly,
+    /// Values are written as separate ID3v2.4 text values
+    Native,
+    /// Values are joined into one string with the given separator
+    Joined(&'static str),
+}
+
+/// Settings that control which frames are written and how
+#[derive(Debug, Clone, Copy)]
+pub struct TagSettings {
+    pub version: Version,
+    /// Write TCMP=1 for Various Artists releases
+    pub write_compilation: bool,
+    /// Write TSO2 (album artist sort order)
+    pub write_album_artist_sort: bool,
+    pub multi_value: MultiValue,
+    /// Maximum cover art width/height in pixels
+    pub max_art_size: u32,
+    /// Largest cover art to embed, in bytes; bigger art is re-encoded or
+    /// shrunk to fit, or left out
+    pub max_embed_bytes: usize,
+}
+
+impl Default for TagSettings {
+    fn default() -> Self {
+        Self {
+            version: Version::Id3v24,
+            write_compilation: false,
+            write_album_artist_sort: false,
+            multi_value: MultiValue::FirstOnly,
+            max_art_size: 1200,
+            max_embed_bytes: 1024 * 1024,
+        }
+    }
+}
+
+impl Profile {
+    /// Tag settings that suit this player
+    pub fn settings(self) -> TagSettings {
+        match self {
+            // Plex ignores null-separated values and shows them run together
+            Profile::Plex => TagSettings {
+                version: Version::Id3v24,
+                write_compilation: true,
+                write_album_artist_sort: true,
+                multi_value: MultiValue::Joined("; "),
+                max_art_size: 1200,
+                max_embed_bytes: 1024 * 1024,
+            },
+            Profile::Navidrome => TagSettings {
+                version: Version::Id3v24,
+                write_compilation: true,
+                write_album_artist_sort: true,
+                multi_value: MultiValue::Native,
+                max_art_size: 1200,
+                max_embed_bytes: 1024 * 1024,
+            },
+            // iTunes and iPods only reliably read ID3v2.3 and choke on large art
+            Profile::Itunes => TagSettings {
+                version: Version::Id3v23,
+                write_compilation: true,
+                write_album_artist_sort: true,
+                multi_value: MultiValue::Joined(" & "),
+                max_art_size: 600,
+                max_embed_bytes: 512 * 1024,
+            },
+            Profile::Foobar => TagSettings {
+                version: Version::Id3v24,
+                write_compilation: false,
+                write_album_artist_sort: true,
+                multi_value: MultiValue::Native,
+                max_art_size: 1200,
+                max_embed_bytes: 1024 * 1024,
+            },
+            // Kodi splits on " / " by default (musiclibrary.separators)
+            Profile::Kodi => TagSettings {
+                version: Version::Id3v24,
+                write_compilation: true,
+                write_album_artist_sort: false,
+                multi_value: MultiValue::Joined(" / "),
+                max_art_size: 1000,
+                max_embed_bytes: 1024 * 1024,
+            },
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// src/progress.rs
+use crate::events::{Event, EventSink};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+static HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Hide every bar and spinner from now on, e.g. while stderr carries
+/// `--progress-json` events
+pub fn hide() {
+    HIDDEN.store(true, Ordering::Relaxed);
+}
+
+/// Bar for a phase with a known number of steps. Hidden automatically when
+/// stderr isn't a terminal.
+pub fn bar(len: usize, phase: &str) -> ProgressBar {
+    if HIDDEN.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    let pb = ProgressBar::new(len as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+    pb.set_message(phase.to_string());
+    pb
+}
+
+/// Spinner with a running count, for walks whose size isn't known up front
+pub fn spinner(phase: &str) -> ProgressBar {
+    if HIDDEN.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg} {pos}")
+            .unwrap(),
+    );
+    pb.set_message(phase.to_string());
+    pb.enable_steady_tick(Duration::from_millis(100));
+    pb
+}
+
+/// Writes each event as one line of JSON to stderr, for `--progress-json`
+#[derive(Clone, Copy)]
+pub struct JsonLines;
+
+impl EventSink for JsonLines {
+    fn emit(&self, event: Event) {
+        let Ok(line) = serde_json::to_string(&event) else {
+            return;
+        };
+        // Whole lines only, even when events come from several threads
+        let mut stderr = std::io::stderr().lock();
+        let _ = writeln!(stderr, "{}", line);
+    }
+}
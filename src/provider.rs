@@ -0,0 +1,184 @@
+// src/provider.rs
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::future::Future;
+use std::str::FromStr;
+
+use crate::error::Result;
+use crate::musicbrainz::Album;
+
+/// A search hit, enough to pick a release before fetching it in full
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseSummary {
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+    pub date: Option<String>,
+    pub country: Option<String>,
+    /// Media formats as MusicBrainz names them ("CD", "Digital Media",
+    /// "12\" Vinyl"), each once
+    pub formats: Vec<String>,
+    pub track_count: u32,
+    /// How well the release matches the query, 0-100
+    pub score: u32,
+}
+
+impl ReleaseSummary {
+    /// Year from the release date, if it has one
+    pub fn year(&self) -> Option<i32> {
+        self.date.as_deref()?.get(..4)?.parse().ok()
+    }
+}
+
+/// Which edition to list first when several releases fit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DatePreference {
+    /// Keep the provider's order
+    #[default]
+    Relevance,
+    /// Earliest release first, e.g. the original pressing
+    Original,
+    /// Newest release first, e.g. the latest remaster
+    Latest,
+}
+
+/// Release years to keep, inclusive; either end may be open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct YearRange {
+    pub from: Option<i32>,
+    pub to: Option<i32>,
+}
+
+impl YearRange {
+    pub fn is_open(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+
+    /// Undated releases only pass an open range
+    pub fn contains(&self, year: Option<i32>) -> bool {
+        match year {
+            Some(year) => {
+                self.from.is_none_or(|from| year >= from) && self.to.is_none_or(|to| year <= to)
+            }
+            None => self.is_open(),
+        }
+    }
+}
+
+/// `1997`, `1990..1999`, `2010..`, or `..1979`
+impl FromStr for YearRange {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, String> {
+        let year = |s: &str| -> std::result::Result<Option<i32>, String> {
+            if s.trim().is_empty() {
+                return Ok(None);
+            }
+            s.trim().parse().map(Some).map_err(|_| {
+                format!(
+                    "invalid year '{}': expected a year or a range like 1990..1999",
+                    text
+                )
+            })
+        };
+        Ok(match text.split_once("..") {
+            Some((from, to)) => YearRange {
+                from: year(from)?,
+                to: year(to)?,
+            },
+            None => {
+                let exact = year(text)?;
+                YearRange {
+                    from: exact,
+                    to: exact,
+                }
+            }
+        })
+    }
+}
+
+/// How to order candidate releases before offering them
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseRanking {
+    pub date: DatePreference,
+    pub years: YearRange,
+    /// Country codes, most preferred first (e.g. `US`, `GB`, `XW`)
+    pub countries: Vec<String>,
+    /// Media formats, most preferred first. "Vinyl" also matches
+    /// "12\" Vinyl" and "CD" matches "Enhanced CD".
+    pub formats: Vec<String>,
+}
+
+impl ReleaseRanking {
+    /// Drop releases outside the year range, then order by preferred format,
+    /// then country, then date. Releases in unlisted formats or countries go
+    /// after listed ones and undated releases after dated ones; otherwise
+    /// releases keep their order.
+    pub fn apply(&self, releases: &mut Vec<ReleaseSummary>) {
+        releases.retain(|release| self.years.contains(release.year()));
+        releases.sort_by(|a, b| {
+            self.format_rank(a)
+                .cmp(&self.format_rank(b))
+                .then_with(|| self.country_rank(a).cmp(&self.country_rank(b)))
+                .then_with(|| self.compare_dates(a, b))
+        });
+    }
+
+    /// Position in `formats` of the release's best format, or past the end
+    fn format_rank(&self, release: &ReleaseSummary) -> usize {
+        release
+            .formats
+            .iter()
+            .filter_map(|format| {
+                let format = format.to_lowercase();
+                self.formats
+                    .iter()
+                    .position(|wanted| format.contains(&wanted.to_lowercase()))
+            })
+            .min()
+            .unwrap_or(self.formats.len())
+    }
+
+    /// Position in `countries`, or past the end for unlisted countries
+    fn country_rank(&self, release: &ReleaseSummary) -> usize {
+        release
+            .country
+            .as_deref()
+            .and_then(|country| {
+                self.countries
+                    .iter()
+                    .position(|c| c.eq_ignore_ascii_case(country))
+            })
+            .unwrap_or(self.countries.len())
+    }
+
+    fn compare_dates(&self, a: &ReleaseSummary, b: &ReleaseSummary) -> Ordering {
+        match (self.date, &a.date, &b.date) {
+            (DatePreference::Relevance, _, _) => Ordering::Equal,
+            (DatePreference::Original, Some(a), Some(b)) => a.cmp(b),
+            (DatePreference::Latest, Some(a), Some(b)) => b.cmp(a),
+            (_, a, b) => a.is_none().cmp(&b.is_none()),
+        }
+    }
+}
+
+/// A source of release metadata and cover art. The matcher and tagger only see
+/// the returned `Album`, so any source that can fill one in can be used.
+pub trait MetadataProvider {
+    /// Fetch a release with all of its tracks by the provider's own ID
+    fn get_release(&self, release_id: &str) -> impl Future<Output = Result<Album>> + Send;
+
+    /// Find releases by album title and, if given, artist. Best matches first.
+    fn search_releases(
+        &self,
+        title: &str,
+        artist: Option<&str>,
+    ) -> impl Future<Output = Result<Vec<ReleaseSummary>>> + Send;
+
+    /// Download the release's front cover, shrunk to at most `max_size` pixels
+    fn get_cover_art(
+        &self,
+        release_id: &str,
+        max_size: u32,
+    ) -> impl Future<Output = Result<Vec<u8>>> + Send;
+}
@@ -0,0 +1,52 @@
+// src/quality.rs
+use std::path::Path;
+
+use crate::audio::{self, AudioInfo};
+
+/// Average bitrates below these have audible artifacts
+const LOW_BITRATE_KBPS: u32 = 128;
+const LOW_BITRATE_MONO_KBPS: u32 = 64;
+/// Below CD quality
+const LOW_SAMPLE_RATE: u32 = 44_100;
+/// Files at this bitrate or more are checked for a lowpass that gives away a
+/// lower-bitrate source
+const TRANSCODE_CHECK_KBPS: u32 = 256;
+/// LAME cuts at 17 kHz for 128 kbps; a genuine 256+ kbps encode keeps 19 kHz
+/// and more
+const TRANSCODE_CUTOFF_HZ: u32 = 18_000;
+
+/// Reasons to doubt a rip is worth tagging or keeping. The lowpass check
+/// decodes part of the file, so it only runs with `check_transcodes`.
+pub fn warnings(path: &Path, info: &AudioInfo, check_transcodes: bool) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let low_bitrate = if info.channels == Some(1) {
+        LOW_BITRATE_MONO_KBPS
+    } else {
+        LOW_BITRATE_KBPS
+    };
+    if let Some(kbps) = info.bitrate_kbps.filter(|kbps| *kbps < low_bitrate) {
+        warnings.push(format!("low bitrate ({} kbps)", kbps));
+    }
+    if let Some(rate) = info.sample_rate.filter(|rate| *rate < LOW_SAMPLE_RATE) {
+        warnings.push(format!(
+            "sample rate below CD quality ({:.2} kHz)",
+            rate as f64 / 1000.0
+        ));
+    }
+    // At lower sample rates the encoder's own lowpass sits below the threshold
+    let full_band = info.sample_rate.is_some_and(|rate| rate >= LOW_SAMPLE_RATE);
+    if check_transcodes
+        && full_band
+        && info
+            .bitrate_kbps
+            .is_some_and(|kbps| kbps >= TRANSCODE_CHECK_KBPS)
+    {
+        if let Some(cutoff) = audio::cutoff_hz(path).filter(|hz| *hz < TRANSCODE_CUTOFF_HZ) {
+            warnings.push(format!(
+                "nothing above {:.1} kHz despite the bitrate; likely upsampled from a low-bitrate source",
+                cutoff as f64 / 1000.0
+            ));
+        }
+    }
+    warnings
+}
@@ -0,0 +1,294 @@
+// src/query.rs
+use anyhow::Result;
+use colored::Colorize;
+use rusqlite::types::Value;
+use std::path::Path;
+
+use crate::index::LibraryIndex;
+use crate::symbols;
+
+#[derive(Clone, Copy)]
+enum Field {
+    /// Case-insensitive substring
+    Text(&'static str),
+    /// Exact value or `a..b` range, either end optional
+    Number(&'static str),
+    /// Exact, case-insensitive (MBIDs)
+    Exact(&'static str),
+}
+
+const FIELDS: &[(&str, Field)] = &[
+    ("artist", Field::Text("artist")),
+    ("albumartist", Field::Text("album_artist")),
+    ("album", Field::Text("album")),
+    ("title", Field::Text("title")),
+    ("genre", Field::Text("genre")),
+    ("path", Field::Text("path")),
+    ("year", Field::Number("year")),
+    ("track", Field::Number("track")),
+    ("disc", Field::Number("disc")),
+    ("mb_albumid", Field::Exact("album_id")),
+    ("mb_trackid", Field::Exact("track_id")),
+    ("mb_recordingid", Field::Exact("recording_id")),
+];
+
+/// What `missing:` and `has:` accept besides the field names
+const FLAGS: &[(&str, &str)] = &[
+    ("art", "has_art = 1"),
+    ("lyrics", "has_lyrics = 1"),
+    ("replaygain", "has_replaygain = 1"),
+    ("mbid", "coalesce(album_id, '') != ''"),
+];
+
+/// Fields a bare word is matched against
+const BARE_FIELDS: &[&str] = &["artist", "album", "title"];
+
+/// A parsed query: every term must match
+pub struct Query {
+    clauses: Vec<String>,
+    values: Vec<Value>,
+}
+
+impl Query {
+    /// Parse space-separated terms such as `artist:radiohead year:2000..2010
+    /// missing:art`. A leading `-` or `^` negates a term; double quotes keep
+    /// spaces inside a value (`album:"ok computer"`).
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut query = Query {
+            clauses: Vec::new(),
+            values: Vec::new(),
+        };
+        for token in tokenize(text)? {
+            query.add_term(&token)?;
+        }
+        Ok(query)
+    }
+
+    /// SQL condition on the index's `files` table and its bound values
+    pub fn condition(&self) -> (String, &[Value]) {
+        let condition = if self.clauses.is_empty() {
+            "1".to_string()
+        } else {
+            self.clauses.join(" AND ")
+        };
+        (condition, &self.values)
+    }
+
+    fn add_term(&mut self, token: &str) -> Result<()> {
+        let (negated, term) = match token.strip_prefix(['-', '^']) {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, token),
+        };
+
+        let clause = match term.split_once(':') {
+            Some((name, value)) if is_field_name(name) => self.field_clause(name, value)?,
+            _ => {
+                let pattern = like_pattern(term);
+                let parts: Vec<String> = BARE_FIELDS
+                    .iter()
+                    .map(|column| {
+                        self.values.push(Value::Text(pattern.clone()));
+                        format!("{} LIKE ? ESCAPE '\\'", column)
+                    })
+                    .collect();
+                parts.join(" OR ")
+            }
+        };
+
+        // NULL columns make a comparison NULL; count those as "doesn't match"
+        self.clauses.push(if negated {
+            format!("NOT coalesce(({}), 0)", clause)
+        } else {
+            format!("coalesce(({}), 0)", clause)
+        });
+        Ok(())
+    }
+
+    fn field_clause(&mut self, name: &str, value: &str) -> Result<String> {
+        let name = name.to_lowercase();
+        if name == "missing" || name == "has" {
+            let present = presence_clause(value)?;
+            return Ok(if name == "has" {
+                present
+            } else {
+                format!("NOT coalesce(({}), 0)", present)
+            });
+        }
+
+        let Some(&(_, field)) = FIELDS.iter().find(|(n, _)| *n == name) else {
+            anyhow::bail!(
+                "Unknown field '{}'. Known fields: {}, missing, has",
+                name,
+                FIELDS
+                    .iter()
+                    .map(|(n, _)| *n)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        };
+
+        Ok(match field {
+            Field::Text(column) | Field::Exact(column) if value.is_empty() => {
+                format!("coalesce({}, '') = ''", column)
+            }
+            Field::Number(column) if value.is_empty() => format!("{} IS NULL", column),
+            Field::Text(column) => {
+                self.values.push(Value::Text(like_pattern(value)));
+                format!("{} LIKE ? ESCAPE '\\'", column)
+            }
+            Field::Exact(column) => {
+                self.values.push(Value::Text(value.to_lowercase()));
+                format!("lower({}) = ?", column)
+            }
+            Field::Number(column) => self.range_clause(&name, column, value)?,
+        })
+    }
+
+    fn range_clause(&mut self, name: &str, column: &str, value: &str) -> Result<String> {
+        let number = |s: &str| -> Result<Option<i64>> {
+            if s.is_empty() {
+                return Ok(None);
+            }
+            s.parse().map(Some).map_err(|_| {
+                anyhow::anyhow!(
+                    "Invalid {} '{}': expected a number or a range like 2000..2010",
+                    name,
+                    value
+                )
+            })
+        };
+
+        let (low, high) = match value.split_once("..") {
+            Some((low, high)) => (number(low)?, number(high)?),
+            None => {
+                let exact = number(value)?;
+                (exact, exact)
+            }
+        };
+
+        let mut parts = Vec::new();
+        if let Some(low) = low {
+            self.values.push(Value::Integer(low));
+            parts.push(format!("{} >= ?", column));
+        }
+        if let Some(high) = high {
+            self.values.push(Value::Integer(high));
+            parts.push(format!("{} <= ?", column));
+        }
+        if parts.is_empty() {
+            anyhow::bail!("Invalid {} range '..': give at least one end", name);
+        }
+        Ok(parts.join(" AND "))
+    }
+}
+
+/// Condition for a value of `has:`/`missing:`: a flag, or any non-empty field
+fn presence_clause(value: &str) -> Result<String> {
+    let value = value.to_lowercase();
+    if let Some((_, clause)) = FLAGS.iter().find(|(n, _)| *n == value) {
+        return Ok(clause.to_string());
+    }
+    match FIELDS.iter().find(|(n, _)| *n == value) {
+        Some((_, Field::Number(column))) => Ok(format!("{} IS NOT NULL", column)),
+        Some((_, Field::Text(column) | Field::Exact(column))) => {
+            Ok(format!("coalesce({}, '') != ''", column))
+        }
+        None => anyhow::bail!(
+            "Unknown value '{}' for missing:/has:. Use {} or a field name",
+            value,
+            FLAGS.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// A word followed by a colon is a field; `12:34` in a title is not
+fn is_field_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphabetic() || c == '_')
+}
+
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Split on whitespace, keeping double-quoted runs together
+fn tokenize(text: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if quoted {
+        anyhow::bail!("Unterminated quote in query");
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+/// Print the indexed files matching `terms`, one path per line so the output
+/// can be piped into another command. `scan` brings the index up to date for
+/// a tree first.
+pub fn run(terms: &[String], scan: Option<&Path>) -> Result<()> {
+    let query = Query::parse(&terms.join(" "))?;
+    let index = LibraryIndex::open()?;
+
+    // Progress and summaries go to stderr so stdout stays a clean path list
+    if let Some(root) = scan {
+        let (total, refreshed) = scan_tree(&index, root)?;
+        eprintln!(
+            "{} Indexed {} file(s), {} updated",
+            symbols::ok().bright_green(),
+            total,
+            refreshed
+        );
+    }
+
+    let (condition, values) = query.condition();
+    let mut matched = 0;
+    for path in index.select(&condition, values)? {
+        if !path.exists() {
+            index.forget(&path)?;
+            continue;
+        }
+        println!("{}", path.display());
+        matched += 1;
+    }
+
+    eprintln!(
+        "{} {} matching file(s)",
+        symbols::ok().bright_green(),
+        matched
+    );
+    Ok(())
+}
+
+/// Refresh every changed MP3 under `root` in the index. Returns how many files
+/// were found and how many had to be re-read.
+pub fn scan_tree(index: &LibraryIndex, root: &Path) -> Result<(usize, usize)> {
+    let files = crate::maintenance::collect_tree(root)?;
+    let pb = crate::progress::bar(files.len(), "Updating index");
+    let mut refreshed = 0;
+    for file in &files {
+        if !index.is_unchanged(file)? {
+            index.refresh(file)?;
+            refreshed += 1;
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+    Ok((files.len(), refreshed))
+}
@@ -0,0 +1,93 @@
+// src/rename.rs
+use anyhow::Result;
+use colored::Colorize;
+use id3::TagLike;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::maintenance::display_name;
+use crate::organize::{self, OrganizeOptions, TemplateValues};
+use crate::symbols;
+use crate::tagger::read_tag;
+
+/// Rename or organize files by their existing tags alone: nothing is looked
+/// up and no tag is written. Files without a title keep their names.
+pub fn run(files: &[PathBuf], source_root: &Path, options: &OrganizeOptions) -> Result<()> {
+    let mut plan = Vec::new();
+    let mut untitled = Vec::new();
+    let pb = crate::progress::bar(files.len(), "Reading tags");
+    for file in files {
+        pb.inc(1);
+        match values_from_tags(file) {
+            Some(values) => plan.push((file.clone(), values)),
+            None => untitled.push(file),
+        }
+    }
+    pb.finish_and_clear();
+    fill_total_discs(&mut plan);
+
+    if !untitled.is_empty() {
+        println!(
+            "{} {} file(s) have no title tag and keep their names:",
+            symbols::warn().bright_yellow(),
+            untitled.len()
+        );
+        for file in &untitled {
+            println!("  {}", display_name(file).bright_black());
+        }
+        println!();
+    }
+    if plan.is_empty() {
+        anyhow::bail!("None of the files have tags to name them by");
+    }
+
+    organize::organize(&plan, source_root, options)?;
+    Ok(())
+}
+
+fn values_from_tags(file: &Path) -> Option<TemplateValues> {
+    let tag = read_tag(file).ok()?;
+    let title = tag.title().filter(|t| !t.trim().is_empty())?.to_string();
+    let artist = tag.artist().unwrap_or_default().to_string();
+    Some(TemplateValues {
+        title,
+        // Files from single-artist albums often only have the track artist
+        album_artist: tag
+            .album_artist()
+            .filter(|a| !a.trim().is_empty())
+            .map_or_else(|| artist.clone(), str::to_string),
+        artist,
+        album: tag.album().unwrap_or_default().to_string(),
+        year: tag
+            .year()
+            .or_else(|| tag.date_recorded().map(|d| d.year))
+            .or_else(|| tag.date_released().map(|d| d.year)),
+        track: tag.track(),
+        total_tracks: tag.total_tracks(),
+        disc_tracks: None,
+        disc: tag.disc(),
+        total_discs: tag.total_discs(),
+        genre: tag
+            .genres()
+            .and_then(|genres| genres.first().map(|g| g.to_string())),
+    })
+}
+
+/// Files tagged "disc 2" without "of 2" still belong to a multi-disc album;
+/// take the disc count from the highest disc among the album's files
+fn fill_total_discs(plan: &mut [(PathBuf, TemplateValues)]) {
+    let mut highest: HashMap<(String, String), u32> = HashMap::new();
+    for (_, values) in plan.iter() {
+        if let Some(disc) = values.disc {
+            let key = (values.album_artist.clone(), values.album.clone());
+            let entry = highest.entry(key).or_default();
+            *entry = (*entry).max(disc);
+        }
+    }
+    for (_, values) in plan.iter_mut() {
+        if values.total_discs.is_none() && !values.album.is_empty() {
+            let key = (values.album_artist.clone(), values.album.clone());
+            values.total_discs = highest.get(&key).copied();
+        }
+    }
+}
@@ -0,0 +1,292 @@
+// src/repair.rs
+use id3::{Tag, TagLike, Version};
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+
+const UNSYNCHRONISATION: u8 = 0x80;
+const EXTENDED_HEADER: u8 = 0x40;
+const FOOTER: u8 = 0x10;
+
+/// What a repair kept and dropped
+#[derive(Debug, Clone, Copy)]
+pub struct RepairReport {
+    pub recovered: usize,
+    pub lost: usize,
+}
+
+/// Why the file's tag can't be read as-is, or `None` if it reads fine (or
+/// there is no tag at all)
+pub fn tag_problem(file_path: &Path) -> Option<String> {
+    match Tag::read_from_path(file_path) {
+        Ok(_) => None,
+        Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => None,
+        // Opening the file is someone else's problem
+        Err(e) if matches!(e.kind, id3::ErrorKind::Io(_)) => None,
+        Err(e) => Some(e.description),
+    }
+}
+
+/// Replace the file's ID3v2 tag with a clean one holding every frame that
+/// still decodes, written as `version`. One malformed frame makes the id3
+/// crate reject the whole tag, so frames are decoded one at a time here and
+/// only the broken ones are lost. The repaired file is built next to the
+/// original and renamed over it, so a failure leaves the original as it was.
+pub fn repair_tag(file_path: &Path, version: Version) -> Result<RepairReport> {
+    let io_error = |source| Error::Io {
+        path: file_path.to_path_buf(),
+        source,
+    };
+    let data = std::fs::read(file_path).map_err(io_error)?;
+
+    let (tag, report, audio_start) = salvage(&data);
+    let audio_start = audio_start.ok_or_else(|| Error::NoAudio(file_path.to_path_buf()))?;
+    let mut repaired = Vec::with_capacity(data.len());
+    if report.recovered > 0 {
+        tag.write_to(&mut repaired, version)
+            .map_err(|source| Error::TagWriteError {
+                path: file_path.to_path_buf(),
+                source,
+            })?;
+    }
+    repaired.extend_from_slice(&data[audio_start..]);
+
+    let mut temp_name = file_path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(".repair-{}", std::process::id()));
+    let temp_path = file_path.with_file_name(temp_name);
+    let written = std::fs::File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(&repaired)?;
+            file.sync_all()
+        })
+        .and_then(|()| {
+            let permissions = std::fs::metadata(file_path)?.permissions();
+            std::fs::set_permissions(&temp_path, permissions)
+        })
+        .and_then(|()| std::fs::rename(&temp_path, file_path));
+    if let Err(source) = written {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(io_error(source));
+    }
+    Ok(report)
+}
+
+/// Decode what we can from the tag at the start of `data`. Also returns
+/// where the audio starts, or `None` if no MPEG frame can be found.
+fn salvage(data: &[u8]) -> (Tag, RepairReport, Option<usize>) {
+    let mut report = RepairReport {
+        recovered: 0,
+        lost: 0,
+    };
+    if data.len() < 10 || &data[..3] != b"ID3" {
+        return (Tag::new(), report, audio_start(data, 0));
+    }
+
+    let major = data[3];
+    let flags = data[5];
+    let mut tag_end = 10 + synchsafe(&data[6..10]) as usize;
+    if major == 4 && flags & FOOTER != 0 {
+        tag_end += 10;
+    }
+    // The size field is part of what may be damaged, so the audio is looked
+    // for rather than assumed to follow it
+    let Some(audio_start) = audio_start(data, tag_end) else {
+        return (Tag::new(), report, None);
+    };
+    let tag_end = tag_end.min(audio_start);
+
+    // Before v2.4 unsynchronisation covers the whole tag body
+    let mut body = data[10..tag_end].to_vec();
+    if major < 4 && flags & UNSYNCHRONISATION != 0 {
+        body = resynchronise(&body);
+    }
+
+    let mut pos = 0;
+    if major >= 3 && flags & EXTENDED_HEADER != 0 && body.len() >= 4 {
+        pos = match major {
+            3 => 4 + read_u32(&body[..4]) as usize,
+            _ => synchsafe(&body[..4]) as usize,
+        };
+    }
+
+    let header_len = if major == 2 { 6 } else { 10 };
+    let id_len = if major == 2 { 3 } else { 4 };
+    let mut frames = Vec::new();
+    while pos + header_len <= body.len() {
+        let header = &body[pos..pos + header_len];
+        if !header[..id_len]
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        {
+            // Padding, or garbage we can't find the next frame in
+            break;
+        }
+
+        let size = frame_size(major, &body, pos);
+        let end = (pos + header_len + size).min(body.len());
+        match decode_frame(major, &body[pos..end]) {
+            Some(decoded) => {
+                report.recovered += decoded.len();
+                frames.extend(decoded);
+            }
+            None => report.lost += 1,
+        }
+        pos = end;
+    }
+
+    let mut tag = Tag::with_version(match major {
+        2 => Version::Id3v22,
+        3 => Version::Id3v23,
+        _ => Version::Id3v24,
+    });
+    for frame in frames {
+        tag.add_frame(frame);
+    }
+    if tag.version() == Version::Id3v22 {
+        tag = crate::tagger::upgrade_v22(&tag);
+    }
+    (tag, report, Some(audio_start))
+}
+
+/// Where the MPEG frames begin: at `expected` when a frame sits there,
+/// otherwise the first place after the ID3 header where two frames follow
+/// each other
+fn audio_start(data: &[u8], expected: usize) -> Option<usize> {
+    let frames_at = |pos: usize| match mpeg_frame_len(data, pos) {
+        Some(len) => pos + len == data.len() || mpeg_frame_len(data, pos + len).is_some(),
+        None => false,
+    };
+    if frames_at(expected) {
+        return Some(expected);
+    }
+    (10.min(data.len())..data.len()).find(|&pos| frames_at(pos))
+}
+
+/// The length of the MPEG audio frame whose header starts at `pos`
+fn mpeg_frame_len(data: &[u8], pos: usize) -> Option<usize> {
+    const V1_L1: [u32; 14] = [
+        32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ];
+    const V1_L2: [u32; 14] = [
+        32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ];
+    const V1_L3: [u32; 14] = [
+        32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const V2_L1: [u32; 14] = [
+        32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ];
+    const V2_L23: [u32; 14] = [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+    let header = data.get(pos..pos + 4)?;
+    if header[0] != 0xff || header[1] & 0xe0 != 0xe0 {
+        return None;
+    }
+    // 0 = MPEG 2.5, 2 = MPEG 2, 3 = MPEG 1; layer 3 = Layer I, 1 = Layer III
+    let version = (header[1] >> 3) & 0x03;
+    let layer = (header[1] >> 1) & 0x03;
+    let bitrate_index = usize::from(header[2] >> 4);
+    let rate_index = usize::from((header[2] >> 2) & 0x03);
+    if version == 1 || layer == 0 || bitrate_index == 0 || bitrate_index == 15 || rate_index == 3 {
+        return None;
+    }
+
+    let bitrates = match (version == 3, layer) {
+        (true, 3) => &V1_L1,
+        (true, 2) => &V1_L2,
+        (true, _) => &V1_L3,
+        (false, 3) => &V2_L1,
+        (false, _) => &V2_L23,
+    };
+    let bitrate = bitrates[bitrate_index - 1] * 1000;
+    let sample_rate = [44_100, 48_000, 32_000][rate_index]
+        / match version {
+            3 => 1,
+            2 => 2,
+            _ => 4,
+        };
+    let padding = u32::from((header[2] >> 1) & 0x01);
+    let len = match layer {
+        3 => (12 * bitrate / sample_rate + padding) * 4,
+        1 if version != 3 => 72 * bitrate / sample_rate + padding,
+        _ => 144 * bitrate / sample_rate + padding,
+    };
+    Some(len as usize)
+}
+
+fn frame_size(major: u8, body: &[u8], pos: usize) -> usize {
+    match major {
+        2 => {
+            let b = &body[pos + 3..pos + 6];
+            (usize::from(b[0]) << 16) | (usize::from(b[1]) << 8) | usize::from(b[2])
+        }
+        3 => read_u32(&body[pos + 4..pos + 8]) as usize,
+        _ => {
+            // Some old writers used plain sizes in v2.4; trust whichever one
+            // lands on the next frame (or the end of the tag)
+            let bytes = &body[pos + 4..pos + 8];
+            let safe = synchsafe(bytes) as usize;
+            let plain = read_u32(bytes) as usize;
+            if safe != plain
+                && !lands_on_frame(body, pos + 10 + safe)
+                && lands_on_frame(body, pos + 10 + plain)
+            {
+                plain
+            } else {
+                safe
+            }
+        }
+    }
+}
+
+fn lands_on_frame(body: &[u8], pos: usize) -> bool {
+    match body.get(pos..pos + 4) {
+        Some(id) => id
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()),
+        None => pos >= body.len(),
+    }
+}
+
+/// Parse one frame by wrapping it in a minimal tag of its own
+fn decode_frame(major: u8, frame: &[u8]) -> Option<Vec<id3::Frame>> {
+    let size = frame.len() as u32;
+    let mut buffer = Vec::with_capacity(frame.len() + 10);
+    buffer.extend_from_slice(b"ID3");
+    buffer.extend_from_slice(&[major, 0, 0]);
+    buffer.extend_from_slice(&[
+        ((size >> 21) & 0x7f) as u8,
+        ((size >> 14) & 0x7f) as u8,
+        ((size >> 7) & 0x7f) as u8,
+        (size & 0x7f) as u8,
+    ]);
+    buffer.extend_from_slice(frame);
+
+    let tag = Tag::read_from2(Cursor::new(buffer)).ok()?;
+    let frames: Vec<_> = tag.frames().cloned().collect();
+    (!frames.is_empty()).then_some(frames)
+}
+
+fn synchsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |size, &b| (size << 7) | u32::from(b & 0x7f))
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Undo unsynchronisation: every 0xFF 0x00 pair was an 0xFF
+fn resynchronise(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut previous = 0;
+    for &b in data {
+        if !(previous == 0xff && b == 0) {
+            out.push(b);
+        }
+        previous = b;
+    }
+    out
+}
@@ -0,0 +1,602 @@
+// src/replaygain.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use id3::frame::{Comment, ExtendedText};
+use id3::{Tag, TagLike};
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::symbols;
+use crate::tagger::{extended_text, read_tag};
+
+/// ReplayGain 2.0 reference loudness
+const REFERENCE_LUFS: f64 = -18.0;
+/// BS.1770 gates: blocks below -70 LUFS are silence, then anything 10 LU
+/// below the loudness of what's left is dropped
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+const TRACK_GAIN: &str = "REPLAYGAIN_TRACK_GAIN";
+const TRACK_PEAK: &str = "REPLAYGAIN_TRACK_PEAK";
+const ALBUM_GAIN: &str = "REPLAYGAIN_ALBUM_GAIN";
+const ALBUM_PEAK: &str = "REPLAYGAIN_ALBUM_PEAK";
+/// Description of the COMM frame iTunes reads Sound Check from
+const SOUND_CHECK: &str = "iTunNORM";
+/// Sound Check levels are 16-bit
+const SOUND_CHECK_MAX: f64 = 65534.0;
+
+/// Compute and write ReplayGain for files that lack it. Files tagged with the
+/// same MusicBrainz release are analyzed together so album gain covers the
+/// whole release; files without a release ID only get track gain. With
+/// `sound_check`, the track gain is also written as iTunes Sound Check,
+/// taken from the stored gain for files that already have one.
+pub fn run(path: &Path, sound_check: bool, dry_run: bool) -> Result<()> {
+    let files = crate::maintenance::collect_tree(path)?;
+    if files.is_empty() {
+        anyhow::bail!("No MP3 files found in {}", path.display());
+    }
+
+    let mut releases: BTreeMap<String, Release> = BTreeMap::new();
+    let mut singles = Vec::new();
+    let mut from_stored: Vec<(PathBuf, Gain)> = Vec::new();
+    let pb = crate::progress::bar(files.len(), "Reading tags");
+    for file in files {
+        pb.inc(1);
+        let tag = Tag::read_from_path(&file).ok();
+        let has = |name: &str| tag.as_ref().is_some_and(|t| has_text(t, name));
+        let needs_track = !has(TRACK_GAIN);
+        let needs_album = !has(ALBUM_GAIN);
+        let needs_sound_check = sound_check && !tag.as_ref().is_some_and(has_sound_check);
+        if needs_sound_check && !needs_track {
+            if let Some(gain) = tag.as_ref().and_then(stored_track_gain) {
+                from_stored.push((file.clone(), gain));
+            }
+        }
+
+        match tag
+            .as_ref()
+            .and_then(|t| extended_text(t, "MusicBrainz Album Id"))
+        {
+            Some(album_id) => {
+                let release = releases.entry(album_id).or_insert_with(|| Release {
+                    title: tag.as_ref().and_then(|t| t.album()).map(str::to_string),
+                    files: Vec::new(),
+                    incomplete: false,
+                });
+                release.incomplete |= needs_track || needs_album;
+                release.files.push((file, needs_track));
+            }
+            None if needs_track => singles.push(file),
+            None => {}
+        }
+    }
+    pb.finish_and_clear();
+    releases.retain(|_, release| release.incomplete);
+
+    let release_files: usize = releases.values().map(|r| r.files.len()).sum();
+    if release_files == 0 && singles.is_empty() && from_stored.is_empty() {
+        println!(
+            "{} Every file already has ReplayGain",
+            symbols::ok().bright_green()
+        );
+        return Ok(());
+    }
+
+    if release_files > 0 || !singles.is_empty() {
+        println!(
+            "{} Analyzing {} file(s) in {} release(s) and {} file(s) without a release ID",
+            symbols::ok().bright_green(),
+            release_files,
+            releases.len(),
+            singles.len()
+        );
+        println!();
+    }
+
+    // One parallel pass over everything; results are handed back in order
+    let work: Vec<&Path> = releases
+        .values()
+        .flat_map(|r| r.files.iter().map(|(f, _)| f.as_path()))
+        .chain(singles.iter().map(PathBuf::as_path))
+        .collect();
+    let mut results = analyze_all(&work).into_iter();
+
+    let mut written = 0;
+    let mut failed = 0;
+    for (album_id, release) in &releases {
+        let analyses: Vec<_> = results.by_ref().take(release.files.len()).collect();
+        let name = release.title.as_deref().unwrap_or(album_id);
+
+        // Album gain over a partial release would be wrong, so leave it off
+        let complete = analyses.iter().all(Option::is_some);
+        let album = if complete {
+            Analysis::combine(analyses.iter().flatten()).gain()
+        } else {
+            None
+        };
+
+        for ((file, needs_track), analysis) in release.files.iter().zip(&analyses) {
+            let Some(analysis) = analysis else {
+                println!(
+                    "  {} {}: could not decode audio",
+                    symbols::warn().bright_yellow(),
+                    display_name(file)
+                );
+                failed += 1;
+                continue;
+            };
+            let track = analysis.gain().filter(|_| *needs_track);
+            if track.is_none() && album.is_none() {
+                continue;
+            }
+            if !dry_run {
+                write_gain(file, track.as_ref(), album.as_ref(), sound_check)?;
+            }
+            written += 1;
+        }
+
+        match &album {
+            Some(gain) => println!(
+                "  {} {} {} {}",
+                symbols::ok().bright_green(),
+                name.bright_white(),
+                gain.format_db().bright_cyan(),
+                format!("({} file(s))", release.files.len()).bright_black()
+            ),
+            None if complete => println!(
+                "  {} {}: no audible audio",
+                symbols::warn().bright_yellow(),
+                name.bright_white()
+            ),
+            None => println!(
+                "  {} {}: album gain skipped, not every track could be analyzed",
+                symbols::warn().bright_yellow(),
+                name.bright_white()
+            ),
+        }
+    }
+
+    for (file, analysis) in singles.iter().zip(results) {
+        let Some(analysis) = analysis else {
+            println!(
+                "  {} {}: could not decode audio",
+                symbols::warn().bright_yellow(),
+                display_name(file)
+            );
+            failed += 1;
+            continue;
+        };
+        let Some(track) = analysis.gain() else {
+            println!(
+                "  {} {}: no audible audio",
+                symbols::warn().bright_yellow(),
+                display_name(file)
+            );
+            continue;
+        };
+        if !dry_run {
+            write_gain(file, Some(&track), None, sound_check)?;
+        }
+        println!(
+            "  {} {} {}",
+            symbols::ok().bright_green(),
+            display_name(file).bright_white(),
+            track.format_db().bright_cyan()
+        );
+        written += 1;
+    }
+
+    if !dry_run {
+        for (file, gain) in &from_stored {
+            write_sound_check(file, gain)?;
+        }
+    }
+
+    println!();
+    let verb = if dry_run { "Would write" } else { "Wrote" };
+    println!(
+        "{} {} ReplayGain to {} file(s); {} could not be analyzed",
+        symbols::ok().bright_green(),
+        verb,
+        written,
+        failed
+    );
+    if !from_stored.is_empty() {
+        println!(
+            "{} {} Sound Check to {} file(s) from their existing track gain",
+            symbols::ok().bright_green(),
+            verb,
+            from_stored.len()
+        );
+    }
+    if dry_run {
+        println!("{}", "Dry run - no files were modified".bright_yellow());
+    }
+
+    Ok(())
+}
+
+struct Release {
+    title: Option<String>,
+    /// Each file, and whether it still needs track gain
+    files: Vec<(PathBuf, bool)>,
+    incomplete: bool,
+}
+
+struct Gain {
+    db: f64,
+    peak: f32,
+}
+
+impl Gain {
+    fn format_db(&self) -> String {
+        format!("{:+.2} dB", self.db)
+    }
+}
+
+/// Mean-square energy of each 400ms block (75% overlap) plus the sample peak
+struct Analysis {
+    blocks: Vec<f64>,
+    peak: f32,
+}
+
+impl Analysis {
+    fn combine<'a>(analyses: impl Iterator<Item = &'a Analysis>) -> Analysis {
+        let mut combined = Analysis {
+            blocks: Vec::new(),
+            peak: 0.0,
+        };
+        for analysis in analyses {
+            combined.blocks.extend_from_slice(&analysis.blocks);
+            combined.peak = combined.peak.max(analysis.peak);
+        }
+        combined
+    }
+
+    /// Gated integrated loudness turned into a gain, `None` for pure silence
+    fn gain(&self) -> Option<Gain> {
+        let loudness = |energy: f64| -0.691 + 10.0 * energy.log10();
+        let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len() as f64;
+
+        let audible: Vec<f64> = self
+            .blocks
+            .iter()
+            .copied()
+            .filter(|&e| e > 0.0 && loudness(e) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if audible.is_empty() {
+            return None;
+        }
+        let threshold = loudness(mean(&audible)) + RELATIVE_GATE_LU;
+        let gated: Vec<f64> = audible
+            .into_iter()
+            .filter(|&e| loudness(e) > threshold)
+            .collect();
+
+        Some(Gain {
+            db: REFERENCE_LUFS - loudness(mean(&gated)),
+            peak: self.peak,
+        })
+    }
+}
+
+/// Analyze files across all cores, since decoding is by far the slowest part
+fn analyze_all(files: &[&Path]) -> Vec<Option<Analysis>> {
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let chunk_size = files.len().div_ceil(threads).max(1);
+
+    let pb = crate::progress::bar(files.len(), "Analyzing audio");
+    let analyses = std::thread::scope(|scope| {
+        let handles: Vec<_> = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let pb = &pb;
+                let handle = scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|f| {
+                            let analysis = analyze(f);
+                            pb.inc(1);
+                            analysis
+                        })
+                        .collect::<Vec<_>>()
+                });
+                (chunk.len(), handle)
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|(len, handle)| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| (0..len).map(|_| None).collect())
+            })
+            .collect()
+    });
+    pb.finish_and_clear();
+    analyses
+}
+
+/// Decode a file and measure its BS.1770 block loudness
+fn analyze(path: &Path) -> Option<Analysis> {
+    let file = File::open(path).ok()?;
+    let source = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?
+        .format;
+    let track = format.default_track()?;
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .ok()?;
+
+    let mut meter: Option<Meter> = None;
+    let mut samples: Option<SampleBuffer<f32>> = None;
+    while let Ok(packet) = format.next_packet() {
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt frame shouldn't sink the whole file
+            Err(DecodeError::DecodeError(_)) => continue,
+            Err(_) => break,
+        };
+
+        let spec = *decoded.spec();
+        let buffer = match &mut samples {
+            Some(buffer) if buffer.capacity() >= decoded.capacity() * spec.channels.count() => {
+                buffer
+            }
+            _ => samples.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+
+        meter
+            .get_or_insert_with(|| Meter::new(sample_rate, spec.channels.count()))
+            .process(buffer.samples());
+    }
+
+    Some(meter?.finish())
+}
+
+/// Biquad filter in direct form I
+#[derive(Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Biquad {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// The two BS.1770 K-weighting stages, with coefficients derived for any
+/// sample rate rather than the 48 kHz table in the spec
+fn k_weighting(rate: u32) -> [Biquad; 2] {
+    let rate = rate as f64;
+
+    // High shelf modelling the acoustic effect of the head
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    // High pass (the "RLB" curve)
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+/// Accumulates K-weighted energy in 100ms steps; each 400ms block is four steps
+struct Meter {
+    filters: Vec<[Biquad; 2]>,
+    step_frames: usize,
+    frames: usize,
+    energy: f64,
+    steps: Vec<f64>,
+    peak: f32,
+}
+
+impl Meter {
+    fn new(rate: u32, channels: usize) -> Self {
+        Meter {
+            filters: vec![k_weighting(rate); channels],
+            step_frames: (rate as usize / 10).max(1),
+            frames: 0,
+            energy: 0.0,
+            steps: Vec::new(),
+            peak: 0.0,
+        }
+    }
+
+    fn process(&mut self, interleaved: &[f32]) {
+        let channels = self.filters.len();
+        for frame in interleaved.chunks_exact(channels) {
+            // Stereo and mono are all MP3 carries, so every channel is weighted 1.0
+            for (sample, filters) in frame.iter().zip(&mut self.filters) {
+                self.peak = self.peak.max(sample.abs());
+                let [shelf, high_pass] = filters;
+                let weighted = high_pass.process(shelf.process(*sample as f64));
+                self.energy += weighted * weighted;
+            }
+            self.frames += 1;
+            if self.frames == self.step_frames {
+                self.steps.push(self.energy / self.frames as f64);
+                self.frames = 0;
+                self.energy = 0.0;
+            }
+        }
+    }
+
+    fn finish(self) -> Analysis {
+        Analysis {
+            blocks: self
+                .steps
+                .windows(4)
+                .map(|w| w.iter().sum::<f64>() / 4.0)
+                .collect(),
+            peak: self.peak,
+        }
+    }
+}
+
+fn has_text(tag: &Tag, description: &str) -> bool {
+    tag.extended_texts()
+        .any(|t| t.description.eq_ignore_ascii_case(description))
+}
+
+/// The track gain and peak another run (or tool) wrote, for Sound Check
+fn stored_track_gain(tag: &Tag) -> Option<Gain> {
+    let value = |name: &str| {
+        tag.extended_texts()
+            .find(|t| t.description.eq_ignore_ascii_case(name))
+            .map(|t| t.value.clone())
+    };
+    let db = value(TRACK_GAIN)?.split_whitespace().next()?.parse().ok()?;
+    let peak = value(TRACK_PEAK)
+        .and_then(|peak| peak.trim().parse().ok())
+        .unwrap_or(1.0);
+    Some(Gain { db, peak })
+}
+
+fn has_sound_check(tag: &Tag) -> bool {
+    tag.comments().any(|c| c.description == SOUND_CHECK)
+}
+
+fn write_gain(
+    path: &Path,
+    track: Option<&Gain>,
+    album: Option<&Gain>,
+    sound_check: bool,
+) -> Result<()> {
+    let mut tag = read_tag(path).unwrap_or_else(|_| Tag::new());
+    if let Some(gain) = track.filter(|_| sound_check) {
+        set_sound_check(&mut tag, gain);
+    }
+    let pairs = [
+        (TRACK_GAIN, TRACK_PEAK, track),
+        (ALBUM_GAIN, ALBUM_PEAK, album),
+    ];
+    for (gain_name, peak_name, gain) in pairs {
+        let Some(gain) = gain else {
+            continue;
+        };
+        set_text(&mut tag, gain_name, &gain.format_db());
+        set_text(&mut tag, peak_name, &format!("{:.6}", gain.peak));
+    }
+    tag.write_to_path(path, tag.version())
+        .with_context(|| format!("Failed to write tags to {}", path.display()))
+}
+
+fn write_sound_check(path: &Path, gain: &Gain) -> Result<()> {
+    let mut tag = read_tag(path).unwrap_or_else(|_| Tag::new());
+    set_sound_check(&mut tag, gain);
+    tag.write_to_path(path, tag.version())
+        .with_context(|| format!("Failed to write tags to {}", path.display()))
+}
+
+/// Replace the iTunNORM comment. Its ten hex words are the gain as a level
+/// relative to 1/1000 W (left, right) and to 1/2500 W (left, right), two
+/// unused words, the peak as a 16-bit sample (left, right), and two more
+/// unused words.
+fn set_sound_check(tag: &mut Tag, gain: &Gain) {
+    let level = |scale: f64| {
+        (10f64.powf(-gain.db / 10.0) * scale)
+            .round()
+            .clamp(1.0, SOUND_CHECK_MAX) as u32
+    };
+    let (milliwatt, quieter) = (level(1000.0), level(2500.0));
+    let peak = (f64::from(gain.peak) * 32768.0).min(SOUND_CHECK_MAX) as u32;
+    let words = [
+        milliwatt, milliwatt, quieter, quieter, 0, 0, peak, peak, 0, 0,
+    ];
+    let text: String = words.iter().map(|w| format!(" {:08X}", w)).collect();
+
+    tag.remove_comment(Some(SOUND_CHECK), None);
+    tag.add_frame(Comment {
+        lang: "eng".to_string(),
+        description: SOUND_CHECK.to_string(),
+        text,
+    });
+}
+
+/// Replace a TXXX frame, including any spelled in a different case by another tagger
+fn set_text(tag: &mut Tag, description: &str, value: &str) {
+    let existing: Vec<String> = tag
+        .extended_texts()
+        .filter(|t| t.description.eq_ignore_ascii_case(description))
+        .map(|t| t.description.clone())
+        .collect();
+    for old in existing {
+        tag.remove_extended_text(Some(&old), None);
+    }
+    tag.add_frame(ExtendedText {
+        description: description.to_string(),
+        value: value.to_string(),
+    });
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
@@ -0,0 +1,225 @@
+// src/report.rs
+use std::path::Path;
+
+use crate::matcher::{MatchReport, ScoreBreakdown};
+use crate::musicbrainz::Album;
+use crate::symbols;
+
+/// List the release's tracks, grouped by disc on multi-disc releases
+pub fn print_tracks(album: &Album) {
+    println!("Album tracks from MusicBrainz:");
+
+    if album.media_count > 1 {
+        let mut current_disc = 0;
+        for track in &album.tracks {
+            if track.disc_number != current_disc {
+                current_disc = track.disc_number;
+                let disc_info = if let Some(title) = &track.disc_title {
+                    format!(" - {}", title)
+                } else {
+                    String::new()
+                };
+                println!("\n  Disc {}{}:", current_disc, disc_info);
+            }
+            let duration = track
+                .length
+                .map(|ms| format!(" ({})", format_duration(ms)))
+                .unwrap_or_default();
+            println!("    {}. {}{}", track.position, track.title, duration);
+        }
+    } else {
+        for track in &album.tracks {
+            let duration = track
+                .length
+                .map(|ms| format!(" ({})", format_duration(ms)))
+                .unwrap_or_default();
+            println!("  {}. {}{}", track.position, track.title, duration);
+        }
+    }
+    println!();
+}
+
+/// Print how files were assigned, what was left over, and what was skipped
+pub fn print_match_report(album: &Album, report: &MatchReport) {
+    println!("Assigning matches (highest confidence first)...");
+
+    for assignment in &report.assignments {
+        let track = &album.tracks[assignment.track_index];
+        let file_dur_str = report
+            .durations
+            .get(&assignment.file_path)
+            .map(|&ms| format!(" [file: {}]", format_duration(ms)))
+            .unwrap_or_default();
+        let track_dur_str = track
+            .length
+            .map(|ms| format!(" [track: {}]", format_duration(ms)))
+            .unwrap_or_default();
+        let by_duration = if assignment.duration_resolved {
+            ", resolved by duration"
+        } else {
+            ""
+        };
+
+        if album.media_count > 1 {
+            println!(
+                "  {} {} -> Disc {} Track {} - {} (score: {}, confidence: {}%{}){}{}",
+                symbols::ok(),
+                file_name(&assignment.file_path),
+                track.disc_number,
+                track.position,
+                track.title,
+                format_score(&assignment.breakdown),
+                (assignment.confidence * 100.0) as i32,
+                by_duration,
+                file_dur_str,
+                track_dur_str
+            );
+        } else {
+            println!(
+                "  {} {} -> Track {} - {} (score: {}, confidence: {}%{}){}{}",
+                symbols::ok(),
+                file_name(&assignment.file_path),
+                track.position,
+                track.title,
+                format_score(&assignment.breakdown),
+                (assignment.confidence * 100.0) as i32,
+                by_duration,
+                file_dur_str,
+                track_dur_str
+            );
+        }
+    }
+
+    println!();
+    print_unmatched(album, report);
+
+    for m in &report.low_confidence {
+        println!(
+            "{} Skipping very low confidence match: {} -> {} ({}%)",
+            symbols::warn(),
+            file_name(&m.file_path),
+            m.track.title,
+            (m.confidence * 100.0) as i32
+        );
+    }
+}
+
+/// Matches made from a CUE sheet's order, which have no scores to show
+pub fn print_cue_report(album: &Album, report: &MatchReport) {
+    println!("Pairing files with tracks in CUE sheet order...");
+    for m in &report.matches {
+        let track = if album.media_count > 1 {
+            format!("Disc {} Track {}", m.track.disc_number, m.track.position)
+        } else {
+            format!("Track {}", m.track.position)
+        };
+        // Neither the sheet's title nor the file's length agreed
+        let (symbol, doubt) = if m.confidence < 1.0 {
+            (
+                symbols::warn(),
+                format!(" (confidence: {:.0}%)", m.confidence * 100.0),
+            )
+        } else {
+            (symbols::ok(), String::new())
+        };
+        println!(
+            "  {} {} -> {} - {}{}",
+            symbol,
+            file_name(&m.file_path),
+            track,
+            m.track.title,
+            doubt
+        );
+    }
+    println!();
+    print_unmatched(album, report);
+}
+
+fn print_unmatched(album: &Album, report: &MatchReport) {
+    if !report.unmatched_files.is_empty() {
+        println!("Unmatched files:");
+        for file in &report.unmatched_files {
+            let duration = report
+                .durations
+                .get(file)
+                .map(|&ms| format!(" ({})", format_duration(ms)))
+                .unwrap_or_default();
+            println!("  {} {}{}", symbols::fail(), file_name(file), duration);
+        }
+        println!();
+    }
+
+    if !report.duplicates.is_empty() {
+        println!("Duplicate copies (the better copy is tagged):");
+        for duplicate in &report.duplicates {
+            println!(
+                "  {} {} (copy of {})",
+                symbols::warn(),
+                file_name(&duplicate.file_path),
+                file_name(&duplicate.kept)
+            );
+        }
+        println!();
+    }
+
+    if !report.unmatched_tracks.is_empty() {
+        println!("Unmatched tracks:");
+        for track in &report.unmatched_tracks {
+            let duration = track
+                .length
+                .map(|ms| format!(" ({})", format_duration(ms)))
+                .unwrap_or_default();
+
+            if album.media_count > 1 {
+                println!(
+                    "  {} Disc {} Track {} - {}{}",
+                    symbols::fail(),
+                    track.disc_number,
+                    track.position,
+                    track.title,
+                    duration
+                );
+            } else {
+                println!(
+                    "  {} Track {} - {}{}",
+                    symbols::fail(),
+                    track.position,
+                    track.title,
+                    duration
+                );
+            }
+        }
+        println!();
+    }
+}
+
+/// "260 = name 80, qualifiers +20, duration +80, artist +80", leaving out zero bonuses
+fn format_score(breakdown: &ScoreBreakdown) -> String {
+    let mut parts = vec![format!("name {}", breakdown.name)];
+    if breakdown.qualifiers != 0 {
+        parts.push(format!("qualifiers {:+}", breakdown.qualifiers));
+    }
+    if breakdown.duration != 0 {
+        parts.push(format!("duration {:+}", breakdown.duration));
+    }
+    if breakdown.artist != 0 {
+        parts.push(format!("artist {:+}", breakdown.artist));
+    }
+    if parts.len() == 1 {
+        return breakdown.total().to_string();
+    }
+    format!("{} = {}", breakdown.total(), parts.join(", "))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+pub fn format_duration(ms: u32) -> String {
+    let total_seconds = ms / 1000;
+    let minutes = total_seconds / 60;
+    let seconds = total_seconds % 60;
+    format!("{}:{:02}", minutes, seconds)
+}
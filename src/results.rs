@@ -0,0 +1,239 @@
+// src/results.rs
+use colored::Colorize;
+use id3::frame::Content;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::matcher::FileMatch;
+use crate::musicbrainz::Album;
+use crate::symbols;
+
+/// Longest file name or track shown before it is cut short
+const MAX_COLUMN: usize = 32;
+/// Changed fields named before the rest are only counted
+const MAX_FIELDS: usize = 3;
+
+/// What happened to one file, printed as a table at the end of a run and
+/// sent with the `--webhook` summary
+#[derive(Debug, Clone, Serialize)]
+pub struct FileResult {
+    pub file: PathBuf,
+    /// `tagged`, `skipped`, or `failed`
+    pub status: &'static str,
+    pub track: String,
+    pub confidence: f64,
+    /// Tag fields whose value changed
+    pub changed: Vec<String>,
+    pub art_embedded: bool,
+    /// Where organizing (or copying) put the file
+    pub renamed_to: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+impl FileResult {
+    pub fn new(file_match: &FileMatch, album: &Album, status: &'static str) -> Self {
+        let track = &file_match.track;
+        let position = if album.media_count > 1 {
+            format!("{}-{:02}", track.disc_number, track.position)
+        } else {
+            track.position.to_string()
+        };
+        FileResult {
+            file: file_match.file_path.clone(),
+            status,
+            track: format!("{}. {}", position, track.title),
+            confidence: file_match.confidence,
+            changed: Vec::new(),
+            art_embedded: false,
+            renamed_to: None,
+            error: None,
+        }
+    }
+}
+
+/// Every frame's value, keyed so that repeated frames (TXXX, COMM, APIC)
+/// are told apart by description or picture type
+pub type Snapshot = BTreeMap<String, String>;
+
+pub fn snapshot(file: &Path) -> Snapshot {
+    let Ok(tag) = crate::tagger::read_tag(file) else {
+        return Snapshot::new();
+    };
+    tag.frames()
+        .map(|frame| {
+            let id = frame.id();
+            match frame.content() {
+                Content::ExtendedText(text) => {
+                    (format!("{}:{}", id, text.description), text.value.clone())
+                }
+                Content::Comment(comment) => (
+                    format!("{}:{}", id, comment.description),
+                    comment.text.clone(),
+                ),
+                Content::UniqueFileIdentifier(ufid) => (
+                    format!("{}:{}", id, ufid.owner_identifier),
+                    String::from_utf8_lossy(&ufid.identifier).into_owned(),
+                ),
+                Content::Picture(picture) => {
+                    // Display leaves out the image data
+                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                    picture.data.hash(&mut hasher);
+                    (
+                        format!("{}:{}", id, picture.picture_type),
+                        format!("{}:{:x}", picture.mime_type, hasher.finish()),
+                    )
+                }
+                content => (id.to_string(), content.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Readable names of the fields that differ, in tag order, each named once
+pub fn changed_fields(before: &Snapshot, after: &Snapshot) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let keys = before
+        .keys()
+        .chain(after.keys().filter(|k| !before.contains_key(*k)));
+    for key in keys {
+        if before.get(key) != after.get(key) {
+            let name = field_name(key);
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+fn field_name(key: &str) -> String {
+    let (id, description) = key.split_once(':').unwrap_or((key, ""));
+    match id {
+        "TIT2" => "title",
+        "TPE1" => "artist",
+        "TALB" => "album",
+        "TPE2" => "album artist",
+        "TRCK" => "track",
+        "TPOS" => "disc",
+        "TDRC" | "TYER" | "TDAT" | "TDRL" | "TDOR" | "TORY" => "date",
+        "TCON" => "genre",
+        "TCMP" => "compilation",
+        "TSOP" | "TSO2" | "TSOA" | "TSOT" => "sort names",
+        "APIC" => "art",
+        "COMM" => "comment",
+        "UFID" => "MusicBrainz IDs",
+        "TXXX" if description.starts_with("MusicBrainz") => "MusicBrainz IDs",
+        "TXXX" => description,
+        _ => id,
+    }
+    .to_string()
+}
+
+/// One line per file: status, file, track, confidence, changed fields, art,
+/// and where it was moved. Destinations are shown relative to `library_root`.
+pub fn print_table(results: &[FileResult], library_root: Option<&Path>) {
+    if results.is_empty() {
+        return;
+    }
+    let files: Vec<String> = results
+        .iter()
+        .map(|r| shorten(&r.file.file_name().unwrap_or_default().to_string_lossy()))
+        .collect();
+    let tracks: Vec<String> = results.iter().map(|r| shorten(&r.track)).collect();
+    let changes: Vec<String> = results
+        .iter()
+        .map(|r| describe_changes(&r.changed))
+        .collect();
+    let file_width = column_width("File", &files);
+    let track_width = column_width("Track", &tracks);
+    let changed_width = column_width("Changed", &changes);
+    let renamed = results.iter().any(|r| r.renamed_to.is_some());
+
+    println!("{}", "Results:".bright_white().bold());
+    let mut header = format!(
+        "    {:<file_width$}  {:<track_width$}  {:>4}  {:<changed_width$}  {:<3}",
+        "File", "Track", "Conf", "Changed", "Art"
+    );
+    if renamed {
+        header.push_str("  Renamed to");
+    }
+    println!("{}", header.trim_end().bold());
+
+    for (i, result) in results.iter().enumerate() {
+        let conf = format!("{}%", (result.confidence * 100.0) as i32);
+        let prefix = format!(
+            "{:<file_width$}  {:<track_width$}  {:>4}",
+            files[i], tracks[i], conf
+        );
+        match result.status {
+            "tagged" => {
+                let mut line = format!(
+                    "{}  {:<changed_width$}  {:<3}",
+                    prefix,
+                    changes[i],
+                    if result.art_embedded { "yes" } else { "no" }
+                );
+                if let Some(to) = &result.renamed_to {
+                    let to = library_root
+                        .and_then(|root| to.strip_prefix(root).ok())
+                        .unwrap_or(to);
+                    line.push_str(&format!("  {}", to.display()));
+                }
+                println!("  {} {}", symbols::ok().bright_green(), line.trim_end());
+            }
+            "skipped" => println!(
+                "  {} {}  {}",
+                symbols::warn().bright_yellow(),
+                prefix,
+                "skipped".bright_yellow()
+            ),
+            _ => println!(
+                "  {} {}  {}",
+                symbols::fail().bright_red(),
+                prefix,
+                result.error.as_deref().unwrap_or("failed").bright_red()
+            ),
+        }
+    }
+}
+
+/// "title, artist, album +4", or "-" when nothing changed
+fn describe_changes(changed: &[String]) -> String {
+    if changed.is_empty() {
+        return "-".to_string();
+    }
+    let mut text = changed
+        .iter()
+        .take(MAX_FIELDS)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if changed.len() > MAX_FIELDS {
+        text.push_str(&format!(" +{}", changed.len() - MAX_FIELDS));
+    }
+    text
+}
+
+fn shorten(text: &str) -> String {
+    if text.chars().count() <= MAX_COLUMN {
+        return text.to_string();
+    }
+    let ellipsis = symbols::ellipsis();
+    let mut short: String = text
+        .chars()
+        .take(MAX_COLUMN - ellipsis.chars().count())
+        .collect();
+    short.push_str(ellipsis);
+    short
+}
+
+fn column_width(header: &str, values: &[String]) -> usize {
+    values
+        .iter()
+        .map(|v| v.chars().count())
+        .chain(std::iter::once(header.len()))
+        .max()
+        .unwrap_or(0)
+}
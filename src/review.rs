@@ -0,0 +1,85 @@
+// src/review.rs
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::{Input, Select};
+
+use crate::matcher::FileMatch;
+use crate::musicbrainz::Album;
+use crate::symbols;
+
+/// Ask whether to apply the matches, letting the user fix individual matches
+/// or check the release on MusicBrainz first. Returns false if the user aborted.
+pub fn confirm(matches: &mut [FileMatch], album: &Album) -> Result<bool> {
+    loop {
+        let choice = Select::new()
+            .with_prompt("Do you want to apply these tags?")
+            .items(&["Apply tags", "Edit a match", "Open on MusicBrainz", "Abort"])
+            .default(3)
+            .interact()?;
+
+        match choice {
+            0 => return Ok(true),
+            1 => edit_match(matches)?,
+            2 => crate::browser::choose_and_open(album)?,
+            _ => return Ok(false),
+        }
+    }
+}
+
+fn edit_match(matches: &mut [FileMatch]) -> Result<()> {
+    let items: Vec<String> = matches
+        .iter()
+        .map(|m| {
+            format!(
+                "{} {} {}. {} - {}",
+                m.file_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy(),
+                symbols::arrow(),
+                m.track.position,
+                m.track.artist,
+                m.track.title
+            )
+        })
+        .collect();
+
+    let index = Select::new()
+        .with_prompt("Which match?")
+        .items(&items)
+        .default(0)
+        .interact()?;
+    let track = &mut matches[index].track;
+
+    let title: String = Input::new()
+        .with_prompt("  Title")
+        .default(track.title.clone())
+        .interact_text()?;
+    let artist: String = Input::new()
+        .with_prompt("  Artist")
+        .default(track.artist.clone())
+        .interact_text()?;
+    let position: u32 = Input::new()
+        .with_prompt("  Track Number")
+        .default(track.position)
+        .interact_text()?;
+
+    if artist != track.artist {
+        // An edited artist replaces the individual MusicBrainz credits
+        track.artists = vec![artist.clone()];
+        track.artist = artist;
+    }
+    track.title = title;
+    track.position = position;
+
+    println!(
+        "  {} Track {}: {} - {}",
+        symbols::ok().bright_green(),
+        track.position,
+        track.artist.bright_white(),
+        track.title.bright_white()
+    );
+    println!();
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+// src/search.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use dialoguer::Select;
+
+use crate::musicbrainz::MusicBrainzClient;
+use crate::provider::{DatePreference, MetadataProvider, ReleaseRanking, ReleaseSummary};
+use crate::symbols;
+
+/// Print MusicBrainz releases matching an album title, with the IDs to pass to --album-id
+pub async fn run(title: &str, artist: Option<&str>, ranking: &ReleaseRanking) -> Result<()> {
+    let client = crate::config::musicbrainz_client();
+    let mut releases = client
+        .search_releases(title, artist)
+        .await
+        .context("MusicBrainz search failed")?;
+    ranking.apply(&mut releases);
+
+    if releases.is_empty() {
+        println!("{} No releases found", symbols::warn().bright_yellow());
+        return Ok(());
+    }
+
+    for release in &releases {
+        println!(
+            "{}  {} - {} {}",
+            release.id.bright_cyan(),
+            release.artist.bright_white(),
+            release.title.bright_white(),
+            format!("({})", details(release)).bright_black()
+        );
+    }
+
+    Ok(())
+}
+
+/// Let the user pick one of a release group's editions, ranked by
+/// preference. Without `ask`, the first is taken, which needs a date,
+/// country, or format preference to mean anything.
+pub async fn choose_from_release_group(
+    client: &MusicBrainzClient,
+    group_id: &str,
+    ranking: &ReleaseRanking,
+    ask: bool,
+) -> Result<String> {
+    let mut releases = client
+        .release_group_releases(group_id)
+        .await
+        .context("Failed to list the release group's releases")?;
+    let total = releases.len();
+    ranking.apply(&mut releases);
+    if releases.is_empty() {
+        anyhow::bail!(
+            "None of the release group's {} release(s) fall within the --year range",
+            total
+        );
+    }
+
+    let index = if ask {
+        let labels: Vec<String> = releases.iter().map(label).collect();
+        Select::new()
+            .with_prompt(format!(
+                "{} is a release group; tag from which release?",
+                group_id
+            ))
+            .items(&labels)
+            .default(0)
+            .interact()?
+    } else if releases.len() == 1
+        || ranking.date != DatePreference::Relevance
+        || !ranking.countries.is_empty()
+        || !ranking.formats.is_empty()
+    {
+        0
+    } else {
+        anyhow::bail!(
+            "{} is a release group with {} releases; pick one without --yes, or rank them with --prefer-original, --prefer-latest, --country, or --media",
+            group_id,
+            releases.len()
+        );
+    };
+
+    let release = &releases[index];
+    println!(
+        "{} Using release {} {}",
+        symbols::arrow().bright_cyan(),
+        release.id,
+        format!("({})", details(release)).bright_black()
+    );
+    println!();
+    Ok(release.id.clone())
+}
+
+/// "Artist - Title (12 tracks, CD, 1997-05-21, GB)", for choosing a release
+pub fn label(release: &ReleaseSummary) -> String {
+    format!(
+        "{} - {} ({})",
+        release.artist,
+        release.title,
+        details(release)
+    )
+}
+
+fn details(release: &ReleaseSummary) -> String {
+    let mut details = vec![format!("{} tracks", release.track_count)];
+    details.extend(release.formats.iter().cloned());
+    details.extend(release.date.clone());
+    details.extend(release.country.clone());
+    details.join(", ")
+}
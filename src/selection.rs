@@ -0,0 +1,57 @@
+// src/selection.rs
+use anyhow::Result;
+use std::ops::RangeInclusive;
+
+use crate::musicbrainz::Album;
+
+/// Subset of a release's tracks to match, from `--tracks` and `--disc`
+pub struct TrackSelection {
+    ranges: Vec<RangeInclusive<u32>>,
+    disc: Option<u32>,
+}
+
+impl TrackSelection {
+    /// Parse a list like `1-6,9`; `None` means every track
+    pub fn new(tracks: Option<&str>, disc: Option<u32>) -> Result<Self> {
+        let mut ranges = Vec::new();
+
+        for part in tracks.unwrap_or_default().split(',') {
+            let part = part.trim();
+            if part.is_empty() {
+                continue;
+            }
+            let (start, end) = part.split_once('-').unwrap_or((part, part));
+            let parse = |n: &str| {
+                n.trim().parse::<u32>().map_err(|_| {
+                    anyhow::anyhow!("Invalid track range '{}' (expected e.g. 1-6,9)", part)
+                })
+            };
+            let (start, end) = (parse(start)?, parse(end)?);
+            if start == 0 || start > end {
+                anyhow::bail!("Invalid track range '{}'", part);
+            }
+            ranges.push(start..=end);
+        }
+
+        Ok(Self { ranges, disc })
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.ranges.is_empty() && self.disc.is_none()
+    }
+
+    /// Drop tracks outside the selection. Track numbers are positions on the
+    /// disc, so `--disc 2 --tracks 1-3` means the first three tracks of disc 2.
+    pub fn apply(&self, album: &mut Album) -> Result<()> {
+        album.tracks.retain(|track| {
+            self.disc.is_none_or(|disc| track.disc_number == disc)
+                && (self.ranges.is_empty()
+                    || self.ranges.iter().any(|r| r.contains(&track.position)))
+        });
+
+        if album.tracks.is_empty() {
+            anyhow::bail!("No tracks on this release match the --tracks/--disc selection");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,409 @@
+// src/serve.rs
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::error::Error as MbError;
+use crate::events::Event;
+use crate::inputs;
+use crate::matcher::{match_files, FileMatch};
+use crate::musicbrainz::{Album, MusicBrainzClient};
+use crate::profile::TagSettings;
+use crate::provider::MetadataProvider;
+use crate::symbols;
+use crate::CancellationToken;
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// Holds the token clients send as their first line, readable only by the user
+const TOKEN_FILE: &str = "serve-token";
+
+#[derive(Deserialize)]
+struct LookupParams {
+    release_id: String,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    title: String,
+    artist: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MatchParams {
+    /// Fetched from MusicBrainz unless `album` is given
+    release_id: Option<String>,
+    album: Option<Album>,
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct TagParams {
+    album: Album,
+    matches: Vec<FileMatch>,
+    #[serde(default = "default_true")]
+    cover_art: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+    kind: Option<&'static str>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            kind: None,
+        }
+    }
+}
+
+impl From<MbError> for RpcError {
+    fn from(e: MbError) -> Self {
+        let kind = match &e {
+            MbError::NetworkError(_) => "network",
+            MbError::NotFound(_) => "not_found",
+            MbError::RateLimited(_) => "rate_limited",
+            MbError::ApiError { .. } => "api",
+            MbError::ParseError { .. } => "parse",
+            MbError::ReleaseCount(_) => "release_count",
+            MbError::ImageError(_) | MbError::NotAnImage(_) => "image",
+            MbError::TagWriteError { .. } => "tag_write",
+            MbError::UnsupportedFormat(_) => "unsupported_format",
+            MbError::NoAudio(_) => "no_audio",
+            MbError::InvalidCue(_) => "invalid_cue",
+            MbError::InvalidPattern { .. } => "invalid_pattern",
+            MbError::Cancelled => "cancelled",
+            #[cfg(feature = "fixtures")]
+            MbError::MissingFixture(_) => "missing_fixture",
+            MbError::Io { .. } => "io",
+        };
+        Self {
+            code: SERVER_ERROR,
+            message: format!("{:#}", anyhow::Error::from(e)),
+            kind: Some(kind),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(SERVER_ERROR, format!("{:#}", e))
+    }
+}
+
+/// Serve lookup, search, match, and tag requests as line-delimited JSON-RPC 2.0
+pub async fn run(listen: SocketAddr, settings: TagSettings) -> Result<()> {
+    if !listen.ip().is_loopback() {
+        println!(
+            "{} Listening on a non-loopback address; anyone who can reach it can tag files on this machine",
+            symbols::warn().bright_yellow()
+        );
+    }
+
+    // Any local process, including a web page posting to localhost, can
+    // connect; only those that can read the token file get to send requests
+    let token = new_token()?;
+    let token_path = dirs::data_dir()
+        .map(|dir| dir.join("musictagger_rs").join(TOKEN_FILE))
+        .context("Could not determine the data directory for the token file")?;
+    write_token(&token_path, &token)
+        .with_context(|| format!("Failed to write {}", token_path.display()))?;
+
+    let listener = TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("Failed to listen on {}", listen))?;
+    println!(
+        "{} Serving JSON-RPC on {} (one request per line)",
+        symbols::ok().bright_green(),
+        listen.to_string().bright_cyan()
+    );
+    println!(
+        "  Clients first send the token in {}",
+        token_path.display().to_string().bright_cyan()
+    );
+
+    let client = crate::config::musicbrainz_client();
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let client = client.clone();
+        let token = token.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &token, client, settings).await {
+                println!(
+                    "{} Connection from {} failed: {}",
+                    symbols::fail().bright_red(),
+                    peer,
+                    e
+                );
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    token: &str,
+    client: MusicBrainzClient,
+    settings: TagSettings,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let first = lines.next_line().await?.unwrap_or_default();
+    if first.trim() != token {
+        let response = error_response(
+            Value::Null,
+            RpcError::new(
+                INVALID_REQUEST,
+                "Send the token from the server's token file first",
+            ),
+        );
+        writer
+            .write_all(format!("{}\n", response).as_bytes())
+            .await?;
+        anyhow::bail!("it did not send the token");
+    }
+
+    // Responses and event notifications share the socket through one writer
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let writer_task = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            let mut line = message.to_string();
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if is_http_request_line(&line) {
+            anyhow::bail!("it sent an HTTP request");
+        }
+        let tx = tx.clone();
+        let client = client.clone();
+        tokio::spawn(async move {
+            if let Some(response) = handle_request(&line, &client, settings, &tx).await {
+                let _ = tx.send(response);
+            }
+        });
+    }
+
+    drop(tx);
+    let _ = writer_task.await;
+    Ok(())
+}
+
+/// "POST / HTTP/1.1" and the like: a browser, not a JSON-RPC client
+fn is_http_request_line(line: &str) -> bool {
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let _target = parts.next();
+    let version = parts.next().unwrap_or_default();
+    !method.is_empty()
+        && method.chars().all(|c| c.is_ascii_uppercase())
+        && version.starts_with("HTTP/")
+}
+
+fn new_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).context("Failed to generate a token")?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Write the token where only the user can read it
+fn write_token(path: &Path, token: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    // The mode only applies to new files
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::io::Write::write_all(&mut file, token.as_bytes())
+}
+
+/// Handle one request line. Returns `None` for notifications (no `id`).
+async fn handle_request(
+    line: &str,
+    client: &MusicBrainzClient,
+    settings: TagSettings,
+    tx: &UnboundedSender<Value>,
+) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            ))
+        }
+    };
+
+    let id = request.get("id").cloned();
+    let result = match request.get("method").and_then(Value::as_str) {
+        Some(method) => {
+            let params = request.get("params").cloned().unwrap_or(Value::Null);
+            dispatch(method, params, client, settings, tx).await
+        }
+        None => Err(RpcError::new(INVALID_REQUEST, "Missing method")),
+    };
+
+    let id = id?;
+    Some(match result {
+        Ok(value) => json!({ "jsonrpc": "2.0", "id": id, "result": value }),
+        Err(e) => error_response(id, e),
+    })
+}
+
+async fn dispatch(
+    method: &str,
+    params: Value,
+    client: &MusicBrainzClient,
+    settings: TagSettings,
+    tx: &UnboundedSender<Value>,
+) -> Result<Value, RpcError> {
+    // Events are forwarded as `event` notifications while the request runs
+    let events = {
+        let tx = tx.clone();
+        move |event: Event| {
+            let _ = tx.send(json!({ "jsonrpc": "2.0", "method": "event", "params": event }));
+        }
+    };
+
+    match method {
+        "lookup" => {
+            let params: LookupParams = parse_params(params)?;
+            let album = client.get_release(&params.release_id).await?;
+            Ok(to_value(&album))
+        }
+        "search" => {
+            let params: SearchParams = parse_params(params)?;
+            let releases = client
+                .search_releases(&params.title, params.artist.as_deref())
+                .await?;
+            Ok(to_value(&releases))
+        }
+        "match" => {
+            let params: MatchParams = parse_params(params)?;
+            let album = match (params.album, params.release_id) {
+                (Some(album), _) => album,
+                (None, Some(release_id)) => client.get_release(&release_id).await?,
+                (None, None) => {
+                    return Err(RpcError::new(
+                        INVALID_PARAMS,
+                        "Either release_id or album is required",
+                    ))
+                }
+            };
+            let inputs = inputs::resolve(&params.paths, &[], 3)?;
+            let report =
+                match_files(&inputs.files, &album, &events, &CancellationToken::new()).await?;
+            Ok(json!({
+                "album": album,
+                "matches": report.matches,
+                "low_confidence": report.low_confidence,
+                "unmatched_files": report.unmatched_files,
+                "unmatched_tracks": report.unmatched_tracks,
+                "duplicates": report.duplicates,
+            }))
+        }
+        "tag" => {
+            let params: TagParams = parse_params(params)?;
+            let cover_art = if params.cover_art {
+                match client
+                    .get_cover_art(&params.album.id, settings.max_art_size)
+                    .await
+                {
+                    Ok(art) => Some(art),
+                    Err(e) => {
+                        events(Event::Warning {
+                            message: format!("Could not fetch cover art: {}", e),
+                        });
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            let cover_art = cover_art.and_then(|art| {
+                match crate::art::fit_to_bytes(&art, settings.max_embed_bytes) {
+                    Ok((Some(fitted), _)) => Some(fitted),
+                    _ => {
+                        events(Event::Warning {
+                            message: format!(
+                                "Cover art ({} bytes) can't be made to fit {} bytes; not embedded",
+                                art.len(),
+                                settings.max_embed_bytes
+                            ),
+                        });
+                        None
+                    }
+                }
+            });
+
+            let tagged = params.matches.len();
+            tokio::task::spawn_blocking(move || {
+                crate::tagger::tag_files(
+                    &params.matches,
+                    &params.album,
+                    crate::tagger::Artwork::cover(cover_art.as_deref()),
+                    &settings,
+                    &events,
+                )
+            })
+            .await
+            .map_err(|e| RpcError::new(SERVER_ERROR, format!("Tagging task failed: {}", e)))??;
+            Ok(json!({ "tagged": tagged }))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method: {}", method),
+        )),
+    }
+}
+
+fn parse_params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value<T: serde::Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    let mut body = json!({ "code": error.code, "message": error.message });
+    if let Some(kind) = error.kind {
+        body["data"] = json!({ "kind": kind });
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": body })
+}
@@ -0,0 +1,246 @@
+// src/session_log.rs
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::events::{Event, EventSink};
+use crate::musicbrainz::{Album, Track};
+use crate::progress::JsonLines;
+use crate::results::FileResult;
+
+/// Appended to in the album folder with `--session-log album`
+const ALBUM_LOG_FILE: &str = "tagging.log";
+
+/// Logs kept in the data directory; older ones are removed
+const MAX_DATA_LOGS: usize = 100;
+
+/// Options whose values may hold secrets, logged as `<redacted>`
+const SECRET_OPTIONS: &[&str] = &["--webhook"];
+
+/// Where the log of a tagging run goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Destination {
+    /// One file per run under the data directory, keeping the latest 100
+    Data,
+    /// Appended to tagging.log in the album folder
+    Album,
+    #[default]
+    Off,
+}
+
+/// Keeps the warnings reported during a run for the log, and passes every
+/// event on to `--progress-json` when it is on
+pub struct Recorder {
+    json: bool,
+    warnings: Mutex<Vec<String>>,
+}
+
+impl Recorder {
+    pub fn new(json: bool) -> Self {
+        Recorder {
+            json,
+            warnings: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        self.warnings.lock().map(|w| w.clone()).unwrap_or_default()
+    }
+}
+
+impl EventSink for Recorder {
+    fn emit(&self, event: Event) {
+        if let Event::Warning { message } = &event {
+            if let Ok(mut warnings) = self.warnings.lock() {
+                warnings.push(message.clone());
+            }
+        }
+        if self.json {
+            JsonLines.emit(event);
+        }
+    }
+}
+
+/// What happened in one run, as written to the log
+pub struct Session<'a> {
+    pub album: &'a Album,
+    /// The folder that was tagged
+    pub source: &'a Path,
+    pub results: &'a [FileResult],
+    pub unmatched_files: &'a [PathBuf],
+    pub unmatched_tracks: &'a [Track],
+    pub recorder: &'a Recorder,
+}
+
+/// Write the log to `destination`; `album_dir` is where the files ended up.
+/// Returns the log's path, or `None` when logging is off.
+pub fn write(
+    destination: Destination,
+    session: &Session,
+    album_dir: &Path,
+) -> Result<Option<PathBuf>> {
+    let (path, append) = match destination {
+        Destination::Off => return Ok(None),
+        Destination::Album => (album_dir.join(ALBUM_LOG_FILE), true),
+        Destination::Data => {
+            let dir = dirs::data_dir()
+                .context("Could not determine the data directory")?
+                .join("musictagger_rs")
+                .join("sessions");
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            let name = format!(
+                "{}-{}.log",
+                chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+                session.album.id
+            );
+            prune(&dir, MAX_DATA_LOGS - 1);
+            (dir.join(name), false)
+        }
+    };
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut text = render(session);
+    if append && file.metadata().map(|m| m.len() > 0).unwrap_or(false) {
+        text.insert(0, '\n');
+    }
+    file.write_all(text.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(Some(path))
+}
+
+/// Remove the oldest logs in `dir` so at most `keep` remain. Names start
+/// with the run's time, so they sort oldest first.
+fn prune(dir: &Path, keep: usize) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut logs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    logs.sort();
+    let excess = logs.len().saturating_sub(keep);
+    for log in &logs[..excess] {
+        let _ = std::fs::remove_file(log);
+    }
+}
+
+/// The command line as given, with the values of secret options hidden
+fn redacted_args() -> Vec<String> {
+    let mut args = Vec::new();
+    let mut hide_next = false;
+    for arg in std::env::args().skip(1) {
+        if std::mem::take(&mut hide_next) {
+            args.push("<redacted>".to_string());
+        } else if let Some((name, _)) = arg
+            .split_once('=')
+            .filter(|(name, _)| SECRET_OPTIONS.contains(name))
+        {
+            args.push(format!("{}=<redacted>", name));
+        } else {
+            hide_next = SECRET_OPTIONS.contains(&arg.as_str());
+            args.push(arg);
+        }
+    }
+    args
+}
+
+fn render(session: &Session) -> String {
+    let album = session.album;
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "== {} · musictagger_rs {} ==",
+        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+        env!("CARGO_PKG_VERSION")
+    );
+    let _ = writeln!(out, "Release: {} - {}", album.artist, album.title);
+    let _ = writeln!(
+        out,
+        "         {}",
+        crate::browser::entity_url("release", &album.id)
+    );
+    let _ = writeln!(out, "Folder:  {}", session.source.display());
+    let _ = writeln!(out, "Options: {}", redacted_args().join(" "));
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Files:");
+    for result in session.results {
+        let name = result
+            .file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let _ = writeln!(
+            out,
+            "  {} -> {} ({:.0}%): {}",
+            name,
+            result.track,
+            result.confidence * 100.0,
+            result.status
+        );
+        match result.status {
+            "tagged" => {
+                let changed = if result.changed.is_empty() {
+                    "nothing".to_string()
+                } else {
+                    result.changed.join(", ")
+                };
+                let _ = writeln!(out, "      changed: {}", changed);
+                if result.art_embedded {
+                    let _ = writeln!(out, "      art embedded");
+                }
+            }
+            "failed" => {
+                let _ = writeln!(
+                    out,
+                    "      error: {}",
+                    result.error.as_deref().unwrap_or("unknown")
+                );
+            }
+            _ => {}
+        }
+        if let Some(to) = &result.renamed_to {
+            let _ = writeln!(out, "      moved to {}", to.display());
+        }
+    }
+
+    if !session.unmatched_files.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "Unmatched files:");
+        for file in session.unmatched_files {
+            let _ = writeln!(out, "  {}", file.display());
+        }
+    }
+    if !session.unmatched_tracks.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "Unmatched tracks:");
+        for track in session.unmatched_tracks {
+            let _ = writeln!(
+                out,
+                "  {}-{:02} {}",
+                track.disc_number, track.position, track.title
+            );
+        }
+    }
+    let warnings = session.recorder.warnings();
+    if !warnings.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "Warnings:");
+        for warning in warnings {
+            let _ = writeln!(out, "  {}", warning);
+        }
+    }
+    out
+}
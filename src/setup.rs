@@ -0,0 +1,183 @@
+// src/setup.rs
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::{Confirm, Input};
+use std::path::PathBuf;
+
+use crate::config::{self, Config};
+use crate::filename::FilenameRules;
+use crate::organize::{self, TemplateValues};
+use crate::symbols;
+
+/// Art size offered when none is configured; same as the default profile
+const DEFAULT_ART_SIZE: u32 = 1200;
+
+/// Ask for the preferences kept in the config file and save them, starting
+/// from `current`. On a first run the user may skip, which saves an empty
+/// config so they aren't asked again.
+pub fn run(current: Option<Config>) -> Result<Config> {
+    let first_run = current.is_none();
+    let current = current.unwrap_or_default();
+    let path = config::path()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+
+    if first_run {
+        println!(
+            "{}",
+            "Welcome to MusicBrainz MP3 Tagger!".bright_cyan().bold()
+        );
+        println!(
+            "A few questions set up your defaults; they are saved to {} and can be changed with `musictagger_rs setup`.",
+            path
+        );
+        println!();
+        let go = Confirm::new()
+            .with_prompt("Set up now?")
+            .default(true)
+            .interact()?;
+        if !go {
+            config::save(&current)?;
+            println!("Skipped; run `musictagger_rs setup` any time.");
+            println!();
+            return Ok(current);
+        }
+    } else {
+        println!("{}", "Setup".bright_cyan().bold());
+        println!("Editing {}", path);
+    }
+    println!();
+
+    println!(
+        "{}",
+        "MusicBrainz asks apps to send a contact address with their requests.".bright_black()
+    );
+    let contact: String = Input::new()
+        .with_prompt("Contact email (leave empty to skip)")
+        .default(current.contact.clone().unwrap_or_default())
+        .allow_empty(true)
+        .interact_text()?;
+
+    let art_size: u32 = Input::new()
+        .with_prompt("Largest cover art to download, in pixels per side")
+        .default(current.art_size.unwrap_or(DEFAULT_ART_SIZE))
+        .validate_with(|size: &u32| {
+            if *size >= 100 {
+                Ok(())
+            } else {
+                Err("Use at least 100 pixels")
+            }
+        })
+        .interact_text()?;
+
+    let min_art_size: u32 = Input::new()
+        .with_prompt("Smallest cover art worth embedding, in pixels per side (0 for no minimum)")
+        .default(current.min_art_size.unwrap_or(0))
+        .interact_text()?;
+
+    println!(
+        "{}",
+        "Fields: {albumartist} {album} {year} {disc} {track} {totaltracks} {title} {artist} {genre}"
+            .bright_black()
+    );
+    let template: String = Input::new()
+        .with_prompt("Rename template for --organize")
+        .default(
+            current
+                .template
+                .clone()
+                .unwrap_or_else(|| organize::DEFAULT_TEMPLATE.to_string()),
+        )
+        .validate_with(|template: &String| {
+            organize::render_template(template, &sample_values(), &FilenameRules::default())
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })
+        .interact_text()?;
+    let example =
+        organize::render_template(&template, &sample_values(), &FilenameRules::default())?;
+    println!(
+        "  {} {}.mp3",
+        "e.g.".bright_black(),
+        example.display().to_string().bright_white()
+    );
+
+    let library: String = Input::new()
+        .with_prompt("Library root for --organize (leave empty to skip)")
+        .default(
+            current
+                .library
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+        )
+        .allow_empty(true)
+        .interact_text()?;
+
+    let countries: String = Input::new()
+        .with_prompt(
+            "Release countries to list first, most preferred first (e.g. US, GB, XW; leave empty to skip)",
+        )
+        .default(current.countries.join(", "))
+        .allow_empty(true)
+        .interact_text()?;
+
+    let formats: String = Input::new()
+        .with_prompt(
+            "Media formats to list first, most preferred first (e.g. Digital Media, CD; leave empty to skip)",
+        )
+        .default(current.formats.join(", "))
+        .allow_empty(true)
+        .interact_text()?;
+
+    let config = Config {
+        contact: non_empty(contact),
+        art_size: Some(art_size),
+        min_art_size: Some(min_art_size).filter(|size| *size > 0),
+        small_art: current.small_art,
+        template: Some(template),
+        library: non_empty(library).map(PathBuf::from),
+        countries: countries
+            .split([',', ' '])
+            .filter(|c| !c.is_empty())
+            .map(|c| c.to_uppercase())
+            .collect(),
+        // Format names have spaces ("Digital Media")
+        formats: formats
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(str::to_string)
+            .collect(),
+    };
+    let saved = config::save(&config)?;
+    println!();
+    println!(
+        "{} Saved settings to {}",
+        symbols::ok().bright_green(),
+        saved.display()
+    );
+    println!();
+    Ok(config)
+}
+
+fn non_empty(value: String) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn sample_values() -> TemplateValues {
+    TemplateValues {
+        title: "Airbag".to_string(),
+        artist: "Radiohead".to_string(),
+        album: "OK Computer".to_string(),
+        album_artist: "Radiohead".to_string(),
+        year: Some(1997),
+        track: Some(1),
+        total_tracks: Some(12),
+        disc_tracks: Some(12),
+        disc: Some(1),
+        total_discs: Some(1),
+        genre: Some("Alternative Rock".to_string()),
+    }
+}
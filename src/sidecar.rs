@@ -0,0 +1,60 @@
+// src/sidecar.rs
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::matcher::FileMatch;
+use crate::musicbrainz::Album;
+use crate::profile::TagSettings;
+use crate::tagger::TagData;
+
+/// What a sidecar holds: the tags the file got (or would get), and how sure
+/// the match was
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    file: String,
+    confidence: f64,
+    #[serde(flatten)]
+    tags: &'a TagData,
+}
+
+/// Where the sidecar for `file` goes: `<stem>.json` next to it, or with
+/// `dir`, at the same place under `dir` as `file` is under `root`
+pub fn path_for(file: &Path, root: &Path, dir: Option<&Path>) -> PathBuf {
+    let sidecar = file.with_extension("json");
+    match dir {
+        None => sidecar,
+        Some(dir) => match sidecar.strip_prefix(root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => dir.join(relative),
+            _ => dir.join(sidecar.file_name().unwrap_or_default()),
+        },
+    }
+}
+
+/// Write the sidecar for one match. `file` is where the audio is now, in
+/// case it was moved since matching.
+pub fn write(
+    file_match: &FileMatch,
+    file: &Path,
+    sidecar_path: &Path,
+    album: &Album,
+    settings: &TagSettings,
+) -> Result<()> {
+    let tags = TagData::new(&file_match.track, album, settings);
+    let sidecar = Sidecar {
+        file: file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        confidence: file_match.confidence,
+        tags: &tags,
+    };
+    if let Some(dir) = sidecar_path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let json = serde_json::to_string_pretty(&sidecar).context("Failed to serialize sidecar")?;
+    std::fs::write(sidecar_path, json + "\n")
+        .with_context(|| format!("Failed to write {}", sidecar_path.display()))
+}
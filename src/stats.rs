@@ -0,0 +1,247 @@
+// src/stats.rs
+use anyhow::Result;
+use colored::Colorize;
+use id3::{Tag, TagLike, Version};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::audio;
+use crate::tagger::extended_text;
+
+/// Extensions counted as audio when tallying formats
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "flac", "ogg", "opus", "m4a", "aac", "wav", "aiff", "wma", "ape", "wv",
+];
+
+/// How many genres and years to list before summarizing the rest
+const TOP_N: usize = 15;
+
+#[derive(Default)]
+struct Stats {
+    formats: BTreeMap<String, usize>,
+    mp3_files: usize,
+    versions: BTreeMap<&'static str, usize>,
+    with_album_id: usize,
+    with_recording_id: usize,
+    with_art: usize,
+    with_lyrics: usize,
+    bitrates: BTreeMap<usize, usize>,
+    years: BTreeMap<String, usize>,
+    genres: BTreeMap<String, usize>,
+}
+
+const BITRATE_BUCKETS: &[(u32, &str)] = &[
+    (128, "< 128 kbps"),
+    (192, "128-191 kbps"),
+    (256, "192-255 kbps"),
+    (320, "256-319 kbps"),
+    (u32::MAX, "320+ kbps"),
+];
+
+/// Scan a tree and print counts by format, tag version, MBIDs, art, lyrics,
+/// bitrate, year, and genre
+pub fn run(path: &Path) -> Result<()> {
+    if !path.exists() {
+        anyhow::bail!("Path does not exist: {}", path.display());
+    }
+
+    let mut stats = Stats::default();
+    let mut mp3_files = Vec::new();
+
+    let pb = crate::progress::spinner("Scanning");
+    for entry in WalkDir::new(path)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let Some(ext) = entry
+            .path()
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+        else {
+            continue;
+        };
+        if !AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+            continue;
+        }
+        pb.inc(1);
+        if ext == "mp3" {
+            mp3_files.push(entry.path().to_path_buf());
+        }
+        *stats.formats.entry(ext).or_default() += 1;
+    }
+    pb.finish_and_clear();
+
+    if stats.formats.is_empty() {
+        anyhow::bail!("No audio files found in {}", path.display());
+    }
+
+    stats.mp3_files = mp3_files.len();
+    let pb = crate::progress::bar(mp3_files.len(), "Reading tags");
+    for file in &mp3_files {
+        read_tags(file, &mut stats);
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    for bitrate in probe_bitrates(&mp3_files).into_iter().flatten() {
+        let bucket = BITRATE_BUCKETS
+            .iter()
+            .position(|&(below, _)| bitrate < below)
+            .unwrap_or(BITRATE_BUCKETS.len() - 1);
+        *stats.bitrates.entry(bucket).or_default() += 1;
+    }
+
+    print(&stats);
+    Ok(())
+}
+
+fn read_tags(file: &Path, stats: &mut Stats) {
+    let Ok(tag) = Tag::read_from_path(file) else {
+        *stats.versions.entry("untagged").or_default() += 1;
+        return;
+    };
+
+    let version = match tag.version() {
+        Version::Id3v22 => "ID3v2.2",
+        Version::Id3v23 => "ID3v2.3",
+        Version::Id3v24 => "ID3v2.4",
+    };
+    *stats.versions.entry(version).or_default() += 1;
+
+    if extended_text(&tag, "MusicBrainz Album Id").is_some() {
+        stats.with_album_id += 1;
+    }
+    if extended_text(&tag, "MusicBrainz Recording Id").is_some() {
+        stats.with_recording_id += 1;
+    }
+    if tag.pictures().next().is_some() {
+        stats.with_art += 1;
+    }
+    if tag.lyrics().any(|l| !l.text.trim().is_empty()) {
+        stats.with_lyrics += 1;
+    }
+
+    let year = tag
+        .year()
+        .or_else(|| tag.date_released().map(|d| d.year))
+        .or_else(|| tag.date_recorded().map(|d| d.year));
+    let year = year
+        .map(|y| y.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    *stats.years.entry(year).or_default() += 1;
+
+    let genres = tag.genres().unwrap_or_default();
+    if genres.is_empty() {
+        *stats.genres.entry("unknown".to_string()).or_default() += 1;
+    }
+    for genre in genres {
+        *stats.genres.entry(genre.to_string()).or_default() += 1;
+    }
+}
+
+/// Average bitrate of every file, probed in parallel since it reads every frame
+fn probe_bitrates(files: &[PathBuf]) -> Vec<Option<u32>> {
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let chunk_size = files.len().div_ceil(threads).max(1);
+
+    let pb = crate::progress::bar(files.len(), "Reading audio");
+    let bitrates = std::thread::scope(|scope| {
+        let handles: Vec<_> = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let pb = &pb;
+                let handle = scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|f| {
+                            let bitrate = audio::probe(f).and_then(|info| info.bitrate_kbps);
+                            pb.inc(1);
+                            bitrate
+                        })
+                        .collect::<Vec<_>>()
+                });
+                (chunk.len(), handle)
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|(len, handle)| handle.join().unwrap_or_else(|_| vec![None; len]))
+            .collect()
+    });
+    pb.finish_and_clear();
+    bitrates
+}
+
+fn print(stats: &Stats) {
+    let total: usize = stats.formats.values().sum();
+
+    println!("{}", "Library Stats".bright_cyan().bold());
+
+    section("Formats");
+    let mut formats: Vec<_> = stats.formats.iter().collect();
+    formats.sort_by_key(|(_, &count)| std::cmp::Reverse(count));
+    for (format, &count) in formats {
+        row(&format.to_uppercase(), count, total);
+    }
+
+    if stats.mp3_files == 0 {
+        return;
+    }
+    let mp3s = stats.mp3_files;
+
+    section("Tag versions (MP3)");
+    for (version, &count) in &stats.versions {
+        row(version, count, mp3s);
+    }
+
+    section("Coverage (MP3)");
+    row("MusicBrainz album ID", stats.with_album_id, mp3s);
+    row("MusicBrainz recording ID", stats.with_recording_id, mp3s);
+    row("Embedded cover art", stats.with_art, mp3s);
+    row("Lyrics", stats.with_lyrics, mp3s);
+
+    section("Bitrate (MP3)");
+    for (bucket, &count) in &stats.bitrates {
+        row(BITRATE_BUCKETS[*bucket].1, count, mp3s);
+    }
+
+    section("Years (MP3)");
+    top(&stats.years, mp3s);
+
+    section("Genres (MP3)");
+    top(&stats.genres, mp3s);
+}
+
+fn section(title: &str) {
+    println!();
+    println!("{}", title.bright_white().bold());
+}
+
+fn row(label: &str, count: usize, total: usize) {
+    let percent = if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    };
+    println!("  {:<26} {:>7}  {:>5.1}%", label, count, percent);
+}
+
+/// The most common entries, with the remainder folded into one line
+fn top(counts: &BTreeMap<String, usize>, total: usize) {
+    let mut entries: Vec<_> = counts.iter().collect();
+    entries.sort_by_key(|(_, &count)| std::cmp::Reverse(count));
+
+    for (label, &count) in entries.iter().take(TOP_N) {
+        row(label, count, total);
+    }
+    if entries.len() > TOP_N {
+        let rest: usize = entries[TOP_N..].iter().map(|(_, &count)| count).sum();
+        row(&format!("{} others", entries.len() - TOP_N), rest, total);
+    }
+}
@@ -0,0 +1,91 @@
+// src/symbols.rs
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Use ASCII stand-ins for the status glyphs, on consoles that would show
+/// them as mojibake ("âœ“")
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+/// Pick Unicode or ASCII glyphs for the rest of the run. On Windows this also
+/// switches the console to UTF-8, falling back to ASCII if that isn't possible.
+pub fn init(force_ascii: bool) {
+    let ascii =
+        force_ascii || std::env::var_os("MUSICTAGGER_ASCII").is_some() || !supports_unicode();
+    ASCII.store(ascii, Ordering::Relaxed);
+}
+
+#[cfg(windows)]
+fn supports_unicode() -> bool {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetConsoleOutputCP(code_page: u32) -> i32;
+    }
+    const CP_UTF8: u32 = 65001;
+
+    // SAFETY: takes a plain integer and only changes this process's console
+    let utf8_console = unsafe { SetConsoleOutputCP(CP_UTF8) } != 0;
+    // Windows Terminal and VS Code handle UTF-8 even when output is redirected
+    utf8_console
+        || std::env::var_os("WT_SESSION").is_some()
+        || std::env::var_os("TERM_PROGRAM").is_some()
+}
+
+#[cfg(not(windows))]
+fn supports_unicode() -> bool {
+    // The first locale variable that is set decides, as in setlocale(3)
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty());
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_uppercase();
+            locale.contains("UTF-8") || locale.contains("UTF8")
+        }
+        // Unset on macOS and in most containers, which are UTF-8 anyway
+        None => true,
+    }
+}
+
+fn pick(unicode: &'static str, ascii: &'static str) -> &'static str {
+    if ASCII.load(Ordering::Relaxed) {
+        ascii
+    } else {
+        unicode
+    }
+}
+
+pub fn ok() -> &'static str {
+    pick("✓", "+")
+}
+
+pub fn warn() -> &'static str {
+    pick("⚠", "!")
+}
+
+pub fn fail() -> &'static str {
+    pick("✗", "x")
+}
+
+pub fn info() -> &'static str {
+    pick("ℹ", "i")
+}
+
+pub fn arrow() -> &'static str {
+    pick("→", "->")
+}
+
+pub fn star() -> &'static str {
+    pick("★", "*")
+}
+
+pub fn note() -> &'static str {
+    pick("♪", "-")
+}
+
+pub fn folder() -> &'static str {
+    pick("📁", "+")
+}
+
+pub fn ellipsis() -> &'static str {
+    pick("…", "...")
+}
@@ -1,105 +1,384 @@
 // src/tagger.rs
-use anyhow::{Context, Result};
-use chrono::Datelike;
-use id3::{frame, Tag, TagLike, Timestamp, Version};
-use indicatif::{ProgressBar, ProgressStyle};
+use id3::{frame, Content, Frame, Tag, TagLike, Timestamp, Version};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 
-use crate::manual_mode::ManualAlbum;
+use crate::error::{Error, Result};
+use crate::events::{Event, EventSink};
 use crate::matcher::FileMatch;
-use crate::musicbrainz::Album;
-
-pub fn tag_files(matches: &[FileMatch], album: &Album, cover_art: Option<Vec<u8>>) -> Result<()> {
-    let pb = ProgressBar::new(matches.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
-            .unwrap()
-            .progress_chars("=>-"),
-    );
+use crate::musicbrainz::{Album, RecordingInfo, Track};
+use crate::profile::{MultiValue, TagSettings};
+
+/// Pictures to embed with the tags
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Artwork<'a> {
+    /// The front cover
+    pub cover: Option<&'a [u8]>,
+    /// Pictures of other types (back cover, media), each replacing any
+    /// picture of its type
+    pub extra: &'a [(frame::PictureType, Vec<u8>)],
+}
 
-    for file_match in matches {
-        pb.set_message(format!(
-            "{}",
-            file_match.file_path.file_name().unwrap().to_string_lossy()
-        ));
+impl<'a> Artwork<'a> {
+    /// Just a front cover, if there is one
+    pub fn cover(cover: Option<&'a [u8]>) -> Self {
+        Self { cover, extra: &[] }
+    }
+}
 
+/// Write tags for every match, stopping at the first file that fails
+pub fn tag_files(
+    matches: &[FileMatch],
+    album: &Album,
+    art: Artwork,
+    settings: &TagSettings,
+    events: &dyn EventSink,
+) -> Result<()> {
+    for file_match in matches {
         write_tags(
             &file_match.file_path,
             &file_match.track,
             album,
-            cover_art.as_deref(),
-        )
-        .with_context(|| format!("Failed to write tags to {}", file_match.file_path.display()))?;
+            art,
+            settings,
+        )?;
+        events.emit(Event::TagWritten {
+            file: file_match.file_path.clone(),
+        });
+    }
+
+    Ok(())
+}
 
-        pb.inc(1);
+/// Write tags for every match, carrying on past files that fail. Returns the
+/// failed files with their errors, in match order.
+pub fn tag_files_keep_going(
+    matches: &[FileMatch],
+    album: &Album,
+    art: Artwork,
+    settings: &TagSettings,
+    events: &dyn EventSink,
+) -> Vec<(PathBuf, Error)> {
+    let mut failures = Vec::new();
+    for file_match in matches {
+        let file = file_match.file_path.clone();
+        match write_tags(&file, &file_match.track, album, art, settings) {
+            Ok(()) => events.emit(Event::TagWritten { file }),
+            Err(e) => {
+                events.emit(Event::TagFailed {
+                    file: file.clone(),
+                    error: e.to_string(),
+                });
+                failures.push((file, e));
+            }
+        }
     }
+    failures
+}
+
+/// Format-agnostic tag values for one file, built from a release and track.
+/// Which optional fields are present already reflects the user's settings.
+#[derive(Debug, Clone, Serialize)]
+pub struct TagData {
+    pub title: String,
+    /// Primary credited artist
+    pub artist: String,
+    /// Every credited artist, for formats that store several values
+    pub artists: Vec<String>,
+    pub album: String,
+    pub album_artist: String,
+    pub album_artist_sort: Option<String>,
+    pub track_number: u32,
+    pub total_tracks: u32,
+    /// Disc number and total, only on multi-disc releases
+    pub disc: Option<(u32, u32)>,
+    pub disc_subtitle: Option<String>,
+    pub genres: Vec<String>,
+    /// Release date as `YYYY`, `YYYY-MM`, or `YYYY-MM-DD`
+    pub date: Option<String>,
+    pub compilation: bool,
+    pub album_id: String,
+    /// Empty for a file holding the whole album
+    pub track_id: String,
+    /// Empty for a file holding the whole album
+    pub recording_id: String,
+    pub album_artist_id: Option<String>,
+}
 
-    pb.finish_with_message("Complete");
+impl TagData {
+    pub fn new(track: &Track, album: &Album, settings: &TagSettings) -> Self {
+        Self {
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            artists: track.artists.clone(),
+            album: album.title.clone(),
+            album_artist: album.artist.clone(),
+            album_artist_sort: album
+                .album_artist_sort
+                .clone()
+                .filter(|_| settings.write_album_artist_sort),
+            track_number: track.position,
+            total_tracks: album.total_tracks,
+            disc: (album.media_count > 1).then_some((track.disc_number, album.media_count as u32)),
+            disc_subtitle: track.disc_title.clone(),
+            genres: album.genres.clone(),
+            date: album.date.clone(),
+            compilation: settings.write_compilation && album.is_compilation,
+            album_id: album.id.clone(),
+            track_id: track.id.clone(),
+            recording_id: track.recording_id.clone(),
+            album_artist_id: album.album_artist_id.clone(),
+        }
+    }
+}
 
-    Ok(())
+/// Writes `TagData` into one container format, keeping any fields it doesn't
+/// manage. `Id3Writer` is the only implementation; there are no Vorbis
+/// comment or MP4 writers.
+pub trait TagWriter {
+    fn write(&self, file_path: &Path, data: &TagData, art: Artwork) -> Result<()>;
+}
+
+/// Pick the backend for a file by its extension; anything but `.mp3` is
+/// `Error::UnsupportedFormat`
+pub fn writer_for(file_path: &Path, settings: &TagSettings) -> Result<Box<dyn TagWriter>> {
+    let extension = file_path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "mp3" => Ok(Box::new(Id3Writer::new(settings))),
+        _ => Err(Error::UnsupportedFormat(extension)),
+    }
 }
 
-fn write_tags(
-    file_path: &std::path::Path,
-    track: &crate::musicbrainz::Track,
+/// Write the release and track metadata (plus any pictures) into one file,
+/// keeping any frames this crate doesn't manage
+pub fn write_tags(
+    file_path: &Path,
+    track: &Track,
     album: &Album,
-    cover_art: Option<&[u8]>,
+    art: Artwork,
+    settings: &TagSettings,
 ) -> Result<()> {
-    let mut tag = Tag::read_from_path(file_path).unwrap_or_else(|_| Tag::new());
-
-    // Basic metadata
-    tag.set_title(&track.title);
-    tag.set_artist(&track.artist);
-    tag.set_album(&album.title);
-    tag.set_album_artist(&album.artist);
-    tag.set_track(track.position);
-    tag.set_total_tracks(album.total_tracks);
-
-    // Disc number (only set if multi-disc release)
-    if album.media_count > 1 {
-        tag.set_disc(track.disc_number);
-        tag.set_total_discs(album.media_count as u32);
+    let data = TagData::new(track, album, settings);
+    writer_for(file_path, settings)?.write(file_path, &data, art)
+}
+
+/// ID3v2 backend for MP3 files
+pub struct Id3Writer {
+    version: Version,
+    multi_value: MultiValue,
+}
+
+impl Id3Writer {
+    pub fn new(settings: &TagSettings) -> Self {
+        Self {
+            version: settings.version,
+            multi_value: settings.multi_value,
+        }
     }
+}
+
+impl TagWriter for Id3Writer {
+    fn write(&self, file_path: &Path, data: &TagData, art: Artwork) -> Result<()> {
+        let mut tag = read_tag(file_path).unwrap_or_else(|_| Tag::new());
+
+        // Basic metadata
+        tag.set_title(&data.title);
+        set_artists(&mut tag, &data.artist, &data.artists, self.multi_value);
+        tag.set_album(&data.album);
+        tag.set_album_artist(&data.album_artist);
+        tag.set_track(data.track_number);
+        tag.set_total_tracks(data.total_tracks);
+
+        if let Some((disc, total_discs)) = data.disc {
+            tag.set_disc(disc);
+            tag.set_total_discs(total_discs);
+        }
+
+        // Genres (already mapped through the user's genre rules)
+        set_genres(&mut tag, &data.genres, self.multi_value);
+
+        if data.compilation {
+            tag.set_text("TCMP", "1");
+        }
+
+        if let Some(sort_name) = &data.album_artist_sort {
+            tag.set_text("TSO2", sort_name);
+        }
 
-    // Year from date
-    if let Some(date) = &album.date {
-        if let Some(year_str) = date.split('-').next() {
-            if let Ok(year) = year_str.parse::<i32>() {
-                tag.set_year(year);
+        // Year from date
+        if let Some(date) = &data.date {
+            if let Some(year_str) = date.split('-').next() {
+                if let Ok(year) = year_str.parse::<i32>() {
+                    tag.set_year(year);
+                }
+            }
+
+            if let Some(timestamp) = parse_date_to_timestamp(date) {
+                tag.set_date_released(timestamp);
             }
         }
 
-        if let Some(timestamp) = parse_date_to_timestamp(date) {
-            tag.set_date_released(timestamp);
+        // Add cover art
+        if let Some(image_data) = art.cover {
+            add_cover_art(&mut tag, image_data);
         }
-    }
+        add_pictures(&mut tag, art.extra);
 
-    // Add cover art
-    if let Some(image_data) = cover_art {
-        add_cover_art(&mut tag, image_data)?;
+        // MusicBrainz IDs
+        add_txxx_frame(&mut tag, "MusicBrainz Album Id", &data.album_id);
+        if !data.track_id.is_empty() {
+            add_txxx_frame(&mut tag, "MusicBrainz Release Track Id", &data.track_id);
+        }
+        if !data.recording_id.is_empty() {
+            add_txxx_frame(&mut tag, "MusicBrainz Recording Id", &data.recording_id);
+        }
+
+        if let Some(artist_id) = &data.album_artist_id {
+            add_txxx_frame(&mut tag, "MusicBrainz Album Artist Id", artist_id);
+        }
+
+        // Disc subtitle if present
+        if let Some(disc_title) = &data.disc_subtitle {
+            tag.set_text("TSST", disc_title); // Set subtitle for disc
+        }
+
+        tag.write_to_path(file_path, self.version)
+            .map_err(|source| Error::TagWriteError {
+                path: file_path.to_path_buf(),
+                source,
+            })?;
+
+        Ok(())
     }
+}
 
-    // MusicBrainz IDs
-    add_txxx_frame(&mut tag, "MusicBrainz Album Id", &album.id);
-    add_txxx_frame(&mut tag, "MusicBrainz Release Track Id", &track.id);
-    add_txxx_frame(&mut tag, "MusicBrainz Recording Id", &track.recording_id);
+fn set_artists(tag: &mut Tag, artist: &str, artists: &[String], multi_value: MultiValue) {
+    match multi_value {
+        MultiValue::FirstOnly => tag.set_artist(artist),
+        MultiValue::Native => tag.set_text_values("TPE1", artists.iter()),
+        MultiValue::Joined(separator) => tag.set_artist(artists.join(separator)),
+    }
+}
 
-    if let Some(artist_id) = &album.album_artist_id {
-        add_txxx_frame(&mut tag, "MusicBrainz Album Artist Id", artist_id);
+/// Tag a loose file as a recording: title, artists, and the recording and
+/// artist IDs. Album fields are left as they are.
+pub fn write_recording(
+    file_path: &Path,
+    recording: &RecordingInfo,
+    settings: &TagSettings,
+) -> Result<()> {
+    // Only ID3 has a backend; this fails for anything else
+    writer_for(file_path, settings)?;
+    let mut tag = read_tag(file_path).unwrap_or_else(|_| Tag::new());
+    tag.set_title(&recording.title);
+    set_artists(
+        &mut tag,
+        &recording.artist,
+        &recording.artists,
+        settings.multi_value,
+    );
+    add_txxx_frame(&mut tag, "MusicBrainz Recording Id", &recording.id);
+    if let Some(artist_id) = &recording.artist_id {
+        add_txxx_frame(&mut tag, "MusicBrainz Artist Id", artist_id);
     }
+    tag.write_to_path(file_path, settings.version)
+        .map_err(|source| Error::TagWriteError {
+            path: file_path.to_path_buf(),
+            source,
+        })
+}
+
+/// iTunes-era ID3v2.2 frames the id3 crate leaves under their three-letter
+/// IDs, with their v2.3 equivalents
+const V22_ITUNES_IDS: &[(&str, &str)] = &[
+    ("TCP", "TCMP"),
+    ("TST", "TSOT"),
+    ("TSA", "TSOA"),
+    ("TSP", "TSOP"),
+    ("TS2", "TSO2"),
+    ("TSC", "TSOC"),
+    ("TCT", "TCAT"),
+    ("TDS", "TDES"),
+    ("TID", "TGID"),
+    ("TKW", "TKWD"),
+    ("TDR", "TDRL"),
+    ("GP1", "GRP1"),
+    ("MVN", "MVNM"),
+    ("MVI", "MVIN"),
+    ("PCS", "PCST"),
+    ("WFD", "WFED"),
+];
+
+/// Read a file's ID3 tag. ID3v2.2 tags come back upgraded to v2.3, so
+/// writing them with `tag.version()` no longer fails on three-letter frames.
+pub fn read_tag(file_path: &Path) -> id3::Result<Tag> {
+    let tag = Tag::read_from_path(file_path)?;
+    Ok(if tag.version() == Version::Id3v22 {
+        upgrade_v22(&tag)
+    } else {
+        tag
+    })
+}
 
-    // Disc subtitle if present
-    if let Some(disc_title) = &track.disc_title {
-        tag.set_text("TSST", disc_title); // Set subtitle for disc
+/// Copy a v2.2 tag into a v2.3 one. The id3 crate already renames the frames
+/// v2.3 has equivalents for; the rest become TXXX (text) or experimental
+/// `X???` frames (anything else) so no value is lost.
+pub(crate) fn upgrade_v22(tag: &Tag) -> Tag {
+    let mut upgraded = Tag::with_version(Version::Id3v23);
+    for frame in tag.frames() {
+        let id = frame.id();
+        let frame = if id == "RVA2" {
+            // v2.2 RVA has the v2.3 RVAD layout, not the v2.4 RVA2 one
+            Frame::with_content("RVAD", frame.content().clone())
+        } else if id.len() == 4 {
+            frame.clone()
+        } else if let Some((_, v23)) = V22_ITUNES_IDS.iter().find(|(v22, _)| *v22 == id) {
+            Frame::with_content(*v23, frame.content().clone())
+        } else if let Some(text) = frame.content().text() {
+            Frame::with_content(
+                "TXXX",
+                Content::ExtendedText(frame::ExtendedText {
+                    description: id.to_string(),
+                    value: text.to_string(),
+                }),
+            )
+        } else {
+            Frame::with_content(format!("X{}", id), frame.content().clone())
+        };
+        upgraded.add_frame(frame);
     }
+    upgraded
+}
 
-    tag.write_to_path(file_path, Version::Id3v24)
-        .context("Failed to write ID3 tag")?;
+/// Write TCON the way the settings ask for several values; leaves the tag
+/// alone if there are no genres
+pub fn set_genres(tag: &mut Tag, genres: &[String], multi_value: MultiValue) {
+    if genres.is_empty() {
+        return;
+    }
+    match multi_value {
+        MultiValue::FirstOnly => tag.set_genre(&genres[0]),
+        MultiValue::Native => tag.set_text_values("TCON", genres.iter()),
+        MultiValue::Joined(separator) => tag.set_genre(genres.join(separator)),
+    }
+}
 
-    Ok(())
+/// Replace the front cover picture; the MIME type is sniffed from the bytes
+pub fn add_cover_art(tag: &mut Tag, image_data: &[u8]) {
+    add_picture(tag, frame::PictureType::CoverFront, "Cover", image_data);
 }
 
-fn add_cover_art(tag: &mut Tag, image_data: &[u8]) -> Result<()> {
+/// Replace the picture of `picture_type`; the MIME type is sniffed from the bytes
+pub fn add_picture(
+    tag: &mut Tag,
+    picture_type: frame::PictureType,
+    description: &str,
+    image_data: &[u8],
+) {
     let mime_type = if image_data.starts_with(&[0xFF, 0xD8, 0xFF]) {
         "image/jpeg"
     } else if image_data.starts_with(&[0x89, 0x50, 0x4E, 0x47]) {
@@ -110,15 +389,56 @@ fn add_cover_art(tag: &mut Tag, image_data: &[u8]) -> Result<()> {
 
     let picture = frame::Picture {
         mime_type: mime_type.to_string(),
-        picture_type: frame::PictureType::CoverFront,
-        description: "Cover".to_string(),
+        picture_type,
+        description: description.to_string(),
         data: image_data.to_vec(),
     };
 
-    tag.remove_picture_by_type(frame::PictureType::CoverFront);
+    tag.remove_picture_by_type(picture_type);
     tag.add_frame(picture);
+}
 
-    Ok(())
+/// Embed more pictures (a back cover, the disc) in an already tagged file,
+/// each replacing any picture of its type. Descriptions are the type names,
+/// since ID3 wants them unique within a tag.
+pub fn embed_pictures(
+    file_path: &Path,
+    pictures: &[(frame::PictureType, Vec<u8>)],
+    settings: &TagSettings,
+) -> Result<()> {
+    let mut tag = read_tag(file_path).unwrap_or_else(|_| Tag::new());
+    add_pictures(&mut tag, pictures);
+    tag.write_to_path(file_path, settings.version)
+        .map_err(|source| Error::TagWriteError {
+            path: file_path.to_path_buf(),
+            source,
+        })
+}
+
+fn add_pictures(tag: &mut Tag, pictures: &[(frame::PictureType, Vec<u8>)]) {
+    for (picture_type, image_data) in pictures {
+        add_picture(tag, *picture_type, &picture_type.to_string(), image_data);
+    }
+}
+
+/// TXXX description for the audio stream checksum from `audio::stream_hash`
+pub const CHECKSUM_FIELD: &str = "Audio Checksum";
+
+/// The audio checksum stored in a file's tag, if any
+pub fn stored_checksum(tag: &Tag) -> Option<String> {
+    extended_text(tag, CHECKSUM_FIELD).filter(|c| !c.is_empty())
+}
+
+/// Store an audio checksum, keeping the tag's own version
+pub fn write_checksum(file_path: &Path, checksum: &str) -> Result<()> {
+    let mut tag = read_tag(file_path).unwrap_or_else(|_| Tag::new());
+    add_txxx_frame(&mut tag, CHECKSUM_FIELD, checksum);
+    let version = tag.version();
+    tag.write_to_path(file_path, version)
+        .map_err(|source| Error::TagWriteError {
+            path: file_path.to_path_buf(),
+            source,
+        })
 }
 
 fn parse_date_to_timestamp(date_str: &str) -> Option<Timestamp> {
@@ -173,82 +493,59 @@ fn add_txxx_frame(tag: &mut Tag, description: &str, value: &str) {
     tag.add_frame(frame);
 }
 
+/// Value of the TXXX frame with the given description, if present
+pub fn extended_text(tag: &Tag, description: &str) -> Option<String> {
+    tag.extended_texts()
+        .find(|t| t.description == description)
+        .map(|t| t.value.clone())
+}
+
+/// Basic text frames already present in a file
 pub struct ExistingTags {
     pub title: Option<String>,
     pub artist: Option<String>,
     pub album: Option<String>,
     pub album_artist: Option<String>,
+    pub track: Option<u32>,
+    pub disc: Option<u32>,
+    pub year: Option<i32>,
+    /// Multiple genres joined with "; "
+    pub genre: Option<String>,
+    pub comment: Option<String>,
 }
 
-pub fn read_existing_tags(file_path: &std::path::Path) -> ExistingTags {
-    match Tag::read_from_path(file_path) {
+/// Read the basic text frames, all `None` if the file has no readable tag
+pub fn read_existing_tags(file_path: &Path) -> ExistingTags {
+    match read_tag(file_path) {
         Ok(tag) => ExistingTags {
             title: tag.title().map(|s| s.to_string()),
             artist: tag.artist().map(|s| s.to_string()),
             album: tag.album().map(|s| s.to_string()),
             album_artist: tag.album_artist().map(|s| s.to_string()),
+            track: tag.track(),
+            disc: tag.disc(),
+            year: tag
+                .year()
+                .or_else(|| tag.date_recorded().map(|d| d.year))
+                .or_else(|| tag.date_released().map(|d| d.year)),
+            genre: tag.genres().filter(|g| !g.is_empty()).map(|g| g.join("; ")),
+            comment: tag
+                .comments()
+                .find(|c| c.description.is_empty())
+                .or_else(|| tag.comments().next())
+                .map(|c| c.text.clone())
+                .filter(|t| !t.trim().is_empty()),
         },
         Err(_) => ExistingTags {
             title: None,
             artist: None,
             album: None,
             album_artist: None,
+            track: None,
+            disc: None,
+            year: None,
+            genre: None,
+            comment: None,
         },
     }
 }
-
-pub fn tag_files_manual(album: &ManualAlbum) -> Result<()> {
-    let pb = ProgressBar::new(album.tracks.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
-            .unwrap()
-            .progress_chars("=>-"),
-    );
-
-    let total_tracks = album.tracks.len() as u32;
-    let current_year = chrono::Utc::now().year();
-
-    for track in &album.tracks {
-        pb.set_message(format!(
-            "{}",
-            track.file_path.file_name().unwrap().to_string_lossy()
-        ));
-
-        write_manual_tags(&track.file_path, track, album, total_tracks, current_year)
-            .with_context(|| format!("Failed to write tags to {}", track.file_path.display()))?;
-
-        pb.inc(1);
-    }
-
-    pb.finish_with_message("Complete");
-    Ok(())
-}
-
-fn write_manual_tags(
-    file_path: &std::path::Path,
-    track: &crate::manual_mode::ManualTrackInfo,
-    album: &ManualAlbum,
-    total_tracks: u32,
-    year: i32,
-) -> Result<()> {
-    let mut tag = Tag::read_from_path(file_path).unwrap_or_else(|_| Tag::new());
-
-    tag.set_title(&track.title);
-    tag.set_artist(&track.artist);
-    tag.set_album(&album.title);
-    tag.set_album_artist(&album.artist);
-    tag.set_track(track.track_number);
-    tag.set_total_tracks(total_tracks);
-    tag.set_year(year);
-
-    // Add cover art if provided
-    if let Some(image_data) = &album.cover_art {
-        add_cover_art(&mut tag, image_data)?;
-    }
-
-    tag.write_to_path(file_path, Version::Id3v24)
-        .context("Failed to write ID3 tag")?;
-
-    Ok(())
-}
@@ -0,0 +1,145 @@
+// src/unmatched.rs
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use dialoguer::Confirm;
+use std::path::{Path, PathBuf};
+
+use crate::matcher::Duplicate;
+use crate::symbols;
+
+/// Folder next to the files that duplicate copies are moved into
+pub use crate::matcher::DUPLICATES_DIR;
+/// Folder that unmatched files are moved into; skipped when discovering files
+pub use crate::matcher::UNMATCHED_DIR;
+pub const REPORT_NAME: &str = "unmatched.txt";
+
+/// What to do with files that could not be matched to a track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UnmatchedAction {
+    /// Move them into an `_unmatched/` subfolder
+    Move,
+    /// List them in `unmatched.txt`
+    Report,
+}
+
+pub fn handle(
+    action: UnmatchedAction,
+    unmatched: &[PathBuf],
+    album_dir: &Path,
+    dry_run: bool,
+) -> Result<()> {
+    if unmatched.is_empty() {
+        return Ok(());
+    }
+
+    match action {
+        UnmatchedAction::Move => {
+            let target = album_dir.join(UNMATCHED_DIR);
+            let heading = if dry_run {
+                "Would move unmatched files to"
+            } else {
+                "Moving unmatched files to"
+            };
+            println!(
+                "{} {}",
+                heading.bright_yellow(),
+                target.display().to_string().bright_white()
+            );
+            for file in unmatched {
+                let Some(name) = file.file_name() else {
+                    continue;
+                };
+                // Left there by an earlier run, or from another subfolder
+                let destination = crate::organize::unique_path(&target.join(name));
+                println!(
+                    "  {} {}",
+                    symbols::arrow().bright_cyan(),
+                    destination
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                );
+                if !dry_run {
+                    crate::organize::move_file(file, &destination)?;
+                }
+            }
+        }
+        UnmatchedAction::Report => {
+            let report_path = album_dir.join(REPORT_NAME);
+            let contents: String = unmatched
+                .iter()
+                .map(|f| {
+                    let relative = f.strip_prefix(album_dir).unwrap_or(f);
+                    format!("{}\n", relative.display())
+                })
+                .collect();
+
+            if dry_run {
+                println!(
+                    "{} Would list {} unmatched file(s) in {}",
+                    "Dry run -".bright_yellow(),
+                    unmatched.len(),
+                    report_path.display()
+                );
+                return Ok(());
+            }
+            std::fs::write(&report_path, contents)
+                .with_context(|| format!("Failed to write {}", report_path.display()))?;
+            println!(
+                "{} Listed {} unmatched file(s) in {}",
+                symbols::ok().bright_green(),
+                unmatched.len(),
+                report_path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Offer to move extra copies of matched tracks into a `_duplicates/`
+/// subfolder next to them. Without `ask` (`--yes`), they are left in place.
+pub fn offer_duplicates(duplicates: &[&Duplicate], ask: bool) -> Result<()> {
+    if duplicates.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "{} {} file(s) are lower-quality copies of tracks being tagged",
+        symbols::warn().bright_yellow(),
+        duplicates.len()
+    );
+    if !ask {
+        println!("  Leaving them in place");
+        return Ok(());
+    }
+    if !Confirm::new()
+        .with_prompt(format!("Move them into {}/?", DUPLICATES_DIR))
+        .default(true)
+        .interact()?
+    {
+        return Ok(());
+    }
+
+    for duplicate in duplicates {
+        let file = &duplicate.file_path;
+        let Some(name) = file.file_name() else {
+            continue;
+        };
+        let target = crate::organize::unique_path(
+            &file
+                .parent()
+                .unwrap_or(Path::new("."))
+                .join(DUPLICATES_DIR)
+                .join(name),
+        );
+        println!(
+            "  {} {}",
+            symbols::arrow().bright_cyan(),
+            name.to_string_lossy()
+        );
+        crate::organize::move_file(file, &target)?;
+    }
+    Ok(())
+}
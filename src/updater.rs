@@ -2,6 +2,8 @@ use anyhow::{Context, Result};
 use colored::Colorize;
 use self_update::cargo_crate_version;
 
+use crate::symbols;
+
 const REPO_OWNER: &str = "nilutz";
 const REPO_NAME: &str = "musictagger_rs";
 
@@ -65,12 +67,12 @@ pub fn update() -> Result<()> {
             if OpenOptions::new().write(true).open(parent).is_err() {
                 println!(
                     "{} Binary is installed in a system directory: {}",
-                    "⚠".yellow(),
+                    symbols::warn().yellow(),
                     exe_path.display()
                 );
                 println!(
                     "{} You may need to run: {}",
-                    "ℹ".cyan(),
+                    symbols::info().cyan(),
                     format!("sudo {} --update", exe_path.display()).yellow()
                 );
                 println!();
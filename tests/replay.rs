@@ -0,0 +1,114 @@
+//! Replays the responses recorded in `tests/fixtures` through lookup, cover
+//! art, matching, and tagging, without the network. Needs the `fixtures`
+//! feature: `cargo test --features fixtures`.
+#![cfg(feature = "fixtures")]
+
+use id3::{Tag, TagLike};
+use musictagger_rs::error::Error;
+use musictagger_rs::events::NoEvents;
+use musictagger_rs::fixtures::Fixtures;
+use musictagger_rs::matcher::match_files;
+use musictagger_rs::musicbrainz::MusicBrainzClient;
+use musictagger_rs::profile::TagSettings;
+use musictagger_rs::provider::MetadataProvider;
+use musictagger_rs::tagger::{tag_files, Artwork};
+use musictagger_rs::CancellationToken;
+use std::path::{Path, PathBuf};
+
+/// The recorded release: "Fixture Album" with two 5 second tracks
+const RELEASE_ID: &str = "6a1f7c2e-3b4d-4e5f-8a9b-0c1d2e3f4a5b";
+
+fn client() -> MusicBrainzClient {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    MusicBrainzClient::builder()
+        .fixtures(Some(Fixtures::replay(dir)))
+        .build()
+        .expect("client builds")
+}
+
+/// An empty directory for one test's files
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "musictagger_rs-replay-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("scratch dir");
+    dir
+}
+
+/// Write `seconds` of silence as MPEG-1 Layer III frames (128 kbps, 44.1 kHz,
+/// mono), each 1152 samples long
+fn write_silent_mp3(path: &Path, seconds: u32) {
+    const FRAME_LEN: usize = 417;
+    let frames = (seconds * 44_100).div_ceil(1152) as usize;
+    let mut frame = vec![0u8; FRAME_LEN];
+    frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0xC0]);
+    std::fs::write(path, frame.repeat(frames)).expect("write mp3");
+}
+
+#[tokio::test]
+async fn replays_a_release() {
+    let album = client().get_release(RELEASE_ID).await.unwrap();
+
+    assert_eq!(album.title, "Fixture Album");
+    assert_eq!(album.artist, "Fixture Artist");
+    assert_eq!(album.date.as_deref(), Some("2004-05-17"));
+    assert_eq!(album.genres, ["electronic", "ambient"]);
+    let titles: Vec<&str> = album.tracks.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(titles, ["Song 1", "Song 2"]);
+}
+
+#[tokio::test]
+async fn replays_cover_art() {
+    let art = client().get_cover_art(RELEASE_ID, 500).await.unwrap();
+
+    assert_eq!(musictagger_rs::art::dimensions(&art), Some((500, 500)));
+}
+
+#[tokio::test]
+async fn fails_on_requests_that_were_never_recorded() {
+    let result = client()
+        .get_release("00000000-0000-0000-0000-000000000000")
+        .await;
+
+    assert!(matches!(result, Err(Error::MissingFixture(_))));
+}
+
+#[tokio::test]
+async fn matches_and_tags_files_from_a_replayed_release() {
+    let dir = scratch_dir("tag");
+    let files = vec![dir.join("02 - Song 2.mp3"), dir.join("01 - Song 1.mp3")];
+    for file in &files {
+        write_silent_mp3(file, 5);
+    }
+
+    let client = client();
+    let album = client.get_release(RELEASE_ID).await.unwrap();
+    let art = client.get_cover_art(RELEASE_ID, 500).await.unwrap();
+    let report = match_files(&files, &album, &NoEvents, &CancellationToken::new())
+        .await
+        .unwrap();
+    assert!(report.unmatched_files.is_empty());
+    assert!(report.unmatched_tracks.is_empty());
+
+    tag_files(
+        &report.matches,
+        &album,
+        Artwork::cover(Some(&art)),
+        &TagSettings::default(),
+        &NoEvents,
+    )
+    .unwrap();
+
+    for (file, title, number) in [(&files[1], "Song 1", 1), (&files[0], "Song 2", 2)] {
+        let tag = Tag::read_from_path(file).unwrap();
+        assert_eq!(tag.title(), Some(title));
+        assert_eq!(tag.artist(), Some("Fixture Artist"));
+        assert_eq!(tag.album(), Some("Fixture Album"));
+        assert_eq!(tag.track(), Some(number));
+        assert_eq!(tag.pictures().count(), 1);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}